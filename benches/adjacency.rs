@@ -0,0 +1,23 @@
+//! Compares the adjacency list representations, run once with and once without `--features smallvec`.
+#[macro_use]
+extern crate criterion;
+extern crate graph;
+
+use criterion::Criterion;
+use graph::generators::{grid_network, random_network};
+use graph::{FlowGraph, BFS};
+
+fn construction(c: &mut Criterion) {
+    c.bench_function("build grid 100x100", |b| b.iter(|| grid_network(100, 100, 1..=100, 1)));
+    c.bench_function("build random 10000/40000", |b| b.iter(|| random_network(10000, 40000, 1..=100, 1)));
+}
+
+fn traversal(c: &mut Criterion) {
+    let (source, sink, grid) = grid_network(100, 100, 1..=100, 1);
+    c.bench_function("max flow grid 100x100", |b| b.iter(|| grid.clone().max_flow(source, sink, BFS).ok()));
+    let (source, sink, random) = random_network(2000, 8000, 1..=100, 1);
+    c.bench_function("max flow random 2000/8000", |b| b.iter(|| random.clone().max_flow(source, sink, BFS).ok()));
+}
+
+criterion_group!(benches, construction, traversal);
+criterion_main!(benches);
@@ -0,0 +1,36 @@
+//! Measures every solver on each generated network family at a few sizes.
+#[macro_use]
+extern crate criterion;
+extern crate graph;
+
+use criterion::{BenchmarkId, Criterion};
+use graph::benchmark::Family;
+use graph::solver::{Dinic, EdmondsKarp, FordFulkerson, MaxFlowSolver, PushRelabel};
+
+fn families(c: &mut Criterion) {
+    let families = vec![
+        Family::Random {vertexes: 500, edges: 2500},
+        Family::Random {vertexes: 2000, edges: 10000},
+        Family::Grid {rows: 20, cols: 20},
+        Family::Grid {rows: 60, cols: 60},
+        Family::Layered {layers: 10, width: 50, degree: 3},
+        Family::Layered {layers: 40, width: 100, degree: 3},
+        Family::WorstCase {capacity: 10000}
+    ];
+    let solvers: Vec<Box<dyn MaxFlowSolver>> =
+        vec![Box::new(EdmondsKarp), Box::new(FordFulkerson), Box::new(Dinic), Box::new(PushRelabel::default())];
+    let mut group = c.benchmark_group("max_flow");
+    group.sample_size(20);
+    for family in &families {
+        let (source, sink, g) = family.generate(1..=100, 0);
+        for solver in &solvers {
+            group.bench_with_input(BenchmarkId::new(solver.name(), family), &g, |b, g| {
+                b.iter(|| solver.solve(g, source, sink).ok())
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, families);
+criterion_main!(benches);
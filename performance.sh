@@ -6,11 +6,11 @@ do
     ipython generate_graph.py -- --connect-ratio $ratio test.txt
     for i in {1..5}
     do
-        target/release/max_flow dfs dicaps test.txt >> data/performance/experiments.txt
+        target/release/max_flow solve --algorithm dfs --format dicaps test.txt >> data/performance/experiments.txt
     done
 
     for i in {1..5}
     do
-        target/release/max_flow bfs dicaps test.txt >> data/performance/experiments.txt
+        target/release/max_flow solve --algorithm bfs --format dicaps test.txt >> data/performance/experiments.txt
     done
 done
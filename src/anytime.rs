@@ -0,0 +1,84 @@
+use std::time::{Duration, Instant};
+use levels::UNREACHED;
+use super::{FlowEdge, FlowError, FlowGraph, Graph, SolveStats, VertexId, BFS};
+
+/// Flow found by `Graph::max_flow_with_deadline` and how far from optimal it can be.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct DeadlineResult {
+    /// Value of the feasible flow left in the graph.
+    pub flow: i64,
+    /// Smallest capacity of the cuts seen, the maximum flow is between `flow` and this bound.
+    pub upper_bound: i64,
+    /// Whether the flow is proven maximum, which is when it reaches the upper bound.
+    pub optimal: bool,
+    pub stats: SolveStats
+}
+
+impl Graph<FlowEdge> {
+    /// Pushes flow along shortest augmenting paths until none is left or `deadline` has passed, and leaves the best
+    /// flow found in the graph. The deadline is checked after every augmentation, so a single long search can overrun
+    /// it. When time runs out the upper bound is the smallest capacity of the cuts between the residual levels from
+    /// the source and from the sink, each of which is the flow value plus the residual capacity of the arcs crossing
+    /// it. Fails like `FlowGraph::max_flow`.
+    pub fn max_flow_with_deadline(&mut self, source: VertexId, sink: VertexId, deadline: Duration)
+                                  -> Result<DeadlineResult, FlowError> {
+        let start_time = Instant::now();
+        let (flow, stats) = self.max_flow_observed_with_stats(source, sink, BFS, |_| start_time.elapsed() < deadline)?;
+        let levels = self.residual_levels(source);
+        if levels[sink] == UNREACHED {
+            return Ok(DeadlineResult {flow, upper_bound: flow, optimal: true, stats});
+        }
+        // Residual arcs only climb one level at a time, so the arcs crossing the cut below level k + 1 all start on
+        // level k, and the same holds for the distances to the sink
+        let distances = self.distances_to_sink(sink);
+        let mut from_source = vec![0i64; levels[sink] as usize];
+        let mut from_sink = vec![0i64; distances[source] as usize];
+        for u in self.vertexes() {
+            for a in self.out_arcs(u) {
+                let (v, residual) = (self.head(a), self.arcs[a].residual() as i64);
+                if residual <= 0 {
+                    continue;
+                }
+                if levels[u] < levels[sink] && levels[v] == levels[u] + 1 {
+                    from_source[levels[u] as usize] += residual;
+                }
+                if distances[v] < distances[source] && distances[u] == distances[v] + 1 {
+                    from_sink[distances[v] as usize] += residual;
+                }
+            }
+        }
+        let upper_bound = flow + from_source.iter().chain(&from_sink).copied().min().unwrap_or(0);
+        Ok(DeadlineResult {flow, upper_bound, optimal: upper_bound == flow, stats})
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use generators::random_network;
+    use verify::{verify_flow, Violation};
+    use flow_from_dicaps;
+
+    #[test]
+    fn test_max_flow_with_deadline() {
+        let (source, sink, mut g) = flow_from_dicaps("data/dicaps/central.txt");
+        let result = g.max_flow_with_deadline(source, sink, Duration::from_secs(60)).unwrap();
+        assert_eq!((result.flow, result.upper_bound, result.optimal), (5, 5, true));
+
+        // Without time a single augmentation is made, the flow stays feasible and the bound holds
+        for seed in 0..20 {
+            let (source, sink, g) = random_network(30, 150, 1..=20, seed);
+            let expected = match g.clone().max_flow(source, sink, BFS) {
+                Ok(flow) => flow,
+                Err(_) => continue
+            };
+            let mut g = g;
+            let result = g.max_flow_with_deadline(source, sink, Duration::ZERO).unwrap();
+            assert_eq!(result.stats.augmenting_paths, 1);
+            let violations = verify_flow(&g, source, sink);
+            assert!(violations.iter().all(|v| matches!(v, Violation::NotMaximum {..})), "seed {}", seed);
+            assert!(result.flow <= expected && expected <= result.upper_bound, "seed {}", seed);
+            assert!(!result.optimal || result.flow == expected, "seed {}", seed);
+        }
+    }
+}
@@ -0,0 +1,116 @@
+use alloc::vec::Vec;
+use core::ops::RangeInclusive;
+use proptest::arbitrary::Arbitrary;
+use proptest::collection::vec;
+use proptest::strategy::{BoxedStrategy, Just, Strategy};
+use super::{FlowEdge, Graph, VertexId};
+
+/// Sizes of the networks generated by `flow_networks` and the `Arbitrary` implementations.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NetworkParams {
+    /// Number of vertexes, at least two.
+    pub vertexes: RangeInclusive<usize>,
+    /// Number of edges, which may be parallel.
+    pub edges: RangeInclusive<usize>,
+    /// Capacity of every edge, never negative.
+    pub capacity: RangeInclusive<i32>
+}
+
+impl Default for NetworkParams {
+    fn default() -> NetworkParams {
+        NetworkParams {vertexes: 2..=10, edges: 0..=30, capacity: 0..=20}
+    }
+}
+
+/// Flow network of `flow_networks`: a graph with residual arcs and no flow, distinct terminals and no self loops.
+#[derive(Debug, Clone)]
+pub struct FlowNetwork {
+    pub source: VertexId,
+    pub sink: VertexId,
+    pub graph: Graph<FlowEdge>
+}
+
+/// Generates flow networks within `params` for property tests. A failing network shrinks towards fewer vertexes, fewer
+/// edges, lower vertex ids and smaller capacities, and every shrunk network is still valid. Panics if `params` allows
+/// fewer than two vertexes or negative capacities.
+pub fn flow_networks(params: NetworkParams) -> impl Strategy<Value = FlowNetwork> {
+    assert!(*params.vertexes.start() >= 2, "A flow network needs at least two vertexes");
+    assert!(*params.capacity.start() >= 0, "Capacities can not be negative");
+    let NetworkParams {vertexes, edges, capacity} = params;
+    vertexes
+        .prop_flat_map(move |n| {
+            // The head of an edge and the sink are drawn among the other vertexes, so a loop or a source which is the
+            // sink are never generated, not even while shrinking
+            let edge = (0..n, 0..n - 1, capacity.clone());
+            (Just(n), vec(edge, edges.clone()), 0..n, 0..n - 1)
+        })
+        .prop_map(|(n, edges, source, sink)| {
+            let other = |u: usize, v: usize| if v >= u { v + 1 } else { v };
+            let vertexes = (0..n).map(VertexId).collect::<Vec<_>>();
+            let edges = edges.into_iter()
+                .map(|(u, v, capacity)| (VertexId(u), VertexId(other(u, v)), FlowEdge {capacity, flow: 0}))
+                .collect::<Vec<_>>();
+            let graph = Graph::flow_network(&vertexes, &edges);
+            FlowNetwork {source: VertexId(source), sink: VertexId(other(source, sink)), graph}
+        })
+}
+
+impl Arbitrary for FlowNetwork {
+    type Parameters = NetworkParams;
+    type Strategy = BoxedStrategy<FlowNetwork>;
+
+    fn arbitrary_with(params: NetworkParams) -> Self::Strategy {
+        flow_networks(params).boxed()
+    }
+}
+
+/// Graphs of `flow_networks` without their terminals.
+impl Arbitrary for Graph<FlowEdge> {
+    type Parameters = NetworkParams;
+    type Strategy = BoxedStrategy<Graph<FlowEdge>>;
+
+    fn arbitrary_with(params: NetworkParams) -> Self::Strategy {
+        flow_networks(params).prop_map(|network| network.graph).boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::arbitrary::any;
+    use proptest::{prop_assert, proptest};
+    use proptest::test_runner::{TestCaseError, TestError, TestRunner};
+    use verify::certify;
+    use {FlowGraph, BFS};
+
+    proptest! {
+        #[test]
+        fn test_flow_networks(network in any::<FlowNetwork>()) {
+            let FlowNetwork {source, sink, mut graph} = network;
+            prop_assert!(source != sink && graph.has_residual_arcs());
+            prop_assert!(graph.edges().all(|(u, v, _)| u != v));
+            let value = graph.max_flow(source, sink, BFS).unwrap_or(0);
+            prop_assert!(certify(&graph, source, sink, value).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_shrinking() {
+        // The smallest network with an edge of capacity 10 or more is a single such edge between two vertexes
+        let mut runner = TestRunner::deterministic();
+        let result = runner.run(&any::<Graph<FlowEdge>>(), |graph| {
+            if graph.edges().any(|(_, _, a)| graph.arcs[a].capacity >= 10) {
+                return Err(TestCaseError::fail("large capacity"));
+            }
+            Ok(())
+        });
+        match result {
+            Err(TestError::Fail(_, graph)) => {
+                assert_eq!((graph.n_vertexes(), graph.n_edges()), (2, 1));
+                assert_eq!(graph.edges().map(|(u, v, a)| (u, v, graph.arcs[a].capacity)).collect::<Vec<_>>(),
+                           [(VertexId(0), VertexId(1), 10)]);
+            },
+            other => panic!("Expected a failing network, got {:?}", other)
+        }
+    }
+}
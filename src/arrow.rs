@@ -0,0 +1,100 @@
+use std::convert::TryFrom;
+use arrow_array::cast::AsArray;
+use arrow_array::types::{Int16Type, Int32Type, Int64Type, Int8Type, UInt16Type, UInt32Type, UInt64Type, UInt8Type};
+use arrow_array::{Array, ArrowPrimitiveType, RecordBatch};
+use arrow_schema::DataType;
+use super::{DuplicatePolicy, Error, FlowEdge, Graph, SelfLoopPolicy, VertexId};
+
+/// Builds a flow network from Arrow record batches with one edge per row, read from the integer columns `source`,
+/// `target` and `capacity`. The batches may come from Parquet files or a query engine. The graph has `n_vertexes`
+/// vertexes, or one more than the largest vertex of an edge when `None`. Fails if a column is missing, is not of an
+/// integer type or has nulls, if a vertex or capacity is negative or a capacity does not fit in an `i32`, or if the
+/// graph is invalid as for `Graph::try_new`.
+pub fn flow_from_record_batches<'a, I>(batches: I, n_vertexes: Option<usize>) -> Result<Graph<FlowEdge>, Error>
+    where I: IntoIterator<Item = &'a RecordBatch> {
+    let mut edges: Vec<(VertexId, VertexId, FlowEdge)> = Vec::new();
+    for batch in batches {
+        let tails = integer_column(batch, "source")?;
+        let heads = integer_column(batch, "target")?;
+        let capacities = integer_column(batch, "capacity")?;
+        for i in 0..batch.num_rows() {
+            let row = edges.len();
+            let vertex = |v: i64| usize::try_from(v).map(VertexId)
+                .map_err(|_| Error::Construction(format!("Row {} has the negative vertex {}", row, v)));
+            let capacity = i32::try_from(capacities[i])
+                .map_err(|_| Error::Overflow(format!("Capacity {} in row {} overflows", capacities[i], row)))?;
+            if capacity < 0 {
+                return Err(Error::Construction(format!("Row {} has the negative capacity {}", row, capacity)));
+            }
+            edges.push((vertex(tails[i])?, vertex(heads[i])?, FlowEdge{capacity, flow: 0}));
+        }
+    }
+    let n_vertexes = n_vertexes.unwrap_or_else(|| edges.iter().map(|e| e.0.max(e.1).index() + 1).max().unwrap_or(0));
+    let vertexes = (0..n_vertexes).map(VertexId).collect::<Vec<_>>();
+    Graph::try_flow_network(&vertexes, edges, DuplicatePolicy::Parallel, SelfLoopPolicy::Skip)
+}
+
+/// Reads the column `name` of a batch, which may have any integer type, as `i64` values.
+fn integer_column(batch: &RecordBatch, name: &str) -> Result<Vec<i64>, Error> {
+    let column = batch.column_by_name(name)
+        .ok_or_else(|| Error::Construction(format!("Record batch has no {} column", name)))?;
+    if column.null_count() > 0 {
+        return Err(Error::Construction(format!("Column {} has null values", name)));
+    }
+    match column.data_type() {
+        DataType::Int8 => widen::<Int8Type>(column, name),
+        DataType::Int16 => widen::<Int16Type>(column, name),
+        DataType::Int32 => widen::<Int32Type>(column, name),
+        DataType::Int64 => widen::<Int64Type>(column, name),
+        DataType::UInt8 => widen::<UInt8Type>(column, name),
+        DataType::UInt16 => widen::<UInt16Type>(column, name),
+        DataType::UInt32 => widen::<UInt32Type>(column, name),
+        DataType::UInt64 => widen::<UInt64Type>(column, name),
+        other => Err(Error::Construction(format!("Column {} has type {}, expected integers", name, other)))
+    }
+}
+
+fn widen<T: ArrowPrimitiveType>(column: &dyn Array, name: &str) -> Result<Vec<i64>, Error>
+    where i64: TryFrom<T::Native> {
+    column.as_primitive::<T>().values().iter()
+        .map(|&v| i64::try_from(v).map_err(|_| Error::Overflow(format!("Column {} has a value which overflows", name))))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use arrow_array::{ArrayRef, Float64Array, Int64Array, RecordBatch, UInt32Array};
+    use super::*;
+    use {FlowGraph, BFS};
+
+    fn batch(tails: Vec<u32>, heads: Vec<u32>, capacities: Vec<i64>) -> RecordBatch {
+        RecordBatch::try_from_iter(vec![
+            ("source", Arc::new(UInt32Array::from(tails)) as ArrayRef),
+            ("target", Arc::new(UInt32Array::from(heads)) as ArrayRef),
+            ("capacity", Arc::new(Int64Array::from(capacities)) as ArrayRef)
+        ]).unwrap()
+    }
+
+    #[test]
+    fn test_flow_from_record_batches() {
+        let batches = [batch(vec![0, 0, 2], vec![2, 3, 3], vec![5, 5, 1]), batch(vec![2, 3], vec![1, 1], vec![5, 5])];
+        let mut g = flow_from_record_batches(&batches, None).unwrap();
+        assert_eq!((g.n_vertexes(), g.n_edges()), (4, 5));
+        assert_eq!(g.max_flow(VertexId(0), VertexId(1), BFS), Ok(10));
+        assert_eq!(flow_from_record_batches(&batches, Some(6)).unwrap().n_vertexes(), 6);
+        assert!(matches!(flow_from_record_batches(&batches, Some(3)), Err(Error::Construction(_))));
+
+        assert_eq!(flow_from_record_batches(&[batch(vec![0], vec![1], vec![-2])], None).unwrap_err(),
+                   Error::Construction("Row 0 has the negative capacity -2".to_string()));
+        assert!(matches!(flow_from_record_batches(&[batch(vec![0], vec![1], vec![1 << 40])], None),
+                         Err(Error::Overflow(_))));
+        let floats = RecordBatch::try_from_iter(vec![
+            ("source", Arc::new(UInt32Array::from(vec![0])) as ArrayRef),
+            ("target", Arc::new(UInt32Array::from(vec![1])) as ArrayRef),
+            ("capacity", Arc::new(Float64Array::from(vec![1.5])) as ArrayRef)
+        ]).unwrap();
+        assert_eq!(flow_from_record_batches(&[floats], None).unwrap_err(),
+                   Error::Construction("Column capacity has type Float64, expected integers".to_string()));
+    }
+}
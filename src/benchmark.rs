@@ -0,0 +1,153 @@
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::time::{Duration, Instant};
+use generators::{grid_network, layered_network, random_network, worst_case_network};
+use super::{FlowEdge, FlowGraph, Graph, Search, VertexId, BFS, DFS};
+
+/// Family and size of a generated benchmark network, see the `generators` module for the layouts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Family {
+    Random { vertexes: usize, edges: usize },
+    Grid { rows: usize, cols: usize },
+    Layered { layers: usize, width: usize, degree: usize },
+    WorstCase { capacity: i32 }
+}
+
+impl Family {
+    /// Generates the network, returning (source, sink, graph).
+    pub fn generate(&self, capacity: RangeInclusive<i32>, seed: u64) -> (VertexId, VertexId, Graph<FlowEdge>) {
+        match *self {
+            Family::Random {vertexes, edges} => random_network(vertexes, edges, capacity, seed),
+            Family::Grid {rows, cols} => grid_network(rows, cols, capacity, seed),
+            Family::Layered {layers, width, degree} => layered_network(layers, width, degree, capacity, seed),
+            Family::WorstCase {capacity} => worst_case_network(capacity)
+        }
+    }
+}
+
+impl fmt::Display for Family {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Family::Random {vertexes, edges} => write!(f, "random-{}-{}", vertexes, edges),
+            Family::Grid {rows, cols} => write!(f, "grid-{}x{}", rows, cols),
+            Family::Layered {layers, width, degree} => write!(f, "layered-{}x{}-{}", layers, width, degree),
+            Family::WorstCase {capacity} => write!(f, "worst-case-{}", capacity)
+        }
+    }
+}
+
+/// Networks and searches measured by `run`. Every network is generated once and solved `runs` times per search.
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub families: Vec<Family>,
+    pub searches: Vec<Search>,
+    pub capacity: RangeInclusive<i32>,
+    pub seed: u64,
+    pub runs: usize
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            families: vec![
+                Family::Random {vertexes: 1000, edges: 5000},
+                Family::Grid {rows: 50, cols: 50},
+                Family::Layered {layers: 20, width: 50, degree: 3},
+                Family::WorstCase {capacity: 1000}
+            ],
+            searches: vec![BFS, DFS],
+            capacity: 1..=100,
+            seed: 0,
+            runs: 5
+        }
+    }
+}
+
+/// Result of solving one network with one search.
+#[derive(Debug, Clone)]
+pub struct Measurement {
+    pub family: Family,
+    pub search: Search,
+    pub vertexes: usize,
+    pub edges: usize,
+    pub flow: i64,
+    pub augmentations: usize,
+    /// Runtime of every run, in the order they were measured.
+    pub runtimes: Vec<Duration>
+}
+
+impl Measurement {
+    pub fn min(&self) -> Duration {
+        self.runtimes.iter().cloned().min().unwrap_or_default()
+    }
+
+    pub fn median(&self) -> Duration {
+        let mut runtimes = self.runtimes.clone();
+        runtimes.sort();
+        runtimes.get(runtimes.len() / 2).cloned().unwrap_or_default()
+    }
+
+    pub fn mean(&self) -> Duration {
+        if self.runtimes.is_empty() {
+            Duration::default()
+        } else {
+            self.runtimes.iter().sum::<Duration>() / self.runtimes.len() as u32
+        }
+    }
+}
+
+/// Generates every network of the configuration and measures each search on it. Each run solves a fresh copy of the
+/// network, so graph generation and copying are not part of the runtimes.
+pub fn run(config: &Config) -> Vec<Measurement> {
+    let mut measurements: Vec<Measurement> = Vec::new();
+    for family in &config.families {
+        let (source, sink, graph) = family.generate(config.capacity.clone(), config.seed);
+        for search in &config.searches {
+            let mut measurement = Measurement {
+                family: family.clone(),
+                search: *search,
+                vertexes: graph.n_vertexes(),
+                edges: graph.n_edges(),
+                flow: 0,
+                augmentations: 0,
+                runtimes: Vec::with_capacity(config.runs)
+            };
+            for _ in 0..config.runs {
+                let mut g = graph.clone();
+                let mut augmentations = 0;
+                let start_time = Instant::now();
+                // Generated terminals are always valid, so the only error is a sink which no flow reaches
+                measurement.flow = g.max_flow_observed(source, sink, *search, |p| {
+                    augmentations = p.augmentations;
+                    true
+                }).unwrap_or(0);
+                measurement.runtimes.push(start_time.elapsed());
+                measurement.augmentations = augmentations;
+            }
+            measurements.push(measurement);
+        }
+    }
+    measurements
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run() {
+        let config = Config {
+            families: vec![Family::Grid {rows: 3, cols: 4}, Family::WorstCase {capacity: 10}],
+            capacity: 5..=5,
+            runs: 3,
+            ..Config::default()
+        };
+        let measurements = run(&config);
+        assert_eq!(measurements.len(), 4);
+        assert_eq!(measurements[0].family.to_string(), "grid-3x4");
+        assert_eq!((measurements[0].search, measurements[0].flow), (BFS, 10));
+        assert_eq!((measurements[2].search, measurements[2].flow, measurements[2].augmentations), (BFS, 20, 2));
+        assert_eq!((measurements[3].search, measurements[3].flow), (DFS, 20));
+        assert!(measurements.iter().all(|m| m.runtimes.len() == 3 && m.min() <= m.median()));
+    }
+}
@@ -1,38 +1,870 @@
+extern crate clap;
 extern crate graph;
-extern crate time;
 
-use std::env;
-use graph::{flow_from_dicaps, flow_from_txt, FlowGraph, DFS, BFS};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::process;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+use clap::{Args, CommandFactory, Parser, Subcommand, ValueEnum};
+use clap::error::ErrorKind;
+use graph::generators;
+use graph::solver::{Dinic, EdmondsKarp, FordFulkerson, MaxFlowSolver, PushRelabel};
+use graph::trace::{FrameFormat, Trace};
+use graph::verify::{verify_flow, verify_solution};
+use graph::{detect_format, detect_format_reader, flow_from_reader, read_solution, write_flow, write_solution,
+            write_solution_csv, DuplicatePolicy, Error, FlowEdge, FlowError, FlowGraph, Format, Graph, Search,
+            SolveStats, VertexId, DFS, BFS};
 
-fn main() {
-    let args: Vec<String> = env::args().collect();
-    let search_str = args[1].as_str();
-    let search = match search_str {
-        "bfs" => Some(BFS),
-        "dfs" => Some(DFS),
+#[derive(Parser)]
+#[command(name = "max_flow", version, about = "Maximum flow solvers for DIMACS, txt, DOT, JSON or CSV networks")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Solve the maximum flow problem of a network
+    Solve(SolveArgs),
+    /// Convert a network between file formats, keeping its source and sink
+    Convert(ConvertArgs),
+    /// Generate a synthetic network
+    Generate(GenerateArgs),
+    /// Check that a flow solution is feasible and maximum, exits with status 1 otherwise
+    Verify(VerifyArgs),
+    /// Print the size, degrees, capacities and number of components of networks
+    Stats(StatsArgs),
+    /// Print the edges added, removed or with changed capacities between two networks, exits with status 1 if they
+    /// differ
+    Diff(DiffArgs)
+}
+
+#[derive(Args)]
+struct SolveArgs {
+    /// Input files, or - to read from stdin, several files are solved concurrently
+    #[arg(required_unless_present = "manifest")]
+    files: Vec<String>,
+    /// File listing one input file per line, solved in addition to the input files
+    #[arg(long)]
+    manifest: Option<String>,
+    /// Number of files solved concurrently, defaults to the number of available cores
+    #[arg(long, short, value_parser = clap::value_parser!(u32).range(1..))]
+    jobs: Option<u32>,
+    /// Maximum flow algorithm, --progress, the limits, --trace and --frames need an augmenting path algorithm
+    #[arg(long, value_enum, default_value_t = Algorithm::EdmondsKarp)]
+    algorithm: Algorithm,
+    /// Scan the neighbors of every vertex in sorted order, so the flows do not depend on the order of the input edges
+    #[arg(long)]
+    deterministic: bool,
+    /// How several edges from one vertex to another are treated
+    #[arg(long, value_enum, default_value_t = DuplicateArg::Parallel)]
+    duplicates: DuplicateArg,
+    /// Input format, detected from the file contents when omitted
+    #[arg(long, value_enum)]
+    format: Option<FormatArg>,
+    /// Number of measured runs, more than one run adds a warm up run and runtime statistics
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+    repeat: u32,
+    /// Periodically print the number of augmentations, current flow and estimated progress to stderr
+    #[arg(long)]
+    progress: bool,
+    /// Stop after this many seconds and report the flow found so far
+    #[arg(long, value_parser = parse_seconds)]
+    time_limit: Option<Duration>,
+    /// Stop after this many augmenting paths and report the flow found so far
+    #[arg(long)]
+    max_augmentations: Option<usize>,
+    /// Write the flow of every edge to this file, or - for stdout, only for a single input file
+    #[arg(long)]
+    output: Option<String>,
+    /// Format of the flows written with --output, CSV is used for a .csv file when omitted
+    #[arg(long, value_enum)]
+    output_format: Option<SolutionFormat>,
+    /// Append one row per measured run to this CSV file, writing a header when the file is new
+    #[arg(long)]
+    log_csv: Option<String>,
+    /// Write every augmenting path and its bottleneck as JSON to this file, or - for stdout, only for a single input file
+    #[arg(long)]
+    trace: Option<String>,
+    /// Write a snapshot of the flow after every augmentation to this directory, for animations, only for a single
+    /// input file
+    #[arg(long)]
+    frames: Option<String>,
+    /// Format of the snapshots written with --frames
+    #[arg(long, value_enum, default_value_t = FrameArg::Dot)]
+    frame_format: FrameArg,
+    /// Add the work done by the last run to the result: augmentations, searches, scanned vertexes and arcs, and pushes
+    #[arg(long)]
+    stats: bool
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum)]
+enum FrameArg {
+    /// DOT digraphs with flow/capacity labels and the last augmenting path in red
+    Dot,
+    /// JSON objects with the flow of every edge and the last augmenting path
+    Json
+}
+
+impl From<FrameArg> for FrameFormat {
+    fn from(format: FrameArg) -> FrameFormat {
+        match format {
+            FrameArg::Dot => FrameFormat::Dot,
+            FrameArg::Json => FrameFormat::Json
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum)]
+enum SolutionFormat {
+    /// DIMACS solution lines, `s <flow value>` followed by `f <from> <to> <flow>` per edge
+    Dimacs,
+    /// Rows of from,to,flow,capacity
+    Csv
+}
+
+#[derive(Args)]
+struct ConvertArgs {
+    /// Input file, or - to read from stdin
+    input: String,
+    /// Output file, or - to write to stdout
+    output: String,
+    /// Input format, detected from the file contents when omitted
+    #[arg(long, value_enum)]
+    from: Option<FormatArg>,
+    /// Output format, guessed from the .max, .dot, .json or .csv extension of the output file when omitted
+    #[arg(long, value_enum)]
+    to: Option<FormatArg>
+}
+
+#[derive(Args)]
+struct GenerateArgs {
+    #[command(subcommand)]
+    family: Family,
+    /// Output file, or - to write to stdout
+    #[arg(long, short, default_value = "-", global = true)]
+    output: String,
+    /// Output format
+    #[arg(long, value_enum, default_value_t = FormatArg::Dicaps, global = true)]
+    to: FormatArg,
+    /// Smallest capacity of a generated edge
+    #[arg(long, default_value_t = 1, global = true)]
+    min_capacity: i32,
+    /// Largest capacity of a generated edge
+    #[arg(long, default_value_t = 100, global = true)]
+    max_capacity: i32,
+    /// Seed of the random number generator, the same seed always generates the same network
+    #[arg(long, default_value_t = 0, global = true)]
+    seed: u64
+}
+
+#[derive(Args)]
+struct VerifyArgs {
+    /// Problem file, or - to read from stdin
+    problem: String,
+    /// Solution file in DIMACS solution format, the problem is solved with --algorithm when omitted
+    solution: Option<String>,
+    /// Algorithm used when no solution file is given
+    #[arg(long, value_enum, default_value_t = Algorithm::EdmondsKarp)]
+    algorithm: Algorithm,
+    /// Problem format, detected from the file contents when omitted
+    #[arg(long, value_enum)]
+    format: Option<FormatArg>
+}
+
+#[derive(Args)]
+struct StatsArgs {
+    /// Input files, or - to read from stdin
+    #[arg(required = true)]
+    files: Vec<String>,
+    /// Input format, detected from the file contents when omitted
+    #[arg(long, value_enum)]
+    format: Option<FormatArg>
+}
+
+#[derive(Args)]
+struct DiffArgs {
+    /// Network before, or - to read from stdin
+    before: String,
+    /// Network after, or - to read from stdin
+    after: String,
+    /// Format of both networks, detected from the file contents when omitted
+    #[arg(long, value_enum)]
+    format: Option<FormatArg>
+}
+
+#[derive(Subcommand)]
+enum Family {
+    /// Random edges between vertex 0 (source) and the last vertex (sink)
+    Random {
+        #[arg(long, default_value_t = 100)]
+        vertexes: usize,
+        #[arg(long, default_value_t = 400)]
+        edges: usize
+    },
+    /// Erdős–Rényi network joining each pair of vertexes with the given probability, from vertex 0 to the last vertex
+    ErdosRenyi {
+        #[arg(long, default_value_t = 100)]
+        vertexes: usize,
+        #[arg(long, default_value_t = 0.05)]
+        probability: f64
+    },
+    /// Scale free network by preferential attachment, from the oldest vertex 0 to the newest vertex
+    BarabasiAlbert {
+        #[arg(long, default_value_t = 100)]
+        vertexes: usize,
+        /// Number of earlier vertexes each new vertex is joined to
+        #[arg(long, default_value_t = 2)]
+        attachments: usize,
+        /// Shape of a heavy tailed Pareto distribution of the capacities between the capacity options, uniform
+        /// capacities are drawn when omitted
+        #[arg(long)]
+        pareto_shape: Option<f64>
+    },
+    /// Grid with edges to the right and down, from the top left corner to the bottom right corner
+    Grid {
+        #[arg(long, default_value_t = 10)]
+        rows: usize,
+        #[arg(long, default_value_t = 10)]
+        cols: usize
+    },
+    /// Layers with random edges between consecutive layers
+    Layered {
+        #[arg(long, default_value_t = 10)]
+        layers: usize,
+        #[arg(long, default_value_t = 10)]
+        width: usize,
+        /// Number of edges from each vertex to the next layer
+        #[arg(long, default_value_t = 2)]
+        degree: usize
+    },
+    /// Layered acyclic network with random edges between consecutive layers, no vertex is cut off
+    LayeredDag {
+        #[arg(long, default_value_t = 10)]
+        layers: usize,
+        #[arg(long, default_value_t = 10)]
+        width: usize,
+        /// Probability of an edge between a vertex and a vertex of the next layer
+        #[arg(long, default_value_t = 0.2)]
+        density: f64
+    },
+    /// Network which can need 2 * max capacity augmentations with bad path choices
+    WorstCase {
+        /// Number of cross edges between the two paths
+        #[arg(long, default_value_t = 1)]
+        rungs: usize
+    },
+    /// GENRMF frames of side x side grids joined by random permutations, from the first to the last vertex
+    Genrmf {
+        #[arg(long, default_value_t = 4)]
+        side: usize,
+        #[arg(long, default_value_t = 4)]
+        frames: usize
+    },
+    /// Random level graph of the Washington generator, with three edges from each vertex to the next level
+    WashingtonRlg {
+        #[arg(long, default_value_t = 10)]
+        levels: usize,
+        #[arg(long, default_value_t = 10)]
+        width: usize
+    },
+    /// Bipartite matching instance with unit capacities, the capacity options are ignored
+    Bipartite {
+        #[arg(long, default_value_t = 50)]
+        left: usize,
+        #[arg(long, default_value_t = 50)]
+        right: usize,
+        /// Probability of an edge between a left and a right vertex
+        #[arg(long, default_value_t = 0.1)]
+        density: f64
+    }
+}
+
+#[derive(Copy, Clone, ValueEnum)]
+enum Algorithm {
+    /// Shortest augmenting paths found by breadth first search
+    #[value(alias = "bfs")]
+    EdmondsKarp,
+    /// Augmenting paths found by depth first search
+    #[value(alias = "dfs")]
+    FordFulkerson,
+    /// Blocking flows on level graphs
+    Dinic,
+    /// FIFO push-relabel with global relabeling and the gap heuristic
+    PushRelabel
+}
+
+impl Algorithm {
+    fn name(self) -> &'static str {
+        self.solver().name()
+    }
+
+    fn solver(self) -> Box<dyn MaxFlowSolver> {
+        match self {
+            Algorithm::EdmondsKarp => Box::new(EdmondsKarp),
+            Algorithm::FordFulkerson => Box::new(FordFulkerson),
+            Algorithm::Dinic => Box::new(Dinic),
+            Algorithm::PushRelabel => Box::new(PushRelabel::default())
+        }
+    }
+
+    /// Returns the search of an augmenting path algorithm, which can be observed after every augmentation.
+    fn search(self) -> Option<Search> {
+        match self {
+            Algorithm::EdmondsKarp => Some(BFS),
+            Algorithm::FordFulkerson => Some(DFS),
+            Algorithm::Dinic | Algorithm::PushRelabel => None
+        }
+    }
+}
+
+#[derive(Copy, Clone, ValueEnum)]
+enum DuplicateArg {
+    /// Keep every edge separately
+    Parallel,
+    /// Merge the edges, adding up their capacities
+    Sum,
+    /// Keep the edge with the largest capacity
+    KeepMax,
+    /// Keep the first edge
+    KeepFirst,
+    /// Fail on duplicate edges
+    Error
+}
+
+impl From<DuplicateArg> for DuplicatePolicy {
+    fn from(duplicates: DuplicateArg) -> DuplicatePolicy {
+        match duplicates {
+            DuplicateArg::Parallel => DuplicatePolicy::Parallel,
+            DuplicateArg::Sum => DuplicatePolicy::Sum,
+            DuplicateArg::KeepMax => DuplicatePolicy::KeepMax,
+            DuplicateArg::KeepFirst => DuplicatePolicy::KeepFirst,
+            DuplicateArg::Error => DuplicatePolicy::Error
+        }
+    }
+}
+
+#[derive(Copy, Clone, ValueEnum)]
+enum FormatArg {
+    Dicaps, Txt, Dot, Json, Csv
+}
+
+impl From<FormatArg> for Format {
+    fn from(format: FormatArg) -> Format {
+        match format {
+            FormatArg::Dicaps => Format::Dicaps,
+            FormatArg::Txt => Format::Txt,
+            FormatArg::Dot => Format::Dot,
+            FormatArg::Json => Format::Json,
+            FormatArg::Csv => Format::Csv
+        }
+    }
+}
+
+/// Parses a number of seconds for clap, rejecting negative, infinite and NaN values which are no duration.
+fn parse_seconds(value: &str) -> Result<Duration, String> {
+    let seconds = value.parse::<f64>().map_err(|e| e.to_string())?;
+    Duration::try_from_secs_f64(seconds)
+        .map_err(|_| format!("{} is not a finite, non-negative number of seconds", value))
+}
+
+/// Exits with a clap formatted error message.
+fn fail(kind: ErrorKind, message: String) -> ! {
+    Cli::command().error(kind, message).exit()
+}
+
+/// Reads a flow problem from a file or stdin, detecting the format unless it is given. Fails with a message naming
+/// the input if it can not be read, its format detected or its contents parsed, so that a batch only fails that input.
+fn read_network(file_name: &str,
+                format: Option<FormatArg>) -> Result<(VertexId, VertexId, Graph<FlowEdge>), String> {
+    let format = format.map(Format::from);
+    let parse_error = |e: Error| format!("Could not parse {}: {}", file_name, e);
+    let undetected = || format!("Could not detect the format of {}, pass its format explicitly", file_name);
+    if file_name == "-" {
+        let stdin = io::stdin();
+        let mut stdin = stdin.lock();
+        if let Some(format) = format {
+            return flow_from_reader(stdin, format).map_err(parse_error);
+        }
+        // Stdin can only be read once, so the lines needed to sniff the format are buffered and parsed before the
+        // rest of the input, which is streamed
+        let mut head: Vec<u8> = Vec::new();
+        let mut lines = 0;
+        while lines < 2 {
+            let start = head.len();
+            let n = stdin.read_until(b'\n', &mut head).map_err(|e| format!("Could not read from stdin: {}", e))?;
+            if n == 0 {
+                break;
+            }
+            if !head[start..].iter().all(u8::is_ascii_whitespace) {
+                lines += 1;
+            }
+        }
+        let format = detect_format_reader(head.as_slice()).ok_or_else(undetected)?;
+        flow_from_reader(head.as_slice().chain(stdin), format).map_err(parse_error)
+    } else {
+        let format = match format {
+            Some(format) => format,
+            None => detect_format(file_name)
+                .map_err(|e| format!("Could not read {}: {}", file_name, e))?
+                .ok_or_else(undetected)?
+        };
+        let f = File::open(file_name).map_err(|e| format!("Could not open {}: {}", file_name, e))?;
+        flow_from_reader(BufReader::new(f), format).map_err(parse_error)
+    }
+}
+
+/// Same as `read_network` but exits if the input can not be read or parsed.
+fn load(file_name: &str, format: Option<FormatArg>) -> (VertexId, VertexId, Graph<FlowEdge>) {
+    read_network(file_name, format).unwrap_or_else(|message| fail(ErrorKind::InvalidValue, message))
+}
+
+/// Returns (min, median, mean, sample standard deviation) of the runtimes in seconds.
+fn summarize(runtimes: &mut [f64]) -> (f64, f64, f64, f64) {
+    runtimes.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let n = runtimes.len();
+    let median = if n.is_multiple_of(2) {
+        (runtimes[n / 2 - 1] + runtimes[n / 2]) / 2.0
+    } else {
+        runtimes[n / 2]
+    };
+    let mean = runtimes.iter().sum::<f64>() / n as f64;
+    let variance = if n > 1 {
+        runtimes.iter().map(|r| (r - mean) * (r - mean)).sum::<f64>() / (n - 1) as f64
+    } else {
+        0.0
+    };
+    (runtimes[0], median, mean, variance.sqrt())
+}
+
+/// Returns the flow value of a solver result. A sink which no flow reaches has a maximum flow of zero, any other error
+/// fails with a message naming the vertexes of `graph` by their names, so that only the file being solved fails.
+fn flow_value(result: Result<i64, FlowError>, graph: &Graph<FlowEdge>, file_name: &str) -> Result<i64, String> {
+    match result {
+        Ok(flow) => Ok(flow),
+        Err(FlowError::UnreachableSink {..}) => Ok(0),
+        Err(e) => Err(format!("Could not solve {}: {}", file_name, graph.describe_error(&e)))
+    }
+}
+
+/// Solves a single file as configured by `args`, returning its result line and median runtime, or a message if the
+/// file can not be parsed or solved.
+fn solve_file(args: &SolveArgs,
+              file_name: &str,
+              log: Option<&Mutex<(String, File)>>) -> Result<(String, f64), String> {
+    let (source, sink, g) = read_network(file_name, args.format)?;
+    let mut g = g.merge_duplicates(args.duplicates.into())
+        .map_err(|e| format!("Could not load {}: {}", file_name, e))?;
+    g.set_deterministic(args.deterministic);
+    let solver = args.algorithm.solver();
+    let search = args.algorithm.search();
+    let repeat = args.repeat as usize;
+    if repeat > 1 {
+        // Warm up caches and the allocator before any measured run
+        flow_value(g.clone().max_flow_with(&*solver, source, sink).map(|(flow, _)| flow), &g, file_name)?;
+    }
+    let mut total_flow = 0;
+    let mut runtimes: Vec<f64> = Vec::with_capacity(repeat);
+    let mut solved = None;
+    let mut stats = SolveStats::default();
+    let mut limit_reached = false;
+    let time_limit = args.time_limit;
+    for _ in 0..repeat {
+        // Every run starts from a fresh copy so the flows of the previous run are discarded
+        let mut run_graph = g.clone();
+        let start_time = Instant::now();
+        let result = match search {
+            Some(search) if args.progress || time_limit.is_some() || args.max_augmentations.is_some() => {
+                let mut last_report = start_time;
+                run_graph.max_flow_observed_with_stats(source, sink, search, |p| {
+                    let elapsed = start_time.elapsed();
+                    // Progress goes to stderr so the result line on stdout stays machine readable
+                    if args.progress && last_report.elapsed() >= Duration::from_secs(1) {
+                        last_report = Instant::now();
+                        eprintln!("Augmentations:{}\tFlow:{}\tUpperBound:{}\tProgress:{:.1}%\tElapsed:{:.1}s",
+                            p.augmentations, p.flow, p.upper_bound, 100.0 * p.progress(), elapsed.as_secs_f64());
+                    }
+                    limit_reached = time_limit.is_some_and(|t| elapsed >= t) ||
+                        args.max_augmentations.is_some_and(|m| p.augmentations >= m);
+                    !limit_reached
+                })
+            },
+            _ => run_graph.max_flow_with(&*solver, source, sink)
+        };
+        let runtime = start_time.elapsed().as_secs_f64();
+        runtimes.push(runtime);
+        total_flow = flow_value(result.map(|(flow, run_stats)| {
+            stats = run_stats;
+            flow
+        }), &g, file_name)?;
+        // A limit hit by the last augmentation still leaves a maximum flow, which is checked outside of the runtime
+        limit_reached = limit_reached && run_graph.augmenting_path(source, sink, BFS).is_some();
+        if let Some(log) = log {
+            let (ref log_name, ref mut writer) = *log.lock().unwrap();
+            writeln!(writer, "{},{},{},{},{},{},{}", file_name, args.algorithm.name(), g.n_vertexes(), g.n_edges(),
+                     total_flow, runtime, stats.augmenting_paths)
+                .unwrap_or_else(|e| fail(ErrorKind::Io, format!("Could not write {}: {}", log_name, e)));
+        }
+        solved = Some(run_graph);
+    }
+    if let (Some(output), Some(solved)) = (args.output.as_ref(), solved) {
+        let csv = args.output_format.map_or(output.ends_with(".csv"), |f| f == SolutionFormat::Csv);
+        write_to(output, |mut writer| if csv {
+            write_solution_csv(&mut writer, &solved)
+        } else {
+            write_solution(&mut writer, &solved, source)
+        });
+    }
+    if let (Some(search), true) = (search, args.trace.is_some() || args.frames.is_some()) {
+        // Traced separately so that recording the paths does not slow down the measured runs
+        let (_, trace) = g.clone().max_flow_traced(source, sink, search)
+            .unwrap_or_else(|_| (0, Trace {source, sink, search, steps: Vec::new()}));
+        if let Some(ref trace_file) = args.trace {
+            write_to(trace_file, |mut writer| trace.write_json(&mut writer));
+        }
+        if let Some(ref directory) = args.frames {
+            trace.write_frames(&g, directory, args.frame_format.into())
+                .unwrap_or_else(|e| fail(ErrorKind::Io, format!("Could not write frames to {}: {}", directory, e)));
+        }
+    }
+    let (min, median, mean, stddev) = summarize(&mut runtimes);
+    let algorithm = args.algorithm.name();
+    let mut line = format!("Algorithm:{}\tVertexes:{}\tEdges:{}\tFlow:{}\tRuntime:{}s",
+        algorithm, g.n_vertexes(), g.n_edges(), total_flow, median);
+    if repeat > 1 {
+        line.push_str(&format!("\tRuns:{}\tMin:{}s\tMean:{}s\tStddev:{}s", repeat, min, mean, stddev));
+    }
+    if time_limit.is_some() || args.max_augmentations.is_some() {
+        line.push_str(if limit_reached { "\tStatus:limit-reached" } else { "\tStatus:optimal" });
+    }
+    if args.stats {
+        line.push_str(&format!("\t{}", stats));
+    }
+    Ok((line, median))
+}
+
+fn solve(args: &SolveArgs) {
+    let mut files = args.files.clone();
+    if let Some(ref manifest) = args.manifest {
+        let contents = fs::read_to_string(manifest)
+            .unwrap_or_else(|e| fail(ErrorKind::Io, format!("Could not read {}: {}", manifest, e)));
+        files.extend(contents.lines().map(str::trim).filter(|l| !l.is_empty() && !l.starts_with('#')).map(String::from));
+    }
+    if args.algorithm.search().is_none() && (args.progress || args.time_limit.is_some() ||
+                                             args.max_augmentations.is_some() || args.trace.is_some() ||
+                                             args.frames.is_some()) {
+        fail(ErrorKind::ArgumentConflict, format!("--progress, --time-limit, --max-augmentations, --trace and --frames \
+                                                   need an augmenting path algorithm, not {}", args.algorithm.name()));
+    }
+    let log = args.log_csv.as_ref().map(|file_name| Mutex::new(open_log(file_name)));
+    if files.len() == 1 {
+        let (line, _) = solve_file(args, &files[0], log.as_ref())
+            .unwrap_or_else(|message| fail(ErrorKind::InvalidValue, message));
+        println!("{}", line);
+        return;
+    }
+    if args.output.is_some() || args.trace.is_some() || args.frames.is_some() {
+        fail(ErrorKind::ArgumentConflict,
+             "--output, --trace and --frames can only be used with a single input file".to_string());
+    }
+    if files.iter().filter(|f| *f == "-").count() > 1 {
+        fail(ErrorKind::ArgumentConflict, "Stdin can only be read once".to_string());
+    }
+    // Check every file up front so a typo does not abort a batch which has been running for hours
+    for file_name in files.iter().filter(|f| *f != "-") {
+        if !Path::new(file_name).is_file() {
+            fail(ErrorKind::Io, format!("Input file does not exist: {}", file_name));
+        }
+    }
+    let start_time = Instant::now();
+    let results = solve_batch(args, &files, log.as_ref());
+    let solved = results.iter().flatten().count();
+    println!("Files:{}\tSolved:{}\tFailed:{}\tTotalRuntime:{}s\tWallTime:{}s", files.len(), solved,
+             files.len() - solved, results.iter().flatten().sum::<f64>(), start_time.elapsed().as_secs_f64());
+    if solved < files.len() {
+        process::exit(1);
+    }
+}
+
+/// Solves `files` on `--jobs` threads, printing the result line of every file as it is solved. Returns the median
+/// runtime of every file, or `None` for a file which failed.
+fn solve_batch(args: &SolveArgs, files: &[String], log: Option<&Mutex<(String, File)>>) -> Vec<Option<f64>> {
+    let jobs = args.jobs.map_or_else(|| thread::available_parallelism().map_or(1, |n| n.get()), |j| j as usize);
+    let next = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<f64>>> = Mutex::new(vec![None; files.len()]);
+    thread::scope(|scope| {
+        for _ in 0..jobs.min(files.len()) {
+            scope.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::SeqCst);
+                if i >= files.len() {
+                    break;
+                }
+                // A malformed or unsolvable file only fails that file
+                match solve_file(args, &files[i], log) {
+                    Ok((line, runtime)) => {
+                        println!("File:{}\t{}", files[i], line);
+                        results.lock().unwrap()[i] = Some(runtime);
+                    },
+                    Err(message) => {
+                        eprintln!("{}", message);
+                        println!("File:{}\tStatus:failed", files[i]);
+                    }
+                }
+            });
+        }
+    });
+    results.into_inner().unwrap()
+}
+
+/// Opens a CSV benchmark log for appending, writing the header first when the file is new or empty.
+fn open_log(file_name: &str) -> (String, File) {
+    let mut file = OpenOptions::new().create(true).append(true).open(file_name)
+        .unwrap_or_else(|e| fail(ErrorKind::Io, format!("Could not open {}: {}", file_name, e)));
+    let is_empty = file.metadata().map(|m| m.len() == 0).unwrap_or(false);
+    if is_empty {
+        writeln!(file, "file,algorithm,vertexes,edges,flow,runtime,augmentations")
+            .unwrap_or_else(|e| fail(ErrorKind::Io, format!("Could not write {}: {}", file_name, e)));
+    }
+    (file_name.to_string(), file)
+}
+
+/// Runs `write` on a buffered writer for a file or stdout.
+fn write_to<F>(file_name: &str, write: F) where F: FnOnce(&mut dyn Write) -> io::Result<()> {
+    let result = if file_name == "-" {
+        let stdout = io::stdout();
+        let mut writer = BufWriter::new(stdout.lock());
+        write(&mut writer).and_then(|_| writer.flush())
+    } else {
+        File::create(file_name).and_then(|f| {
+            let mut writer = BufWriter::new(f);
+            write(&mut writer).and_then(|_| writer.flush())
+        })
+    };
+    result.unwrap_or_else(|e| fail(ErrorKind::Io, format!("Could not write {}: {}", file_name, e)));
+}
+
+/// Writes a flow problem to a file or stdout.
+fn save(file_name: &str, format: Format, g: &Graph<FlowEdge>, source: VertexId, sink: VertexId) {
+    write_to(file_name, |mut writer| write_flow(&mut writer, format, g, source, sink));
+}
+
+fn convert(args: &ConvertArgs) {
+    let (source, sink, g) = load(&args.input, args.from);
+    let extension = Path::new(&args.output).extension().and_then(|e| e.to_str());
+    let format = args.to.map(Format::from).or(match extension {
+        Some("max") => Some(Format::Dicaps),
+        Some("dot") => Some(Format::Dot),
+        Some("json") => Some(Format::Json),
+        Some("csv") => Some(Format::Csv),
         _ => None
-    }.expect("Expected 'bfs' or 'dfs'");
-    let file_type = args[2].as_str();
-    let file_name = &args[3];
-    let parsed_opt = match file_type {
-        "dicaps" => {
-            Some(flow_from_dicaps(&file_name))
+    }).unwrap_or_else(|| fail(ErrorKind::MissingRequiredArgument,
+                              format!("Could not guess the format of {}, pass --to", args.output)));
+    save(&args.output, format, &g, source, sink);
+}
+
+fn generate(args: &GenerateArgs) {
+    if args.min_capacity < 1 || args.min_capacity > args.max_capacity {
+        fail(ErrorKind::InvalidValue, "Capacities must satisfy 1 <= --min-capacity <= --max-capacity".to_string());
+    }
+    let capacity = args.min_capacity..=args.max_capacity;
+    let (source, sink, g) = match args.family {
+        Family::Random {vertexes, edges} => {
+            if vertexes < 2 || edges > vertexes * (vertexes - 1) / 2 {
+                fail(ErrorKind::InvalidValue, format!("Can not place {} edges between {} vertexes", edges, vertexes));
+            }
+            generators::random_network(vertexes, edges, capacity, args.seed)
+        },
+        Family::ErdosRenyi {vertexes, probability} => {
+            if vertexes < 2 || !(0.0..=1.0).contains(&probability) {
+                fail(ErrorKind::InvalidValue, "Needs at least two vertexes and a probability in [0, 1]".to_string());
+            }
+            generators::erdos_renyi_network(vertexes, probability, capacity, args.seed)
         },
-        "txt" => {
-            Some(flow_from_txt(&file_name))
+        Family::BarabasiAlbert {vertexes, attachments, pareto_shape} => {
+            if attachments < 1 || vertexes <= attachments || pareto_shape.is_some_and(|shape| shape <= 0.0) {
+                fail(ErrorKind::InvalidValue,
+                     "Needs at least one attachment, more vertexes than attachments and a positive shape".to_string());
+            }
+            let capacity = match pareto_shape {
+                Some(shape) => generators::CapacityDistribution::Pareto {range: capacity, shape},
+                None => generators::CapacityDistribution::Uniform(capacity)
+            };
+            generators::barabasi_albert_network(vertexes, attachments, &capacity, args.seed)
         },
-        _ => {
-            None
+        Family::Grid {rows, cols} => {
+            if rows * cols < 2 {
+                fail(ErrorKind::InvalidValue, "A grid needs at least two vertexes".to_string());
+            }
+            generators::grid_network(rows, cols, capacity, args.seed)
+        },
+        Family::Layered {layers, width, degree} => {
+            if layers < 1 || width < 1 || degree > width {
+                fail(ErrorKind::InvalidValue, "Layers need at least one vertex and --degree at most --width".to_string());
+            }
+            generators::layered_network(layers, width, degree, capacity, args.seed)
+        },
+        Family::LayeredDag {layers, width, density} => {
+            if layers < 1 || width < 1 || !(0.0..=1.0).contains(&density) {
+                fail(ErrorKind::InvalidValue, "Layers need at least one vertex and a density in [0, 1]".to_string());
+            }
+            generators::layered_dag(layers, width, density, capacity, args.seed)
+        },
+        Family::WorstCase {rungs} => {
+            if rungs < 1 {
+                fail(ErrorKind::InvalidValue, "The network needs at least one rung".to_string());
+            }
+            generators::zigzag_network(args.max_capacity, rungs)
+        },
+        Family::Genrmf {side, frames} => {
+            if side < 1 || frames < 1 || side * side * frames < 2 {
+                fail(ErrorKind::InvalidValue, "GENRMF needs at least two vertexes".to_string());
+            }
+            generators::genrmf_network(side, frames, capacity, args.seed)
+        },
+        Family::WashingtonRlg {levels, width} => {
+            if levels < 1 || width < 1 {
+                fail(ErrorKind::InvalidValue, "Levels need at least one vertex".to_string());
+            }
+            generators::washington_rlg_network(levels, width, capacity, args.seed)
+        },
+        Family::Bipartite {left, right, density} => {
+            if !(0.0..=1.0).contains(&density) {
+                fail(ErrorKind::InvalidValue, "The density must be between 0 and 1".to_string());
+            }
+            generators::random_bipartite(left, right, density, args.seed)
+        }
+    };
+    save(&args.output, args.to.into(), &g, source, sink);
+}
+
+fn verify(args: &VerifyArgs) {
+    let (source, sink, mut g) = load(&args.problem, args.format);
+    let violations = match args.solution {
+        Some(ref file_name) => {
+            let f = File::open(file_name)
+                .unwrap_or_else(|_| fail(ErrorKind::Io, format!("Solution file does not exist: {}", file_name)));
+            let solution = read_solution(BufReader::new(f))
+                .unwrap_or_else(|e| fail(ErrorKind::InvalidValue, format!("Could not parse {}: {}", file_name, e)));
+            verify_solution(&mut g, source, sink, &solution)
+        },
+        None => {
+            match g.max_flow_with(&*args.algorithm.solver(), source, sink) {
+                Ok(_) | Err(FlowError::UnreachableSink {..}) => {},
+                Err(e) => fail(ErrorKind::InvalidValue, format!("Could not solve {}: {}", args.problem, e))
+            }
+            verify_flow(&g, source, sink)
         }
     };
-    let parsed = parsed_opt.expect("Expected either \"dicaps\" or \"txt\"");
-    let source = parsed.0;
-    let sink = parsed.1;
-    let mut g = parsed.2;
-    let start_time = time::get_time();
-    let total_flow = g.max_flow(source, sink, search);
-    let end_time = time::get_time();
-    let diff = end_time - start_time;
-    println!("Algorithm:{}\tVertexes:{}\tEdges:{}\tFlow:{}\tRuntime:{}s",
-        search_str, g.n_vertexes(), g.n_edges(), total_flow, diff.num_milliseconds() as f64 / 1000.0);
+    if violations.is_empty() {
+        println!("Valid maximum flow");
+    } else {
+        for violation in &violations {
+            println!("Violation: {}", violation);
+        }
+        process::exit(1);
+    }
+}
+
+fn stats(args: &StatsArgs) {
+    for file_name in &args.files {
+        let (source, sink, g) = load(file_name, args.format);
+        println!("File:{}\tSource:{}\tSink:{}\t{}", file_name, source, sink, g.stats());
+    }
+}
+
+fn diff(args: &DiffArgs) {
+    if args.before == "-" && args.after == "-" {
+        fail(ErrorKind::ArgumentConflict, "Only one of the networks can be read from stdin".to_string());
+    }
+    let (before_source, before_sink, before) = load(&args.before, args.format);
+    let (after_source, after_sink, after) = load(&args.after, args.format);
+    let diff = before.diff(&after);
+    if before_source != after_source {
+        println!("Source: {} -> {}", before_source, after_source);
+    }
+    if before_sink != after_sink {
+        println!("Sink: {} -> {}", before_sink, after_sink);
+    }
+    print!("{}", diff);
+    if !diff.is_empty() || (before_source, before_sink) != (after_source, after_sink) {
+        process::exit(1);
+    }
+}
+
+fn main() {
+    let cli = Cli::parse();
+    match cli.command {
+        Command::Solve(ref args) => solve(args),
+        Command::Convert(ref args) => convert(args),
+        Command::Generate(ref args) => generate(args),
+        Command::Verify(ref args) => verify(args),
+        Command::Stats(ref args) => stats(args),
+        Command::Diff(ref args) => diff(args)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn solve_args(args: &[&str]) -> SolveArgs {
+        match Cli::try_parse_from(["max_flow", "solve"].iter().chain(args)).unwrap() {
+            Cli {command: Command::Solve(args)} => args,
+            _ => panic!("Expected a solve command")
+        }
+    }
+
+    #[test]
+    fn test_time_limit() {
+        let time_limit = |value: &str| {
+            let arg = format!("--time-limit={}", value);
+            match Cli::try_parse_from(["max_flow", "solve", arg.as_str(), "a.max"]) {
+                Ok(Cli {command: Command::Solve(args)}) => Ok(args.time_limit),
+                Ok(_) => panic!("Expected a solve command"),
+                Err(e) => Err(e.kind())
+            }
+        };
+        assert_eq!(time_limit("1.5"), Ok(Some(Duration::from_millis(1500))));
+        assert_eq!(time_limit("0"), Ok(Some(Duration::ZERO)));
+        for value in &["inf", "nan", "-1", "1e400", "soon"] {
+            assert_eq!(time_limit(value), Err(ErrorKind::ValueValidation), "{}", value);
+        }
+    }
+
+    #[test]
+    fn test_solve_batch() {
+        // Malformed and undetectable files fail on their own, the other files of the batch are still solved
+        let files = ["data/dicaps/central.txt", "data/dicaps/malformed.txt", "README.md", "data/txt/test_1.txt"];
+        let args = solve_args(&[&["--jobs", "2"][..], &files].concat());
+        let files = files.iter().map(|f| f.to_string()).collect::<Vec<_>>();
+        let solved = solve_batch(&args, &files, None).iter().map(Option::is_some).collect::<Vec<_>>();
+        assert_eq!(solved, [true, false, false, true]);
+        assert!(read_network("README.md", None).unwrap_err().starts_with("Could not detect the format of README.md"));
+        assert!(read_network("data/missing.txt", Some(FormatArg::Dicaps)).is_err());
+    }
+
+    #[test]
+    fn test_solve_stats() {
+        let file_name = "data/dicaps/central.txt";
+        let (line, _) = solve_file(&solve_args(&[file_name]), file_name, None).unwrap();
+        assert!(!line.contains("Augmentations:"), "{}", line);
+        let (line, _) = solve_file(&solve_args(&["--stats", file_name]), file_name, None).unwrap();
+        let fields = line.split('\t').filter_map(|f| f.split_once(':')).collect::<HashMap<_, _>>();
+        assert_eq!(fields["Flow"], "5");
+        let augmentations = fields["Augmentations"].parse::<u64>().unwrap();
+        assert_eq!(fields["Phases"].parse::<u64>().unwrap(), augmentations + 1);
+        assert!(fields["Pushes"].parse::<u64>().unwrap() >= augmentations);
+        assert_eq!(fields["Relabels"], "0");
+        // Preflow solvers count their own operations
+        let (line, _) = solve_file(&solve_args(&["--stats", "--algorithm", "push-relabel", file_name]), file_name,
+                                   None).unwrap();
+        let fields = line.split('\t').filter_map(|f| f.split_once(':')).collect::<HashMap<_, _>>();
+        assert_eq!((fields["Flow"], fields["Augmentations"]), ("5", "0"));
+        assert!(fields["SaturatingPushes"].parse::<u64>().unwrap() > 0);
+    }
 }
@@ -0,0 +1,116 @@
+use alloc::vec::Vec;
+use super::{ArcId, FlowEdge, FlowError, Graph, VertexId};
+
+/// Bounds on the maximum flow value found by `Graph::flow_bounds`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct FlowBounds {
+    /// Value of a flow found greedily, the maximum flow is at least this.
+    pub lower: i64,
+    /// Smallest capacity of the trivial cuts, the maximum flow is at most this.
+    pub upper: i64
+}
+
+impl FlowBounds {
+    /// Whether the bounds meet, which proves the lower bound to be the maximum flow.
+    pub fn is_exact(&self) -> bool {
+        self.lower == self.upper
+    }
+}
+
+impl Graph<FlowEdge> {
+    /// Bounds the maximum flow value from `source` to `sink` without solving, for triaging many instances. The lower
+    /// bound is the value of a flow pushed greedily along edges, never along reverse arcs, by a single depth first pass
+    /// which skips an edge for good once it is saturated or leads onto the path or to a dead end. The upper bound is
+    /// the smaller of the capacity leaving the source and the capacity entering the sink. Flow stored in the graph is
+    /// ignored and the graph is left unchanged. Fails if the terminals are invalid or the graph has no residual arcs.
+    pub fn flow_bounds(&self, source: VertexId, sink: VertexId) -> Result<FlowBounds, FlowError> {
+        self.check_terminals(source, sink)?;
+        if !self.has_residual_arcs() {
+            return Err(FlowError::MissingResidualArcs);
+        }
+        let capacity = |a: ArcId| self.arcs[a].capacity.max(0) as i64;
+        let leaving = self.out_arcs(source).filter(|a| a.is_multiple_of(2)).map(capacity).sum::<i64>();
+        let entering = self.out_arcs(sink).filter(|a| !a.is_multiple_of(2)).map(|a| capacity(a ^ 1)).sum::<i64>();
+        let bounds = FlowBounds {lower: self.greedy_flow(source, sink), upper: leaving.min(entering)};
+        debug!("Flow from {} to {} is between {} and {}", source, sink, bounds.lower, bounds.upper);
+        Ok(bounds)
+    }
+
+    /// Pushes flow along edges by a depth first pass with a current arc per vertex, see `flow_bounds`, and returns its
+    /// value.
+    fn greedy_flow(&self, source: VertexId, sink: VertexId) -> i64 {
+        let out_edges = self.vertexes()
+            .map(|u| self.out_arcs(u).filter(|a| a.is_multiple_of(2)).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        let mut remaining = self.arcs.iter().map(|arc| arc.capacity.max(0)).collect::<Vec<_>>();
+        let mut current = vec![0; self.n_vertexes()];
+        let mut blocked = vec![false; self.n_vertexes()];
+        let mut path: Vec<ArcId> = Vec::new();
+        let mut value = 0;
+        let mut u = source;
+        blocked[source] = true;
+        loop {
+            if u == sink {
+                let bottleneck = path.iter().map(|&a| remaining[a]).min().unwrap_or(0);
+                path.iter().for_each(|&a| remaining[a] -= bottleneck);
+                value += bottleneck as i64;
+                // The vertexes of the path are open again, only its saturated edges are given up
+                for &a in &path {
+                    blocked[self.head(a)] = false;
+                }
+                path.clear();
+                u = source;
+                continue;
+            }
+            match out_edges[u].get(current[u]) {
+                Some(&a) if remaining[a] > 0 && !blocked[self.head(a)] => {
+                    // Vertexes on the path are blocked, so the path stays simple
+                    path.push(a);
+                    u = self.head(a);
+                    blocked[u] = true;
+                },
+                Some(_) => current[u] += 1,
+                None => {
+                    // A dead end stays blocked, no later path passes through it
+                    match path.pop() {
+                        Some(a) => {
+                            u = self.tail(a);
+                            current[u] += 1;
+                        },
+                        None => break
+                    }
+                }
+            }
+        }
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use generators::random_network;
+    use {FlowGraph, BFS};
+
+    #[test]
+    fn test_flow_bounds() {
+        for seed in 0..20 {
+            let (source, sink, g) = random_network(20, 80, 1..=20, seed);
+            let bounds = g.flow_bounds(source, sink).unwrap();
+            let flow = g.clone().max_flow(source, sink, BFS).unwrap_or(0);
+            assert!(bounds.lower <= flow && flow <= bounds.upper, "seed {}: {:?} and {}", seed, bounds, flow);
+        }
+
+        // A greedy pass through the middle edge blocks both other paths
+        let vertexes = (0..4).map(VertexId).collect::<Vec<_>>();
+        let edges = [(0, 1, 1), (1, 2, 1), (2, 3, 1), (0, 2, 1), (1, 3, 1)]
+            .iter()
+            .map(|&(u, v, capacity)| (VertexId(u), VertexId(v), FlowEdge{capacity, flow: 0}))
+            .collect::<Vec<_>>();
+        let g = Graph::flow_network(&vertexes, &edges);
+        let bounds = g.flow_bounds(VertexId(0), VertexId(3)).unwrap();
+        assert_eq!(bounds, FlowBounds {lower: 1, upper: 2});
+        assert!(!bounds.is_exact());
+        assert_eq!(g.flow_bounds(VertexId(0), VertexId(0)), Err(FlowError::SourceIsSink {vertex: VertexId(0)}));
+    }
+}
@@ -0,0 +1,139 @@
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use super::{ArcId, Error, FlowEdge, FlowError, Graph, VertexId};
+
+/// Flow found by `Graph::max_flow_within_budget`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct BudgetedFlow {
+    /// Value of the flow left in the graph.
+    pub value: i64,
+    /// Total cost of the flow, the sum over the edges of their flow times their cost, at most the budget.
+    pub cost: i64
+}
+
+impl Graph<FlowEdge> {
+    /// Computes the largest flow from `source` to `sink` whose total cost stays within `budget`, where `costs` gives
+    /// the cost per unit of flow of every edge in the order of `edges`. Starting from no flow, flow is pushed along
+    /// cheapest augmenting paths, whose reverse arcs refund the cost of their edges, so the cost of each flow value is
+    /// the minimum cost of that value. Path costs never decrease, so the last path only gets as many units as the
+    /// remaining budget pays for. The flow left in the graph is a minimum cost flow of the returned value. Fails if the
+    /// terminals are invalid, the sink can not be reached, there is not one non-negative cost per edge, or the cost
+    /// overflows.
+    pub fn max_flow_within_budget(&mut self, source: VertexId, sink: VertexId, costs: &[i64], budget: i64)
+                                  -> Result<BudgetedFlow, Error> {
+        self.check_terminals(source, sink)?;
+        if !self.has_residual_arcs() {
+            return Err(Error::Infeasible(FlowError::MissingResidualArcs));
+        }
+        if costs.len() != self.n_edges() {
+            return Err(Error::Construction(format!("Expected {} edge costs, got {}", self.n_edges(), costs.len())));
+        }
+        if let Some(e) = costs.iter().position(|&cost| cost < 0) {
+            return Err(Error::Construction(format!("Edge {} has negative cost {}", e, costs[e])));
+        }
+        if budget < 0 {
+            return Err(Error::Construction(format!("Budget {} is negative", budget)));
+        }
+        self.reset_flow();
+        let arc_cost = |a: ArcId| if a.is_multiple_of(2) { costs[a / 2] } else { -costs[a / 2] };
+        let mut result = BudgetedFlow {value: 0, cost: 0};
+        let mut reached = false;
+        loop {
+            // Bellman-Ford, since reverse arcs have negative costs, with no negative cycles as the flow has minimum cost
+            let mut distances: Vec<Option<i64>> = vec![None; self.n_vertexes()];
+            let mut parent_arcs: Vec<Option<ArcId>> = vec![None; self.n_vertexes()];
+            distances[source] = Some(0);
+            let mut updated = true;
+            for _ in 0..self.n_vertexes() {
+                if !updated {
+                    break;
+                }
+                updated = false;
+                for u in self.vertexes() {
+                    let distance = match distances[u] {
+                        Some(distance) => distance,
+                        None => continue
+                    };
+                    for a in self.out_arcs(u) {
+                        let v = self.head(a);
+                        let through = distance + arc_cost(a);
+                        if self.arcs[a].residual() > 0 && distances[v].is_none_or(|d| through < d) {
+                            distances[v] = Some(through);
+                            parent_arcs[v] = Some(a);
+                            updated = true;
+                        }
+                    }
+                }
+            }
+            let path_cost = match distances[sink] {
+                Some(cost) => cost,
+                None => break
+            };
+            reached = true;
+            let mut path: Vec<ArcId> = Vec::new();
+            let mut v = sink;
+            while let Some(a) = parent_arcs[v] {
+                path.push(a);
+                v = self.head(a ^ 1);
+            }
+            let bottleneck = path.iter().map(|&a| self.arcs[a].residual()).min().unwrap_or(0) as i64;
+            let units = if path_cost == 0 { bottleneck } else { bottleneck.min((budget - result.cost) / path_cost) };
+            if units == 0 {
+                break;
+            }
+            for &a in &path {
+                self.arcs[a].flow += units as i32;
+                self.arcs[a ^ 1].flow -= units as i32;
+            }
+            result.value += units;
+            result.cost = units.checked_mul(path_cost)
+                .and_then(|cost| cost.checked_add(result.cost))
+                .ok_or_else(|| Error::Overflow("Cost of the flow overflows".to_string()))?;
+            trace!("Budgeted augmentation of {} units at cost {} per unit", units, path_cost);
+        }
+        if !reached {
+            return Err(Error::Infeasible(FlowError::UnreachableSink {source, sink}));
+        }
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use {FlowGraph, BFS};
+
+    fn network() -> Graph<FlowEdge> {
+        // A cheap narrow route through 1 and an expensive wide route through 2
+        let vertexes = (0..4).map(VertexId).collect::<Vec<_>>();
+        let edges = [(0, 1, 2), (1, 3, 2), (0, 2, 5), (2, 3, 5), (1, 2, 1)]
+            .iter()
+            .map(|&(u, v, capacity)| (VertexId(u), VertexId(v), FlowEdge{capacity, flow: 0}))
+            .collect::<Vec<_>>();
+        Graph::flow_network(&vertexes, &edges)
+    }
+
+    #[test]
+    fn test_max_flow_within_budget() {
+        let costs = [1, 1, 3, 3, 0];
+        let (source, sink) = (VertexId(0), VertexId(3));
+        let mut g = network();
+        assert_eq!(g.max_flow_within_budget(source, sink, &costs, 0), Ok(BudgetedFlow {value: 0, cost: 0}));
+        assert_eq!(g.max_flow_within_budget(source, sink, &costs, 5), Ok(BudgetedFlow {value: 2, cost: 4}));
+        assert_eq!(g.max_flow_within_budget(source, sink, &costs, 17), Ok(BudgetedFlow {value: 4, cost: 16}));
+        assert!(g.conserves_flow(source, sink));
+
+        // A large budget reaches the maximum flow
+        let result = g.max_flow_within_budget(source, sink, &costs, 1000).unwrap();
+        assert_eq!(result, BudgetedFlow {value: 7, cost: 34});
+        assert_eq!(Ok(result.value), network().max_flow(source, sink, BFS));
+
+        // Free edges are not limited by the budget
+        assert_eq!(g.max_flow_within_budget(source, sink, &[0; 5], 0), Ok(BudgetedFlow {value: 7, cost: 0}));
+
+        assert!(matches!(g.max_flow_within_budget(source, sink, &costs[..4], 10), Err(Error::Construction(_))));
+        assert!(matches!(g.max_flow_within_budget(source, sink, &[1, 1, -1, 1, 0], 10), Err(Error::Construction(_))));
+        assert_eq!(g.max_flow_within_budget(sink, source, &costs, 10),
+                   Err(Error::Infeasible(FlowError::UnreachableSink {source: sink, sink: source})));
+    }
+}
@@ -0,0 +1,106 @@
+use alloc::sync::Arc;
+use core::sync::atomic::{AtomicBool, Ordering};
+use super::{FlowEdge, FlowError, FlowGraph, Graph, Search, SolveStats, VertexId};
+
+/// Flag with which another thread, such as the user interface of an interactive application, asks a running solve to
+/// stop. Clones share the flag, so a clone is handed to the solve and the original is kept to cancel it.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>
+}
+
+impl CancellationToken {
+    pub fn new() -> CancellationToken {
+        CancellationToken::default()
+    }
+
+    /// Makes a token of a flag which is already shared with other code.
+    pub fn from_flag(flag: Arc<AtomicBool>) -> CancellationToken {
+        CancellationToken {cancelled: flag}
+    }
+
+    /// Asks the solves polling this token, or any of its clones, to stop.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+/// Whether a cancellable solve ran to the end.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SolveStatus {
+    /// The flow is maximum.
+    Completed,
+    /// The solve was cancelled, the flow is feasible but may not be maximum.
+    Cancelled
+}
+
+/// Flow found by `Graph::max_flow_cancellable`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct CancellableFlow {
+    /// Value of the flow left in the graph.
+    pub flow: i64,
+    pub status: SolveStatus,
+    pub stats: SolveStats
+}
+
+impl Graph<FlowEdge> {
+    /// Same as `FlowGraph::max_flow` but polls `token` after every augmentation and stops once it is cancelled, leaving
+    /// the feasible flow found so far in the graph with the status `Cancelled`. Solving again continues from that flow.
+    /// At least one augmenting path is searched, so a token cancelled before the call still lets the first
+    /// augmentation through. Fails like `max_flow`.
+    pub fn max_flow_cancellable(&mut self,
+                                source: VertexId,
+                                sink: VertexId,
+                                search: Search,
+                                token: &CancellationToken) -> Result<CancellableFlow, FlowError> {
+        let mut cancelled = false;
+        let (flow, stats) = self.max_flow_observed_with_stats(source, sink, search, |_| {
+            cancelled = token.is_cancelled();
+            !cancelled
+        })?;
+        // A cancel which arrives with the last augmentation still leaves a maximum flow
+        let status = if cancelled && self.augmenting_path(source, sink, search).is_some() {
+            SolveStatus::Cancelled
+        } else {
+            SolveStatus::Completed
+        };
+        debug!("Cancellable max flow {} from {} to {} ended {:?}", flow, source, sink, status);
+        Ok(CancellableFlow {flow, status, stats})
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use generators::random_network;
+    use solver::{Dinic, MaxFlowSolver};
+    use verify::{verify_flow, Violation};
+    use BFS;
+
+    #[test]
+    fn test_max_flow_cancellable() {
+        let (source, sink, g) = random_network(30, 150, 1..=20, 1);
+        let expected = g.clone().max_flow(source, sink, BFS).unwrap();
+        let token = CancellationToken::new();
+        let result = g.clone().max_flow_cancellable(source, sink, BFS, &token).unwrap();
+        assert_eq!((result.flow, result.status), (expected, SolveStatus::Completed));
+
+        token.clone().cancel();
+        assert!(token.is_cancelled());
+        let mut cancelled = g.clone();
+        let result = cancelled.max_flow_cancellable(source, sink, BFS, &token).unwrap();
+        assert_eq!((result.stats.augmenting_paths, result.status), (1, SolveStatus::Cancelled));
+        assert!(verify_flow(&cancelled, source, sink).iter().all(|v| matches!(v, Violation::NotMaximum {..})));
+        let resumed = cancelled.max_flow_cancellable(source, sink, BFS, &CancellationToken::new()).unwrap();
+        assert_eq!(resumed.flow, expected);
+
+        let (result, status) = Dinic.solve_cancellable(&g, source, sink, &token).unwrap();
+        assert_eq!((result.value, status), (0, SolveStatus::Cancelled));
+        let (result, status) = Dinic.solve_cancellable(&g, source, sink, &CancellationToken::new()).unwrap();
+        assert_eq!((result.value, status), (Dinic.solve(&g, source, sink).unwrap().value, SolveStatus::Completed));
+    }
+}
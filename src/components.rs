@@ -0,0 +1,262 @@
+use alloc::vec::Vec;
+use super::{ArcId, DuplicatePolicy, Error, FlowEdge, Graph, SelfLoopPolicy, VertexId};
+
+const UNVISITED: usize = usize::MAX;
+
+impl Graph<FlowEdge> {
+    /// Returns the arcs of the bridges, the edges whose removal disconnects their endpoints when directions are ignored.
+    /// Parallel edges between two vertexes are never bridges and self loops are ignored. The arcs are in increasing
+    /// order.
+    pub fn bridges(&self) -> Vec<ArcId> {
+        let mut discovered = vec![UNVISITED; self.n_vertexes()];
+        let mut low = vec![UNVISITED; self.n_vertexes()];
+        let mut bridges: Vec<ArcId> = Vec::new();
+        let mut time = 0;
+        // Depth first search with an explicit stack of (vertex, arc it was entered by, next out arc to look at)
+        let mut stack: Vec<(VertexId, ArcId, usize)> = Vec::new();
+        for root in self.vertexes() {
+            if discovered[root] != UNVISITED {
+                continue;
+            }
+            discovered[root] = time;
+            low[root] = time;
+            time += 1;
+            stack.push((root, UNVISITED, 0));
+            while let Some(&mut (u, entered_by, ref mut next)) = stack.last_mut() {
+                if let Some(&a) = self.adjacency[u].get(*next) {
+                    *next += 1;
+                    let (a, v) = (a as ArcId, self.head(a as ArcId));
+                    // Both arcs of an edge lead back, only the arc of another edge may close a cycle
+                    if (entered_by != UNVISITED && a ^ 1 == entered_by) || v == u {
+                        continue;
+                    }
+                    if discovered[v] == UNVISITED {
+                        discovered[v] = time;
+                        low[v] = time;
+                        time += 1;
+                        stack.push((v, a, 0));
+                    } else {
+                        low[u] = low[u].min(discovered[v]);
+                    }
+                } else {
+                    stack.pop();
+                    if let Some(&(parent, _, _)) = stack.last() {
+                        low[parent] = low[parent].min(low[u]);
+                        if low[u] > discovered[parent] {
+                            bridges.push(entered_by & !1);
+                        }
+                    }
+                }
+            }
+        }
+        bridges.sort_unstable();
+        bridges
+    }
+
+    /// Returns the connected component of every vertex when directions are ignored, numbered from 0 in the order of
+    /// their smallest vertex.
+    pub fn connected_components(&self) -> Vec<usize> {
+        self.components_without(&vec![false; self.n_edges()])
+    }
+
+    /// Returns the 2-edge-connected component of every vertex, the parts left connected after removing the bridges,
+    /// numbered from 0 in the order of their smallest vertex. Any cut between two vertexes of one component has at
+    /// least two edges, so a single edge of a component never separates its vertexes.
+    pub fn two_edge_connected_components(&self) -> Vec<usize> {
+        let mut is_bridge = vec![false; self.n_edges()];
+        for a in self.bridges() {
+            is_bridge[a / 2] = true;
+        }
+        self.components_without(&is_bridge)
+    }
+
+    /// Returns the connected components left when directions are ignored and the edges marked in `removed` are taken
+    /// out.
+    fn components_without(&self, removed: &[bool]) -> Vec<usize> {
+        let mut components = vec![UNVISITED; self.n_vertexes()];
+        let mut n_components = 0;
+        let mut stack: Vec<VertexId> = Vec::new();
+        for root in self.vertexes() {
+            if components[root] != UNVISITED {
+                continue;
+            }
+            components[root] = n_components;
+            stack.push(root);
+            while let Some(u) = stack.pop() {
+                for a in self.out_arcs(u) {
+                    let v = self.head(a);
+                    if !removed[a / 2] && components[v] == UNVISITED {
+                        components[v] = n_components;
+                        stack.push(v);
+                    }
+                }
+            }
+            n_components += 1;
+        }
+        components
+    }
+
+    /// Splits the vertexes into two sides such that every edge joins both sides when directions are ignored, or returns
+    /// `None` if the graph has an odd cycle or a self loop. The smallest vertex of every connected part, including
+    /// isolated vertexes, is on the first side. Both sides are in increasing order.
+    pub fn bipartition(&self) -> Option<(Vec<VertexId>, Vec<VertexId>)> {
+        const UNCOLORED: u8 = 2;
+        let mut colors = vec![UNCOLORED; self.n_vertexes()];
+        let mut stack: Vec<VertexId> = Vec::new();
+        for root in self.vertexes() {
+            if colors[root] != UNCOLORED {
+                continue;
+            }
+            colors[root] = 0;
+            stack.push(root);
+            while let Some(u) = stack.pop() {
+                for a in self.out_arcs(u) {
+                    let v = self.head(a);
+                    if colors[v] == UNCOLORED {
+                        colors[v] = 1 - colors[u];
+                        stack.push(v);
+                    } else if colors[v] == colors[u] {
+                        return None;
+                    }
+                }
+            }
+        }
+        let (left, right): (Vec<VertexId>, Vec<VertexId>) = self.vertexes().partition(|&v| colors[v] == 0);
+        Some((left, right))
+    }
+
+    /// Returns the strongly connected component of every vertex, following only the edges with positive capacity. The
+    /// components are numbered in topological order, so every edge between two components goes to a larger number.
+    pub fn strongly_connected_components(&self) -> Vec<usize> {
+        // Tarjan's algorithm with an explicit stack of (vertex, next out arc to look at)
+        let mut index = vec![UNVISITED; self.n_vertexes()];
+        let mut low = vec![UNVISITED; self.n_vertexes()];
+        let mut components = vec![UNVISITED; self.n_vertexes()];
+        let mut open: Vec<VertexId> = Vec::new();
+        let mut stack: Vec<(VertexId, usize)> = Vec::new();
+        let mut time = 0;
+        let mut n_components = 0;
+        for root in self.vertexes() {
+            if index[root] != UNVISITED {
+                continue;
+            }
+            index[root] = time;
+            low[root] = time;
+            time += 1;
+            open.push(root);
+            stack.push((root, 0));
+            while let Some(&mut (u, ref mut next)) = stack.last_mut() {
+                if let Some(&a) = self.adjacency[u].get(*next) {
+                    *next += 1;
+                    let a = a as ArcId;
+                    if a & 1 == 1 || self.arcs[a].capacity <= 0 {
+                        continue;
+                    }
+                    let v = self.head(a);
+                    if index[v] == UNVISITED {
+                        index[v] = time;
+                        low[v] = time;
+                        time += 1;
+                        open.push(v);
+                        stack.push((v, 0));
+                    } else if components[v] == UNVISITED {
+                        low[u] = low[u].min(index[v]);
+                    }
+                } else {
+                    stack.pop();
+                    if let Some(&(parent, _)) = stack.last() {
+                        low[parent] = low[parent].min(low[u]);
+                    }
+                    if low[u] == index[u] {
+                        while let Some(v) = open.pop() {
+                            components[v] = n_components;
+                            if v == u {
+                                break;
+                            }
+                        }
+                        n_components += 1;
+                    }
+                }
+            }
+        }
+        // Tarjan's algorithm completes a component after every component it reaches, which is reverse topological order
+        components.iter().map(|&c| n_components - 1 - c).collect()
+    }
+
+    /// Returns the strongly connected components, see `strongly_connected_components`, and the condensation: a flow
+    /// network without flow whose vertexes are the components and whose edges are the edges between different
+    /// components, merged into one edge per pair with the sum of their capacities. The condensation is acyclic. It
+    /// keeps every cut which does not split a component, so its maximum flow between the components of two terminals
+    /// is an upper bound of theirs. Fails if a merged capacity overflows.
+    pub fn condensation(&self) -> Result<(Vec<usize>, Graph<FlowEdge>), Error> {
+        let components = self.strongly_connected_components();
+        let n_components = components.iter().map(|&c| c + 1).max().unwrap_or(0);
+        let edges = self.edges()
+            .filter(|&(u, v, a)| components[u] != components[v] && self.arcs[a].capacity > 0)
+            .map(|(u, v, a)| {
+                (VertexId(components[u]), VertexId(components[v]), FlowEdge{capacity: self.arcs[a].capacity, flow: 0})
+            })
+            .collect::<Vec<_>>();
+        let vertexes = (0..n_components).map(VertexId).collect::<Vec<_>>();
+        let graph = Graph::try_flow_network(&vertexes, edges, DuplicatePolicy::Sum, SelfLoopPolicy::Reject)?;
+        Ok((components, graph))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn network(n_vertexes: usize, edges: &[(usize, usize)]) -> Graph<FlowEdge> {
+        let vertexes = (0..n_vertexes).map(VertexId).collect::<Vec<_>>();
+        let edges = edges.iter().map(|&(u, v)| (VertexId(u), VertexId(v), FlowEdge{capacity: 1, flow: 0}));
+        Graph::flow_network(&vertexes, edges)
+    }
+
+    fn ids(vertexes: &[usize]) -> Vec<VertexId> {
+        vertexes.iter().map(|&v| VertexId(v)).collect()
+    }
+
+    #[test]
+    fn test_two_edge_connected_components() {
+        // Two triangles joined by the edge (2, 3), with a pendant vertex 6 and an isolated vertex 7
+        let g = network(8, &[(0, 1), (1, 2), (2, 0), (2, 3), (3, 4), (4, 5), (5, 3), (5, 6)]);
+        let edge = |u, v| g.find_edge(VertexId(u), VertexId(v)).unwrap();
+        assert_eq!(g.bridges(), [edge(2, 3), edge(5, 6)]);
+        assert_eq!(g.two_edge_connected_components(), [0, 0, 0, 1, 1, 1, 2, 3]);
+        assert_eq!(g.connected_components(), [0, 0, 0, 0, 0, 0, 0, 1]);
+
+        // Parallel and opposite edges keep their endpoints together, self loops do not
+        let g = network(4, &[(0, 1), (0, 1), (2, 1), (1, 2), (2, 3), (3, 3)]);
+        assert_eq!(g.bridges(), [g.find_edge(VertexId(2), VertexId(3)).unwrap()]);
+        assert_eq!(g.two_edge_connected_components(), [0, 0, 0, 1]);
+    }
+
+    #[test]
+    fn test_condensation() {
+        // The cycles 0 -> 1 -> 2 -> 0 and 3 <-> 4 joined by two edges, and the sink 5 behind a zero capacity edge
+        let mut g = network(6, &[(0, 1), (1, 2), (2, 0), (1, 3), (2, 4), (3, 4), (4, 3), (4, 5), (5, 4)]);
+        let arc = g.find_edge(VertexId(5), VertexId(4)).unwrap();
+        g.arcs[arc].capacity = 0;
+        assert_eq!(g.strongly_connected_components(), [0, 0, 0, 1, 1, 2]);
+        let (components, condensed) = g.condensation().unwrap();
+        assert_eq!(components, [0, 0, 0, 1, 1, 2]);
+        let edges = condensed.edges()
+            .map(|(u, v, a)| (u.index(), v.index(), condensed.arcs[a].capacity))
+            .collect::<Vec<_>>();
+        assert_eq!(edges, [(0, 1, 2), (1, 2, 1)]);
+
+        let (components, condensed) = network(3, &[(2, 1), (1, 0)]).condensation().unwrap();
+        assert_eq!((components, condensed.n_edges()), (vec![2, 1, 0], 2));
+    }
+
+    #[test]
+    fn test_bipartition() {
+        // A matching instance: source 0, left side 1 and 2, right side 3 and 4, sink 5, and an isolated vertex 6
+        let g = network(7, &[(0, 1), (0, 2), (1, 3), (1, 4), (2, 4), (3, 5), (4, 5)]);
+        assert_eq!(g.bipartition(), Some((ids(&[0, 3, 4, 6]), ids(&[1, 2, 5]))));
+        assert_eq!(network(3, &[(0, 1), (1, 2), (2, 0)]).bipartition(), None);
+        assert_eq!(network(2, &[(0, 1), (1, 1)]).bipartition(), None);
+        assert_eq!(network(2, &[(0, 1), (1, 0)]).bipartition(), Some((ids(&[0]), ids(&[1]))));
+    }
+}
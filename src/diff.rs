@@ -0,0 +1,107 @@
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::fmt;
+use super::{FlowEdge, Graph, VertexId};
+
+/// Differences between two flow networks found by `Graph::diff`. Edges are compared by their endpoints, with the
+/// capacities of parallel edges added up, and are listed in order of their endpoints.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GraphDiff {
+    /// Number of vertexes of the first and the second graph.
+    pub vertexes: (usize, usize),
+    /// Edges only in the second graph, with their capacity.
+    pub added: Vec<(VertexId, VertexId, i64)>,
+    /// Edges only in the first graph, with their capacity.
+    pub removed: Vec<(VertexId, VertexId, i64)>,
+    /// Edges in both graphs with different capacities, the capacity in the first and in the second graph.
+    pub changed: Vec<(VertexId, VertexId, i64, i64)>
+}
+
+impl GraphDiff {
+    /// Whether the graphs have the same vertexes and capacities.
+    pub fn is_empty(&self) -> bool {
+        self.vertexes.0 == self.vertexes.1 && self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// One line per difference, `+` for added and `-` for removed edges and `~` for changed capacities.
+impl fmt::Display for GraphDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.vertexes.0 != self.vertexes.1 {
+            writeln!(f, "Vertexes: {} -> {}", self.vertexes.0, self.vertexes.1)?;
+        }
+        for &(u, v, capacity) in &self.removed {
+            writeln!(f, "- ({}, {}) capacity {}", u, v, capacity)?;
+        }
+        for &(u, v, capacity) in &self.added {
+            writeln!(f, "+ ({}, {}) capacity {}", u, v, capacity)?;
+        }
+        for &(u, v, before, after) in &self.changed {
+            writeln!(f, "~ ({}, {}) capacity {} -> {}", u, v, before, after)?;
+        }
+        Ok(())
+    }
+}
+
+impl Graph<FlowEdge> {
+    /// Compares the edges and capacities of this graph with `other`, such as an instance before and after a
+    /// preprocessing step. Flows are ignored, and so are edges without capacity, which no file format keeps.
+    pub fn diff(&self, other: &Graph<FlowEdge>) -> GraphDiff {
+        let (before, after) = (self.pair_capacities(), other.pair_capacities());
+        let mut diff = GraphDiff {vertexes: (self.n_vertexes(), other.n_vertexes()), ..GraphDiff::default()};
+        for (&(u, v), &capacity) in &before {
+            match after.get(&(u, v)) {
+                None => diff.removed.push((u, v, capacity)),
+                Some(&changed) if changed != capacity => diff.changed.push((u, v, capacity, changed)),
+                Some(_) => {}
+            }
+        }
+        diff.added = after.iter()
+            .filter(|(pair, _)| !before.contains_key(pair))
+            .map(|(&(u, v), &capacity)| (u, v, capacity))
+            .collect();
+        debug!("Diff of {} removed, {} added and {} changed edges",
+               diff.removed.len(), diff.added.len(), diff.changed.len());
+        diff
+    }
+
+    /// Returns the total capacity of the edges between every pair of vertexes, leaving out pairs without capacity.
+    fn pair_capacities(&self) -> BTreeMap<(VertexId, VertexId), i64> {
+        let mut capacities: BTreeMap<(VertexId, VertexId), i64> = BTreeMap::new();
+        for (u, v, a) in self.edges() {
+            *capacities.entry((u, v)).or_insert(0) += self.arcs[a].capacity as i64;
+        }
+        capacities.retain(|_, capacity| *capacity != 0);
+        capacities
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn network(n_vertexes: usize, edges: &[(usize, usize, i32)]) -> Graph<FlowEdge> {
+        let vertexes = (0..n_vertexes).map(VertexId).collect::<Vec<_>>();
+        let edges = edges.iter()
+            .map(|&(u, v, capacity)| (VertexId(u), VertexId(v), FlowEdge{capacity, flow: 0}))
+            .collect::<Vec<_>>();
+        Graph::flow_network(&vertexes, &edges)
+    }
+
+    #[test]
+    fn test_diff() {
+        let g = network(4, &[(0, 1, 3), (1, 3, 2), (0, 2, 4), (2, 3, 0)]);
+        assert!(g.diff(&g).is_empty());
+        // Parallel edges are added up and edges without capacity do not count
+        assert!(g.diff(&network(4, &[(0, 1, 1), (0, 1, 2), (1, 3, 2), (0, 2, 4)])).is_empty());
+
+        let h = network(5, &[(0, 1, 3), (1, 3, 5), (2, 3, 1), (3, 4, 1)]);
+        let diff = g.diff(&h);
+        assert_eq!(diff.vertexes, (4, 5));
+        assert_eq!(diff.removed, [(VertexId(0), VertexId(2), 4)]);
+        assert_eq!(diff.added, [(VertexId(2), VertexId(3), 1), (VertexId(3), VertexId(4), 1)]);
+        assert_eq!(diff.changed, [(VertexId(1), VertexId(3), 2, 5)]);
+        assert_eq!(diff.to_string(), "Vertexes: 4 -> 5\n- (0, 2) capacity 4\n+ (2, 3) capacity 1\n\
+                                      + (3, 4) capacity 1\n~ (1, 3) capacity 2 -> 5\n");
+    }
+}
@@ -0,0 +1,1283 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use std::str;
+use serde_json;
+use super::{add_super_terminals, DuplicatePolicy, Error, FlowEdge, FlowError, Graph, Index, SelfLoopPolicy, VertexId};
+use verify::flow_value;
+
+/// Parses a flow problem in DIMACS format from a file, see `flow_from_dicaps_reader`. Panics if the file does not
+/// exist or can not be parsed.
+pub fn flow_from_dicaps(file_name: &str) -> (VertexId, VertexId, Graph<FlowEdge>) {
+    let f = File::open(file_name).unwrap_or_else(|_| panic!("Input file does not exist: {}", file_name));
+    flow_from_dicaps_reader(BufReader::new(f)).unwrap_or_else(|e| panic!("{}: {}", file_name, e))
+}
+
+/// Parses a flow problem in DIMACS format from any buffered reader, such as stdin or an in-memory buffer, see
+/// `parse_dicaps_bytes`. The input is parsed line by line as it is read, and the number of vertexes is not limited.
+pub fn flow_from_dicaps_reader<R: BufRead>(reader: R) -> Result<(VertexId, VertexId, Graph<FlowEdge>), Error> {
+    parse_dicaps(reader, usize::MAX)
+}
+
+/// Largest number of vertexes `parse_dicaps_bytes` accepts on a problem line, so that a few bytes of input can not
+/// claim an arbitrary amount of memory.
+pub const MAX_DICAPS_BYTES_VERTEXES: usize = 1 << 20;
+
+/// Parses a flow problem in DIMACS format and fails instead of panicking on any input, for untrusted uploads and as a
+/// fuzz target. Comment lines start with a `c` token, a problem line `p max <vertexes> <edges>` gives the number of
+/// vertexes and edges, `n <id> s` and `n <id> t` lines the terminals and `a <from> <to> <capacity>` lines the edges,
+/// with vertexes numbered from 0. A lone `a` ends the input. Several sources or sinks are joined into a supersource and
+/// a supersink, see `add_super_terminals`. Fails on input which is not UTF-8, malformed lines, problems other than max
+/// flow, capacities beyond 32 bits, vertexes out of range, missing terminals or a source which is the sink, a number of
+/// edges other than announced and problem lines with more than `MAX_DICAPS_BYTES_VERTEXES` vertexes. Nothing is
+/// allocated from the number of edges announced, so the memory used is bounded by the size of the input and the number
+/// of vertexes.
+pub fn parse_dicaps_bytes(bytes: &[u8]) -> Result<(VertexId, VertexId, Graph<FlowEdge>), Error> {
+    parse_dicaps(bytes, MAX_DICAPS_BYTES_VERTEXES)
+}
+
+/// Parses a flow problem in DIMACS format with at most `max_vertexes` vertexes, see `parse_dicaps_bytes`. Only the
+/// current line is buffered.
+fn parse_dicaps<R: BufRead>(mut reader: R,
+                            max_vertexes: usize) -> Result<(VertexId, VertexId, Graph<FlowEdge>), Error> {
+    let mut num_vertexes = 0;
+    let mut num_edges = 0;
+    let mut sources: Vec<VertexId> = Vec::new();
+    let mut sinks: Vec<VertexId> = Vec::new();
+    let mut edges: Vec<(VertexId, VertexId, FlowEdge)> = Vec::new();
+    let mut num_parsed_edges = 0;
+    let mut raw_line: Vec<u8> = Vec::new();
+    let mut num_bytes = 0;
+    for line in 1.. {
+        let parse_error = |message: String| Error::Parse {line, message};
+        raw_line.clear();
+        match reader.read_until(b'\n', &mut raw_line).map_err(|e| parse_error(e.to_string()))? {
+            0 => break,
+            n => num_bytes += n
+        }
+        let text = str::from_utf8(&raw_line).map_err(|e| parse_error(e.to_string()))?;
+        let tokens = text.split_whitespace().collect::<Vec<_>>();
+        let count = |i: usize, what: &str| tokens[i].parse::<usize>()
+            .map_err(|_| parse_error(format!("Expected an integer for {}, got {}", what, tokens[i])));
+        // Comment lines can have any number of tokens so they are skipped before matching on the record length
+        match (tokens.first(), tokens.len()) {
+            (None, _) => {},
+            (Some(&"c"), _) => {},
+            (Some(&"p"), 4) if tokens[1] != "max" => {
+                return Err(parse_error(format!("Expected a max flow problem, got {}", tokens[1])));
+            },
+            (Some(&"p"), 4) => {
+                num_vertexes = count(2, "number of vertexes")?;
+                num_edges = count(3, "number of edges")?;
+                if num_vertexes > max_vertexes {
+                    return Err(Error::Limit(format!("{} vertexes are more than the {} accepted",
+                                                    num_vertexes, max_vertexes)));
+                }
+                // Checked before the vertexes are allocated, building the graph would only find out afterwards
+                if num_vertexes as u64 > Index::MAX as u64 {
+                    return Err(Graph::<FlowEdge>::too_large());
+                }
+            },
+            (Some(&"a"), 4) => {
+                let (u, v) = (VertexId(count(1, "source in edge")?), VertexId(count(2, "destination in edge")?));
+                let capacity = parse_capacity(tokens[3]).map_err(parse_error)?;
+                if capacity > 0 {
+                    edges.push((u, v, FlowEdge{flow: 0, capacity}));
+                }
+                num_parsed_edges += 1;
+            },
+            (Some(&"n"), 3) if tokens[2] == "s" => sources.push(VertexId(count(1, "source")?)),
+            (Some(&"n"), 3) if tokens[2] == "t" => sinks.push(VertexId(count(1, "sink")?)),
+            (Some(&"a"), 1) => break,
+            _ => return Err(parse_error(format!("Invalid line: {}", text)))
+        }
+    }
+    if num_parsed_edges != num_edges {
+        return Err(Error::Construction(format!("Number of edges specified and found are different: {} vs {}",
+                                               num_edges, num_parsed_edges)));
+    }
+    // Vertexes without edges are valid, generated networks can have them, so only the range of the ids is checked
+    let outside = |v: &VertexId| v.index() >= num_vertexes;
+    if let Some(e) = edges.iter().find(|e| outside(&e.0) || outside(&e.1)) {
+        return Err(Error::Construction(format!("Edge ({}, {}) has a vertex outside of the {} vertexes specified",
+                                               e.0, e.1, num_vertexes)));
+    }
+    if let Some(v) = sources.iter().chain(&sinks).find(|v| outside(v)) {
+        return Err(Error::Construction(format!("Terminal {} is outside of the {} vertexes specified",
+                                               v, num_vertexes)));
+    }
+    // Repeated terminals are dropped in a single pass, a quadratic search would let many terminal lines stall
+    let mut seen: HashSet<(VertexId, bool)> = HashSet::new();
+    sources.retain(|&v| seen.insert((v, true)));
+    sinks.retain(|&v| seen.insert((v, false)));
+    let (source, sink) = match (sources.len(), sinks.len()) {
+        (0, _) => return Err(Error::Construction("Must have a source".to_string())),
+        (_, 0) => return Err(Error::Construction("Must have a sink".to_string())),
+        (1, 1) if sources[0] == sinks[0] => {
+            return Err(Error::Infeasible(FlowError::SourceIsSink {vertex: sources[0]}));
+        },
+        (1, 1) => (sources[0], sinks[0]),
+        _ => {
+            let n = num_vertexes;
+            num_vertexes = n.checked_add(2)
+                .ok_or_else(|| Error::Overflow(format!("{} vertexes leave no room for the super terminals", n)))?;
+            add_super_terminals(&mut edges, n, &sources, &sinks)?
+        }
+    };
+    debug!("Parsed {} bytes into a network with {} vertexes and {} edges", num_bytes, num_vertexes, edges.len());
+    let vertexes = (0..num_vertexes).map(VertexId).collect::<Vec<_>>();
+    let graph = Graph::try_flow_network(&vertexes, &edges, DuplicatePolicy::Parallel, SelfLoopPolicy::Skip)?;
+    Ok((source, sink, graph))
+}
+
+/// Parses a flow problem in the dense txt format from a file, see `flow_from_txt_reader`. Panics if the file does not
+/// exist or can not be parsed.
+pub fn flow_from_txt(file_name: &str) -> (VertexId, VertexId, Graph<FlowEdge>) {
+    let f = File::open(file_name).unwrap_or_else(|_| panic!("Input file does not exist: {}", file_name));
+    flow_from_txt_reader(BufReader::new(f)).unwrap_or_else(|e| panic!("{}: {}", file_name, e))
+}
+
+/// Parses a flow problem in the dense txt format from any buffered reader, such as stdin or an in-memory buffer. The
+/// first line gives the number of vertexes and every following line a row of the capacity matrix, the source is the
+/// first vertex and the sink the last.
+pub fn flow_from_txt_reader<R: BufRead>(reader: R) -> Result<(VertexId, VertexId, Graph<FlowEdge>), Error> {
+    let mut edges: Vec<(VertexId, VertexId, FlowEdge)> = Vec::new();
+    let mut num_vertexes = None;
+    let mut row = 0;
+    for (i, raw_line) in reader.lines().enumerate() {
+        let line = i + 1;
+        let parse_error = |message: String| Error::Parse {line, message};
+        let text = raw_line.map_err(|e| parse_error(e.to_string()))?;
+        let tokens = text.split_whitespace().collect::<Vec<_>>();
+        if num_vertexes.is_none() {
+            let n = tokens.first().and_then(|t| t.parse::<usize>().ok())
+                .ok_or_else(|| parse_error(format!("Expected an integer for the number of vertexes, got {}", text)))?;
+            num_vertexes = Some(n);
+            continue;
+        }
+        for (column, token) in tokens.iter().enumerate() {
+            let capacity = parse_capacity(token).map_err(parse_error)?;
+            if capacity > 0 {
+                edges.push((VertexId(row), VertexId(column), FlowEdge{capacity, flow: 0}));
+            }
+        }
+        row += 1;
+    }
+    let num_vertexes = num_vertexes
+        .ok_or_else(|| Error::Parse {line: 0, message: "Missing number of vertexes".to_string()})?;
+    let sink = num_vertexes.checked_sub(1)
+        .ok_or_else(|| Error::Construction("A flow network needs at least two vertexes".to_string()))?;
+    build_flow_graph(num_vertexes, edges, VertexId(0), VertexId(sink))
+}
+
+/// Parses a flow problem written by `write_dot`. Only the subset of DOT emitted by the writer is understood: one
+/// statement per line, a `capacity` attribute on edges and a `terminal` attribute on the source and sink vertexes.
+/// Vertexes are integer ids, named by an optional `label` attribute, or names if any vertex is not an integer, see
+/// `VertexNames`. The names are kept in the graph.
+pub fn flow_from_dot_reader<R: BufRead>(reader: R) -> Result<(VertexId, VertexId, Graph<FlowEdge>), Error> {
+    // Whether vertexes are ids or names depends on all of them, so the statements are collected first
+    let mut statements: Vec<DotStatement> = Vec::new();
+    for (i, raw_line) in reader.lines().enumerate() {
+        let line = i + 1;
+        let text = raw_line.map_err(|e| Error::Parse {line, message: e.to_string()})?;
+        let invalid = || Error::Parse {line, message: format!("Invalid line: {}", text)};
+        let statement = text.trim().trim_end_matches(';').trim();
+        if statement.is_empty() || statement.starts_with("//") || statement.ends_with('{') || statement == "}" {
+            continue;
+        }
+        let (ids, attributes) = match statement.find('[') {
+            Some(i) => (statement[..i].trim(), parse_dot_attributes(&statement[i..]).ok_or_else(invalid)?),
+            None => (statement, Vec::new())
+        };
+        let ids = ids.split("->").map(|id| unquote_dot(id.trim())).collect::<Option<Vec<_>>>().ok_or_else(invalid)?;
+        if ids.len() > 2 {
+            return Err(invalid());
+        }
+        statements.push((ids, attributes, line));
+    }
+    let mut names = VertexNames::new(statements.iter().flat_map(|s| s.0.iter().map(|id| id.as_str())));
+    let mut num_vertexes = 0;
+    let mut source = None;
+    let mut sink = None;
+    let mut edges: Vec<(VertexId, VertexId, FlowEdge)> = Vec::new();
+    for &(ref ids, ref attributes, line) in &statements {
+        let attribute = |key: &str| attributes.iter().find(|a| a.0 == key).map(|a| a.1.as_str());
+        let vertexes = ids.iter().map(|id| names.resolve(id, line)).collect::<Result<Vec<_>, _>>()?;
+        num_vertexes = vertexes.iter().fold(num_vertexes, |n, v| n.max(v.index() + 1));
+        if let [u, v] = vertexes[..] {
+            let capacity = parse_capacity(attribute("capacity").unwrap_or(""))
+                .map_err(|message| Error::Parse {line, message})?;
+            if capacity > 0 {
+                edges.push((u, v, FlowEdge{capacity, flow: 0}));
+            }
+            continue;
+        }
+        let v = vertexes[0];
+        if let Some(label) = attribute("label") {
+            names.set_name(v, label);
+        }
+        match attribute("terminal") {
+            Some("source") => source = Some(v),
+            Some("sink") => sink = Some(v),
+            Some(terminal) => return Err(Error::Parse {line, message: format!("Invalid terminal: {}", terminal)}),
+            None => {}
+        }
+    }
+    let (source, sink) = required_terminals(source, sink)?;
+    let (source, sink, mut graph) = build_flow_graph(num_vertexes, edges, source, sink)?;
+    names.apply(&mut graph)?;
+    Ok((source, sink, graph))
+}
+
+/// Vertexes, one for a vertex statement and two for an edge, attributes and line number of a DOT statement.
+type DotStatement = (Vec<String>, Vec<(String, String)>, usize);
+
+/// Splits a DOT attribute list such as `[capacity=5, terminal="source"]` into key value pairs. Commas inside quoted
+/// values do not split, and quoted values are unescaped, see `unquote_dot`. Returns `None` if the list is malformed.
+fn parse_dot_attributes(list: &str) -> Option<Vec<(String, String)>> {
+    let inner = list.trim().strip_prefix('[').and_then(|l| l.strip_suffix(']'))?;
+    let (mut quoted, mut escaped) = (false, false);
+    inner.split(|c| {
+            if escaped {
+                escaped = false;
+                return false;
+            }
+            escaped = quoted && c == '\\';
+            quoted ^= c == '"';
+            c == ',' && !quoted
+        })
+        .filter(|a| !a.trim().is_empty())
+        .map(|a| {
+            let mut kv = a.splitn(2, '=');
+            let key = kv.next()?.trim().to_string();
+            let value = unquote_dot(kv.next()?.trim())?;
+            Some((key, value))
+        })
+        .collect()
+}
+
+/// Quotes a DOT id such as a label, escaping quotes, backslashes and line breaks so that `unquote_dot` restores it.
+fn quote_dot(id: &str) -> String {
+    format!("\"{}\"", id.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n"))
+}
+
+/// Returns a DOT id without its quotes and escapes, see `quote_dot`. Unquoted ids are returned as they are and other
+/// escapes are kept. Returns `None` if a quoted id is not closed.
+fn unquote_dot(id: &str) -> Option<String> {
+    let inner = match id.strip_prefix('"') {
+        Some(quoted) => quoted.strip_suffix('"')?,
+        None => return Some(id.to_string())
+    };
+    let mut unquoted = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unquoted.push(c);
+            continue;
+        }
+        match chars.next()? {
+            'n' => unquoted.push('\n'),
+            escaped @ ('"' | '\\') => unquoted.push(escaped),
+            other => {
+                unquoted.push('\\');
+                unquoted.push(other);
+            }
+        }
+    }
+    Some(unquoted)
+}
+
+/// Ids of the vertexes of a file which gives its vertexes either as integer ids or as names. If any vertex is not an
+/// integer all of them are names, numbered in the order they first appear, otherwise they are ids which can be named
+/// separately. The names end up in the graph, see `Graph::set_vertex_names`.
+struct VertexNames {
+    by_name: bool,
+    ids: HashMap<String, VertexId>,
+    names: Vec<Option<String>>
+}
+
+impl VertexNames {
+    fn new<'a, I>(vertexes: I) -> VertexNames where I: IntoIterator<Item = &'a str> {
+        let by_name = vertexes.into_iter().any(|v| v.parse::<usize>().is_err());
+        VertexNames {by_name, ids: HashMap::new(), names: Vec::new()}
+    }
+
+    /// Returns the vertex given as `vertex` on `line`, numbering a new name.
+    fn resolve(&mut self, vertex: &str, line: usize) -> Result<VertexId, Error> {
+        if !self.by_name {
+            return vertex.parse::<VertexId>()
+                .map_err(|_| Error::Parse {line, message: format!("Expected an integer vertex, got {}", vertex)});
+        }
+        if let Some(&v) = self.ids.get(vertex) {
+            return Ok(v);
+        }
+        let v = VertexId(self.names.len());
+        self.ids.insert(vertex.to_string(), v);
+        self.names.push(Some(vertex.to_string()));
+        Ok(v)
+    }
+
+    /// Names the vertex with id `v`. Vertexes given by name keep that name.
+    fn set_name(&mut self, v: VertexId, name: &str) {
+        if !self.by_name {
+            if self.names.len() <= v.index() {
+                self.names.resize(v.index() + 1, None);
+            }
+            self.names[v] = Some(name.to_string());
+        }
+    }
+
+    /// Gives the names to the vertexes of `graph`, vertexes without a name are named by their id. Does nothing if no
+    /// vertex is named, and fails if a name is repeated.
+    fn apply(self, graph: &mut Graph<FlowEdge>) -> Result<(), Error> {
+        if self.names.iter().all(|name| name.is_none()) {
+            return Ok(());
+        }
+        let names = graph.vertexes()
+            .map(|v| self.names.get(v.index()).cloned().flatten().unwrap_or_else(|| v.to_string()))
+            .collect::<Vec<_>>();
+        graph.set_vertex_names(names)
+    }
+}
+
+/// Parses a flow problem from a JSON object with `vertexes`, `source`, `sink` and a list of `edges`, each edge being an
+/// object with `from`, `to` and `capacity` fields. This is the format emitted by `write_json`.
+pub fn flow_from_json_reader<R: BufRead>(reader: R) -> Result<(VertexId, VertexId, Graph<FlowEdge>), Error> {
+    let value: serde_json::Value = serde_json::from_reader(reader)
+        .map_err(|e| Error::Parse {line: e.line(), message: e.to_string()})?;
+    // The fields of a parsed value have no line, so their errors are on line 0
+    let invalid = |message: String| Error::Parse {line: 0, message};
+    let field = |object: &serde_json::Value, key: &str| object.get(key).and_then(|v| v.as_u64())
+        .map(|v| v as usize)
+        .ok_or_else(|| invalid(format!("Expected a non-negative integer for \"{}\"", key)));
+    let num_vertexes = field(&value, "vertexes")?;
+    let source = VertexId(field(&value, "source")?);
+    let sink = VertexId(field(&value, "sink")?);
+    let mut edges: Vec<(VertexId, VertexId, FlowEdge)> = Vec::new();
+    let edge_list = value.get("edges").and_then(|e| e.as_array())
+        .ok_or_else(|| invalid("Expected a list of edges".to_string()))?;
+    for edge in edge_list {
+        let capacity = edge.get("capacity").and_then(|c| c.as_i64())
+            .ok_or_else(|| invalid("Expected an integer for \"capacity\"".to_string()))?;
+        let capacity = checked_capacity(capacity).map_err(invalid)?;
+        if capacity > 0 {
+            let (u, v) = (VertexId(field(edge, "from")?), VertexId(field(edge, "to")?));
+            edges.push((u, v, FlowEdge{capacity, flow: 0}));
+        }
+    }
+    build_flow_graph(num_vertexes, edges, source, sink)
+}
+
+/// Parses a flow problem from CSV rows of `from,to,capacity`. The terminals are given by a leading comment line of the
+/// form `# source=0 sink=3 vertexes=4`, the number of vertexes is optional and an optional header row is skipped.
+/// Vertexes are integer ids or names as for `flow_from_dot_reader`, and a comment line `# names=a,b,c` names the
+/// vertexes in the order of their ids. The names are kept in the graph.
+pub fn flow_from_csv_reader<R: BufRead>(reader: R) -> Result<(VertexId, VertexId, Graph<FlowEdge>), Error> {
+    let mut num_vertexes = 0;
+    let mut named: Vec<String> = Vec::new();
+    let mut named_line = 0;
+    let mut terminals: Vec<(String, String, usize)> = Vec::new();
+    let mut rows: Vec<(Vec<String>, usize)> = Vec::new();
+    for (i, raw_line) in reader.lines().enumerate() {
+        let line = i + 1;
+        let parse_error = |message: String| Error::Parse {line, message};
+        let text = raw_line.map_err(|e| parse_error(e.to_string()))?;
+        let trimmed = text.trim();
+        if trimmed.is_empty() || trimmed == "from,to,capacity" {
+            continue;
+        }
+        if let Some(comment) = trimmed.strip_prefix('#') {
+            // Names may contain whitespace, so the names line is not split into key value pairs
+            if let Some(list) = comment.trim_start().strip_prefix("names=") {
+                named = list.split(',').map(|name| name.trim().to_string()).collect();
+                named_line = line;
+                continue;
+            }
+            for (key, value) in comment.split_whitespace().filter_map(|t| t.split_once('=')) {
+                match key {
+                    "source" | "sink" => terminals.push((key.to_string(), value.to_string(), line)),
+                    "vertexes" => num_vertexes = num_vertexes.max(value.parse::<usize>()
+                        .map_err(|_| parse_error(format!("Expected an integer for vertexes, got {}", value)))?),
+                    _ => {}
+                }
+            }
+            continue;
+        }
+        let fields = trimmed.split(',').map(|f| f.trim().to_string()).collect::<Vec<_>>();
+        if fields.len() != 3 {
+            return Err(parse_error(format!("Invalid line: {}", text)));
+        }
+        rows.push((fields, line));
+    }
+    let vertexes = terminals.iter().map(|t| t.1.as_str())
+        .chain(rows.iter().flat_map(|row| row.0[..2].iter().map(|f| f.as_str())));
+    let mut names = VertexNames::new(vertexes);
+    // Named vertexes are numbered first, so that the ids of a file written by `write_csv` are kept
+    for (i, name) in named.iter().enumerate() {
+        names.set_name(VertexId(i), name);
+        if names.by_name {
+            names.resolve(name, named_line)?;
+        }
+    }
+    let (mut source, mut sink) = (None, None);
+    for (key, value, line) in &terminals {
+        let v = names.resolve(value, *line)?;
+        num_vertexes = num_vertexes.max(v.index() + 1);
+        match key.as_str() {
+            "source" => source = Some(v),
+            _ => sink = Some(v)
+        }
+    }
+    let mut edges: Vec<(VertexId, VertexId, FlowEdge)> = Vec::new();
+    for &(ref fields, line) in &rows {
+        let u = names.resolve(&fields[0], line)?;
+        let v = names.resolve(&fields[1], line)?;
+        let capacity = parse_capacity(&fields[2]).map_err(|message| Error::Parse {line, message})?;
+        num_vertexes = num_vertexes.max(u.index() + 1).max(v.index() + 1);
+        if capacity > 0 {
+            edges.push((u, v, FlowEdge{capacity, flow: 0}));
+        }
+    }
+    num_vertexes = num_vertexes.max(named.len());
+    let (source, sink) = required_terminals(source, sink)?;
+    let (source, sink, mut graph) = build_flow_graph(num_vertexes, edges, source, sink)?;
+    names.apply(&mut graph)?;
+    Ok((source, sink, graph))
+}
+
+/// Parses the capacity of an edge. Capacities are read as 64 bit integers so that one beyond the 32 bit capacities of
+/// `FlowEdge` is reported as such rather than as a malformed number.
+fn parse_capacity(token: &str) -> Result<i32, String> {
+    let capacity = token.parse::<i64>().map_err(|_| format!("Expected an integer capacity, got {}", token))?;
+    checked_capacity(capacity)
+}
+
+/// Narrows a parsed capacity to the capacity type of `FlowEdge`, failing with a message if it does not fit.
+fn checked_capacity(capacity: i64) -> Result<i32, String> {
+    if capacity < i32::MIN as i64 || capacity > i32::MAX as i64 {
+        return Err(format!("Capacity {} exceeds the 32 bit capacity type of the flow network", capacity));
+    }
+    Ok(capacity as i32)
+}
+
+/// Returns the terminals of a parsed file, failing if either is missing.
+fn required_terminals(source: Option<VertexId>, sink: Option<VertexId>) -> Result<(VertexId, VertexId), Error> {
+    let source = source.ok_or_else(|| Error::Construction("Must have a source".to_string()))?;
+    let sink = sink.ok_or_else(|| Error::Construction("Must have a sink".to_string()))?;
+    Ok((source, sink))
+}
+
+/// Builds the flow graph of parsed edges. Fails if the terminals or the edges are not vertexes of the graph, or if
+/// the source is the sink.
+fn build_flow_graph(num_vertexes: usize,
+                    edges: Vec<(VertexId, VertexId, FlowEdge)>,
+                    source: VertexId,
+                    sink: VertexId) -> Result<(VertexId, VertexId, Graph<FlowEdge>), Error> {
+    if let Some(v) = [source, sink].iter().find(|v| v.index() >= num_vertexes) {
+        return Err(Error::Construction(format!("Terminal {} is outside of the {} vertexes", v, num_vertexes)));
+    }
+    if source == sink {
+        return Err(Error::Infeasible(FlowError::SourceIsSink {vertex: source}));
+    }
+    let vertexes = (0..num_vertexes).map(VertexId).collect::<Vec<_>>();
+    let graph = Graph::try_flow_network(&vertexes, &edges, DuplicatePolicy::Parallel, SelfLoopPolicy::Skip)?;
+    Ok((source, sink, graph))
+}
+
+/// Flow assignment of a maximum flow solution. `value` is the claimed flow value and `flows` holds the flow of each
+/// edge as (from, to, flow).
+#[derive(Debug, Clone, Default)]
+pub struct Solution {
+    pub value: Option<i64>,
+    pub flows: Vec<(VertexId, VertexId, i32)>
+}
+
+/// Parses a solution in the DIMACS max flow solution format: an `s <value>` line with the flow value and one
+/// `f <from> <to> <flow>` line per edge. Comment and blank lines are skipped. Fails on malformed lines.
+pub fn read_solution<R: BufRead>(reader: R) -> Result<Solution, Error> {
+    let mut solution: Solution = Default::default();
+    for (i, raw_line) in reader.lines().enumerate() {
+        let line = i + 1;
+        let parse_error = |message: String| Error::Parse {line, message};
+        let text = raw_line.map_err(|e| parse_error(e.to_string()))?;
+        let tokens = text.split_whitespace().collect::<Vec<_>>();
+        let expected = |what: &str, token: &str| {
+            parse_error(format!("Expected an integer for {}, got {}", what, token))
+        };
+        match (tokens.first(), tokens.len()) {
+            (None, _) | (Some(&"c"), _) => {},
+            (Some(&"s"), 2) => {
+                solution.value = Some(tokens[1].parse::<i64>().map_err(|_| expected("the flow value", tokens[1]))?);
+            },
+            (Some(&"f"), 4) => {
+                let u = tokens[1].parse::<VertexId>().map_err(|_| expected("source in flow", tokens[1]))?;
+                let v = tokens[2].parse::<VertexId>().map_err(|_| expected("destination in flow", tokens[2]))?;
+                let flow = tokens[3].parse::<i32>().map_err(|_| expected("flow", tokens[3]))?;
+                solution.flows.push((u, v, flow));
+            },
+            _ => return Err(parse_error(format!("Invalid line: {}", text)))
+        }
+    }
+    Ok(solution)
+}
+
+/// Writes the flow of every edge of a solved graph in the DIMACS max flow solution format read by `read_solution`.
+pub fn write_solution<W: Write>(writer: &mut W, graph: &Graph<FlowEdge>, source: VertexId) -> io::Result<()> {
+    writeln!(writer, "s {}", flow_value(graph, source))?;
+    for (u, v, edge) in capacity_edges(graph) {
+        writeln!(writer, "f {} {} {}", u, v, edge.flow)?;
+    }
+    Ok(())
+}
+
+/// Writes the flow of every edge of a solved graph as CSV rows of `from,to,flow,capacity`.
+pub fn write_solution_csv<W: Write>(writer: &mut W, graph: &Graph<FlowEdge>) -> io::Result<()> {
+    writeln!(writer, "from,to,flow,capacity")?;
+    for (u, v, edge) in capacity_edges(graph) {
+        writeln!(writer, "{},{},{},{}", u, v, edge.flow, edge.capacity)?;
+    }
+    Ok(())
+}
+
+/// Minimum cost flow problem in the DIMACS `.min` format, see `min_cost_from_dimacs_reader`. The graph has the upper
+/// bound of every arc as its capacity, and `lower_bounds` and `costs` follow the order of `Graph::edges`.
+#[derive(Debug, Clone)]
+pub struct MinCostProblem {
+    pub graph: Graph<FlowEdge>,
+    /// Supply of every vertex, negative for a demand.
+    pub supplies: Vec<i64>,
+    pub lower_bounds: Vec<i32>,
+    pub costs: Vec<i64>
+}
+
+/// Parses a minimum cost flow problem in the DIMACS `.min` format produced by generators such as netgen: a
+/// `p min <vertexes> <arcs>` line, `n <id> <supply>` lines for vertexes with a supply or, when negative, a demand, and
+/// `a <from> <to> <lower> <upper> <cost>` lines for the arcs. Vertexes are numbered from 1 in the file and from 0 in
+/// the graph. Comment and blank lines are skipped. Fails on malformed lines, vertexes out of range, a lower bound above
+/// the upper bound, self loops, a number of arcs other than announced, or more vertexes announced than bytes of input.
+/// Nothing is allocated from the counts of the problem line until the whole input has been read and they were checked.
+pub fn min_cost_from_dimacs_reader<R: BufRead>(reader: R) -> Result<MinCostProblem, Error> {
+    let mut n_vertexes: Option<usize> = None;
+    let mut n_arcs = 0;
+    let mut n_bytes = 0;
+    let mut supply_lines: Vec<(VertexId, i64)> = Vec::new();
+    let mut edges: Vec<(VertexId, VertexId, FlowEdge)> = Vec::new();
+    let mut lower_bounds: Vec<i32> = Vec::new();
+    let mut costs: Vec<i64> = Vec::new();
+    for (i, raw_line) in reader.lines().enumerate() {
+        let line = i + 1;
+        let parse_error = |message: String| Error::Parse {line, message};
+        let text = raw_line.map_err(|e| parse_error(e.to_string()))?;
+        n_bytes += text.len() + 1;
+        let tokens = text.split_whitespace().collect::<Vec<_>>();
+        let number = |i: usize, what: &str| tokens[i].parse::<i64>()
+            .map_err(|_| parse_error(format!("Expected an integer for {}, got {}", what, tokens[i])));
+        let count = |i: usize, what: &str| tokens[i].parse::<usize>()
+            .map_err(|_| parse_error(format!("Expected a non negative integer for {}, got {}", what, tokens[i])));
+        let vertex = |i: usize, what: &str| match (number(i, what)?, n_vertexes) {
+            (id, Some(n)) if id >= 1 && id as usize <= n => Ok(VertexId(id as usize - 1)),
+            (id, Some(n)) => Err(parse_error(format!("{} {} is not between 1 and {}", what, id, n))),
+            (_, None) => Err(parse_error("Problem line must come first".to_string()))
+        };
+        match (tokens.first(), tokens.len()) {
+            (None, _) | (Some(&"c"), _) => {},
+            (Some(&"p"), 4) if tokens[1] == "min" => {
+                n_vertexes = Some(count(2, "number of vertexes")?);
+                n_arcs = count(3, "number of arcs")?;
+                supply_lines.clear();
+            },
+            (Some(&"n"), 3) => {
+                let v = vertex(1, "Vertex")?;
+                supply_lines.push((v, number(2, "supply")?));
+            },
+            (Some(&"a"), 6) => {
+                let (u, v) = (vertex(1, "Tail")?, vertex(2, "Head")?);
+                let bound = |i: usize, what: &str| checked_capacity(number(i, what)?).map_err(parse_error);
+                let (lower, upper) = (bound(3, "lower bound")?, bound(4, "upper bound")?);
+                if lower < 0 || lower > upper {
+                    return Err(parse_error(format!("Bounds {} and {} of arc ({}, {}) are not ordered",
+                                                   lower, upper, tokens[1], tokens[2])));
+                }
+                edges.push((u, v, FlowEdge{capacity: upper, flow: 0}));
+                lower_bounds.push(lower);
+                costs.push(number(5, "cost")?);
+            },
+            _ => return Err(parse_error(format!("Invalid line: {}", text)))
+        }
+    }
+    let n_vertexes = n_vertexes.ok_or_else(|| Error::Parse {line: 0, message: "Missing problem line".to_string()})?;
+    if edges.len() != n_arcs {
+        return Err(Error::Construction(format!("Expected {} arcs, got {}", n_arcs, edges.len())));
+    }
+    // Every vertex takes far more memory than a byte of input, so a short input can not claim an arbitrary amount
+    if n_vertexes > n_bytes {
+        return Err(Error::Limit(format!("{} vertexes are more than the {} bytes of input", n_vertexes, n_bytes)));
+    }
+    if n_vertexes as u64 > Index::MAX as u64 {
+        return Err(Graph::<FlowEdge>::too_large());
+    }
+    let mut supplies = vec![0; n_vertexes];
+    for (v, supply) in supply_lines {
+        supplies[v] = supply;
+    }
+    let vertexes = (0..n_vertexes).map(VertexId).collect::<Vec<_>>();
+    // Skipping self loops would shift the lower bounds and costs of the later arcs, so they are rejected
+    let graph = Graph::try_flow_network(&vertexes, &edges, DuplicatePolicy::Parallel, SelfLoopPolicy::Reject)?;
+    debug!("Parsed a min cost flow problem with {} vertexes and {} arcs", n_vertexes, n_arcs);
+    Ok(MinCostProblem {graph, supplies, lower_bounds, costs})
+}
+
+/// Writes a minimum cost flow problem in the DIMACS `.min` format read by `min_cost_from_dimacs_reader`, with an `n`
+/// line for every vertex with a supply or demand.
+pub fn write_min_cost_dimacs<W: Write>(writer: &mut W, problem: &MinCostProblem) -> io::Result<()> {
+    let graph = &problem.graph;
+    writeln!(writer, "p min {} {}", graph.n_vertexes(), graph.n_edges())?;
+    for (v, &supply) in problem.supplies.iter().enumerate().filter(|&(_, &supply)| supply != 0) {
+        writeln!(writer, "n {} {}", v + 1, supply)?;
+    }
+    for (e, (u, v, a)) in graph.edges().enumerate() {
+        writeln!(writer, "a {} {} {} {} {}", u.index() + 1, v.index() + 1, problem.lower_bounds[e],
+                 graph.arcs[a].capacity, problem.costs[e])?;
+    }
+    Ok(())
+}
+
+/// Input formats understood by the flow parsers and writers.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Format {
+    Dicaps, Txt, Dot, Json, Csv
+}
+
+/// Guesses the format of a flow problem by looking at its first lines. DIMACS files start with comment or problem
+/// lines, txt files start with the number of vertexes followed by a matrix row of that many capacities, DOT files
+/// with a digraph header, JSON files with an object and CSV files with their terminal comment or header row.
+/// Returns `None` if the contents match none of the formats, and fails if the file can not be opened.
+pub fn detect_format(file_name: &str) -> io::Result<Option<Format>> {
+    let f = File::open(file_name)?;
+    Ok(detect_format_reader(BufReader::new(f)))
+}
+
+/// Same as `detect_format` but reads the first lines from a buffered reader. The lines are consumed, so callers that
+/// can not reopen their input should buffer it first.
+pub fn detect_format_reader<R: BufRead>(reader: R) -> Option<Format> {
+    let mut lines = reader.lines()
+        .map(|l| l.unwrap_or_default())
+        .filter(|l| !l.trim().is_empty());
+    let first = lines.next()?;
+    let tokens = first.split_whitespace().collect::<Vec<_>>();
+    if first.starts_with('{') {
+        return Some(Format::Json);
+    }
+    let csv_row = first.split(',').count() == 3 && first.split(',').all(|f| f.trim().parse::<i64>().is_ok());
+    if first.starts_with("# source=") || first.trim() == "from,to,capacity" || csv_row {
+        return Some(Format::Csv);
+    }
+    match tokens[0] {
+        "c" | "p" | "n" | "a" => Some(Format::Dicaps),
+        "digraph" | "strict" => Some(Format::Dot),
+        _ => {
+            let n = tokens[0].parse::<usize>().ok()?;
+            let row = lines.next()?;
+            let row_tokens = row.split_whitespace().collect::<Vec<_>>();
+            if tokens.len() == 1 && row_tokens.len() == n && row_tokens.iter().all(|t| t.parse::<i32>().is_ok()) {
+                Some(Format::Txt)
+            } else {
+                None
+            }
+        }
+    }
+}
+
+/// Parses a flow problem from a file with the parser for the given format. Panics if the file does not exist or can
+/// not be parsed, see `flow_from_reader`.
+pub fn flow_from_file(file_name: &str, format: Format) -> (VertexId, VertexId, Graph<FlowEdge>) {
+    let f = File::open(file_name).unwrap_or_else(|_| panic!("Input file does not exist: {}", file_name));
+    flow_from_reader(BufReader::new(f), format).unwrap_or_else(|e| panic!("{}: {}", file_name, e))
+}
+
+/// Parses a flow problem from a buffered reader with the parser for the given format.
+pub fn flow_from_reader<R: BufRead>(reader: R, format: Format) -> Result<(VertexId, VertexId, Graph<FlowEdge>), Error> {
+    let (source, sink, graph) = match format {
+        Format::Dicaps => flow_from_dicaps_reader(reader),
+        Format::Txt => flow_from_txt_reader(reader),
+        Format::Dot => flow_from_dot_reader(reader),
+        Format::Json => flow_from_json_reader(reader),
+        Format::Csv => flow_from_csv_reader(reader)
+    }?;
+    debug!("Parsed a {:?} network with {} vertexes and {} edges, source {} and sink {}",
+           format, graph.n_vertexes(), graph.n_edges(), source, sink);
+    Ok((source, sink, graph))
+}
+
+/// Parses a flow problem with the parser for the given format and merges its parallel edges as `duplicates` says.
+/// A file which can not be opened fails as a parse error on line 0.
+pub fn flow_from_file_with(file_name: &str,
+                           format: Format,
+                           duplicates: DuplicatePolicy) -> Result<(VertexId, VertexId, Graph<FlowEdge>), Error> {
+    let f = File::open(file_name)
+        .map_err(|e| Error::Parse {line: 0, message: format!("Could not open {}: {}", file_name, e)})?;
+    flow_from_reader_with(BufReader::new(f), format, duplicates)
+}
+
+/// Parses a flow problem from a buffered reader with the parser for the given format and merges its parallel edges
+/// as `duplicates` says.
+pub fn flow_from_reader_with<R: BufRead>(reader: R,
+                                         format: Format,
+                                         duplicates: DuplicatePolicy)
+                                         -> Result<(VertexId, VertexId, Graph<FlowEdge>), Error> {
+    let (source, sink, graph) = flow_from_reader(reader, format)?;
+    Ok((source, sink, graph.merge_duplicates(duplicates)?))
+}
+
+/// Writes a flow problem in the given format. Only capacities and terminals are written, flows are dropped.
+pub fn write_flow<W: Write>(writer: &mut W,
+                            format: Format,
+                            graph: &Graph<FlowEdge>,
+                            source: VertexId,
+                            sink: VertexId) -> io::Result<()> {
+    match format {
+        Format::Dicaps => write_dicaps(writer, graph, source, sink),
+        Format::Txt => write_txt(writer, graph, source, sink),
+        Format::Dot => write_dot(writer, graph, source, sink),
+        Format::Json => write_json(writer, graph, source, sink),
+        Format::Csv => write_csv(writer, graph, source, sink)
+    }
+}
+
+/// Returns the edges with positive capacity.
+fn capacity_edges(graph: &Graph<FlowEdge>) -> Vec<(VertexId, VertexId, FlowEdge)> {
+    graph.edges()
+        .map(|(u, v, a)| (u, v, graph.arcs[a]))
+        .filter(|e| e.2.capacity > 0)
+        .collect()
+}
+
+/// Writes a flow problem in the DIMACS format read by `flow_from_dicaps`.
+pub fn write_dicaps<W: Write>(writer: &mut W,
+                              graph: &Graph<FlowEdge>,
+                              source: VertexId,
+                              sink: VertexId) -> io::Result<()> {
+    let edges = capacity_edges(graph);
+    writeln!(writer, "p max {} {}", graph.n_vertexes(), edges.len())?;
+    writeln!(writer, "n {} s", source)?;
+    writeln!(writer, "n {} t", sink)?;
+    for (u, v, edge) in edges {
+        writeln!(writer, "a {} {} {}", u, v, edge.capacity)?;
+    }
+    Ok(())
+}
+
+/// Writes a flow problem as a DOT digraph. Every vertex gets its own statement so isolated vertexes survive a round
+/// trip, the terminals are marked with a `terminal` attribute, named vertexes carry their name in a quoted and escaped
+/// `label` attribute and edges carry a `capacity` attribute.
+pub fn write_dot<W: Write>(writer: &mut W,
+                           graph: &Graph<FlowEdge>,
+                           source: VertexId,
+                           sink: VertexId) -> io::Result<()> {
+    writeln!(writer, "digraph max_flow {{")?;
+    for v in graph.vertexes() {
+        let mut attributes: Vec<String> = Vec::new();
+        if let Some(name) = graph.vertex_name(v) {
+            attributes.push(format!("label={}", quote_dot(name)));
+        }
+        if v == source {
+            attributes.push("terminal=source".to_string());
+        } else if v == sink {
+            attributes.push("terminal=sink".to_string());
+        }
+        if attributes.is_empty() {
+            writeln!(writer, "    {};", v)?;
+        } else {
+            writeln!(writer, "    {} [{}];", v, attributes.join(", "))?;
+        }
+    }
+    for (u, v, edge) in capacity_edges(graph) {
+        writeln!(writer, "    {} -> {} [capacity={}];", u, v, edge.capacity)?;
+    }
+    writeln!(writer, "}}")
+}
+
+/// Writes a flow problem as a JSON object, see `flow_from_json_reader` for the layout.
+pub fn write_json<W: Write>(writer: &mut W,
+                            graph: &Graph<FlowEdge>,
+                            source: VertexId,
+                            sink: VertexId) -> io::Result<()> {
+    writeln!(writer, "{{")?;
+    writeln!(writer, "  \"vertexes\": {},", graph.n_vertexes())?;
+    writeln!(writer, "  \"source\": {},", source)?;
+    writeln!(writer, "  \"sink\": {},", sink)?;
+    write!(writer, "  \"edges\": [")?;
+    for (i, (u, v, edge)) in capacity_edges(graph).into_iter().enumerate() {
+        let separator = if i == 0 { "" } else { "," };
+        write!(writer, "{}\n    {{\"from\": {}, \"to\": {}, \"capacity\": {}}}", separator, u, v, edge.capacity)?;
+    }
+    writeln!(writer, "\n  ]")?;
+    writeln!(writer, "}}")
+}
+
+/// Writes a flow problem as CSV rows of `from,to,capacity` preceded by a comment line holding the terminals. Named
+/// vertexes are written by their names, listed in a `# names=` comment line. Fails with `InvalidInput` before writing
+/// anything if a name contains a comma or whitespace or starts with `#`, since it would not be read back.
+pub fn write_csv<W: Write>(writer: &mut W,
+                           graph: &Graph<FlowEdge>,
+                           source: VertexId,
+                           sink: VertexId) -> io::Result<()> {
+    let unwritable = |name: &&str| name.starts_with('#') || name.contains(|c: char| c == ',' || c.is_whitespace());
+    if let Some(name) = graph.vertexes().filter_map(|v| graph.vertex_name(v)).find(unwritable) {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                  format!("Vertex name {:?} can not be written as CSV", name)));
+    }
+    let label = |v: VertexId| graph.vertex_label(v);
+    writeln!(writer, "# source={} sink={} vertexes={}", label(source), label(sink), graph.n_vertexes())?;
+    if graph.has_vertex_names() {
+        writeln!(writer, "# names={}", graph.vertexes().map(label).collect::<Vec<_>>().join(","))?;
+    }
+    writeln!(writer, "from,to,capacity")?;
+    for (u, v, edge) in capacity_edges(graph) {
+        writeln!(writer, "{},{},{}", label(u), label(v), edge.capacity)?;
+    }
+    Ok(())
+}
+
+/// Writes the capacities of a flow graph in the dense txt format read by `flow_from_txt`: the number of vertexes
+/// followed by one row of the capacity matrix per vertex. The format has no terminal annotations, so vertexes are
+/// relabeled such that `source` is written first and `sink` last, the remaining vertexes keep their relative order.
+/// Parallel edges are merged into one matrix entry. Fails with `InvalidInput` before writing anything if the terminals
+/// are not different vertexes of the graph or merged capacities exceed the 32 bit capacity type.
+pub fn write_txt<W: Write>(writer: &mut W,
+                           graph: &Graph<FlowEdge>,
+                           source: VertexId,
+                           sink: VertexId) -> io::Result<()> {
+    let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidInput, message);
+    if source == sink || source.index() >= graph.n_vertexes() || sink.index() >= graph.n_vertexes() {
+        return Err(invalid(format!("Source {} and sink {} must be different vertexes of the graph", source, sink)));
+    }
+    let mut order: Vec<VertexId> = Vec::with_capacity(graph.n_vertexes());
+    order.push(source);
+    order.extend(graph.vertexes().filter(|v| *v != source && *v != sink));
+    order.push(sink);
+    let mut capacities = vec![vec![0i32; graph.n_vertexes()]; graph.n_vertexes()];
+    for (u, v, edge) in capacity_edges(graph) {
+        capacities[u][v] = capacities[u][v].checked_add(edge.capacity)
+            .ok_or_else(|| invalid(format!("The capacity of the edges from {} to {} exceeds the 32 bit capacity type",
+                                           u, v)))?;
+    }
+    writeln!(writer, "{}", graph.n_vertexes())?;
+    for u in &order {
+        let row = order.iter()
+            .map(|v| capacities[*u][*v].to_string())
+            .collect::<Vec<_>>();
+        writeln!(writer, "{}", row.join(" "))?;
+    }
+    Ok(())
+}
+
+/// Writes the maximum flow linear program of a flow problem in the CPLEX LP format, to cross-check a solution with an
+/// LP solver such as Gurobi or HiGHS. Edge `i` in the order of `edges` becomes the variable `f<i>` bounded by its
+/// capacity, every vertex other than the terminals gets a conservation constraint `v<id>`, and the objective maximizes
+/// `value`, the flow leaving the source minus the flow entering it.
+pub fn write_lp<W: Write>(writer: &mut W, graph: &Graph<FlowEdge>, source: VertexId, sink: VertexId) -> io::Result<()> {
+    writeln!(writer, "\\ Max flow from {} to {}", source, sink)?;
+    writeln!(writer, "Maximize")?;
+    writeln!(writer, " obj: value")?;
+    write_lp_constraints(writer, graph, source, sink, None)
+}
+
+/// Writes the minimum cost flow linear program of a flow problem in the CPLEX LP format, which sends `value` units from
+/// `source` to `sink` at the smallest total cost, where `costs` gives the cost per unit of every edge in the order of
+/// `edges`. The variables and constraints are those of `write_lp`, with the value fixed instead of maximized.
+pub fn write_min_cost_lp<W: Write>(writer: &mut W,
+                                   graph: &Graph<FlowEdge>,
+                                   source: VertexId,
+                                   sink: VertexId,
+                                   costs: &[i64],
+                                   value: i64) -> io::Result<()> {
+    assert_eq!(costs.len(), graph.n_edges(), "Every edge must have a cost");
+    writeln!(writer, "\\ Min cost flow of {} from {} to {}", value, source, sink)?;
+    writeln!(writer, "Minimize")?;
+    write!(writer, " obj:")?;
+    let terms = costs.iter().enumerate().map(|(e, &cost)| (cost, format!("f{}", e))).collect::<Vec<_>>();
+    write_lp_terms(writer, &terms)?;
+    writeln!(writer)?;
+    write_lp_constraints(writer, graph, source, sink, Some(value))
+}
+
+/// Writes the constraints and bounds shared by the LP writers and ends the file. The flow leaving the source is
+/// `value` when it is given and the `value` variable otherwise.
+fn write_lp_constraints<W: Write>(writer: &mut W,
+                                  graph: &Graph<FlowEdge>,
+                                  source: VertexId,
+                                  sink: VertexId,
+                                  value: Option<i64>) -> io::Result<()> {
+    // Self loops leave and enter the same vertex, so they are left out of conservation
+    let mut terms: Vec<Vec<(i64, String)>> = vec![Vec::new(); graph.n_vertexes()];
+    for (e, (u, v, _)) in graph.edges().enumerate().filter(|&(_, (u, v, _))| u != v) {
+        terms[u].push((1, format!("f{}", e)));
+        terms[v].push((-1, format!("f{}", e)));
+    }
+    writeln!(writer, "Subject To")?;
+    write!(writer, " source:")?;
+    match value {
+        Some(value) => {
+            write_lp_terms(writer, &terms[source])?;
+            writeln!(writer, " = {}", value)?;
+        },
+        None => {
+            terms[source].push((-1, "value".to_string()));
+            write_lp_terms(writer, &terms[source])?;
+            writeln!(writer, " = 0")?;
+        }
+    }
+    for v in graph.vertexes().filter(|&v| v != source && v != sink && !terms[v].is_empty()) {
+        write!(writer, " v{}:", v)?;
+        write_lp_terms(writer, &terms[v])?;
+        writeln!(writer, " = 0")?;
+    }
+    writeln!(writer, "Bounds")?;
+    for (e, (_, _, a)) in graph.edges().enumerate() {
+        writeln!(writer, " 0 <= f{} <= {}", e, graph.arcs[a].capacity.max(0))?;
+    }
+    writeln!(writer, "End")
+}
+
+/// Writes a sum of terms, breaking the line every ten terms since LP readers limit the line length.
+fn write_lp_terms<W: Write>(writer: &mut W, terms: &[(i64, String)]) -> io::Result<()> {
+    for (i, (coefficient, name)) in terms.iter().enumerate() {
+        if i > 0 && i % 10 == 0 {
+            write!(writer, "\n  ")?;
+        }
+        let sign = if *coefficient < 0 { "-" } else { "+" };
+        match coefficient.abs() {
+            1 => write!(writer, " {} {}", sign, name)?,
+            magnitude => write!(writer, " {} {} {}", sign, magnitude, name)?
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use FlowGraph;
+    use BFS;
+
+    #[test]
+    fn test_write_txt() {
+        let (source, sink, g) = flow_from_txt("data/txt/test_1.txt");
+        let mut buffer: Vec<u8> = Vec::new();
+        write_txt(&mut buffer, &g, source, sink).unwrap();
+        let mut expected = String::new();
+        File::open("data/txt/test_1.txt").unwrap().read_to_string(&mut expected).unwrap();
+        assert_eq!(String::from_utf8(buffer).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_write_txt_relabels_terminals() {
+        let (source, sink, g) = flow_from_dicaps("data/dicaps/flow-graph.txt");
+        let mut buffer: Vec<u8> = Vec::new();
+        write_txt(&mut buffer, &g, VertexId(2), VertexId(0)).unwrap();
+        let text = String::from_utf8(buffer).unwrap();
+        let mut lines = text.lines();
+        assert_eq!(lines.next(), Some(g.n_vertexes().to_string().as_str()));
+        assert_eq!(lines.count(), g.n_vertexes());
+
+        let mut buffer: Vec<u8> = Vec::new();
+        write_txt(&mut buffer, &g, source, sink).unwrap();
+        let (s, t, mut h) = flow_from_txt_reader(buffer.as_slice()).unwrap();
+        assert_eq!((s, t), (VertexId(0), VertexId(g.n_vertexes() - 1)));
+        assert_eq!(h.max_flow(s, t, BFS), Ok(10));
+
+        // Unsupported terminals and merged capacities beyond 32 bits fail before anything is written
+        let mut buffer: Vec<u8> = Vec::new();
+        for (s, t) in [(source, source), (source, VertexId(g.n_vertexes()))] {
+            assert_eq!(write_txt(&mut buffer, &g, s, t).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+        }
+        let big = FlowEdge{capacity: i32::MAX, flow: 0};
+        let parallel = Graph::flow_network(&[VertexId(0), VertexId(1)], [(VertexId(0), VertexId(1), big); 2]);
+        assert_eq!(write_txt(&mut buffer, &parallel, VertexId(0), VertexId(1)).unwrap_err().kind(),
+                   io::ErrorKind::InvalidInput);
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn test_write_lp() {
+        let input = "p max 4 4\nn 0 s\nn 3 t\na 0 1 4\na 1 3 3\na 0 2 2\na 2 2 1\n";
+        let (source, sink, g) = flow_from_reader(input.as_bytes(), Format::Dicaps).unwrap();
+        let mut buffer: Vec<u8> = Vec::new();
+        write_lp(&mut buffer, &g, source, sink).unwrap();
+        assert_eq!(String::from_utf8(buffer).unwrap(),
+                   "\\ Max flow from 0 to 3\nMaximize\n obj: value\nSubject To\n source: + f0 + f2 - value = 0\n \
+                    v1: - f0 + f1 = 0\n v2: - f2 = 0\nBounds\n 0 <= f0 <= 4\n 0 <= f1 <= 3\n 0 <= f2 <= 2\n \
+                    0 <= f3 <= 1\nEnd\n");
+
+        let mut buffer: Vec<u8> = Vec::new();
+        write_min_cost_lp(&mut buffer, &g, source, sink, &[2, 1, -3, 0], 2).unwrap();
+        let text = String::from_utf8(buffer).unwrap();
+        assert!(text.contains("Minimize\n obj: + 2 f0 + f1 - 3 f2 + 0 f3\n"), "{}", text);
+        assert!(text.contains(" source: + f0 + f2 = 2\n"), "{}", text);
+    }
+
+    #[test]
+    fn test_min_cost_dimacs() {
+        let input = "c netgen style\np min 4 5\nn 1 4\nn 4 -4\na 1 2 0 4 2\na 1 3 0 2 2\na 2 3 1 2 1\n\
+                     a 2 4 0 3 3\na 3 4 0 5 1\n";
+        let problem = min_cost_from_dimacs_reader(input.as_bytes()).unwrap();
+        assert_eq!(problem.graph.size(), (4, 5));
+        assert_eq!(problem.supplies, [4, 0, 0, -4]);
+        assert_eq!(problem.lower_bounds, [0, 0, 1, 0, 0]);
+        assert_eq!(problem.costs, [2, 2, 1, 3, 1]);
+        assert_eq!(problem.graph.edges().nth(2).map(|(u, v, a)| (u, v, problem.graph.arcs[a].capacity)),
+                   Some((VertexId(1), VertexId(2), 2)));
+
+        let mut buffer: Vec<u8> = Vec::new();
+        write_min_cost_dimacs(&mut buffer, &problem).unwrap();
+        let text = String::from_utf8(buffer).unwrap();
+        assert_eq!(text, input.replace("c netgen style\n", ""));
+
+        let invalid = ["p min 2 1\na 1 3 0 1 1\n", "a 1 2 0 1 1\n", "p min 2 1\na 1 2 2 1 1\n",
+                       "p min 2 2\na 1 2 0 1 1\n", "p min 2 1\na 1 2 0 x 1\n",
+                       "p min 2 1\na 1 2 0 3000000000 1\n", "p min 2 1\na 1 1 0 1 1\n"];
+        for input in &invalid {
+            assert!(min_cost_from_dimacs_reader(input.as_bytes()).is_err(), "{}", input);
+        }
+        assert!(matches!(min_cost_from_dimacs_reader(invalid[0].as_bytes()), Err(Error::Parse {line: 2, ..})));
+        // Counts are checked before anything is allocated from them
+        assert!(matches!(min_cost_from_dimacs_reader("p min -1 0\n".as_bytes()), Err(Error::Parse {line: 1, ..})));
+        assert!(matches!(min_cost_from_dimacs_reader("p min 2 -1\n".as_bytes()), Err(Error::Parse {line: 1, ..})));
+        assert!(matches!(min_cost_from_dimacs_reader("p min 100000000000000 0\n".as_bytes()), Err(Error::Limit(_))));
+    }
+
+    #[test]
+    fn test_detect_format() {
+        assert_eq!(detect_format("data/dicaps/flow-graph.txt").unwrap(), Some(Format::Dicaps));
+        assert_eq!(detect_format("data/dicaps/comments.txt").unwrap(), Some(Format::Dicaps));
+        assert_eq!(detect_format("data/txt/test_1.txt").unwrap(), Some(Format::Txt));
+        assert_eq!(detect_format("data/txt/test_6.txt").unwrap(), Some(Format::Txt));
+        assert_eq!(detect_format("README.md").unwrap(), None);
+        assert_eq!(detect_format("data/missing.txt").unwrap_err().kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn test_flow_from_reader() {
+        let input = "c in memory\np max 3 2\nn 0 s\nn 2 t\na 0 1 4\na 1 2 3\n";
+        assert_eq!(detect_format_reader(input.as_bytes()), Some(Format::Dicaps));
+        let (source, sink, mut g) = flow_from_reader(input.as_bytes(), Format::Dicaps).unwrap();
+        assert_eq!(g.max_flow(source, sink, BFS), Ok(3));
+
+        // The input is parsed as it is read, so a reader which fails after the lone `a` ending the input is never read
+        struct Broken;
+        impl Read for Broken {
+            fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+                Err(io::Error::other("broken pipe"))
+            }
+        }
+        let input = "p max 3 2\nn 0 s\nn 2 t\na 0 1 4\na 1 2 3\n";
+        let terminated = format!("{}a\n", input);
+        let (source, sink, mut g) = flow_from_dicaps_reader(terminated.as_bytes().chain(BufReader::new(Broken))).unwrap();
+        assert_eq!(g.max_flow(source, sink, BFS), Ok(3));
+        assert!(matches!(flow_from_dicaps_reader(input.as_bytes().chain(BufReader::new(Broken))),
+                         Err(Error::Parse {line: 6, ..})));
+
+        let input = "3\n0 4 0\n0 0 3\n0 0 0\n";
+        assert_eq!(detect_format_reader(input.as_bytes()), Some(Format::Txt));
+        let (source, sink, mut g) = flow_from_reader(input.as_bytes(), Format::Txt).unwrap();
+        assert_eq!(g.max_flow(source, sink, BFS), Ok(3));
+    }
+
+    #[test]
+    fn test_flow_from_reader_with() {
+        let input = "p max 3 3\nn 0 s\nn 2 t\na 0 1 4\na 1 2 3\na 1 2 2\n";
+        let (source, sink, mut g) = flow_from_reader_with(input.as_bytes(), Format::Dicaps, DuplicatePolicy::KeepMax)
+            .unwrap();
+        assert_eq!(g.n_edges(), 2);
+        assert_eq!(g.max_flow(source, sink, BFS), Ok(3));
+        assert!(flow_from_reader_with(input.as_bytes(), Format::Dicaps, DuplicatePolicy::Error).is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid line")]
+    fn test_dicaps_malformed_record() {
+        flow_from_dicaps("data/dicaps/malformed.txt");
+    }
+
+    #[test]
+    fn test_dicaps_capacity_overflow() {
+        assert_eq!(flow_from_dicaps_reader("p max 2 1\nn 0 s\nn 1 t\na 0 1 3000000000\n".as_bytes()).unwrap_err(),
+                   Error::Parse {line: 4, message: "Capacity 3000000000 exceeds the 32 bit capacity type of the flow \
+                                                    network".to_string()});
+    }
+
+    #[test]
+    fn test_reader_errors() {
+        let invalid = [(Format::Txt, "3\n0 4 0\n0 x 3\n0 0 0\n", 3), (Format::Txt, "x\n", 1),
+                       (Format::Dot, "digraph g {\n    0 [terminal=source];\n    0 -> 1 -> 2;\n}\n", 3),
+                       (Format::Dot, "digraph g {\n    a [terminal=source];\n    a -> b [capacity=x];\n}\n", 3),
+                       (Format::Csv, "# source=0 sink=1\n0,1\n", 2), (Format::Json, "{\"vertexes\": 2,\n]", 2),
+                       (Format::Json, "{\"vertexes\": 2, \"source\": 0}", 0)];
+        for &(format, input, line) in &invalid {
+            match flow_from_reader(input.as_bytes(), format) {
+                Err(Error::Parse {line: l, ..}) => assert_eq!(l, line, "{}", input),
+                other => panic!("Expected a parse error for {}, got {:?}", input, other.map(|n| n.2.size()))
+            }
+        }
+        let unbuildable = [(Format::Txt, "0\n"), (Format::Csv, "# sink=1\n0,1,2\n"),
+                           (Format::Json, "{\"vertexes\": 2, \"source\": 0, \"sink\": 2, \"edges\": []}"),
+                           (Format::Dot, "digraph g {\n    0 [label=a, terminal=source];\n    \
+                                          1 [label=a, terminal=sink];\n}\n")];
+        for &(format, input) in &unbuildable {
+            assert!(matches!(flow_from_reader(input.as_bytes(), format), Err(Error::Construction(_))), "{}", input);
+        }
+        assert_eq!(flow_from_reader("1\n0\n".as_bytes(), Format::Txt).unwrap_err(),
+                   Error::Infeasible(FlowError::SourceIsSink {vertex: VertexId(0)}));
+        assert!(matches!(read_solution("s 10\nf 0 x 5\n".as_bytes()), Err(Error::Parse {line: 2, ..})));
+        assert!(matches!(read_solution("s 10\nq\n".as_bytes()), Err(Error::Parse {line: 2, ..})));
+    }
+
+    #[test]
+    fn test_parse_dicaps_bytes() {
+        for file_name in &["data/dicaps/flow-graph.txt", "data/dicaps/comments.txt", "data/dicaps/multi-terminal.txt"] {
+            let (source, sink, g) = flow_from_dicaps(file_name);
+            let bytes = std::fs::read(file_name).unwrap();
+            let (parsed_source, parsed_sink, parsed) = parse_dicaps_bytes(&bytes).unwrap();
+            assert_eq!((parsed_source, parsed_sink), (source, sink));
+            assert!(g.diff(&parsed).is_empty());
+        }
+
+        let invalid = ["p max 2 1\nn 0 s\nn 1 t\na 0 1 3000000000\n", "p max 2 1\nn 0 s\nn 1 t\na 0 x 1\n",
+                       "p max 2 1\nn 0 s\nn 1 q\n", "p max 2 1\nn 0 s\nn 1 t\na 0 1 1 1\n",
+                       "p max 2 1\nn 0 s\nn 1 t\ncap 0 1 1\na 0 1 1\n", "p min 2 1\nn 0 s\nn 1 t\na 0 1 1\n"];
+        for input in &invalid {
+            assert!(matches!(parse_dicaps_bytes(input.as_bytes()), Err(Error::Parse {..})), "{}", input);
+        }
+        let unbuildable = ["p max 2 2\nn 0 s\nn 1 t\na 0 1 1\n", "p max 2 1\nn 0 s\nn 1 t\na 0 2 1\n",
+                           "p max 2 1\nn 0 s\nn 5 t\na 0 1 1\n", "p max 2 1\nn 1 t\na 0 1 1\n", "n 0 s\nn 0 t\n"];
+        for input in &unbuildable {
+            assert!(matches!(parse_dicaps_bytes(input.as_bytes()), Err(Error::Construction(_))), "{}", input);
+        }
+        assert!(matches!(parse_dicaps_bytes(b"p max 2 1\n\xff\n"), Err(Error::Parse {line: 2, ..})));
+        assert!(matches!(parse_dicaps_bytes(b"p max 99999999999999 0\n"), Err(Error::Limit(_))));
+        assert_eq!(parse_dicaps_bytes(b"p max 2 1\nn 1 s\nn 1 t\na 0 1 1\n").unwrap_err(),
+                   Error::Infeasible(FlowError::SourceIsSink {vertex: VertexId(1)}));
+        // Only the reader takes networks beyond the limit, which still fail if the index type can not hold them
+        #[cfg(feature = "compact-index")]
+        {
+            let huge = b"p max 4294967296 0\nn 0 s\nn 1 t\n";
+            assert!(matches!(parse_dicaps(&huge[..], usize::MAX), Err(Error::Overflow(_))));
+        }
+
+        // Truncated and corrupted inputs fail without panicking
+        let bytes = std::fs::read("data/dicaps/multi-terminal.txt").unwrap();
+        for end in 0..bytes.len() {
+            let _ = parse_dicaps_bytes(&bytes[..end]);
+            for &b in b" \n0-9apnst\xc3" {
+                let mut corrupted = bytes.clone();
+                corrupted[end] = b;
+                let _ = parse_dicaps_bytes(&corrupted);
+            }
+        }
+    }
+
+    #[test]
+    fn test_write_solution() {
+        let (source, sink, mut g) = flow_from_dicaps("data/dicaps/flow-graph.txt");
+        g.max_flow(source, sink, BFS).unwrap();
+        let mut buffer: Vec<u8> = Vec::new();
+        write_solution(&mut buffer, &g, source).unwrap();
+        let solution = read_solution(buffer.as_slice()).unwrap();
+        assert_eq!(solution.value, Some(10));
+        assert_eq!(solution.flows.len(), 5);
+        assert!(solution.flows.contains(&(VertexId(0), VertexId(2), 5)));
+
+        let mut buffer: Vec<u8> = Vec::new();
+        write_solution_csv(&mut buffer, &g).unwrap();
+        let text = String::from_utf8(buffer).unwrap();
+        assert_eq!(text.lines().next(), Some("from,to,flow,capacity"));
+        assert!(text.lines().any(|l| l == "0,2,5,5"));
+    }
+
+    #[test]
+    fn test_round_trip_formats() {
+        let (source, sink, g) = flow_from_dicaps("data/dicaps/central.txt");
+        for format in &[Format::Dicaps, Format::Txt, Format::Dot, Format::Json, Format::Csv] {
+            let mut buffer: Vec<u8> = Vec::new();
+            write_flow(&mut buffer, *format, &g, source, sink).unwrap();
+            assert_eq!(detect_format_reader(buffer.as_slice()), Some(*format));
+            let (s, t, mut h) = flow_from_reader(buffer.as_slice(), *format).unwrap();
+            assert_eq!(h.n_vertexes(), g.n_vertexes());
+            assert_eq!(h.n_edges(), g.n_edges());
+            assert_eq!(h.max_flow(s, t, BFS), Ok(5));
+        }
+    }
+
+    #[test]
+    fn test_dot_terminals() {
+        let input = "digraph g {\n    2 [terminal=source];\n    0 [terminal=\"sink\"];\n\
+                     2 -> 1 [capacity=4];\n    1 -> 0 [capacity=3];\n}\n";
+        let (source, sink, mut g) = flow_from_dot_reader(input.as_bytes()).unwrap();
+        assert_eq!((source, sink), (VertexId(2), VertexId(0)));
+        assert_eq!(g.max_flow(source, sink, BFS), Ok(3));
+    }
+
+    #[test]
+    fn test_named_vertexes() {
+        let input = "digraph g {\n    plant [terminal=source];\n    \"new store\" [terminal=sink];\n\
+                     plant -> depot [capacity=4];\n    depot -> \"new store\" [capacity=3];\n    idle;\n}\n";
+        let (source, sink, g) = flow_from_dot_reader(input.as_bytes()).unwrap();
+        assert_eq!(g.vertex_name(source), Some("plant"));
+        assert_eq!(g.vertex_name(sink), Some("new store"));
+        assert_eq!(g.find_vertex("idle"), Some(VertexId(3)));
+        for format in &[Format::Dot, Format::Csv] {
+            let mut buffer: Vec<u8> = Vec::new();
+            let mut h = g.clone();
+            h.set_vertex_names(vec!["plant".to_string(), "new_store".to_string(), "depot".to_string(),
+                                    "idle".to_string()]).unwrap();
+            write_flow(&mut buffer, *format, &h, source, sink).unwrap();
+            let (s, t, mut read) = flow_from_reader(buffer.as_slice(), *format).unwrap();
+            assert_eq!((s, t), (source, sink));
+            assert_eq!(read.describe_path(&[s, VertexId(2), t]), "plant -> depot -> new_store");
+            assert_eq!(read.vertex_name(VertexId(3)), Some("idle"));
+            assert_eq!(read.max_flow(s, t, BFS), Ok(3));
+        }
+
+        // Integer vertexes are named by labels, the others by their ids
+        let input = "digraph g {\n    0 [label=\"a, b\", terminal=source];\n    1 [terminal=sink];\n\
+                     0 -> 1 [capacity=2];\n}\n";
+        let (_, _, g) = flow_from_dot_reader(input.as_bytes()).unwrap();
+        assert_eq!(g.vertex_name(VertexId(0)), Some("a, b"));
+        assert_eq!(g.vertex_name(VertexId(1)), Some("1"));
+
+        let input = "# source=0 sink=2\n# names=x,y,z\n0,1,5\n1,2,4\n";
+        let (_, _, g) = flow_from_csv_reader(input.as_bytes()).unwrap();
+        assert_eq!(g.describe_arcs(&g.edges().map(|e| e.2).collect::<Vec<_>>()), "(x, y), (y, z)");
+    }
+
+    #[test]
+    fn test_escaped_vertex_names() {
+        let (source, sink, mut g) = flow_from_dicaps("data/dicaps/central.txt");
+        let mut names = (0..g.n_vertexes()).map(|v| v.to_string()).collect::<Vec<_>>();
+        names[source.index()] = "\"north\" plant, \\ 1".to_string();
+        names[sink.index()] = "store\\".to_string();
+        names[1] = "two\nlines".to_string();
+        g.set_vertex_names(names.clone()).unwrap();
+        let mut buffer: Vec<u8> = Vec::new();
+        write_dot(&mut buffer, &g, source, sink).unwrap();
+        let (s, t, read) = flow_from_dot_reader(buffer.as_slice()).unwrap();
+        assert_eq!((s, t), (source, sink));
+        assert_eq!(read.vertexes().map(|v| read.vertex_label(v)).collect::<Vec<_>>(), names);
+        assert_eq!(unquote_dot("\"a\\l\\\"\""), Some("a\\l\"".to_string()));
+        assert_eq!(unquote_dot("\"a\\\""), None);
+
+        // CSV has no quoting, so names which would be split or skipped are not written
+        for name in &["new store", "a,b", "#1", "tab\t"] {
+            let mut h = g.clone();
+            let mut names = names.clone();
+            names[source.index()] = name.to_string();
+            h.set_vertex_names(names).unwrap();
+            let mut buffer: Vec<u8> = Vec::new();
+            let error = write_csv(&mut buffer, &h, source, sink).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidInput, "{}", name);
+            assert!(buffer.is_empty());
+        }
+    }
+}
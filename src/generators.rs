@@ -0,0 +1,741 @@
+use std::collections::HashSet;
+use std::ops::RangeInclusive;
+use rand::Rng;
+use rand::seq::SliceRandom;
+use super::{seeded_rng, FlowEdge, Graph, VertexId};
+
+/// Builds the flow graph of generated edges. The generators number the vertexes by their position, so the terminals
+/// are given as positions as well.
+fn build(n_vertexes: usize,
+         edges: Vec<(VertexId, VertexId, FlowEdge)>,
+         source: usize,
+         sink: usize) -> (VertexId, VertexId, Graph<FlowEdge>) {
+    let vertexes = (0..n_vertexes).map(VertexId).collect::<Vec<_>>();
+    (VertexId(source), VertexId(sink), Graph::flow_network(&vertexes, &edges))
+}
+
+fn edge(u: usize, v: usize, capacity: i32) -> (VertexId, VertexId, FlowEdge) {
+    (VertexId(u), VertexId(v), FlowEdge{capacity, flow: 0})
+}
+
+/// Generates a network with `n_edges` distinct random edges between `n_vertexes` vertexes, with capacities drawn
+/// uniformly from `capacity`. Vertex 0 is the source and vertex `n_vertexes - 1` the sink. Self loops and pairs of
+/// opposite edges are never generated.
+pub fn random_network(n_vertexes: usize,
+                      n_edges: usize,
+                      capacity: RangeInclusive<i32>,
+                      seed: u64) -> (VertexId, VertexId, Graph<FlowEdge>) {
+    random_network_with_rng(n_vertexes, n_edges, capacity, &mut seeded_rng(seed))
+}
+
+/// Same as `random_network` but draws the random numbers from `rng`.
+pub fn random_network_with_rng<R>(n_vertexes: usize,
+                                  n_edges: usize,
+                                  capacity: RangeInclusive<i32>,
+                                  rng: &mut R) -> (VertexId, VertexId, Graph<FlowEdge>)
+    where R: Rng + ?Sized {
+    assert!(n_vertexes >= 2, "A flow network needs at least two vertexes");
+    assert!(n_edges <= n_vertexes * (n_vertexes - 1) / 2,
+            "Can not place {} edges between {} vertexes", n_edges, n_vertexes);
+    let mut used: HashSet<(usize, usize)> = HashSet::with_capacity(n_edges);
+    let mut edges: Vec<(VertexId, VertexId, FlowEdge)> = Vec::with_capacity(n_edges);
+    while edges.len() < n_edges {
+        let u = rng.gen_range(0..n_vertexes);
+        let v = rng.gen_range(0..n_vertexes);
+        if u != v && used.insert((u.min(v), u.max(v))) {
+            edges.push(edge(u, v, rng.gen_range(capacity.clone())));
+        }
+    }
+    build(n_vertexes, edges, 0, n_vertexes - 1)
+}
+
+/// Generates an Erdős–Rényi network where every pair of distinct vertexes is joined with probability `probability`,
+/// by an edge in a random direction with a capacity drawn uniformly from `capacity`. Vertex 0 is the source and vertex
+/// `n_vertexes - 1` the sink. The pairs which are not joined are skipped in geometrically distributed steps, so
+/// generating takes time proportional to the number of edges rather than the number of pairs.
+pub fn erdos_renyi_network(n_vertexes: usize,
+                           probability: f64,
+                           capacity: RangeInclusive<i32>,
+                           seed: u64) -> (VertexId, VertexId, Graph<FlowEdge>) {
+    erdos_renyi_network_with_rng(n_vertexes, probability, capacity, &mut seeded_rng(seed))
+}
+
+/// Same as `erdos_renyi_network` but draws the random numbers from `rng`.
+pub fn erdos_renyi_network_with_rng<R>(n_vertexes: usize,
+                                       probability: f64,
+                                       capacity: RangeInclusive<i32>,
+                                       rng: &mut R) -> (VertexId, VertexId, Graph<FlowEdge>)
+    where R: Rng + ?Sized {
+    assert!(n_vertexes >= 2, "A flow network needs at least two vertexes");
+    assert!((0.0..=1.0).contains(&probability), "The edge probability must be between 0 and 1");
+    let mut edges: Vec<(VertexId, VertexId, FlowEdge)> = Vec::new();
+    if probability > 0.0 {
+        let log_q = (1.0 - probability).ln();
+        // Walk the pairs (v, w) with w < v in order, `w` is the position within the pairs of `v`
+        let mut v: usize = 1;
+        let mut w: i64 = -1;
+        while v < n_vertexes {
+            let skip = if probability < 1.0 { ((1.0 - rng.gen::<f64>()).ln() / log_q).floor() as i64 } else { 0 };
+            w += 1 + skip;
+            while w >= v as i64 && v < n_vertexes {
+                w -= v as i64;
+                v += 1;
+            }
+            if v < n_vertexes {
+                let (u, x) = if rng.gen_bool(0.5) { (v, w as usize) } else { (w as usize, v) };
+                edges.push(edge(u, x, rng.gen_range(capacity.clone())));
+            }
+        }
+    }
+    build(n_vertexes, edges, 0, n_vertexes - 1)
+}
+
+/// Distribution of generated capacities.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CapacityDistribution {
+    /// Capacities drawn uniformly from the range.
+    Uniform(RangeInclusive<i32>),
+    /// Heavy tailed capacities from a Pareto distribution with the given shape, starting at the start of the range and
+    /// truncated at its end. A smaller shape gives a heavier tail.
+    Pareto { range: RangeInclusive<i32>, shape: f64 }
+}
+
+impl CapacityDistribution {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> i32 {
+        match *self {
+            CapacityDistribution::Uniform(ref range) => rng.gen_range(range.clone()),
+            CapacityDistribution::Pareto {ref range, shape} => {
+                let value = *range.start() as f64 * (1.0 - rng.gen::<f64>()).powf(-1.0 / shape);
+                value.min(*range.end() as f64) as i32
+            }
+        }
+    }
+}
+
+/// Generates a scale free network by Barabási–Albert preferential attachment. The network starts as a complete graph
+/// on vertexes `0..=attachments` and every further vertex is joined to `attachments` distinct earlier vertexes, each
+/// chosen with probability proportional to its degree, so the degrees follow a power law. Every edge gets a random
+/// direction and a capacity drawn from `capacity`. Vertex 0 is the source and vertex `n_vertexes - 1` the sink.
+pub fn barabasi_albert_network(n_vertexes: usize,
+                               attachments: usize,
+                               capacity: &CapacityDistribution,
+                               seed: u64) -> (VertexId, VertexId, Graph<FlowEdge>) {
+    barabasi_albert_network_with_rng(n_vertexes, attachments, capacity, &mut seeded_rng(seed))
+}
+
+/// Same as `barabasi_albert_network` but draws the random numbers from `rng`.
+pub fn barabasi_albert_network_with_rng<R>(n_vertexes: usize,
+                                           attachments: usize,
+                                           capacity: &CapacityDistribution,
+                                           rng: &mut R) -> (VertexId, VertexId, Graph<FlowEdge>)
+    where R: Rng + ?Sized {
+    assert!(attachments >= 1 && n_vertexes > attachments, "Needs at least one attachment and more vertexes than that");
+    let mut edges: Vec<(VertexId, VertexId, FlowEdge)> = Vec::new();
+    // Every vertex appears once per incident edge, so a uniform pick is proportional to the degree
+    let mut endpoints: Vec<usize> = Vec::new();
+    let mut join = |u: usize, v: usize, rng: &mut R, endpoints: &mut Vec<usize>| {
+        let (from, to) = if rng.gen_bool(0.5) { (u, v) } else { (v, u) };
+        edges.push(edge(from, to, capacity.sample(rng)));
+        endpoints.push(u);
+        endpoints.push(v);
+    };
+    for v in 1..=attachments {
+        for u in 0..v {
+            join(u, v, rng, &mut endpoints);
+        }
+    }
+    let mut targets: Vec<usize> = Vec::with_capacity(attachments);
+    for v in attachments + 1..n_vertexes {
+        targets.clear();
+        while targets.len() < attachments {
+            let u = endpoints[rng.gen_range(0..endpoints.len())];
+            if !targets.contains(&u) {
+                targets.push(u);
+            }
+        }
+        for &u in &targets {
+            join(u, v, rng, &mut endpoints);
+        }
+    }
+    build(n_vertexes, edges, 0, n_vertexes - 1)
+}
+
+/// Generates a `rows` by `cols` grid with an edge from every vertex to its right and lower neighbor. Vertex
+/// `r * cols + c` is at row `r` and column `c`, the source is the top left corner and the sink the bottom right corner.
+pub fn grid_network(rows: usize,
+                    cols: usize,
+                    capacity: RangeInclusive<i32>,
+                    seed: u64) -> (VertexId, VertexId, Graph<FlowEdge>) {
+    grid_network_with_rng(rows, cols, capacity, &mut seeded_rng(seed))
+}
+
+/// Same as `grid_network` but draws the random numbers from `rng`.
+pub fn grid_network_with_rng<R>(rows: usize,
+                                cols: usize,
+                                capacity: RangeInclusive<i32>,
+                                rng: &mut R) -> (VertexId, VertexId, Graph<FlowEdge>)
+    where R: Rng + ?Sized {
+    assert!(rows * cols >= 2, "A flow network needs at least two vertexes");
+    let mut edges: Vec<(VertexId, VertexId, FlowEdge)> = Vec::new();
+    for r in 0..rows {
+        for c in 0..cols {
+            let u = r * cols + c;
+            if c + 1 < cols {
+                edges.push(edge(u, u + 1, rng.gen_range(capacity.clone())));
+            }
+            if r + 1 < rows {
+                edges.push(edge(u, u + cols, rng.gen_range(capacity.clone())));
+            }
+        }
+    }
+    build(rows * cols, edges, 0, rows * cols - 1)
+}
+
+/// Neighbors of a vertex in a mesh.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Connectivity {
+    /// The vertexes left, right, above and below.
+    Four,
+    /// The four neighbors and the four diagonal neighbors.
+    Eight
+}
+
+/// Placement of the terminals of a mesh.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum MeshTerminals {
+    /// The source is the top left vertex and the sink the bottom right vertex.
+    Corners,
+    /// The source is an extra vertex `rows * cols` with edges to the whole left column and the sink is the extra vertex
+    /// `rows * cols + 1` with edges from the whole right column.
+    Layers
+}
+
+/// Generates a `rows` by `cols` mesh where every vertex has an edge to each of its neighbors, so neighbors are joined
+/// in both directions as in the networks of vision problems. Vertex `r * cols + c` is at row `r` and column `c`. The
+/// capacity of the edge from `u` to `v` is `capacity(u, v)`, which is also called for the edges of terminal layers.
+pub fn mesh_network<F>(rows: usize,
+                       cols: usize,
+                       connectivity: Connectivity,
+                       terminals: MeshTerminals,
+                       mut capacity: F) -> (VertexId, VertexId, Graph<FlowEdge>)
+    where F: FnMut(VertexId, VertexId) -> i32 {
+    assert!(rows * cols >= 2, "A flow network needs at least two vertexes");
+    let mut offsets: Vec<(isize, isize)> = vec![(0, 1), (1, 0), (0, -1), (-1, 0)];
+    if connectivity == Connectivity::Eight {
+        offsets.extend([(1, 1), (1, -1), (-1, 1), (-1, -1)]);
+    }
+    let mut edges: Vec<(VertexId, VertexId, FlowEdge)> = Vec::new();
+    for r in 0..rows {
+        for c in 0..cols {
+            let u = r * cols + c;
+            for &(dr, dc) in &offsets {
+                let (nr, nc) = (r as isize + dr, c as isize + dc);
+                if nr >= 0 && nc >= 0 && (nr as usize) < rows && (nc as usize) < cols {
+                    let v = nr as usize * cols + nc as usize;
+                    edges.push(edge(u, v, capacity(VertexId(u), VertexId(v))));
+                }
+            }
+        }
+    }
+    match terminals {
+        MeshTerminals::Corners => build(rows * cols, edges, 0, rows * cols - 1),
+        MeshTerminals::Layers => {
+            let (source, sink) = (rows * cols, rows * cols + 1);
+            for r in 0..rows {
+                let (first, last) = (r * cols, r * cols + cols - 1);
+                edges.push(edge(source, first, capacity(VertexId(source), VertexId(first))));
+                edges.push(edge(last, sink, capacity(VertexId(last), VertexId(sink))));
+            }
+            build(rows * cols + 2, edges, source, sink)
+        }
+    }
+}
+
+/// Generates `layers` layers of `width` vertexes where every vertex has edges to `degree` distinct random vertexes of
+/// the next layer. Layer `i` holds vertexes `i * width` to `(i + 1) * width - 1`, the source is vertex
+/// `layers * width` with edges to the whole first layer and the sink is vertex `layers * width + 1` with edges from
+/// the whole last layer.
+pub fn layered_network(layers: usize,
+                       width: usize,
+                       degree: usize,
+                       capacity: RangeInclusive<i32>,
+                       seed: u64) -> (VertexId, VertexId, Graph<FlowEdge>) {
+    layered_network_with_rng(layers, width, degree, capacity, &mut seeded_rng(seed))
+}
+
+/// Same as `layered_network` but draws the random numbers from `rng`.
+pub fn layered_network_with_rng<R>(layers: usize,
+                                   width: usize,
+                                   degree: usize,
+                                   capacity: RangeInclusive<i32>,
+                                   rng: &mut R) -> (VertexId, VertexId, Graph<FlowEdge>)
+    where R: Rng + ?Sized {
+    assert!(layers >= 1 && width >= 1, "A layered network needs at least one layer of one vertex");
+    assert!(degree <= width, "The degree can not exceed the layer width");
+    let source = layers * width;
+    let sink = source + 1;
+    let mut edges: Vec<(VertexId, VertexId, FlowEdge)> = Vec::new();
+    for i in 0..width {
+        edges.push(edge(source, i, rng.gen_range(capacity.clone())));
+        edges.push(edge((layers - 1) * width + i, sink, rng.gen_range(capacity.clone())));
+    }
+    for layer in 0..layers - 1 {
+        for i in 0..width {
+            let u = layer * width + i;
+            for offset in rand::seq::index::sample(rng, width, degree).iter() {
+                edges.push(edge(u, (layer + 1) * width + offset, rng.gen_range(capacity.clone())));
+            }
+        }
+    }
+    build(layers * width + 2, edges, source, sink)
+}
+
+/// Generates a layered acyclic network like the task graphs of scheduling and pipeline problems: `layers` layers of
+/// `width` vertexes where every vertex has an edge to each vertex of the next layer with probability `density`, with
+/// capacities drawn uniformly from `capacity`. A vertex without an edge to the next layer gets one to a random vertex
+/// of it and a vertex without an edge from the previous layer gets one from a random vertex of it, so no vertex is cut
+/// off. Layer `i` holds vertexes `i * width` to `(i + 1) * width - 1`, the source is vertex `layers * width` with edges
+/// to the first layer and the sink is vertex `layers * width + 1` with edges from the last layer.
+pub fn layered_dag(layers: usize,
+                   width: usize,
+                   density: f64,
+                   capacity: RangeInclusive<i32>,
+                   seed: u64) -> (VertexId, VertexId, Graph<FlowEdge>) {
+    layered_dag_with_rng(layers, width, density, capacity, &mut seeded_rng(seed))
+}
+
+/// Same as `layered_dag` but draws the random numbers from `rng`.
+pub fn layered_dag_with_rng<R>(layers: usize,
+                               width: usize,
+                               density: f64,
+                               capacity: RangeInclusive<i32>,
+                               rng: &mut R) -> (VertexId, VertexId, Graph<FlowEdge>)
+    where R: Rng + ?Sized {
+    assert!(layers >= 1 && width >= 1, "A layered network needs at least one layer of one vertex");
+    assert!((0.0..=1.0).contains(&density), "The density must be between 0 and 1");
+    let source = layers * width;
+    let sink = source + 1;
+    let mut edges: Vec<(VertexId, VertexId, FlowEdge)> = Vec::new();
+    for i in 0..width {
+        edges.push(edge(source, i, rng.gen_range(capacity.clone())));
+    }
+    for layer in 0..layers - 1 {
+        let (first, next) = (layer * width, (layer + 1) * width);
+        let mut has_in = vec![false; width];
+        for u in first..next {
+            let mut has_out = false;
+            for (offset, reached) in has_in.iter_mut().enumerate() {
+                if rng.gen_bool(density) {
+                    edges.push(edge(u, next + offset, rng.gen_range(capacity.clone())));
+                    *reached = true;
+                    has_out = true;
+                }
+            }
+            if !has_out {
+                let offset = rng.gen_range(0..width);
+                edges.push(edge(u, next + offset, rng.gen_range(capacity.clone())));
+                has_in[offset] = true;
+            }
+        }
+        for (offset, _) in has_in.into_iter().enumerate().filter(|&(_, has)| !has) {
+            edges.push(edge(rng.gen_range(first..next), next + offset, rng.gen_range(capacity.clone())));
+        }
+    }
+    for i in 0..width {
+        edges.push(edge((layers - 1) * width + i, sink, rng.gen_range(capacity.clone())));
+    }
+    build(layers * width + 2, edges, source, sink)
+}
+
+/// Generates a bipartite matching instance with unit capacities. Vertexes `0..n_left` form the left side and
+/// `n_left..n_left + n_right` the right side, and every left vertex is joined to every right vertex with probability
+/// `density`. The source is vertex `n_left + n_right` with an edge to every left vertex and the sink is the next vertex
+/// with an edge from every right vertex, so the maximum flow is the size of a maximum matching.
+pub fn random_bipartite(n_left: usize,
+                        n_right: usize,
+                        density: f64,
+                        seed: u64) -> (VertexId, VertexId, Graph<FlowEdge>) {
+    random_bipartite_with_rng(n_left, n_right, density, &mut seeded_rng(seed))
+}
+
+/// Same as `random_bipartite` but draws the random numbers from `rng`.
+pub fn random_bipartite_with_rng<R>(n_left: usize,
+                                    n_right: usize,
+                                    density: f64,
+                                    rng: &mut R) -> (VertexId, VertexId, Graph<FlowEdge>)
+    where R: Rng + ?Sized {
+    assert!((0.0..=1.0).contains(&density), "The density must be between 0 and 1");
+    let source = n_left + n_right;
+    let sink = source + 1;
+    let mut edges: Vec<(VertexId, VertexId, FlowEdge)> = Vec::new();
+    for u in 0..n_left {
+        edges.push(edge(source, u, 1));
+        for v in n_left..source {
+            if rng.gen_bool(density) {
+                edges.push(edge(u, v, 1));
+            }
+        }
+    }
+    for v in n_left..source {
+        edges.push(edge(v, sink, 1));
+    }
+    build(n_left + n_right + 2, edges, source, sink)
+}
+
+/// Generates a GENRMF network of Goldfarb and Grigoriadis: `frames` frames, each an `side` by `side` grid. Within a
+/// frame every vertex has an edge to each of its four neighbors with capacity `capacity.end() * side * side`, and
+/// every vertex has an edge to the vertex of the next frame given by a random permutation, with a capacity drawn
+/// uniformly from `capacity`. Frame `f` holds vertexes `f * side * side` to `(f + 1) * side * side - 1`, the source is
+/// the first vertex of the first frame and the sink the last vertex of the last frame.
+pub fn genrmf_network(side: usize,
+                      frames: usize,
+                      capacity: RangeInclusive<i32>,
+                      seed: u64) -> (VertexId, VertexId, Graph<FlowEdge>) {
+    genrmf_network_with_rng(side, frames, capacity, &mut seeded_rng(seed))
+}
+
+/// Same as `genrmf_network` but draws the random numbers from `rng`.
+pub fn genrmf_network_with_rng<R>(side: usize,
+                                  frames: usize,
+                                  capacity: RangeInclusive<i32>,
+                                  rng: &mut R) -> (VertexId, VertexId, Graph<FlowEdge>)
+    where R: Rng + ?Sized {
+    assert!(side >= 1 && frames >= 1 && side * side * frames >= 2, "A flow network needs at least two vertexes");
+    let frame_size = side * side;
+    let in_frame = capacity.end().saturating_mul(frame_size.min(i32::MAX as usize) as i32);
+    let mut edges: Vec<(VertexId, VertexId, FlowEdge)> = Vec::new();
+    for f in 0..frames {
+        let first = f * frame_size;
+        for r in 0..side {
+            for c in 0..side {
+                let u = first + r * side + c;
+                if c + 1 < side {
+                    edges.push(edge(u, u + 1, in_frame));
+                    edges.push(edge(u + 1, u, in_frame));
+                }
+                if r + 1 < side {
+                    edges.push(edge(u, u + side, in_frame));
+                    edges.push(edge(u + side, u, in_frame));
+                }
+            }
+        }
+        if f + 1 < frames {
+            let mut permutation = (0..frame_size).collect::<Vec<_>>();
+            permutation.shuffle(rng);
+            for (i, p) in permutation.into_iter().enumerate() {
+                edges.push(edge(first + i, first + frame_size + p, rng.gen_range(capacity.clone())));
+            }
+        }
+    }
+    build(frame_size * frames, edges, 0, frame_size * frames - 1)
+}
+
+/// Generates a random level graph of the Washington generator: `levels` levels of `width` vertexes where every vertex
+/// has edges to three distinct random vertexes of the next level, or to the whole next level if it is narrower, with
+/// capacities drawn uniformly from `capacity`. Level `i` holds vertexes `i * width` to `(i + 1) * width - 1`. The
+/// source is vertex `levels * width` with edges to the first level and the sink is vertex `levels * width + 1` with
+/// edges from the last level, and the terminal edges get capacity `i32::MAX` so they never limit the flow.
+pub fn washington_rlg_network(levels: usize,
+                              width: usize,
+                              capacity: RangeInclusive<i32>,
+                              seed: u64) -> (VertexId, VertexId, Graph<FlowEdge>) {
+    washington_rlg_network_with_rng(levels, width, capacity, &mut seeded_rng(seed))
+}
+
+/// Same as `washington_rlg_network` but draws the random numbers from `rng`.
+pub fn washington_rlg_network_with_rng<R>(levels: usize,
+                                          width: usize,
+                                          capacity: RangeInclusive<i32>,
+                                          rng: &mut R) -> (VertexId, VertexId, Graph<FlowEdge>)
+    where R: Rng + ?Sized {
+    assert!(levels >= 1 && width >= 1, "A level graph needs at least one level of one vertex");
+    let source = levels * width;
+    let sink = source + 1;
+    let mut edges: Vec<(VertexId, VertexId, FlowEdge)> = Vec::new();
+    for i in 0..width {
+        edges.push(edge(source, i, i32::MAX));
+    }
+    for level in 0..levels - 1 {
+        for i in 0..width {
+            let u = level * width + i;
+            for offset in rand::seq::index::sample(rng, width, width.min(3)).iter() {
+                edges.push(edge(u, (level + 1) * width + offset, rng.gen_range(capacity.clone())));
+            }
+        }
+    }
+    for i in 0..width {
+        edges.push(edge((levels - 1) * width + i, sink, i32::MAX));
+    }
+    build(levels * width + 2, edges, source, sink)
+}
+
+/// Generates the classic network on which augmenting along arbitrary paths can need `2 * capacity` augmentations: two
+/// paths of capacity `capacity` from the source (0) to the sink (3) that share a cross edge of capacity 1. Each
+/// augmentation which uses the cross edge, alternately forward and backward, only adds one unit of flow.
+pub fn worst_case_network(capacity: i32) -> (VertexId, VertexId, Graph<FlowEdge>) {
+    zigzag_network(capacity, 1)
+}
+
+/// Generates the worst case network with `rungs` cross edges: two paths of capacity `capacity` from the source (0) to
+/// the sink (`2 * rungs + 1`) through vertexes `1..=rungs` and `rungs + 1..=2 * rungs`, where the `i`th vertexes of
+/// the paths are joined by a cross edge of capacity 1 whose direction alternates. An augmenting path may zig-zag over
+/// every cross edge, so bad path choices, such as the `zigzag_paths`, can need `2 * capacity` augmentations while
+/// shortest augmenting paths need 2. The depth first search of `FlowGraph::max_flow` needs 2 as well, since it gives
+/// both neighbors of the source their parent in its first step and never zig-zags. With one rung this is
+/// `worst_case_network`.
+pub fn zigzag_network(capacity: i32, rungs: usize) -> (VertexId, VertexId, Graph<FlowEdge>) {
+    assert!(rungs >= 1, "The network needs at least one cross edge");
+    let sink = 2 * rungs + 1;
+    let mut edges = vec![edge(0, 1, capacity), edge(0, rungs + 1, capacity)];
+    for i in 1..=rungs {
+        let (top, bottom) = (i, rungs + i);
+        edges.push(if i % 2 == 1 { edge(top, bottom, 1) } else { edge(bottom, top, 1) });
+        if i < rungs {
+            edges.push(edge(top, top + 1, capacity));
+            edges.push(edge(bottom, bottom + 1, capacity));
+        } else {
+            edges.push(edge(top, sink, capacity));
+            edges.push(edge(bottom, sink, capacity));
+        }
+    }
+    build(sink + 1, edges, 0, sink)
+}
+
+/// Returns the two paths of `zigzag_network` with `rungs` cross edges which zig-zag over every cross edge, one
+/// starting along the first and one along the second path. Augmenting along them in turn with `strategy::Scripted`
+/// moves one unit of flow per augmentation, so reaching the maximum flow takes `2 * capacity` augmentations.
+pub fn zigzag_paths(rungs: usize) -> Vec<Vec<VertexId>> {
+    [true, false].iter().map(|&starts_first| {
+        let mut path = vec![VertexId(0)];
+        let mut first = starts_first;
+        for i in 1..=rungs {
+            let (here, there) = if first { (i, rungs + i) } else { (rungs + i, i) };
+            path.push(VertexId(here));
+            path.push(VertexId(there));
+            first = !first;
+        }
+        path.push(VertexId(2 * rungs + 1));
+        path
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use partition::partition_with_rng;
+    use strategy::{PathStrategy, Random, Scripted};
+    use {ArcId, FlowGraph, BFS, DFS};
+
+    /// Returns the edges with their vertexes as positions, which the generators document.
+    fn edges(g: &Graph<FlowEdge>) -> impl Iterator<Item = (usize, usize, ArcId)> + '_ {
+        g.edges().map(|(u, v, a)| (u.index(), v.index(), a))
+    }
+
+    #[test]
+    fn test_random_network() {
+        let (source, sink, g) = random_network(20, 60, 1..=10, 7);
+        assert_eq!((source.index(), sink.index()), (0, 19));
+        assert_eq!(g.n_vertexes(), 20);
+        assert_eq!(g.n_edges(), 60);
+        for (_, _, arc) in g.edges() {
+            assert!((1..=10).contains(&g.arcs[arc].capacity));
+        }
+        let (_, _, mut h) = random_network(20, 60, 1..=10, 7);
+        let mut g = g;
+        assert_eq!(g.max_flow(source, sink, BFS), h.max_flow(source, sink, DFS));
+    }
+
+    #[test]
+    fn test_seeded_rng() {
+        let (_, _, g) = random_network(20, 60, 1..=10, 5);
+        assert!(g.diff(&random_network_with_rng(20, 60, 1..=10, &mut seeded_rng(5)).2).is_empty());
+
+        // A single generator drawn by every step makes the whole run repeatable from its seed
+        let run = |seed: u64| {
+            let mut rng = seeded_rng(seed);
+            let (source, sink, mut g) = layered_dag_with_rng(4, 5, 0.4, 1..=10, &mut rng);
+            let flow = g.max_flow_with_strategy(source, sink, &mut Random::from_rng(&mut rng)).unwrap();
+            let cut = partition_with_rng(&g, 3, &mut rng).unwrap();
+            (g, flow, cut)
+        };
+        let (g, flow, cut) = run(11);
+        let (h, repeated_flow, repeated_cut) = run(11);
+        assert!(g.diff(&h).is_empty());
+        assert_eq!(g.arcs.iter().map(|arc| arc.flow).collect::<Vec<_>>(),
+                   h.arcs.iter().map(|arc| arc.flow).collect::<Vec<_>>());
+        assert_eq!((flow, cut), (repeated_flow, repeated_cut));
+    }
+
+    #[test]
+    fn test_erdos_renyi_network() {
+        let (source, sink, g) = erdos_renyi_network(200, 0.1, 1..=10, 3);
+        assert_eq!((source.index(), sink.index()), (0, 199));
+        // The expected number of edges is 0.1 * 200 * 199 / 2 = 1990 with a standard deviation of about 42
+        assert!((1800..2180).contains(&g.n_edges()), "{} edges", g.n_edges());
+        let mut pairs = edges(&g).map(|(u, v, _)| (u.min(v), u.max(v))).collect::<Vec<_>>();
+        pairs.sort();
+        pairs.dedup();
+        assert_eq!(pairs.len(), g.n_edges());
+        assert!(pairs.iter().all(|&(u, v)| u < v));
+        assert_eq!(erdos_renyi_network(10, 0.0, 1..=1, 0).2.n_edges(), 0);
+        assert_eq!(erdos_renyi_network(10, 1.0, 1..=1, 0).2.n_edges(), 45);
+    }
+
+    #[test]
+    fn test_random_bipartite() {
+        let (source, sink, mut g) = random_bipartite(30, 20, 0.2, 5);
+        assert_eq!((source.index(), sink.index()), (50, 51));
+        assert!(edges(&g).all(|(u, v, a)| {
+            g.arcs[a].capacity == 1 && (u == source.index() || v == sink.index() || (u < 30 && v >= 30))
+        }));
+        let flow = g.max_flow(source, sink, BFS).unwrap();
+        assert!(flow <= 20);
+        // Every unit of flow uses a distinct matching edge
+        assert_eq!(edges(&g).filter(|&(u, v, a)| u < 30 && v < 50 && g.arcs[a].flow == 1).count() as i64, flow);
+
+        let (source, sink, mut g) = random_bipartite(4, 6, 1.0, 0);
+        assert_eq!(g.n_edges(), 4 + 24 + 6);
+        assert_eq!(g.max_flow(source, sink, DFS), Ok(4));
+    }
+
+    #[test]
+    fn test_grid_network() {
+        let (source, sink, mut g) = grid_network(3, 4, 5..=5, 1);
+        assert_eq!((source.index(), sink.index()), (0, 11));
+        assert_eq!(g.n_edges(), 3 * 3 + 2 * 4);
+        assert_eq!(g.max_flow(source, sink, BFS), Ok(10));
+    }
+
+    #[test]
+    fn test_mesh_network() {
+        let (source, sink, mut g) = mesh_network(3, 3, Connectivity::Four, MeshTerminals::Corners, |_, _| 1);
+        assert_eq!((source.index(), sink.index()), (0, 8));
+        assert_eq!(g.n_edges(), 2 * (3 * 2 + 2 * 3));
+        assert_eq!(g.max_flow(source, sink, BFS), Ok(2));
+
+        let (source, sink, mut g) = mesh_network(3, 3, Connectivity::Eight, MeshTerminals::Corners, |_, _| 1);
+        assert_eq!(g.n_edges(), 2 * (3 * 2 + 2 * 3) + 4 * 2 * 2);
+        assert_eq!(g.max_flow(source, sink, BFS), Ok(3));
+
+        let (source, sink, mut g) = mesh_network(3, 4, Connectivity::Four, MeshTerminals::Layers, |u, v| {
+            if u == VertexId(12) || v == VertexId(13) { 5 } else { (u.index() + v.index()) as i32 }
+        });
+        assert_eq!((source.index(), sink.index()), (12, 13));
+        assert!(edges(&g).all(|(u, v, a)| g.arcs[a].capacity == if u == 12 || v == 13 { 5 } else { (u + v) as i32 }));
+        assert_eq!(g.max_flow(source, sink, DFS), Ok(15));
+    }
+
+    #[test]
+    fn test_layered_network() {
+        let (source, sink, mut g) = layered_network(4, 5, 2, 3..=3, 11);
+        assert_eq!((source.index(), sink.index()), (20, 21));
+        assert_eq!(g.n_edges(), 2 * 5 + 3 * 5 * 2);
+        assert!(g.max_flow(source, sink, BFS).unwrap() <= 15);
+    }
+
+    #[test]
+    fn test_layered_dag() {
+        let (source, sink, mut g) = layered_dag(5, 6, 0.0, 1..=1, 3);
+        assert_eq!((source.index(), sink.index(), g.n_vertexes()), (30, 31, 32));
+        // Without density every vertex still reaches the next layer and is reached from the previous one
+        for u in 0..30 {
+            assert!(u >= 24 || edges(&g).any(|(from, to, _)| from == u && to / 6 == u / 6 + 1));
+            assert!(u < 6 || edges(&g).any(|(from, to, _)| to == u && from / 6 + 1 == u / 6));
+        }
+        assert!(g.max_flow(source, sink, BFS).unwrap() >= 1);
+
+        let (source, sink, mut g) = layered_dag(4, 5, 1.0, 2..=2, 7);
+        assert_eq!(g.n_edges(), 2 * 5 + 3 * 5 * 5);
+        assert!(edges(&g).all(|(u, v, _)| u == source.index() || v == sink.index() || v / 5 == u / 5 + 1));
+        assert_eq!(g.max_flow(source, sink, BFS), Ok(10));
+    }
+
+    #[test]
+    fn test_barabasi_albert_network() {
+        let (source, sink, mut g) = barabasi_albert_network(200, 2, &CapacityDistribution::Uniform(1..=9), 4);
+        assert_eq!((source.index(), sink.index()), (0, 199));
+        assert_eq!(g.n_edges(), 3 + 197 * 2);
+        let degree = |v: usize| edges(&g).filter(|&(u, w, _)| u == v || w == v).count();
+        // Preferential attachment makes the oldest vertexes hubs
+        assert!((0..3).map(degree).max().unwrap() >= 15);
+        assert!(g.edges().all(|(_, _, a)| (1..=9).contains(&g.arcs[a].capacity)));
+        assert!(g.max_flow(source, sink, BFS).unwrap_or(0) <= 2 * 9);
+
+        let pareto = CapacityDistribution::Pareto {range: 10..=1000, shape: 1.5};
+        let (_, _, g) = barabasi_albert_network(300, 3, &pareto, 8);
+        let capacities = g.edges().map(|(_, _, a)| g.arcs[a].capacity).collect::<Vec<_>>();
+        assert!(capacities.iter().all(|c| (10..=1000).contains(c)));
+        assert!(capacities.iter().filter(|&&c| c < 20).count() > capacities.len() / 2);
+        assert!(capacities.iter().any(|&c| c > 100));
+    }
+
+    #[test]
+    fn test_genrmf_network() {
+        let (source, sink, mut g) = genrmf_network(3, 4, 1..=10, 2);
+        assert_eq!((source.index(), sink.index(), g.n_vertexes()), (0, 35, 36));
+        assert_eq!(g.n_edges(), 4 * 2 * 12 + 3 * 9);
+        // Every vertex of a frame but the last has exactly one edge to the next frame
+        for u in 0..27 {
+            assert_eq!(edges(&g).filter(|&(from, to, _)| from == u && to / 9 == u / 9 + 1).count(), 1);
+        }
+        assert!(edges(&g).all(|(u, v, a)| u / 9 != v / 9 || g.arcs[a].capacity == 90));
+        let flow = g.max_flow(source, sink, BFS).unwrap();
+        assert!(flow > 0 && flow <= 9 * 10);
+    }
+
+    #[test]
+    fn test_washington_rlg_network() {
+        let (source, sink, mut g) = washington_rlg_network(5, 6, 1..=8, 4);
+        assert_eq!((source.index(), sink.index()), (30, 31));
+        assert_eq!(g.n_edges(), 6 + 4 * 6 * 3 + 6);
+        let flow = g.max_flow(source, sink, DFS).unwrap();
+        assert!(flow > 0 && flow <= 6 * 3 * 8);
+        assert_eq!(washington_rlg_network(3, 2, 1..=1, 0).2.n_edges(), 2 + 2 * 2 * 2 + 2);
+    }
+
+    #[test]
+    fn test_worst_case_network() {
+        let (source, sink, mut g) = worst_case_network(1000);
+        let mut augmentations = 0;
+        assert_eq!(g.max_flow_observed(source, sink, BFS, |p| {
+            augmentations = p.augmentations;
+            true
+        }), Ok(2000));
+        assert_eq!(augmentations, 2);
+        assert_eq!(edges(&g).map(|(u, v, _)| (u, v)).collect::<Vec<_>>(), [(0, 1), (0, 2), (1, 2), (1, 3), (2, 3)]);
+    }
+
+    #[test]
+    fn test_zigzag_network() {
+        let (source, sink, g) = zigzag_network(500, 4);
+        assert_eq!((source.index(), sink.index(), g.n_edges()), (0, 9, 2 + 4 * 3));
+        assert_eq!(edges(&g).filter(|&(_, _, a)| g.arcs[a].capacity == 1).map(|(u, v, _)| (u, v)).collect::<Vec<_>>(),
+                   [(1, 5), (6, 2), (3, 7), (8, 4)]);
+        let (flow, stats) = g.clone().max_flow_with_stats(source, sink, BFS).unwrap();
+        assert_eq!((flow, stats.augmenting_paths), (1000, 2));
+        let (flow, stats) = g.clone().max_flow_with_stats(source, sink, DFS).unwrap();
+        assert_eq!((flow, stats.augmenting_paths), (1000, 2));
+
+        // Zig-zagging over every cross edge moves one unit per augmentation
+        let mut zigzag = Scripted::new(zigzag_paths(4));
+        let mut augmentations = 0;
+        let mut counting = |graph: &Graph<FlowEdge>, s: VertexId, t: VertexId| {
+            augmentations += 1;
+            zigzag.next_path(graph, s, t)
+        };
+        assert_eq!(g.clone().max_flow_with_strategy(source, sink, &mut counting), Ok(1000));
+        // One call per augmentation and a last one which finds no path
+        assert_eq!(augmentations, 2 * 500 + 1);
+
+        // A limit on the augmentations leaves the flow short of the maximum
+        let mut zigzag = Scripted::new(zigzag_paths(4));
+        let mut remaining = 10;
+        let mut limited = |graph: &Graph<FlowEdge>, s: VertexId, t: VertexId| {
+            remaining -= 1;
+            if remaining < 0 { None } else { zigzag.next_path(graph, s, t) }
+        };
+        let mut h = g.clone();
+        assert_eq!(h.max_flow_with_strategy(source, sink, &mut limited), Ok(10));
+        assert!(h.augmenting_path(source, sink, BFS).is_some());
+    }
+}
@@ -0,0 +1,155 @@
+use alloc::vec::Vec;
+use super::{ArcId, FlowEdge, Graph, VertexId};
+
+/// Level of a vertex which can not be reached.
+pub const UNREACHED: u32 = u32::MAX;
+
+/// Frontiers smaller than this are expanded sequentially, splitting them over threads costs more than it saves.
+#[cfg(feature = "parallel")]
+const PARALLEL_FRONTIER: usize = 1024;
+
+impl Graph<FlowEdge> {
+    /// Returns the number of residual arcs on a shortest path from `source` to every vertex, or `UNREACHED`. These are
+    /// the levels of the level graph used by blocking flow algorithms.
+    pub fn residual_levels(&self, source: VertexId) -> Vec<u32> {
+        levels(self, source, false)
+    }
+
+    /// Returns the number of residual arcs on a shortest path from every vertex to `sink`, or `UNREACHED`. These are the
+    /// exact distance labels set by a global relabeling.
+    pub fn distances_to_sink(&self, sink: VertexId) -> Vec<u32> {
+        levels(self, sink, true)
+    }
+
+    /// Returns the arcs of the edges from the vertexes `source` reaches in the residual graph to the other vertexes.
+    /// After a maximum flow these edges form a minimum cut: they are saturated and their capacities add up to the flow
+    /// value.
+    pub fn min_cut(&self, source: VertexId) -> Vec<ArcId> {
+        let levels = self.residual_levels(source);
+        self.edges()
+            .filter(|&(u, v, _)| levels[u] != UNREACHED && levels[v] == UNREACHED)
+            .map(|(_, _, a)| a)
+            .collect()
+    }
+}
+
+/// Returns the vertex reached from the tail of `arc` when it has residual capacity. Searching in `reverse` follows
+/// arcs backwards, so the vertex is reached when the reverse arc `arc ^ 1` has residual capacity.
+fn step(graph: &Graph<FlowEdge>, arc: ArcId, reverse: bool) -> Option<VertexId> {
+    let residual_arc = if reverse { arc ^ 1 } else { arc };
+    if graph.arcs[residual_arc].residual() > 0 {
+        Some(graph.head(arc))
+    } else {
+        None
+    }
+}
+
+/// Breadth first search from `root` which expands one frontier per level.
+#[cfg(not(feature = "parallel"))]
+fn levels(graph: &Graph<FlowEdge>, root: VertexId, reverse: bool) -> Vec<u32> {
+    let mut levels = vec![UNREACHED; graph.n_vertexes()];
+    levels[root] = 0;
+    let mut frontier = vec![root];
+    let mut level = 0;
+    while !frontier.is_empty() {
+        level += 1;
+        let mut next: Vec<VertexId> = Vec::new();
+        for u in &frontier {
+            for a in graph.out_arcs(*u) {
+                if let Some(v) = step(graph, a, reverse) {
+                    if levels[v] == UNREACHED {
+                        levels[v] = level;
+                        next.push(v);
+                    }
+                }
+            }
+        }
+        trace!("Residual level {} has {} vertexes", level, next.len());
+        frontier = next;
+    }
+    levels
+}
+
+/// Breadth first search from `root` which expands large frontiers in parallel. Threads race to claim a vertex of the
+/// next frontier with a compare and swap on its level, so every vertex is added exactly once.
+#[cfg(feature = "parallel")]
+fn levels(graph: &Graph<FlowEdge>, root: VertexId, reverse: bool) -> Vec<u32> {
+    use rayon::prelude::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    let levels = (0..graph.n_vertexes()).map(|_| AtomicU32::new(UNREACHED)).collect::<Vec<_>>();
+    levels[root].store(0, Ordering::Relaxed);
+    let mut frontier = vec![root];
+    let mut level = 0;
+    while !frontier.is_empty() {
+        level += 1;
+        let levels = &levels;
+        let expand = |u: &VertexId| graph.out_arcs(*u)
+            .filter_map(move |a| step(graph, a, reverse))
+            .filter(move |v| levels[*v].compare_exchange(UNREACHED, level, Ordering::Relaxed, Ordering::Relaxed).is_ok());
+        frontier = if frontier.len() < PARALLEL_FRONTIER {
+            frontier.iter().flat_map(expand).collect()
+        } else {
+            frontier.par_iter().flat_map_iter(expand).collect()
+        };
+        trace!("Residual level {} has {} vertexes", level, frontier.len());
+    }
+    levels.into_iter().map(AtomicU32::into_inner).collect()
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use generators::{grid_network, layered_network};
+    use {flow_from_dicaps, FlowGraph, BFS};
+
+    #[test]
+    fn test_residual_levels() {
+        let (source, sink, mut g) = flow_from_dicaps("data/dicaps/flow-graph.txt");
+        assert_eq!(g.residual_levels(source), [0, 2, 1, 1]);
+        assert_eq!(g.distances_to_sink(sink), [2, 0, 1, 1]);
+        g.max_flow(source, sink, BFS).unwrap();
+        assert_eq!(g.residual_levels(source)[sink], UNREACHED);
+        assert_eq!(g.distances_to_sink(sink)[source], UNREACHED);
+    }
+
+    #[test]
+    fn test_min_cut() {
+        let (source, sink, mut g) = flow_from_dicaps("data/dicaps/flow-graph.txt");
+        let flow = g.max_flow(source, sink, BFS).unwrap();
+        let cut = g.min_cut(source);
+        assert_eq!(cut.iter().map(|&a| (g.tail(a).index(), g.head(a).index())).collect::<Vec<_>>(), [(0, 2), (0, 3)]);
+        assert_eq!(cut.iter().map(|&a| g.arcs[a].capacity as i64).sum::<i64>(), flow);
+    }
+
+    #[test]
+    fn test_levels_of_large_frontiers() {
+        let (source, sink, g) = grid_network(40, 40, 1..=1, 3);
+        let levels = g.residual_levels(source);
+        let distances = g.distances_to_sink(sink);
+        for r in 0..40 {
+            for c in 0..40 {
+                assert_eq!(levels[r * 40 + c], (r + c) as u32);
+                assert_eq!(distances[r * 40 + c], (78 - r - c) as u32);
+            }
+        }
+
+        // Layers wider than the parallel threshold, checked against the definition of breadth first levels
+        let (source, _, g) = layered_network(5, 1500, 2, 1..=3, 5);
+        let levels = g.residual_levels(source);
+        for v in g.vertexes() {
+            let parents = g.out_arcs(v)
+                .filter(|a| g.arcs[*a ^ 1].residual() > 0)
+                .map(|a| levels[g.head(a)])
+                .collect::<Vec<_>>();
+            if v == source {
+                assert_eq!(levels[v], 0);
+            } else if levels[v] == UNREACHED {
+                assert!(parents.iter().all(|l| *l == UNREACHED));
+            } else {
+                assert_eq!(parents.iter().min(), Some(&(levels[v] - 1)));
+            }
+        }
+        assert!(levels.iter().filter(|l| **l == 5).count() > 1024);
+    }
+}
@@ -0,0 +1,77 @@
+use alloc::vec::Vec;
+use super::{Error, FlowEdge, FlowError, FlowGraph, Graph, VertexId, BFS};
+
+impl Graph<FlowEdge> {
+    /// Computes a lexicographically maximum flow from `source` to the `sinks`, which are ordered from the highest
+    /// priority: the flow into the first sink is maximum, the flow into the second is maximum among those flows, and so
+    /// on. Sinks are joined to a super sink and solved one at a time, each time continuing from the flow so far with the
+    /// edges of the earlier sinks fixed at their values. An augmenting path ends at the first arc into the super sink,
+    /// so the flow into earlier sinks never decreases. The flow into a sink is the flow it passes to the super sink,
+    /// flow passing through a sink on the way to a later one does not count for it. Returns the flow into every sink
+    /// in the order of `sinks`, and leaves the flow in the graph. Fails if a sink is out of range, the source or
+    /// repeated.
+    pub fn lexicographic_max_flow(&mut self, source: VertexId, sinks: &[VertexId]) -> Result<Vec<i64>, Error> {
+        for (i, &sink) in sinks.iter().enumerate() {
+            self.check_terminals(source, sink)?;
+            if sinks[..i].contains(&sink) {
+                return Err(Error::Construction(format!("Sink {} is given more than once", sink)));
+            }
+        }
+        if !self.has_residual_arcs() {
+            return Err(Error::Infeasible(FlowError::MissingResidualArcs));
+        }
+        let super_sink = VertexId(self.n_vertexes());
+        // The edges of the graph come first, so the arcs of the network start with the arcs of the graph
+        let edges = self.edges()
+            .map(|(u, v, a)| (u, v, FlowEdge{capacity: self.arcs[a].capacity, flow: 0}))
+            .chain(sinks.iter().map(|&sink| (sink, super_sink, FlowEdge{capacity: 0, flow: 0})))
+            .collect::<Vec<_>>();
+        let vertexes = (0..=super_sink.index()).map(VertexId).collect::<Vec<_>>();
+        let mut network = Graph::flow_network(&vertexes, &edges);
+        let first_sink_arc = 2 * self.n_edges();
+        let mut values: Vec<i64> = Vec::with_capacity(sinks.len());
+        for (i, &sink) in sinks.iter().enumerate() {
+            let arc = first_sink_arc + 2 * i;
+            network.arcs[arc].capacity = i32::MAX;
+            match network.max_flow(source, super_sink, BFS) {
+                Ok(_) | Err(FlowError::UnreachableSink {..}) => {},
+                Err(error) => return Err(error.into())
+            }
+            // Fixing the capacity at the flow keeps the value of this sink while later sinks are solved
+            network.arcs[arc].capacity = network.arcs[arc].flow;
+            values.push(network.arcs[arc].flow as i64);
+            debug!("Sink {} of priority {} gets flow {}", sink, i, values[i]);
+        }
+        for (arc, solved) in self.arcs.iter_mut().zip(&network.arcs) {
+            arc.flow = solved.flow;
+        }
+        Ok(values)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lexicographic_max_flow() {
+        // The source feeds 1 with capacity 3, which feeds sinks 2 and 3, and sink 3 also has an edge of its own
+        let vertexes = (0..4).map(VertexId).collect::<Vec<_>>();
+        let edges = [(0, 1, 3), (1, 2, 3), (1, 3, 3), (0, 3, 1)]
+            .iter()
+            .map(|&(u, v, capacity)| (VertexId(u), VertexId(v), FlowEdge{capacity, flow: 0}))
+            .collect::<Vec<_>>();
+        let mut g = Graph::flow_network(&vertexes, &edges);
+        let (source, sinks) = (VertexId(0), [VertexId(2), VertexId(3)]);
+        assert_eq!(g.lexicographic_max_flow(source, &sinks), Ok(vec![3, 1]));
+        assert_eq!(g.arcs[2].flow, 3);
+        let reversed = [VertexId(3), VertexId(2)];
+        assert_eq!(g.lexicographic_max_flow(source, &reversed), Ok(vec![4, 0]));
+        assert_eq!(g.arcs[4].flow, 3);
+
+        assert!(matches!(g.lexicographic_max_flow(source, &[VertexId(2), VertexId(2)]), Err(Error::Construction(_))));
+        assert_eq!(g.lexicographic_max_flow(source, &[VertexId(0)]),
+                   Err(Error::Infeasible(FlowError::SourceIsSink {vertex: VertexId(0)})));
+        assert_eq!(g.lexicographic_max_flow(VertexId(2), &[VertexId(0)]), Ok(vec![0]));
+    }
+}
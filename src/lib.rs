@@ -1,13 +1,224 @@
-use std::collections::{VecDeque, HashSet};
-use std::iter::Iterator;
-use std::{i32, usize, u32};
-use std::fs::File;
-use std::io::BufReader;
-use std::io::BufRead;
-use std::cmp::min;
+//! Without the default `std` feature the crate is `no_std` and only needs `alloc`: graphs, the solvers, levels and
+//! verification are available, while the file formats, generators and benchmarks need `std`.
+#![cfg_attr(not(feature = "std"), no_std)]
 
-/// Alias type to usize for `VertexId` attributes.
-pub type VertexId = usize;
+#[macro_use]
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate core;
+#[cfg(feature = "std")]
+extern crate rand;
+#[cfg(feature = "parallel")]
+extern crate rayon;
+#[cfg(feature = "std")]
+extern crate serde_json;
+#[cfg(feature = "smallvec")]
+extern crate smallvec;
+#[cfg(feature = "wasm")]
+extern crate wasm_bindgen;
+#[cfg(feature = "python")]
+extern crate pyo3;
+#[cfg(feature = "ndarray")]
+extern crate ndarray;
+#[cfg(feature = "arrow")]
+extern crate arrow_array;
+#[cfg(feature = "arrow")]
+extern crate arrow_schema;
+#[cfg(feature = "log")]
+extern crate log;
+#[cfg(feature = "lp-check")]
+extern crate minilp;
+#[cfg(feature = "proptest")]
+extern crate proptest;
+
+/// Emits events through the `log` facade with the `log` feature. Without it the arguments are only borrowed, so they
+/// cause no unused warnings and no formatting work.
+#[cfg(feature = "log")]
+macro_rules! debug {
+    ($($arg:tt)+) => { ::log::debug!($($arg)+) }
+}
+#[cfg(not(feature = "log"))]
+macro_rules! debug {
+    ($($arg:tt)+) => { { let _ = format_args!($($arg)+); } }
+}
+#[cfg(feature = "log")]
+macro_rules! trace {
+    ($($arg:tt)+) => { ::log::trace!($($arg)+) }
+}
+#[cfg(not(feature = "log"))]
+macro_rules! trace {
+    ($($arg:tt)+) => { { let _ = format_args!($($arg)+); } }
+}
+
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+use core::error;
+use core::fmt;
+use core::mem;
+use core::num::ParseIntError;
+use core::ops;
+use core::str::FromStr;
+use core::time::Duration;
+#[cfg(feature = "std")]
+use std::time::Instant;
+use core::iter::Iterator;
+use core::cmp::min;
+use trace::TraceStep;
+
+#[cfg(feature = "std")]
+pub mod anytime;
+#[cfg(feature = "proptest")]
+pub mod arbitrary;
+#[cfg(feature = "arrow")]
+pub mod arrow;
+#[cfg(feature = "std")]
+pub mod benchmark;
+pub mod bounds;
+pub mod budget;
+pub mod cancel;
+pub mod components;
+pub mod diff;
+#[cfg(feature = "std")]
+mod formats;
+mod matrix;
+#[cfg(feature = "std")]
+pub mod generators;
+pub mod levels;
+mod lexicographic;
+#[cfg(feature = "lp-check")]
+pub mod lp_check;
+pub mod matching;
+pub mod metadata;
+mod names;
+pub mod network;
+#[cfg(feature = "parallel")]
+pub mod parallel;
+#[cfg(feature = "std")]
+pub mod partition;
+pub mod paths;
+pub mod real;
+pub mod reference;
+pub mod solver;
+pub mod splittable;
+#[cfg(feature = "std")]
+pub mod sparsify;
+pub mod strategy;
+#[cfg(feature = "std")]
+pub mod testing;
+pub mod trace;
+pub mod verify;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "python")]
+pub mod python;
+
+#[cfg(feature = "std")]
+pub use formats::*;
+
+/// Identifier of a vertex, which is its index in the per vertex arrays of a graph. The newtype keeps vertexes apart
+/// from counts and arc indexes; `VertexId::from` and `usize::from` convert between the two, and vectors and slices can
+/// be indexed by a vertex directly. Vertexes are formatted as their bare number.
+#[derive(Copy, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct VertexId(pub usize);
+
+impl VertexId {
+    /// Returns the position of the vertex in the per vertex arrays of a graph.
+    #[inline]
+    pub fn index(self) -> usize {
+        self.0
+    }
+
+    /// Returns the vertex stored as an `Index` in the compact arrays of a graph.
+    #[inline]
+    #[allow(clippy::unnecessary_cast)] // `Index` is `usize` unless compact-index is enabled
+    fn stored(index: Index) -> VertexId {
+        VertexId(index as usize)
+    }
+}
+
+impl From<usize> for VertexId {
+    fn from(index: usize) -> VertexId {
+        VertexId(index)
+    }
+}
+
+impl From<VertexId> for usize {
+    fn from(vertex: VertexId) -> usize {
+        vertex.0
+    }
+}
+
+impl FromStr for VertexId {
+    type Err = ParseIntError;
+
+    fn from_str(s: &str) -> Result<VertexId, ParseIntError> {
+        s.parse().map(VertexId)
+    }
+}
+
+impl fmt::Display for VertexId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl fmt::Debug for VertexId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&self.0, f)
+    }
+}
+
+impl<T> ops::Index<VertexId> for Vec<T> {
+    type Output = T;
+
+    #[inline]
+    fn index(&self, vertex: VertexId) -> &T {
+        &self[vertex.0]
+    }
+}
+
+impl<T> ops::IndexMut<VertexId> for Vec<T> {
+    #[inline]
+    fn index_mut(&mut self, vertex: VertexId) -> &mut T {
+        &mut self[vertex.0]
+    }
+}
+
+impl<T> ops::Index<VertexId> for [T] {
+    type Output = T;
+
+    #[inline]
+    fn index(&self, vertex: VertexId) -> &T {
+        &self[vertex.0]
+    }
+}
+
+impl<T> ops::IndexMut<VertexId> for [T] {
+    #[inline]
+    fn index_mut(&mut self, vertex: VertexId) -> &mut T {
+        &mut self[vertex.0]
+    }
+}
+
+/// Alias type to usize for the index of an arc in `Graph::arcs`.
+pub type ArcId = usize;
+
+/// Integer type in which the graph stores vertex and arc indexes. With the `compact-index` feature indexes take 32
+/// bits, which halves the memory of the adjacency and parent arrays on 64 bit targets but limits a graph to fewer than
+/// 2^32 vertexes and arcs.
+#[cfg(feature = "compact-index")]
+pub type Index = u32;
+#[cfg(not(feature = "compact-index"))]
+pub type Index = usize;
+
+/// List of the arcs leaving a vertex. With the `smallvec` feature up to four arcs are stored inline, which saves a heap
+/// allocation per vertex of low degree and keeps the arcs of a vertex next to each other during traversals.
+#[cfg(feature = "smallvec")]
+pub type ArcList = smallvec::SmallVec<[Index; 4]>;
+#[cfg(not(feature = "smallvec"))]
+pub type ArcList = Vec<Index>;
 
 #[derive(Debug)]
 pub struct Triplet<T: Property>(pub VertexId, pub T, pub VertexId);
@@ -19,13 +230,18 @@ pub struct Edge(pub VertexId, pub VertexId);
 pub trait Property: Copy + Default {}
 impl<T> Property for T where T: Copy + Default {}
 
-/// Represent a Graph structure.
-#[derive(Debug)]
+/// Represent a Graph structure. Arcs are stored in a flat array: arc `a` points to `head(a)` with property `arcs[a]`
+/// and `out_arcs(u)` lists the arcs leaving `u`. Flow networks store every edge as a pair of arcs, the edge at an even
+/// index followed by its reverse arc, so the reverse of arc `a` is always `a ^ 1`.
+#[derive(Debug, Clone)]
 pub struct Graph<E: Property> {
-    pub edges: Vec<Vec<E>>,
-    pub neighbors: Vec<Vec<VertexId>>,
+    pub arcs: Vec<E>,
+    pub heads: Vec<Index>,
+    pub adjacency: Vec<ArcList>,
     n_edges: usize,
-    n_vertexes: usize
+    n_vertexes: usize,
+    deterministic: bool,
+    names: Vec<String>
 }
 
 /// Edge property that provides fields for a flow graph.
@@ -35,21 +251,33 @@ pub struct FlowEdge {
     pub flow: i32
 }
 
-#[derive(Copy, Clone)]
+impl FlowEdge {
+    /// Remaining capacity of the arc. Flows are stored antisymmetrically, so a reverse arc with no capacity of its own
+    /// has a residual equal to the flow on its forward arc.
+    pub fn residual(&self) -> i32 {
+        self.capacity - self.flow
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum Search {
-    Bfs, Dfs
+    Bfs, Dfs,
+    /// Breadth first searches from the source and backwards from the sink, expanding the smaller frontier one level
+    /// at a time until they meet, which scans fewer vertexes on networks of large diameter. The search stops at the
+    /// first arc joining the two sides, so the path found is short but not always a shortest path.
+    Bidirectional
 }
 
 pub const BFS: Search = Search::Bfs;
 pub const DFS: Search = Search::Dfs;
+pub const BIDIRECTIONAL: Search = Search::Bidirectional;
 
-/// Representation of breadth first search iterator.
+/// Representation of breadth first search iterator. It drives the same search engine as the flow algorithms and only
+/// adds the distance of every visited vertex.
 pub struct GraphIterator<'a, E: 'a + Property, F> {
-    queue: VecDeque<VertexId>,
-    stack: Vec<VertexId>,
+    buffers: SearchBuffers,
     graph: &'a Graph<E>,
     distances: Vec<u32>,
-    parents: Vec<VertexId>,
     predicate: F,
     search: Search,
     sink: VertexId,
@@ -59,50 +287,32 @@ pub struct GraphIterator<'a, E: 'a + Property, F> {
 impl<'a, E: Property, F> GraphIterator<'a, E, F>
     where F: Fn(E) -> bool {
     fn new(graph: &'a Graph<E>, source: VertexId, sink: VertexId, predicate: F, search: Search) -> GraphIterator<'a, E, F> {
-        let mut queue = VecDeque::new();
-        let mut stack = Vec::new();
-        match search {
-            Search::Bfs => {
-                queue.push_back(source);
-            },
-            Search::Dfs => {
-                stack.push(source);
-            }
-        }
+        let mut buffers = SearchBuffers::new(graph.n_vertexes());
+        buffers.start(source, search);
         let mut distances = vec![u32::MAX; graph.n_vertexes()];
-        let parents = vec![usize::MAX; graph.n_vertexes()];
         distances[source] = 0;
         GraphIterator {
-            graph: graph,
-            queue: queue,
-            stack: stack,
-            distances: distances,
-            parents: parents,
-            predicate: predicate,
-            search: search,
-            sink: sink,
+            buffers,
+            graph,
+            distances,
+            predicate,
+            search,
+            sink,
             sink_found: false
         }
     }
 
-    fn pop(&mut self) -> Option<VertexId> {
-        match self.search {
-            Search::Bfs => self.queue.pop_front(),
-            Search::Dfs => self.stack.pop()
-        }
-    }
-
-    fn push(&mut self, v: VertexId) {
-        match self.search {
-            Search::Bfs => self.queue.push_back(v),
-            Search::Dfs => self.stack.push(v)
-        }
-    }
-
     fn evaluate_predicate(&self, edge: E) -> bool {
         let predicate = &self.predicate;
         predicate(edge)
     }
+
+    fn parent(&self, v: VertexId) -> VertexId {
+        match self.buffers.parents[v] {
+            Index::MAX => VertexId(usize::MAX),
+            parent => VertexId::stored(parent)
+        }
+    }
 }
 
 /// Iterator for a breadth first search over a graph
@@ -115,21 +325,21 @@ impl<'a, E: Property, F> Iterator for GraphIterator<'a, E, F>
             None
         }
         else {
-            match self.pop() {
+            match self.buffers.pop(self.search) {
                 Some(vertex) => {
                     if vertex == self.sink {
                         self.sink_found = true;
                     } else {
-                        for v in &self.graph.neighbors[vertex] {
-                            if self.distances[*v] == u32::MAX &&
-                                (self.evaluate_predicate(self.graph.edges[vertex][*v])) {
-                                self.distances[*v] = self.distances[vertex] + 1;
-                                self.parents[*v] = vertex;
-                                self.push(*v);
+                        for a in self.graph.out_arcs(vertex) {
+                            let v = self.graph.head(a);
+                            if self.evaluate_predicate(self.graph.arcs[a]) &&
+                                self.buffers.visit(v, vertex.0 as Index, a as Index) {
+                                self.distances[v] = self.distances[vertex] + 1;
+                                self.buffers.push(v, self.search);
                             }
                         }
                     }
-                    Some((vertex, self.distances[vertex], self.parents[vertex]))
+                    Some((vertex, self.distances[vertex], self.parent(vertex)))
                 }
                 _ => None
             }
@@ -137,29 +347,147 @@ impl<'a, E: Property, F> Iterator for GraphIterator<'a, E, F>
     }
 }
 
-impl<'a, E: Property> Graph<E> {
-    pub fn new(vertex_list: &[VertexId], edge_list: &[(VertexId, VertexId, E)]) -> Graph<E> {
-        let mut neighbors: Vec<Vec<VertexId>> = vec![Vec::new(); vertex_list.len()];
+impl<E: Property> Graph<E> {
+    /// Creates a graph with one arc per edge, in the order of `edge_list`. Panics if the graph is invalid, see
+    /// `try_new`.
+    pub fn new<I>(vertex_list: &[VertexId], edge_list: I) -> Graph<E>
+        where I: IntoIterator, I::Item: Borrow<(VertexId, VertexId, E)> {
+        Graph::try_new(vertex_list, edge_list).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Creates a graph with one arc per edge, in the order of `edge_list`. The edges can be a slice or any iterator,
+    /// which is consumed as the graph is built, so edges streamed from a parser or generator need not be collected
+    /// first. The lower bound of its size hint is reserved up front. Fails if the vertexes are not numbered from 0 to
+    /// n - 1 in order, if an edge has an endpoint which is not a vertex or if the graph is too large for `Index`.
+    pub fn try_new<I>(vertex_list: &[VertexId], edge_list: I) -> Result<Graph<E>, Error>
+        where I: IntoIterator, I::Item: Borrow<(VertexId, VertexId, E)> {
+        let edges = edge_list.into_iter();
+        let mut graph = Graph::with_vertexes(vertex_list, edges.size_hint().0)?;
+        for edge in edges {
+            let &(u, v, property) = edge.borrow();
+            graph.check_edge(u, v, 1)?;
+            graph.add_arc(u, v, property);
+        }
+        graph.n_edges = graph.arcs.len();
+        Ok(graph)
+    }
+
+    /// Creates a graph without arcs with room for `n_arcs` arcs, checking the vertexes.
+    fn with_vertexes(vertex_list: &[VertexId], n_arcs: usize) -> Result<Graph<E>, Error> {
         let mut v_len = 0;
         for v in vertex_list {
-            assert!(*v == v_len, "Must provide vertexes in order from 0 to n - 1");
+            if v.0 != v_len {
+                return Err(Error::Construction("Must provide vertexes in order from 0 to n - 1".to_string()));
+            }
             v_len += 1;
         }
+        if v_len as u64 > Index::MAX as u64 {
+            return Err(Graph::<E>::too_large());
+        }
+        Ok(Graph {
+            arcs: Vec::with_capacity(n_arcs),
+            heads: Vec::with_capacity(n_arcs),
+            adjacency: vec![ArcList::new(); v_len],
+            n_edges: 0,
+            n_vertexes: v_len,
+            deterministic: false,
+            names: Vec::new()
+        })
+    }
 
-        let mut edges: Vec<Vec<E>> = vec![vec![Default::default(); v_len]; v_len];
-        let mut n_edges = 0;
-        for edge in edge_list {
-            n_edges += 1;
-            neighbors.get_mut(edge.0).unwrap().push(edge.1);
-            edges[edge.0][edge.1] = edge.2;
+    /// Checks the endpoints of an edge before its `n_arcs` arcs are added.
+    fn check_edge(&self, u: VertexId, v: VertexId, n_arcs: usize) -> Result<(), Error> {
+        if u.0 >= self.n_vertexes || v.0 >= self.n_vertexes {
+            return Err(Error::Construction(format!("Edge ({}, {}) is not between vertexes of the graph", u, v)));
+        }
+        if (self.arcs.len() + n_arcs) as u64 > Index::MAX as u64 {
+            return Err(Graph::<E>::too_large());
         }
+        Ok(())
+    }
 
-        Graph {
-            edges: edges,
-            neighbors: neighbors,
-            n_edges: n_edges,
-            n_vertexes: v_len
+    /// Returns an error unless both terminals are distinct vertexes of the graph.
+    fn check_terminals(&self, source: VertexId, sink: VertexId) -> Result<(), FlowError> {
+        for &vertex in &[source, sink] {
+            if vertex.index() >= self.n_vertexes() {
+                return Err(FlowError::VertexOutOfRange {vertex, n_vertexes: self.n_vertexes()});
+            }
         }
+        if source == sink {
+            return Err(FlowError::SourceIsSink {vertex: source});
+        }
+        Ok(())
+    }
+
+    fn too_large() -> Error {
+        Error::Overflow("Graph is too large for the index type, build without the compact-index feature".to_string())
+    }
+
+    fn add_arc(&mut self, u: VertexId, v: VertexId, property: E) {
+        let arc = self.arcs.len() as Index;
+        if self.deterministic {
+            // Every later arc has a larger id, so it goes after all arcs to the same head
+            let heads = &self.heads;
+            let position = self.adjacency[u].partition_point(|a| VertexId::stored(heads[*a as ArcId]) <= v);
+            self.adjacency[u].insert(position, arc);
+        } else {
+            self.adjacency[u].push(arc);
+        }
+        self.arcs.push(property);
+        self.heads.push(v.0 as Index);
+    }
+
+    /// Turns the deterministic mode on or off. In deterministic mode the arcs leaving a vertex are scanned in order of
+    /// their heads, arcs to the same head in the order they were added, so every search, and with it the paths chosen
+    /// and the flow assigned to every arc, only depends on the edges of the graph and not on the order in which they
+    /// were listed. The mode is kept when arcs are added or the graph is rebuilt by `ensure_residual_arcs`. Turning it
+    /// off keeps the current order.
+    pub fn set_deterministic(&mut self, deterministic: bool) {
+        self.deterministic = deterministic;
+        if deterministic {
+            let heads = &self.heads;
+            for arcs in &mut self.adjacency {
+                arcs.sort_by_key(|a| (heads[*a as ArcId], *a));
+            }
+        }
+    }
+
+    /// Returns whether the graph is in deterministic mode, see `set_deterministic`.
+    pub fn is_deterministic(&self) -> bool {
+        self.deterministic
+    }
+
+    /// Returns the vertex `arc` points to.
+    #[inline]
+    pub fn head(&self, arc: ArcId) -> VertexId {
+        VertexId::stored(self.heads[arc])
+    }
+
+    /// Iterates over the arcs leaving `u`.
+    #[inline]
+    pub fn out_arcs(&self, u: VertexId) -> impl Iterator<Item = ArcId> + '_ {
+        self.adjacency[u].iter().map(|a| *a as ArcId)
+    }
+
+    /// Iterates over the vertexes in order.
+    pub fn vertexes(&self) -> impl Iterator<Item = VertexId> {
+        (0..self.n_vertexes).map(VertexId)
+    }
+
+    /// Returns the vertex every arc leaves.
+    fn tails(&self) -> Vec<VertexId> {
+        let mut tails = vec![VertexId(0); self.arcs.len()];
+        for u in self.vertexes() {
+            for a in self.out_arcs(u) {
+                tails[a] = u;
+            }
+        }
+        tails
+    }
+
+    /// Returns the first arc from `u` to `v`, if any.
+    pub fn find_arc(&self, u: VertexId, v: VertexId) -> Option<ArcId> {
+        self.out_arcs(u).find(|a| self.head(*a) == v)
     }
 
     pub fn size(&self) -> (usize, usize) {
@@ -174,24 +502,29 @@ impl<'a, E: Property> Graph<E> {
         self.n_edges
     }
 
-    pub fn bfs_iter(&self, source: VertexId, sink: VertexId) -> GraphIterator<E, fn(E) -> bool> {
+    pub fn bfs_iter(&self, source: VertexId, sink: VertexId) -> GraphIterator<'_, E, fn(E) -> bool> {
         GraphIterator::new(self, source, sink, true_predicate, BFS)
     }
 
-    pub fn dfs_iter(&self, source: VertexId, sink: VertexId) -> GraphIterator<E, fn(E) -> bool> {
+    pub fn dfs_iter(&self, source: VertexId, sink: VertexId) -> GraphIterator<'_, E, fn(E) -> bool> {
         GraphIterator::new(self, source, sink, true_predicate, DFS)
     }
 }
 
 /// Creates a path from a list of nodes from a tree search (BFS or DFS). The visited nodes are expected to be in the
-/// format (vertex, `distance_from_source`, parent). The path is computed using the parent back pointers. It is assumed
-/// that there does exist a path, it is a programming error which will cause a panic if that is not true
+/// format (vertex, `distance_from_source`, parent). The path is computed using the parent back pointers. Returns
+/// `None` if the parents do not lead from `sink` back to `source`, because the sink was not reached or a parent is not
+/// a vertex or the parents form a cycle.
 pub fn path_from_visited(source: VertexId,
                          sink: VertexId,
-                         node_parent_map: &[VertexId]) -> Vec<VertexId> {
+                         node_parent_map: &[VertexId]) -> Option<Vec<VertexId>> {
     let mut path: Vec<VertexId> = Vec::new();
     let mut node = sink;
     loop {
+        // A path visits every vertex at most once, so a longer chain of parents must contain a cycle
+        if node.0 >= node_parent_map.len() || path.len() == node_parent_map.len() {
+            return None;
+        }
         path.push(node);
         if node == source {
             break;
@@ -199,200 +532,957 @@ pub fn path_from_visited(source: VertexId,
         node = node_parent_map[node];
     }
     path.reverse();
-    path
+    Some(path)
+}
+
+/// Buffers of a graph search which are kept between searches. Breadth first searches scan the current frontier array
+/// while collecting the next one, and swap the two arrays once per level, which keeps both scans sequential in memory.
+/// Depth first searches use a stack. Visited vertexes are marked in a bitset, which is cheap to clear for a new search.
+struct SearchBuffers {
+    frontier: Vec<VertexId>,
+    next: Vec<VertexId>,
+    position: usize,
+    stack: Vec<VertexId>,
+    parents: Vec<Index>,
+    parent_arcs: Vec<Index>,
+    visited: Vec<u64>,
+    /// Vertexes reached by the backward half of a bidirectional search, with the arc they take towards the sink. They
+    /// are only allocated by the first bidirectional search.
+    sink_side: Vec<u64>,
+    child_arcs: Vec<Index>,
+    backward: Vec<VertexId>,
+    vertexes_scanned: u64,
+    arcs_scanned: u64
+}
+
+impl SearchBuffers {
+    fn new(n_vertexes: usize) -> SearchBuffers {
+        SearchBuffers {
+            frontier: Vec::new(),
+            next: Vec::new(),
+            position: 0,
+            stack: Vec::new(),
+            parents: vec![Index::MAX; n_vertexes],
+            parent_arcs: vec![Index::MAX; n_vertexes],
+            visited: vec![0; n_vertexes.div_ceil(64)],
+            sink_side: Vec::new(),
+            child_arcs: Vec::new(),
+            backward: Vec::new(),
+            vertexes_scanned: 0,
+            arcs_scanned: 0
+        }
+    }
+
+    /// Starts a new search from `source`, forgetting every vertex visited by the previous one.
+    fn start(&mut self, source: VertexId, search: Search) {
+        self.frontier.clear();
+        self.next.clear();
+        self.position = 0;
+        self.stack.clear();
+        self.visited.iter_mut().for_each(|w| *w = 0);
+        self.visit(source, Index::MAX, Index::MAX);
+        self.push(source, search);
+    }
+
+    /// Marks `v` as reached from `parent` through `arc`, returns false if it was already visited by this search.
+    #[inline]
+    fn visit(&mut self, v: VertexId, parent: Index, arc: Index) -> bool {
+        let bit = 1u64 << (v.0 % 64);
+        if self.visited[v.0 / 64] & bit != 0 {
+            false
+        } else {
+            self.visited[v.0 / 64] |= bit;
+            self.parents[v] = parent;
+            self.parent_arcs[v] = arc;
+            true
+        }
+    }
+
+    #[inline]
+    fn is_visited(&self, v: VertexId) -> bool {
+        self.visited[v.0 / 64] & (1u64 << (v.0 % 64)) != 0
+    }
+
+    #[inline]
+    fn pop(&mut self, search: Search) -> Option<VertexId> {
+        match search {
+            Search::Bfs | Search::Bidirectional => {
+                if self.position == self.frontier.len() {
+                    if self.next.is_empty() {
+                        return None;
+                    }
+                    mem::swap(&mut self.frontier, &mut self.next);
+                    self.next.clear();
+                    self.position = 0;
+                }
+                self.position += 1;
+                Some(self.frontier[self.position - 1])
+            },
+            Search::Dfs => self.stack.pop()
+        }
+    }
+
+    #[inline]
+    fn push(&mut self, v: VertexId, search: Search) {
+        match search {
+            Search::Bfs | Search::Bidirectional => self.next.push(v),
+            Search::Dfs => self.stack.push(v)
+        }
+    }
+}
+
+/// Snapshot of a running max flow computation which is passed to observers after every augmentation.
+#[derive(Debug, Copy, Clone)]
+pub struct Progress {
+    /// Number of augmenting paths pushed so far, counting the last one.
+    pub augmentations: usize,
+    /// Number of arcs on the last augmenting path.
+    pub path_length: usize,
+    /// Residual capacity of the last augmenting path, the flow it added.
+    pub bottleneck: i32,
+    /// Flow pushed so far.
+    pub flow: i64,
+    /// Upper bound of the flow the computation can push, the smallest residual capacity of the cuts seen plus the flow
+    /// pushed when the cut was seen. It starts at the smaller of the residual capacities leaving the source and
+    /// entering the sink and is refined by the cuts between the residual levels from the source after 1, 2, 4 and so
+    /// on augmentations.
+    pub upper_bound: i64
+}
+
+impl Progress {
+    /// Fraction of `upper_bound` pushed so far, between 0 and 1, for a progress bar. The bound is refined as the
+    /// computation runs, so the fraction can jump ahead, and it only reaches 1 once a cut proves the flow maximum.
+    pub fn progress(&self) -> f64 {
+        if self.upper_bound <= 0 {
+            1.0
+        } else {
+            (self.flow as f64 / self.upper_bound as f64).min(1.0)
+        }
+    }
+}
+
+/// Work done by a max flow computation.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct SolveStats {
+    /// Number of augmenting paths along which flow was pushed.
+    pub augmenting_paths: usize,
+    /// Number of times a vertex was taken from a search frontier and its arcs were scanned.
+    pub vertexes_scanned: u64,
+    /// Number of arcs looked at by the searches.
+    pub arcs_scanned: u64,
+    /// Number of graph searches, which includes the final search that finds no augmenting path.
+    pub phases: usize,
+    /// Number of times flow was pushed over an arc, one per arc of every augmenting path.
+    pub pushes: u64,
+    /// Number of pushes of a preflow solver which used up the residual capacity of their arc, including the pushes out
+    /// of the source. Always zero for augmenting path solvers.
+    pub saturating_pushes: u64,
+    /// Number of pushes of a preflow solver which moved all the excess of their vertex and left residual capacity.
+    pub nonsaturating_pushes: u64,
+    /// Number of times a preflow solver lifted a single vertex.
+    pub relabels: u64,
+    /// Number of times a preflow solver recomputed all distance labels by a search from the sink.
+    pub global_relabels: u64,
+    /// Number of times the gap heuristic of a preflow solver found an empty label and lifted the vertexes above it.
+    pub gap_events: u64,
+    /// Time spent by the computation, which is only measured with the `std` feature.
+    pub wall_time: Duration
+}
+
+impl fmt::Display for SolveStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Augmentations:{}\tPhases:{}\tVertexesScanned:{}\tArcsScanned:{}\tPushes:{}\t\
+                   SaturatingPushes:{}\tNonsaturatingPushes:{}\tRelabels:{}\tGlobalRelabels:{}\tGapEvents:{}",
+               self.augmenting_paths, self.phases, self.vertexes_scanned, self.arcs_scanned, self.pushes,
+               self.saturating_pushes, self.nonsaturating_pushes, self.relabels, self.global_relabels, self.gap_events)
+    }
+}
+
+/// Summary of the size and shape of a flow network, see `Graph::stats`.
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct GraphStats {
+    pub vertexes: usize,
+    pub edges: usize,
+    /// Edges per ordered pair of distinct vertexes, which exceeds 1 with parallel edges.
+    pub density: f64,
+    /// Smallest, mean and largest number of edges leaving a vertex.
+    pub min_out_degree: usize,
+    pub mean_out_degree: f64,
+    pub max_out_degree: usize,
+    /// Smallest, mean and largest capacity of an edge, all 0 without edges.
+    pub min_capacity: i32,
+    pub mean_capacity: f64,
+    pub max_capacity: i32,
+    /// Number of edges with a capacity of 0, which can never carry flow.
+    pub zero_capacity_edges: usize,
+    /// Number of connected components when directions are ignored.
+    pub components: usize
+}
+
+impl fmt::Display for GraphStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Vertexes:{}\tEdges:{}\tDensity:{:.6}\tMinOutDegree:{}\tMeanOutDegree:{:.3}\tMaxOutDegree:{}\t\
+                   MinCapacity:{}\tMeanCapacity:{:.3}\tMaxCapacity:{}\tZeroCapacityEdges:{}\tComponents:{}",
+               self.vertexes, self.edges, self.density, self.min_out_degree, self.mean_out_degree, self.max_out_degree,
+               self.min_capacity, self.mean_capacity, self.max_capacity, self.zero_capacity_edges, self.components)
+    }
+}
+
+/// Reason why a max flow computation could not be run or has no flow to report.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FlowError {
+    /// A terminal is not a vertex of the graph.
+    VertexOutOfRange { vertex: VertexId, n_vertexes: usize },
+    /// The source and the sink are the same vertex.
+    SourceIsSink { vertex: VertexId },
+    /// No residual path leads from the source to the sink, so no flow can be sent.
+    UnreachableSink { source: VertexId, sink: VertexId },
+    /// The graph holds a flow between other terminals, which must be removed with `reset_flow` first.
+    ExistingFlow,
+    /// The graph was not built with `Graph::flow_network`, so edges lack the reverse arcs a `FlowState` needs.
+    MissingResidualArcs,
+    /// A `FlowState` was used with a graph it was not created for.
+    StateMismatch { arcs: usize, flows: usize }
+}
+
+impl fmt::Display for FlowError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            FlowError::VertexOutOfRange {vertex, n_vertexes} =>
+                write!(f, "vertex {} is out of range for a graph of {} vertexes", vertex, n_vertexes),
+            FlowError::SourceIsSink {vertex} => write!(f, "vertex {} is both the source and the sink", vertex),
+            FlowError::UnreachableSink {source, sink} =>
+                write!(f, "sink {} is unreachable from source {}", sink, source),
+            FlowError::ExistingFlow =>
+                write!(f, "the graph holds a flow between other terminals, call reset_flow before solving again"),
+            FlowError::MissingResidualArcs => write!(f, "the graph has no residual arcs, build it with flow_network"),
+            FlowError::StateMismatch {arcs, flows} =>
+                write!(f, "the flow state holds {} flows for a graph of {} arcs", flows, arcs)
+        }
+    }
+}
+
+impl error::Error for FlowError {}
+
+/// Error type of the crate, for the operations which report failures instead of panicking.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// Input could not be parsed, `line` is 1 based.
+    Parse { line: usize, message: String },
+    /// The vertexes or edges given do not form a valid graph.
+    Construction(String),
+    /// A flow problem which can not be solved as given, such as invalid terminals or a sink no flow reaches.
+    Infeasible(FlowError),
+    /// A value does not fit into its integer type.
+    Overflow(String),
+    /// A time, augmentation or other resource limit was reached before the computation finished.
+    Limit(String)
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Parse {line, ref message} => write!(f, "line {}: {}", line, message),
+            Error::Construction(ref message) | Error::Overflow(ref message) | Error::Limit(ref message) =>
+                write!(f, "{}", message),
+            Error::Infeasible(ref e) => write!(f, "{}", e)
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            Error::Infeasible(ref e) => Some(e),
+            _ => None
+        }
+    }
 }
 
-/// Special type of graph which has edges which can have flow and capacity.
+impl From<FlowError> for Error {
+    fn from(e: FlowError) -> Error {
+        Error::Infeasible(e)
+    }
+}
+
+/// Special type of graph which has edges which can have flow and capacity. Its solvers push flow along augmenting
+/// paths, the other algorithms implement `solver::MaxFlowSolver`.
 pub trait FlowGraph {
     fn augmenting_path(&self, source: VertexId, sink: VertexId, search: Search) -> Option<Vec<VertexId>>;
-    fn max_flow(&mut self, source: VertexId, sink: VertexId, search: Search) -> i32;
+    fn max_flow(&mut self, source: VertexId, sink: VertexId, search: Search) -> Result<i64, FlowError>;
+    fn max_flow_observed<F>(&mut self,
+                            source: VertexId,
+                            sink: VertexId,
+                            search: Search,
+                            observer: F) -> Result<i64, FlowError>
+        where F: FnMut(&Progress) -> bool;
+    fn max_flow_with_stats(&mut self,
+                           source: VertexId,
+                           sink: VertexId,
+                           search: Search) -> Result<(i64, SolveStats), FlowError>;
+    fn max_flow_observed_with_stats<F>(&mut self,
+                                       source: VertexId,
+                                       sink: VertexId,
+                                       search: Search,
+                                       observer: F) -> Result<(i64, SolveStats), FlowError>
+        where F: FnMut(&Progress) -> bool;
 }
 
-impl<'a> FlowGraph for Graph<FlowEdge> {
-    /// Returns a path from source to sink if one exists that has non-zero flow.
-    fn augmenting_path(&self, source: VertexId, sink: VertexId, search: Search) -> Option<Vec<VertexId>> {
-        let iter = GraphIterator::new(self, source, sink, flow_predicate, search);
-        let mut node_parent_map = vec![usize::MAX; self.n_vertexes()];
-        let mut sink_exists = false;
-        for node in iter {
-            node_parent_map[node.0] = node.2;
-            sink_exists = sink_exists || node.0 == sink;
-        }
-        if sink_exists {
-            Some(path_from_visited(source, sink, &node_parent_map))
-        } else {
-            None
+impl Graph<FlowEdge> {
+    /// Builds a flow network from its real edges, pairing every edge with a reverse arc without capacity. The residual
+    /// capacity of both arcs is derived from the flow of the pair, and opposite edges get separate pairs.
+    /// Parallel edges get separate pairs. Panics if the graph is invalid, see `try_new`.
+    pub fn flow_network<I>(vertex_list: &[VertexId], edge_list: I) -> Graph<FlowEdge>
+        where I: IntoIterator, I::Item: Borrow<(VertexId, VertexId, FlowEdge)> {
+        Graph::try_flow_network(vertex_list, edge_list, DuplicatePolicy::Parallel, SelfLoopPolicy::Skip)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Same as `flow_network` but merges parallel edges as `duplicates` says, treats self loops as `self_loops` says
+    /// and fails like `try_new` instead of panicking. Like `try_new` it streams the edges, except that merging
+    /// duplicates collects them first.
+    pub fn try_flow_network<I>(vertex_list: &[VertexId],
+                               edge_list: I,
+                               duplicates: DuplicatePolicy,
+                               self_loops: SelfLoopPolicy) -> Result<Graph<FlowEdge>, Error>
+        where I: IntoIterator, I::Item: Borrow<(VertexId, VertexId, FlowEdge)> {
+        if duplicates != DuplicatePolicy::Parallel {
+            let edges = edge_list.into_iter().map(|e| *e.borrow()).collect::<Vec<_>>();
+            let merged = merge_duplicate_edges(&edges, duplicates)?;
+            return Graph::try_flow_network(vertex_list, merged, DuplicatePolicy::Parallel, self_loops);
+        }
+        let edges = edge_list.into_iter();
+        let mut graph = Graph::with_vertexes(vertex_list, 2 * edges.size_hint().0)?;
+        for edge in edges {
+            let &(u, v, edge) = edge.borrow();
+            if u == v && self_loops == SelfLoopPolicy::Reject {
+                return Err(Error::Construction(format!("Self loop on vertex {}", u)));
+            }
+            graph.check_edge(u, v, 2)?;
+            graph.add_arc(u, v, edge);
+            graph.add_arc(v, u, FlowEdge{capacity: 0, flow: -edge.flow});
         }
+        graph.n_edges = graph.arcs.len() / 2;
+        Ok(graph)
     }
 
-    /// Computes a vector of flow paths. Each path includes edges sequentially with the flow across that edge.
-    fn max_flow(&mut self, source: VertexId, sink: VertexId, search: Search) -> i32 {
-        let mut total_flow = 0;
-        loop {
-            let path_option: Option<Vec<VertexId>> = self.augmenting_path(source, sink, search);
-            match path_option {
-                Some(path) => {
-                    let mut edges: Vec<Triplet<FlowEdge>> = Vec::new();
-                    let mut flow: i32 = i32::MAX;
-                    for i in 0..path.len() {
-                        if i + 1 != path.len() {
-                            let v_0 = path[i];
-                            let v_1 = path[i + 1];
-                            let flow_edge = self.edges[v_0][v_1];
-                            edges.push(Triplet(v_0, flow_edge, v_1));
-                            flow = min(flow_edge.capacity - flow_edge.flow, flow);
+    /// Returns the graph with its parallel edges merged as `duplicates` says, keeping the deterministic mode and the
+    /// vertex names.
+    pub fn merge_duplicates(&self, duplicates: DuplicatePolicy) -> Result<Graph<FlowEdge>, Error> {
+        let edges = self.edges().map(|(u, v, a)| (u, v, self.arcs[a]));
+        let vertexes = self.vertexes().collect::<Vec<_>>();
+        let mut graph = Graph::try_flow_network(&vertexes, edges, duplicates, SelfLoopPolicy::Skip)?;
+        graph.set_deterministic(self.deterministic);
+        graph.names = self.names.clone();
+        Ok(graph)
+    }
+
+    /// Returns whether every edge is followed by its reverse arc without capacity, the layout built by `flow_network`.
+    pub fn has_residual_arcs(&self) -> bool {
+        let tails = self.tails();
+        self.arcs.len().is_multiple_of(2) && (0..self.arcs.len()).step_by(2).all(|a| {
+            tails[a] == self.head(a + 1) && tails[a + 1] == self.head(a) && self.arcs[a + 1].capacity == 0
+        })
+    }
+
+    /// Rebuilds a graph created with `Graph::new` as a flow network, turning every arc into an edge with its own
+    /// reverse arc. Does nothing if the graph already has its residual arcs. Arc ids change when the graph is rebuilt.
+    pub fn ensure_residual_arcs(&mut self) {
+        if !self.has_residual_arcs() {
+            let names = mem::take(&mut self.names);
+            let tails = self.tails();
+            let edges = (0..self.arcs.len()).map(|a| (tails[a], self.head(a), self.arcs[a]));
+            let deterministic = self.deterministic;
+            *self = Graph::flow_network(&self.vertexes().collect::<Vec<_>>(), edges);
+            self.set_deterministic(deterministic);
+            self.names = names;
+        }
+    }
+
+    /// Removes the flow from every arc, so the graph can be solved again for any pair of terminals.
+    pub fn reset_flow(&mut self) {
+        self.arcs.iter_mut().for_each(|arc| arc.flow = 0);
+    }
+
+    /// Returns whether flow is conserved at every vertex other than `source` and `sink`, which holds for a graph
+    /// without flow and for any flow computed between the same terminals.
+    pub fn conserves_flow(&self, source: VertexId, sink: VertexId) -> bool {
+        self.conserves(|a| self.arcs[a].flow, source, sink)
+    }
+
+    fn conserves(&self, flow: impl Fn(ArcId) -> i32, source: VertexId, sink: VertexId) -> bool {
+        let mut excess = vec![0i64; self.n_vertexes()];
+        for (u, v, arc) in self.edges() {
+            excess[u] -= flow(arc) as i64;
+            excess[v] += flow(arc) as i64;
+        }
+        self.vertexes().all(|v| excess[v] == 0 || v == source || v == sink)
+    }
+
+    /// Returns a summary of the size, degrees, capacities and connectivity of the network.
+    pub fn stats(&self) -> GraphStats {
+        let (n, m) = (self.n_vertexes(), self.n_edges());
+        let mut out_degrees = vec![0; n];
+        let mut capacities = (i32::MAX, 0i64, i32::MIN);
+        let mut zero_capacity_edges = 0;
+        for (u, _, a) in self.edges() {
+            let capacity = self.arcs[a].capacity;
+            out_degrees[u] += 1;
+            capacities = (capacities.0.min(capacity), capacities.1 + capacity as i64, capacities.2.max(capacity));
+            zero_capacity_edges += (capacity == 0) as usize;
+        }
+        let components = self.connected_components();
+        GraphStats {
+            vertexes: n,
+            edges: m,
+            density: if n > 1 { m as f64 / (n * (n - 1)) as f64 } else { 0.0 },
+            min_out_degree: out_degrees.iter().cloned().min().unwrap_or(0),
+            mean_out_degree: if n > 0 { m as f64 / n as f64 } else { 0.0 },
+            max_out_degree: out_degrees.iter().cloned().max().unwrap_or(0),
+            min_capacity: if m > 0 { capacities.0 } else { 0 },
+            mean_capacity: if m > 0 { capacities.1 as f64 / m as f64 } else { 0.0 },
+            max_capacity: if m > 0 { capacities.2 } else { 0 },
+            zero_capacity_edges,
+            components: components.iter().map(|&c| c + 1).max().unwrap_or(0)
+        }
+    }
+
+    /// Returns the vertex arc `arc` leaves, which is the head of its reverse arc.
+    #[inline]
+    pub fn tail(&self, arc: ArcId) -> VertexId {
+        self.head(arc ^ 1)
+    }
+
+    /// Returns the arc of the first edge from `u` to `v`, ignoring reverse arcs.
+    pub fn find_edge(&self, u: VertexId, v: VertexId) -> Option<ArcId> {
+        self.out_arcs(u).find(|a| a % 2 == 0 && self.head(*a) == v)
+    }
+
+    /// Iterates over the edges as (from, to, arc) in the order they were added, leaving out the reverse arcs.
+    pub fn edges(&self) -> impl Iterator<Item = (VertexId, VertexId, ArcId)> + '_ {
+        (0..self.arcs.len()).step_by(2).map(move |a| (self.tail(a), self.head(a), a))
+    }
+
+    /// Returns the vertexes of the path to `sink` found by the last search, following the parents back to `source`.
+    fn residual_path(&self, buffers: &SearchBuffers, source: VertexId, sink: VertexId) -> Vec<VertexId> {
+        let mut path: Vec<VertexId> = vec![sink];
+        let mut node = sink;
+        while node != source {
+            node = VertexId::stored(buffers.parents[node]);
+            path.push(node);
+        }
+        path.reverse();
+        path
+    }
+
+    /// Searches the residual graph from `source` until `sink` is reached, leaving the parent pointers of the search in
+    /// `buffers`. Only arcs for which `has_residual` holds are followed. The search stops as soon as the sink gets a
+    /// parent, since the path to it is fixed from then on. Returns whether the sink was reached.
+    fn search_residual<R>(&self,
+                          buffers: &mut SearchBuffers,
+                          source: VertexId,
+                          sink: VertexId,
+                          search: Search,
+                          has_residual: R) -> bool
+        where R: Fn(ArcId) -> bool {
+        buffers.start(source, search);
+        if source == sink {
+            return true;
+        }
+        match search {
+            Search::Bfs => self.search_frontiers(buffers, sink, has_residual),
+            Search::Bidirectional => self.search_bidirectional(buffers, sink, has_residual),
+            Search::Dfs => {
+                while let Some(vertex) = buffers.pop(search) {
+                    buffers.vertexes_scanned += 1;
+                    for a in self.out_arcs(vertex) {
+                        buffers.arcs_scanned += 1;
+                        let v = self.head(a);
+                        if has_residual(a) && buffers.visit(v, vertex.0 as Index, a as Index) {
+                            if v == sink {
+                                return true;
+                            }
+                            buffers.push(v, search);
                         }
                     }
-                    let mut flow_path: Vec<Edge> = Vec::new();
-                    for edge in &edges {
-                        {
-                            let uv_edge = self.edges.get_mut(edge.0).unwrap().get_mut(edge.2).unwrap();
-                            uv_edge.flow += flow;
-                        }
-                        {
-                            let vu_edge = self.edges.get_mut(edge.2).unwrap().get_mut(edge.0).unwrap();
-                            vu_edge.flow -= flow;
-                        }
-                        flow_path.push(Edge(edge.0, edge.2));
+                }
+                false
+            }
+        }
+    }
+
+    /// Solves with the flows stored in the arcs, see `max_flow_observed`. The flows are moved to a `FlowState` for the
+    /// computation and written back afterwards.
+    fn augment_arcs<F>(&mut self,
+                       source: VertexId,
+                       sink: VertexId,
+                       search: Search,
+                       steps: Option<&mut Vec<TraceStep>>,
+                       observer: F) -> Result<(i64, SolveStats), FlowError>
+        where F: FnMut(&Progress) -> bool {
+        // The terminals are checked before the graph is touched, so an invalid call leaves it unchanged
+        self.check_terminals(source, sink)?;
+        self.ensure_residual_arcs();
+        let mut state = FlowState::from_graph(self);
+        let result = self.augment(&mut state.flows, source, sink, search, steps, observer);
+        state.apply(self);
+        result
+    }
+
+    /// Pushes flow along augmenting paths until none is left or the observer stops the computation, see
+    /// `max_flow_observed`. The flow of arc `a` is `flows[a]`, the graph only provides the topology and capacities.
+    /// Every augmenting path is appended to `steps` when given. Returns the flow value and the statistics of the
+    /// computation.
+    fn augment<F>(&self,
+                  flows: &mut [i32],
+                  source: VertexId,
+                  sink: VertexId,
+                  search: Search,
+                  mut steps: Option<&mut Vec<TraceStep>>,
+                  mut observer: F) -> Result<(i64, SolveStats), FlowError>
+        where F: FnMut(&Progress) -> bool {
+        #[cfg(feature = "std")]
+        let start_time = Instant::now();
+        self.check_terminals(source, sink)?;
+        if !self.has_residual_arcs() {
+            return Err(FlowError::MissingResidualArcs);
+        }
+        if !self.conserves(|a| flows[a], source, sink) {
+            return Err(FlowError::ExistingFlow);
+        }
+        debug!("Max flow from {} to {} by {:?} on {} vertexes and {} edges",
+               source, sink, search, self.n_vertexes(), self.n_edges());
+        let mut total_flow = 0;
+        let residual = |a: ArcId| (self.arcs[a].capacity - flows[a]) as i64;
+        let upper_bound = self.out_arcs(source).map(residual).sum::<i64>()
+            .min(self.out_arcs(sink).map(|a| residual(a ^ 1)).sum::<i64>());
+        let mut progress = Progress {augmentations: 0, path_length: 0, bottleneck: 0, flow: 0, upper_bound};
+        let mut stats = SolveStats::default();
+        // The search buffers are allocated once, not for every augmenting path
+        let mut buffers = SearchBuffers::new(self.n_vertexes());
+        loop {
+            stats.phases += 1;
+            if !self.search_residual(&mut buffers, source, sink, search, |a| self.arcs[a].capacity > flows[a]) {
+                break;
+            }
+            // Walk the parent arcs from the sink twice, once for the bottleneck and once to push it, so no path is built
+            let mut flow: i32 = i32::MAX;
+            let mut path_length = 0;
+            let mut node = sink;
+            while node != source {
+                let arc = buffers.parent_arcs[node] as ArcId;
+                flow = min(self.arcs[arc].capacity - flows[arc], flow);
+                path_length += 1;
+                node = VertexId::stored(buffers.parents[node]);
+            }
+            node = sink;
+            while node != source {
+                // The reverse arc is paired with the forward arc, so both are updated without a lookup
+                let arc = buffers.parent_arcs[node] as ArcId;
+                flows[arc] += flow;
+                flows[arc ^ 1] -= flow;
+                node = VertexId::stored(buffers.parents[node]);
+            }
+            if let Some(ref mut steps) = steps {
+                steps.push(TraceStep {path: self.residual_path(&buffers, source, sink), bottleneck: flow});
+            }
+            progress.augmentations += 1;
+            stats.pushes += path_length as u64;
+            progress.path_length = path_length;
+            progress.bottleneck = flow;
+            progress.flow += flow as i64;
+            if progress.augmentations.is_power_of_two() {
+                progress.upper_bound = progress.upper_bound.min(progress.flow + self.level_cut(flows, source, sink));
+            }
+            trace!("Augmentation {} along {} arcs with bottleneck {}, flow {}",
+                   progress.augmentations, path_length, flow, progress.flow);
+            if !observer(&progress) {
+                break;
+            }
+        }
+        for a in self.out_arcs(source) {
+            if a % 2 == 0 {
+                total_flow += flows[a] as i64;
+            }
+        }
+        debug!("Max flow phase ended after {} augmentations and {} searches with flow {}",
+               progress.augmentations, stats.phases, total_flow);
+        stats.augmenting_paths = progress.augmentations;
+        stats.vertexes_scanned = buffers.vertexes_scanned;
+        stats.arcs_scanned = buffers.arcs_scanned;
+        #[cfg(feature = "std")]
+        {
+            stats.wall_time = start_time.elapsed();
+        }
+        if total_flow == 0 && progress.augmentations == 0 {
+            return Err(FlowError::UnreachableSink {source, sink});
+        }
+        Ok((total_flow, stats))
+    }
+
+    /// Returns the smallest residual capacity of the cuts between consecutive residual levels from `source`, which
+    /// bounds the flow that can still be pushed, or 0 if the sink can not be reached. Residual arcs climb at most one
+    /// level, so the arcs crossing the cut above level k all leave level k.
+    fn level_cut(&self, flows: &[i32], source: VertexId, sink: VertexId) -> i64 {
+        let mut levels = vec![u32::MAX; self.n_vertexes()];
+        levels[source] = 0;
+        let mut frontier = vec![source];
+        while !frontier.is_empty() && levels[sink] == u32::MAX {
+            let mut next: Vec<VertexId> = Vec::new();
+            for &u in &frontier {
+                for a in self.out_arcs(u) {
+                    let v = self.head(a);
+                    if levels[v] == u32::MAX && self.arcs[a].capacity > flows[a] {
+                        levels[v] = levels[u] + 1;
+                        next.push(v);
                     }
-                },
-                None => {
-                    for v in &self.neighbors[source] {
-                        if self.edges[source][*v].capacity != 0 {
-                            total_flow += self.edges[source][*v].flow;
+                }
+            }
+            frontier = next;
+        }
+        if levels[sink] == u32::MAX {
+            return 0;
+        }
+        let mut crossing = vec![0i64; levels[sink] as usize];
+        for u in self.vertexes().filter(|&u| levels[u] < levels[sink]) {
+            for a in self.out_arcs(u) {
+                if levels[self.head(a)] == levels[u] + 1 {
+                    crossing[levels[u] as usize] += (self.arcs[a].capacity - flows[a]) as i64;
+                }
+            }
+        }
+        crossing.into_iter().min().unwrap_or(0)
+    }
+
+    /// Breadth first search which scans whole frontiers instead of popping vertexes one at a time. The frontier arrays
+    /// are moved out of the buffers while scanning, so the frontier can be read while the visited marks are written.
+    fn search_frontiers<R>(&self, buffers: &mut SearchBuffers, sink: VertexId, has_residual: R) -> bool
+        where R: Fn(ArcId) -> bool {
+        let mut frontier = mem::take(&mut buffers.next);
+        let mut next = mem::take(&mut buffers.frontier);
+        let mut found = false;
+        'levels: while !frontier.is_empty() {
+            for &vertex in &frontier {
+                buffers.vertexes_scanned += 1;
+                for a in self.out_arcs(vertex) {
+                    buffers.arcs_scanned += 1;
+                    let v = self.head(a);
+                    if has_residual(a) && buffers.visit(v, vertex.0 as Index, a as Index) {
+                        if v == sink {
+                            found = true;
+                            break 'levels;
                         }
+                        next.push(v);
                     }
-                    break;
                 }
             }
+            mem::swap(&mut frontier, &mut next);
+            next.clear();
         }
-
-        total_flow
+        buffers.frontier = frontier;
+        buffers.next = next;
+        found
     }
-}
 
-pub fn create_residual_edges(edge_list: &mut Vec<(VertexId, VertexId, FlowEdge)>) {
-    let mut residuals: Vec<(VertexId, VertexId, FlowEdge)> = Vec::with_capacity(edge_list.len());
-    for e in edge_list.iter() {
-        residuals.push((e.1, e.0, FlowEdge {capacity: 0, flow: 0}));
-    }
-    edge_list.extend(residuals);
-}
-
-pub fn flow_from_dicaps(file_name: &str) -> (VertexId, VertexId, Graph<FlowEdge>) {
-    let f = File::open(file_name).expect(&format!("Input file does not exist: {}", file_name));
-    let reader = BufReader::new(&f);
-    let mut num_vertexes = 0;
-    let mut num_edges = 0;
-    let mut source = None;
-    let mut sink = None;
-    let mut edges: Vec<(VertexId, VertexId, FlowEdge)> = Vec::new();
-    let mut num_parsed_edges = 0;
-    for raw_line in reader.lines() {
-        let line = raw_line.unwrap();
-        let tokens = line.split_whitespace().collect::<Vec<_>>();
-        match tokens.len() {
-            4 => {
-                match tokens[0] {
-                    "p" => {
-                        num_vertexes = tokens[2].parse::<_>().expect("Expected an integer for number of vertexes");
-                        num_edges = tokens[3].parse::<_>().expect("Expected an integer for number of edges");
-                    },
-                    "a" => {
-                        let u = tokens[1].parse::<VertexId>().expect("Expected an integer for source in edge");
-                        let v = tokens[2].parse::<VertexId>().expect("Expected an integer for destination in edge");
-                        let capacity = tokens[3].parse::<_>().expect("Expected an integer for capaicty");
-                        if capacity > 0 {
-                            edges.push((u, v, FlowEdge{flow: 0, capacity: capacity}));
+    /// Bidirectional breadth first search, see `Search::Bidirectional`. The backward search follows an arc into a
+    /// vertex through its paired reverse arc, so the graph must have its residual arcs. When the searches meet, the
+    /// path from the meeting arc to the sink is written into the parent pointers, so the path is read like the path of
+    /// a forward search.
+    fn search_bidirectional<R>(&self, buffers: &mut SearchBuffers, sink: VertexId, has_residual: R) -> bool
+        where R: Fn(ArcId) -> bool {
+        if buffers.child_arcs.len() != self.n_vertexes() {
+            buffers.sink_side = vec![0; self.n_vertexes().div_ceil(64)];
+            buffers.child_arcs = vec![Index::MAX; self.n_vertexes()];
+        }
+        buffers.sink_side.iter_mut().for_each(|w| *w = 0);
+        buffers.sink_side[sink.0 / 64] |= 1u64 << (sink.0 % 64);
+        let on_sink_side = |sink_side: &[u64], v: VertexId| sink_side[v.0 / 64] & (1u64 << (v.0 % 64)) != 0;
+        let mut forward = mem::take(&mut buffers.next);
+        let mut next = mem::take(&mut buffers.frontier);
+        let mut backward = mem::take(&mut buffers.backward);
+        backward.clear();
+        backward.push(sink);
+        // The arc from a vertex reached from the source into a vertex reached from the sink
+        let mut meeting: Option<ArcId> = None;
+        while meeting.is_none() && !forward.is_empty() && !backward.is_empty() {
+            if forward.len() <= backward.len() {
+                'forward: for &u in &forward {
+                    buffers.vertexes_scanned += 1;
+                    for a in self.out_arcs(u) {
+                        buffers.arcs_scanned += 1;
+                        if !has_residual(a) {
+                            continue;
+                        }
+                        let v = self.head(a);
+                        if on_sink_side(&buffers.sink_side, v) {
+                            meeting = Some(a);
+                            break 'forward;
+                        }
+                        if buffers.visit(v, u.0 as Index, a as Index) {
+                            next.push(v);
                         }
-                        num_parsed_edges += 1;
-                    },
-                    _ => {
-                        panic!("Invalid line: {}", line);
                     }
                 }
-            },
-            3 => {
-                match tokens[0] {
-                    "n" => {
-                        match tokens[2] {
-                            "s" => {
-                                source = Some(
-                                    tokens[1].parse::<VertexId>().expect("Expected an integer for source"));
-                            },
-                            "t" => {
-                                sink = Some(
-                                    tokens[1].parse::<VertexId>().expect("Expected an integer for sink"));
-                            },
-                            _ => {
-                                panic!("Invalid line: {}", line);
-                            }
+                mem::swap(&mut forward, &mut next);
+            } else {
+                'backward: for &w in &backward {
+                    buffers.vertexes_scanned += 1;
+                    for b in self.out_arcs(w) {
+                        buffers.arcs_scanned += 1;
+                        // The reverse of an arc leaving w is the arc entering it from the head
+                        let (a, u) = (b ^ 1, self.head(b));
+                        if !has_residual(a) {
+                            continue;
+                        }
+                        if buffers.is_visited(u) {
+                            meeting = Some(a);
+                            break 'backward;
+                        }
+                        if !on_sink_side(&buffers.sink_side, u) {
+                            buffers.sink_side[u.0 / 64] |= 1u64 << (u.0 % 64);
+                            buffers.child_arcs[u] = a as Index;
+                            next.push(u);
                         }
-                    }
-                    _ => {
-                        panic!("Invalid line: {}", line);
                     }
                 }
-            },
-            1 => {
-                if tokens[0] == "a" {
-                    break;
-                } else {
-                    panic!("Invalid line: {}", line);
-                }
-            },
-            0 => {
-                break;
+                mem::swap(&mut backward, &mut next);
             }
-            _ =>{
-                panic!("Invalid line: {}", line)
+            next.clear();
+        }
+        forward.clear();
+        buffers.next = forward;
+        buffers.frontier = next;
+        buffers.backward = backward;
+        let mut a = match meeting {
+            Some(a) => a,
+            None => return false
+        };
+        let mut parent = self.tail(a);
+        loop {
+            let v = self.head(a);
+            buffers.parents[v] = parent.0 as Index;
+            buffers.parent_arcs[v] = a as Index;
+            if v == sink {
+                return true;
             }
+            parent = v;
+            a = buffers.child_arcs[v] as ArcId;
         }
     }
-    assert!(num_parsed_edges == num_edges,
-            "Number of edges specified and found are different: {} vs {}",
-            num_parsed_edges, num_edges);
-    let mut vertex_set: HashSet<VertexId> = HashSet::new();
-    for e in &edges {
-        vertex_set.insert(e.0);
-        vertex_set.insert(e.1);
-    }
-    assert!(vertex_set.len() == num_vertexes,
-            "Number of vertexes specified and found are different: {} vs {}",
-            vertex_set.len(), num_vertexes);
-    let vertexes = (0..num_vertexes).collect::<Vec<_>>();
-    create_residual_edges(&mut edges);
-    (source.expect("Must have a source"), sink.expect("Must have a sink"), Graph::new(&vertexes, &edges))
-}
-
-pub fn flow_from_txt(file_name: &str) -> (VertexId, VertexId, Graph<FlowEdge>) {
-    let f = File::open(file_name).expect(&format!("Input file does not exist: {}", file_name));
-    let reader = BufReader::new(&f);
-    let mut edges: Vec<(VertexId, VertexId, FlowEdge)> = Vec::new();
-    let mut i = 0;
-    let mut num_vertexes = 0;
-    let mut flow_parsed = false;
-    for raw_line in reader.lines() {
-        let line = raw_line.unwrap();
-        let tokens = line.split_whitespace().collect::<Vec<_>>();
-        if !flow_parsed {
-            num_vertexes = tokens[0].parse::<usize>().expect("Expected an integer for number of edges");
-            flow_parsed = true;
+}
+
+impl FlowGraph for Graph<FlowEdge> {
+    /// Returns a path from source to sink if one exists that has non-zero flow.
+    fn augmenting_path(&self, source: VertexId, sink: VertexId, search: Search) -> Option<Vec<VertexId>> {
+        // Searching backwards needs the reverse arcs, which a graph built with `Graph::new` does not have
+        let search = if search == Search::Bidirectional && !self.has_residual_arcs() { BFS } else { search };
+        let mut buffers = SearchBuffers::new(self.n_vertexes());
+        if self.search_residual(&mut buffers, source, sink, search, |a| flow_predicate(self.arcs[a])) {
+            Some(self.residual_path(&buffers, source, sink))
         } else {
-            for v in tokens.iter().enumerate() {
-                let capacity = v.1.parse::<i32>().expect("Expected an integer capacity");
-                if capacity > 0 {
-                    edges.push(
-                        (i, v.0, FlowEdge{capacity: capacity, flow: 0})
-                    );
-                }
+            None
+        }
+    }
+
+    /// Computes a maximum flow from source to sink by augmenting along residual paths found with `search`. The flow of
+    /// every arc is left in the graph and the flow value is returned. Flow already in the graph is kept and augmented,
+    /// which continues a computation between the same terminals. It must be removed with `reset_flow` before solving
+    /// for other terminals. Fails if a terminal is not a vertex, if the source is the sink or if no flow at all reaches
+    /// the sink. Debug builds check the result with `verify::certify`.
+    fn max_flow(&mut self, source: VertexId, sink: VertexId, search: Search) -> Result<i64, FlowError> {
+        let flow = self.max_flow_observed(source, sink, search, |_| true)?;
+        debug_assert_eq!(verify::certify(self, source, sink, flow).err(), None, "Max flow {} is not optimal", flow);
+        Ok(flow)
+    }
+
+    /// Same as `max_flow` but calls `observer` with the current progress after every augmentation. The computation stops
+    /// early when the observer returns false, leaving a valid but possibly not maximum flow in the graph whose value is
+    /// returned. A graph without residual arcs is rebuilt with them first, see `ensure_residual_arcs`.
+    fn max_flow_observed<F>(&mut self,
+                            source: VertexId,
+                            sink: VertexId,
+                            search: Search,
+                            observer: F) -> Result<i64, FlowError>
+        where F: FnMut(&Progress) -> bool {
+        self.augment_arcs(source, sink, search, None, observer).map(|(flow, _)| flow)
+    }
+
+    /// Same as `max_flow` but also returns statistics of the work done.
+    fn max_flow_with_stats(&mut self,
+                           source: VertexId,
+                           sink: VertexId,
+                           search: Search) -> Result<(i64, SolveStats), FlowError> {
+        self.augment_arcs(source, sink, search, None, |_| true)
+    }
+
+    /// Same as `max_flow_observed` but also returns statistics of the work done.
+    fn max_flow_observed_with_stats<F>(&mut self,
+                                       source: VertexId,
+                                       sink: VertexId,
+                                       search: Search,
+                                       observer: F) -> Result<(i64, SolveStats), FlowError>
+        where F: FnMut(&Progress) -> bool {
+        self.augment_arcs(source, sink, search, None, observer)
+    }
+}
+
+/// Flow of every arc of a flow network, kept apart from the graph. The graph only provides the topology and the
+/// capacities, so any number of threads can solve for different terminals on one shared graph, each with its own
+/// state. Arc `a` carries `flows[a]`, with the same antisymmetry as the flows stored in the arcs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FlowState {
+    pub flows: Vec<i32>
+}
+
+impl FlowState {
+    /// Creates a state without flow for `graph`.
+    pub fn new(graph: &Graph<FlowEdge>) -> FlowState {
+        FlowState {flows: vec![0; graph.arcs.len()]}
+    }
+
+    /// Creates a state holding the flows stored in the arcs of `graph`.
+    pub fn from_graph(graph: &Graph<FlowEdge>) -> FlowState {
+        FlowState {flows: graph.arcs.iter().map(|arc| arc.flow).collect()}
+    }
+
+    /// Stores the flows of the state in the arcs of `graph`, which must be the graph the state was created for.
+    pub fn apply(&self, graph: &mut Graph<FlowEdge>) {
+        for (arc, flow) in graph.arcs.iter_mut().zip(&self.flows) {
+            arc.flow = *flow;
+        }
+    }
+
+    /// Computes a maximum flow from source to sink in the state, reading only the topology and capacities of `graph`,
+    /// which must have been built with `Graph::flow_network`. Behaves like `FlowGraph::max_flow` otherwise.
+    pub fn max_flow(&mut self,
+                    graph: &Graph<FlowEdge>,
+                    source: VertexId,
+                    sink: VertexId,
+                    search: Search) -> Result<i64, FlowError> {
+        if self.flows.len() != graph.arcs.len() {
+            return Err(FlowError::StateMismatch {arcs: graph.arcs.len(), flows: self.flows.len()});
+        }
+        graph.augment(&mut self.flows, source, sink, search, None, |_| true).map(|(flow, _)| flow)
+    }
+}
+
+/// How the construction of a flow network treats several edges from one vertex to another.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum DuplicatePolicy {
+    /// Every edge gets its own pair of arcs.
+    #[default]
+    Parallel,
+    /// The edges are merged into one edge with the sum of their capacities and flows.
+    Sum,
+    /// Only the edge with the largest capacity is kept, the first of them on ties.
+    KeepMax,
+    /// Only the first edge is kept.
+    KeepFirst,
+    /// Duplicate edges are a construction error.
+    Error
+}
+
+/// How the construction of a flow network treats edges from a vertex to itself.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum SelfLoopPolicy {
+    /// Self loops are stored like any other edge but never carry flow. The searches of the flow algorithms skip them,
+    /// since the head of a self loop is the vertex being scanned, which is always visited already.
+    #[default]
+    Skip,
+    /// Self loops are a construction error.
+    Reject
+}
+
+/// Merges the edges between the same pair of vertexes as `policy` says. A merged edge takes the place of the first
+/// edge of its pair, so the order of the edges is otherwise kept.
+pub fn merge_duplicate_edges(edge_list: &[(VertexId, VertexId, FlowEdge)],
+                             policy: DuplicatePolicy) -> Result<Vec<(VertexId, VertexId, FlowEdge)>, Error> {
+    if policy == DuplicatePolicy::Parallel {
+        return Ok(edge_list.to_vec());
+    }
+    let mut merged: Vec<(VertexId, VertexId, FlowEdge)> = Vec::with_capacity(edge_list.len());
+    let mut first: BTreeMap<(VertexId, VertexId), usize> = BTreeMap::new();
+    for &(u, v, edge) in edge_list {
+        let i = match first.get(&(u, v)) {
+            Some(&i) => i,
+            None => {
+                first.insert((u, v), merged.len());
+                merged.push((u, v, edge));
+                continue;
             }
-            i += 1;
+        };
+        trace!("Merging a duplicate edge ({}, {}) by {:?}", u, v, policy);
+        let kept = &mut merged[i].2;
+        match policy {
+            DuplicatePolicy::Sum => {
+                let overflow = || Error::Overflow(format!("Total capacity of the edges ({}, {}) overflows", u, v));
+                kept.capacity = kept.capacity.checked_add(edge.capacity).ok_or_else(overflow)?;
+                kept.flow = kept.flow.checked_add(edge.flow).ok_or_else(overflow)?;
+            },
+            DuplicatePolicy::KeepMax => {
+                if edge.capacity > kept.capacity {
+                    *kept = edge;
+                }
+            },
+            DuplicatePolicy::Error => return Err(Error::Construction(format!("Duplicate edge ({}, {})", u, v))),
+            DuplicatePolicy::KeepFirst | DuplicatePolicy::Parallel => {}
+        }
+    }
+    Ok(merged)
+}
+
+/// Follows every edge with its reverse arc without capacity, for graphs built with `Graph::new`.
+#[deprecated(note = "build flow graphs with Graph::flow_network, max_flow also adds missing residual arcs itself")]
+pub fn create_residual_edges(edge_list: &mut Vec<(VertexId, VertexId, FlowEdge)>) {
+    let mut paired: Vec<(VertexId, VertexId, FlowEdge)> = Vec::with_capacity(2 * edge_list.len());
+    for e in edge_list.iter() {
+        paired.push(*e);
+        paired.push((e.1, e.0, FlowEdge {capacity: 0, flow: 0}));
+    }
+    *edge_list = paired;
+}
+
+/// Joins several sources and sinks into a single supersource and supersink, which become vertexes `n_vertexes` and
+/// `n_vertexes + 1`. Each super arc gets the total capacity leaving (or entering) its terminal so it never limits the
+/// flow. Must be called before building the graph, returns the new (source, sink) pair. Fails without changing the
+/// edges if a vertex is both a source and a sink, or with `Error::Overflow` if the total capacity of a terminal does
+/// not fit the capacity type of `FlowEdge`. The totals of all terminals are summed in a single pass over the edges.
+pub fn add_super_terminals(edge_list: &mut Vec<(VertexId, VertexId, FlowEdge)>,
+                           n_vertexes: usize,
+                           sources: &[VertexId],
+                           sinks: &[VertexId]) -> Result<(VertexId, VertexId), Error> {
+    // no_std has no HashSet, ordered collections keep the check and the sums logarithmic in the terminals
+    let sink_set = sinks.iter().collect::<BTreeSet<_>>();
+    if let Some(s) = sources.iter().find(|s| sink_set.contains(s)) {
+        return Err(Error::Construction(format!("Vertex {} can not be both a source and a sink", s)));
+    }
+    let source = VertexId(n_vertexes);
+    let sink = VertexId(n_vertexes + 1);
+    let overflow = |v: VertexId| Error::Overflow(format!("The total capacity at terminal {} exceeds the 32 bit \
+                                                           capacity type of the flow network", v));
+    let mut leaving = sources.iter().map(|&s| (s, 0i32)).collect::<BTreeMap<_, _>>();
+    let mut entering = sinks.iter().map(|&t| (t, 0i32)).collect::<BTreeMap<_, _>>();
+    for &(u, v, edge) in edge_list.iter() {
+        if let Some(total) = leaving.get_mut(&u) {
+            *total = total.checked_add(edge.capacity).ok_or_else(|| overflow(u))?;
+        }
+        if let Some(total) = entering.get_mut(&v) {
+            *total = total.checked_add(edge.capacity).ok_or_else(|| overflow(v))?;
         }
     }
-    let vertexes = (0..num_vertexes).collect::<Vec<_>>();
-    create_residual_edges(&mut edges);
-    (0, num_vertexes - 1, Graph::new(&vertexes, &edges))
+    edge_list.reserve(sources.len() + sinks.len());
+    edge_list.extend(sources.iter().map(|&s| (source, s, FlowEdge{capacity: leaving[&s], flow: 0})));
+    edge_list.extend(sinks.iter().map(|&t| (t, sink, FlowEdge{capacity: entering[&t], flow: 0})));
+    Ok((source, sink))
+}
+
+/// Returns the random number generator behind every seeded feature: the generators, randomized algorithms such as
+/// `partition::partition` and `sparsify::sparsify`, and the shuffled traversal of `strategy::Random`. Each of them also
+/// takes any `rand::Rng` in its `_with_rng` or `from_rng` form, so a whole run drawing from one generator made by this
+/// function is reproducible from a single seed.
+#[cfg(feature = "std")]
+pub fn seeded_rng(seed: u64) -> rand::rngs::StdRng {
+    rand::SeedableRng::seed_from_u64(seed)
 }
 
 fn true_predicate<E: Property>(_: E) -> bool {
@@ -400,35 +1490,105 @@ fn true_predicate<E: Property>(_: E) -> bool {
 }
 
 /// Ensure that there is available flow across the edge.
-fn flow_predicate<'a>(edge: FlowEdge) -> bool {
-    edge.capacity - edge.flow > 0
+fn flow_predicate(edge: FlowEdge) -> bool {
+    edge.residual() > 0
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "std"))]
 mod tests {
     use super::*;
     use std::collections::HashSet;
-    use std::usize;
+
+    fn ids(vertexes: &[usize]) -> Vec<VertexId> {
+        vertexes.iter().map(|&v| VertexId(v)).collect()
+    }
+
+    fn edges<E: Copy>(edges: &[(usize, usize, E)]) -> Vec<(VertexId, VertexId, E)> {
+        edges.iter().map(|&(u, v, e)| (VertexId(u), VertexId(v), e)).collect()
+    }
 
     #[test]
     fn test_new_graph() {
-        let vertex_list = vec![0, 1, 2, 3, 4];
-        let edge_list = vec![(0, 1, 5), (0, 2, 2), (2, 3, 3), (4, 3, 1)];
+        let vertex_list = ids(&[0, 1, 2, 3, 4]);
+        let edge_list = edges(&[(0, 1, 5), (0, 2, 2), (2, 3, 3), (4, 3, 1)]);
         let g = Graph::new(&vertex_list, &edge_list);
         assert_eq!(g.size(), (5, 4));
         assert_eq!(g.n_vertexes(), vertex_list.len());
-        assert_eq!(g.edges[0][1], 5);
-        assert_eq!(g.edges[0][2], 2);
-        assert_eq!(g.edges[2][3], 3);
-        assert_eq!(g.edges[4][3], 1);
+        assert_eq!(g.arcs[g.find_arc(VertexId(0), VertexId(1)).unwrap()], 5);
+        assert_eq!(g.arcs[g.find_arc(VertexId(0), VertexId(2)).unwrap()], 2);
+        assert_eq!(g.arcs[g.find_arc(VertexId(2), VertexId(3)).unwrap()], 3);
+        assert_eq!(g.arcs[g.find_arc(VertexId(4), VertexId(3)).unwrap()], 1);
+        assert_eq!(g.find_arc(VertexId(3), VertexId(2)), None);
+    }
+
+    #[test]
+    fn test_try_new() {
+        assert!(Graph::try_new(&ids(&[0, 1]), edges(&[(0, 1, 5)])).is_ok());
+        assert_eq!(Graph::try_new(&ids(&[0, 2]), edges(&[(0, 1, 5)])).unwrap_err(),
+                   Error::Construction("Must provide vertexes in order from 0 to n - 1".to_string()));
+        let edges = [(VertexId(0), VertexId(2), FlowEdge{capacity: 1, flow: 0})];
+        assert_eq!(Graph::try_flow_network(&ids(&[0, 1]), edges, DuplicatePolicy::Parallel, SelfLoopPolicy::Skip)
+                       .unwrap_err(),
+                   Error::Construction("Edge (0, 2) is not between vertexes of the graph".to_string()));
+        // Edges streamed from an iterator build the same graph as a slice of them
+        let path = || (0..3).map(|u| (VertexId(u), VertexId(u + 1), FlowEdge{capacity: u as i32 + 1, flow: 0}));
+        let streamed = Graph::flow_network(&ids(&[0, 1, 2, 3]), path());
+        let collected = Graph::flow_network(&ids(&[0, 1, 2, 3]), path().collect::<Vec<_>>());
+        let arcs = |g: &Graph<FlowEdge>| g.edges().map(|(u, v, a)| (u, v, g.arcs[a].capacity)).collect::<Vec<_>>();
+        assert_eq!((arcs(&streamed), &streamed.heads, streamed.n_edges()), (arcs(&collected), &collected.heads, 3));
+        assert!(Graph::try_new(&ids(&[0, 1]), (0..3).map(|v| (VertexId(0), VertexId(v), 1))).is_err());
+        let mut edge_list = vec![(VertexId(0), VertexId(1), FlowEdge{capacity: 1, flow: 0})];
+        assert!(matches!(add_super_terminals(&mut edge_list, 2, &ids(&[0]), &ids(&[0])), Err(Error::Construction(_))));
+        assert_eq!(edge_list.len(), 1);
+        let e: Error = FlowError::SourceIsSink {vertex: VertexId(0)}.into();
+        assert_eq!(e.to_string(), "vertex 0 is both the source and the sink");
+    }
+
+    #[test]
+    fn test_merge_duplicate_edges() {
+        let edge = |u, v, capacity| (VertexId(u), VertexId(v), FlowEdge{capacity, flow: 0});
+        let edges = [edge(0, 1, 2), edge(1, 2, 4), edge(0, 1, 5), edge(1, 0, 1), edge(0, 1, 5)];
+        let capacities = |policy| merge_duplicate_edges(&edges, policy)
+            .map(|merged| merged.iter().map(|e| (e.0.index(), e.1.index(), e.2.capacity)).collect::<Vec<_>>());
+        assert_eq!(capacities(DuplicatePolicy::Parallel).unwrap().len(), 5);
+        assert_eq!(capacities(DuplicatePolicy::Sum), Ok(vec![(0, 1, 12), (1, 2, 4), (1, 0, 1)]));
+        assert_eq!(capacities(DuplicatePolicy::KeepMax), Ok(vec![(0, 1, 5), (1, 2, 4), (1, 0, 1)]));
+        assert_eq!(capacities(DuplicatePolicy::KeepFirst), Ok(vec![(0, 1, 2), (1, 2, 4), (1, 0, 1)]));
+        assert_eq!(capacities(DuplicatePolicy::Error), Err(Error::Construction("Duplicate edge (0, 1)".to_string())));
+        assert!(matches!(merge_duplicate_edges(&[edge(0, 1, i32::MAX), edge(0, 1, 1)], DuplicatePolicy::Sum),
+                         Err(Error::Overflow(_))));
+
+        let mut g = Graph::flow_network(&ids(&[0, 1, 2]), edges);
+        let mut h = g.merge_duplicates(DuplicatePolicy::Sum).unwrap();
+        assert_eq!((g.n_edges(), h.n_edges()), (5, 3));
+        assert_eq!(g.max_flow(VertexId(0), VertexId(2), BFS), h.max_flow(VertexId(0), VertexId(2), BFS));
+    }
+
+    #[test]
+    fn test_self_loops() {
+        let edges = [
+            (VertexId(0), VertexId(0), FlowEdge{capacity: 9, flow: 0}),
+            (VertexId(0), VertexId(1), FlowEdge{capacity: 3, flow: 0}),
+            (VertexId(1), VertexId(1), FlowEdge{capacity: 9, flow: 0}),
+            (VertexId(1), VertexId(2), FlowEdge{capacity: 2, flow: 0})
+        ];
+        let mut g = Graph::try_flow_network(&ids(&[0, 1, 2]), edges, DuplicatePolicy::Parallel, SelfLoopPolicy::Skip)
+            .unwrap();
+        assert_eq!(g.n_edges(), 4);
+        assert_eq!(g.max_flow(VertexId(0), VertexId(2), DFS), Ok(2));
+        assert!(g.edges().filter(|e| e.0 == e.1).all(|e| g.arcs[e.2].flow == 0));
+        assert_eq!(verify::verify_flow(&g, VertexId(0), VertexId(2)), vec![]);
+        assert_eq!(Graph::try_flow_network(&ids(&[0, 1, 2]), edges, DuplicatePolicy::Parallel, SelfLoopPolicy::Reject)
+                       .unwrap_err(),
+                   Error::Construction("Self loop on vertex 0".to_string()));
     }
 
     #[test]
     fn test_bfs() {
-        let vertex_list = vec![0, 1, 2, 3, 4, 5];
-        let edge_list = vec![(0, 1, 1), (1, 2, 1), (0, 3, 1), (3, 4, 1), (4, 1, 0), (4, 5, 1), (5, 2, 1)];
+        let vertex_list = ids(&[0, 1, 2, 3, 4, 5]);
+        let edge_list = edges(&[(0, 1, 1), (1, 2, 1), (0, 3, 1), (3, 4, 1), (4, 1, 0), (4, 5, 1), (5, 2, 1)]);
         let g = Graph::new(&vertex_list, &edge_list);
-        let result: Vec<(VertexId, u32, VertexId)> = g.bfs_iter(0, 2).collect();
+        let result = g.bfs_iter(VertexId(0), VertexId(2)).map(|(v, d, p)| (v.index(), d, p.index()));
         let mut result_set = HashSet::new();
         result_set.extend(result);
         let mut expect = HashSet::new();
@@ -441,10 +1601,10 @@ mod tests {
 
     #[test]
     fn test_dfs() {
-        let vertex_list = vec![0, 1, 2, 3, 4];
-        let edge_list = vec![ (0, 3, 1), (0, 1, 1), (1, 2, 1), (2, 4, 1), (3, 4, 1)];
+        let vertex_list = ids(&[0, 1, 2, 3, 4]);
+        let edge_list = edges(&[(0, 3, 1), (0, 1, 1), (1, 2, 1), (2, 4, 1), (3, 4, 1)]);
         let g = Graph::new(&vertex_list, &edge_list);
-        let result: Vec<_> = g.dfs_iter(0, 4).collect();
+        let result = g.dfs_iter(VertexId(0), VertexId(4)).map(|(v, d, p)| (v.index(), d, p.index()));
         let mut result_set = HashSet::new();
         result_set.extend(result);
         let mut expect = HashSet::new();
@@ -457,101 +1617,583 @@ mod tests {
 
     #[test]
     fn test_augmenting_path() {
-        let vertex_list = vec![0, 1, 2, 3, 4, 5, 6];
+        let vertex_list = ids(&[0, 1, 2, 3, 4, 5, 6]);
         let edge_list = vec![
-            (0, 1, FlowEdge{flow: 0, capacity: 1}),
-            (0, 2, FlowEdge{flow: 0, capacity: 1}),
-            (1, 3, FlowEdge{flow: 0, capacity: 1}),
-            (1, 5, FlowEdge{flow: 0, capacity: 1}),
-            (2, 5, FlowEdge{flow: 0, capacity: 1}),
-            (2, 6, FlowEdge{flow: 0, capacity: 1}),
-            (3, 4, FlowEdge{flow: 0, capacity: 1})
+            (VertexId(0), VertexId(1), FlowEdge{flow: 0, capacity: 1}),
+            (VertexId(0), VertexId(2), FlowEdge{flow: 0, capacity: 1}),
+            (VertexId(1), VertexId(3), FlowEdge{flow: 0, capacity: 1}),
+            (VertexId(1), VertexId(5), FlowEdge{flow: 0, capacity: 1}),
+            (VertexId(2), VertexId(5), FlowEdge{flow: 0, capacity: 1}),
+            (VertexId(2), VertexId(6), FlowEdge{flow: 0, capacity: 1}),
+            (VertexId(3), VertexId(4), FlowEdge{flow: 0, capacity: 1})
         ];
         let mut g = Graph::new(&vertex_list, &edge_list);
 
-        assert_eq!(g.augmenting_path(0, 4, Search::Bfs).unwrap(), [0, 1, 3, 4]);
+        assert_eq!(g.augmenting_path(VertexId(0), VertexId(4), Search::Bfs).unwrap(), ids(&[0, 1, 3, 4]));
+        assert_eq!(g.augmenting_path(VertexId(0), VertexId(4), Search::Dfs).unwrap(), ids(&[0, 1, 3, 4]));
+        assert_eq!(g.augmenting_path(VertexId(2), VertexId(2), Search::Bfs).unwrap(), ids(&[2]));
 
         {
-            let edge = g.edges.get_mut(1).unwrap().get_mut(3).unwrap();
-            edge.flow = 1;
+            let arc = g.find_arc(VertexId(1), VertexId(3)).unwrap();
+            g.arcs[arc].flow = 1;
         }
-        assert_eq!(g.augmenting_path(0, 4, Search::Bfs), None);
+        assert_eq!(g.augmenting_path(VertexId(0), VertexId(4), Search::Bfs), None);
     }
 
     #[test]
     fn test_path_from_visited() {
-        let source = 0;
-        let sink = 4;
+        let source = VertexId(0);
+        let sink = VertexId(4);
         let visited = vec![(0, 0, usize::MAX), (1, 1, 0), (2, 1, 0), (5, 2, 1), (3, 2, 1), (4, 3, 3), (6, 2, 2)];
-        let mut node_parent_map = vec![usize::MAX; 7];
+        let mut node_parent_map = vec![VertexId(usize::MAX); 7];
         for n in visited {
-            node_parent_map[n.0] = n.2;
+            node_parent_map[n.0] = VertexId(n.2);
         }
         let path = path_from_visited(source, sink, &node_parent_map);
-        assert_eq!(path, [0, 1, 3, 4]);
+        assert_eq!(path, Some(ids(&[0, 1, 3, 4])));
+        assert_eq!(path_from_visited(source, VertexId(6), &node_parent_map), Some(ids(&[0, 2, 6])));
+        assert_eq!(path_from_visited(source, sink, &[VertexId(usize::MAX); 7]), None);
+        assert_eq!(path_from_visited(source, VertexId(2), &ids(&[usize::MAX, 2, 1])), None);
+        assert_eq!(path_from_visited(source, VertexId(9), &node_parent_map), None);
+    }
+
+    #[test]
+    fn test_path_from_visited_broken_chains() {
+        let source = VertexId(0);
+        // A chain through every vertex is the longest valid path
+        let chain = ids(&[usize::MAX, 0, 1, 2, 3]);
+        assert_eq!(path_from_visited(source, VertexId(4), &chain), Some(ids(&[0, 1, 2, 3, 4])));
+        // A vertex which is its own parent, a cycle avoiding the source and a parent beyond the map
+        assert_eq!(path_from_visited(source, VertexId(2), &ids(&[usize::MAX, 0, 2])), None);
+        assert_eq!(path_from_visited(source, VertexId(4), &ids(&[usize::MAX, 0, 4, 2, 3])), None);
+        assert_eq!(path_from_visited(source, VertexId(3), &ids(&[usize::MAX, 0, 7, 2])), None);
+        // The source must be on the chain, reaching a vertex without parent is not enough
+        assert_eq!(path_from_visited(VertexId(1), VertexId(3), &chain), Some(ids(&[1, 2, 3])));
+        assert_eq!(path_from_visited(VertexId(4), VertexId(3), &chain), None);
     }
 
     #[test]
     fn test_max_flow_0() {
-        let vertex_list = vec![0, 1, 2, 3, 4, 5, 6];
+        let vertex_list = ids(&[0, 1, 2, 3, 4, 5, 6]);
         let mut edge_list = vec![
-            (0, 1, FlowEdge{flow: 0, capacity: 3}),
-            (0, 2, FlowEdge{flow: 0, capacity: 1}),
-            (1, 3, FlowEdge{flow: 0, capacity: 2}),
-            (1, 5, FlowEdge{flow: 0, capacity: 1}),
-            (2, 5, FlowEdge{flow: 0, capacity: 1}),
-            (2, 6, FlowEdge{flow: 0, capacity: 1}),
-            (3, 4, FlowEdge{flow: 0, capacity: 2}),
-            (5, 6, FlowEdge{flow: 0, capacity: 1}),
-            (6, 4, FlowEdge{flow: 0, capacity: 2})
+            (VertexId(0), VertexId(1), FlowEdge{flow: 0, capacity: 3}),
+            (VertexId(0), VertexId(2), FlowEdge{flow: 0, capacity: 1}),
+            (VertexId(1), VertexId(3), FlowEdge{flow: 0, capacity: 2}),
+            (VertexId(1), VertexId(5), FlowEdge{flow: 0, capacity: 1}),
+            (VertexId(2), VertexId(5), FlowEdge{flow: 0, capacity: 1}),
+            (VertexId(2), VertexId(6), FlowEdge{flow: 0, capacity: 1}),
+            (VertexId(3), VertexId(4), FlowEdge{flow: 0, capacity: 2}),
+            (VertexId(5), VertexId(6), FlowEdge{flow: 0, capacity: 1}),
+            (VertexId(6), VertexId(4), FlowEdge{flow: 0, capacity: 2})
         ];
+        #[allow(deprecated)]
         create_residual_edges(&mut edge_list);
         let mut g = Graph::new(&vertex_list, &edge_list);
-        let total_flow = g.max_flow(0, 4, Search::Bfs);
+        assert!(g.has_residual_arcs());
+        let total_flow = g.max_flow(VertexId(0), VertexId(4), Search::Bfs).unwrap();
         assert_eq!(total_flow, 4);
     }
 
     #[test]
     fn test_max_flow_1() {
-        let vertex_list = vec![0, 1, 2, 3];
-        let mut edge_list = vec![
-            (0, 2, FlowEdge{flow: 0, capacity: 5}),
-            (0, 3, FlowEdge{flow: 0, capacity: 5}),
-            (2, 3, FlowEdge{flow: 0, capacity: 1}),
-            (2, 1, FlowEdge{flow: 0, capacity: 5}),
-            (3, 1, FlowEdge{flow: 0, capacity: 5}),
+        let vertex_list = ids(&[0, 1, 2, 3]);
+        let edge_list = vec![
+            (VertexId(0), VertexId(2), FlowEdge{flow: 0, capacity: 5}),
+            (VertexId(0), VertexId(3), FlowEdge{flow: 0, capacity: 5}),
+            (VertexId(2), VertexId(3), FlowEdge{flow: 0, capacity: 1}),
+            (VertexId(2), VertexId(1), FlowEdge{flow: 0, capacity: 5}),
+            (VertexId(3), VertexId(1), FlowEdge{flow: 0, capacity: 5}),
         ];
-        create_residual_edges(&mut edge_list);
+        // Without residual arcs the graph is rebuilt as a flow network by max_flow
         let mut g = Graph::new(&vertex_list, &edge_list);
-        let total_flow = g.max_flow(0, 1, Search::Bfs);
+        assert!(!g.has_residual_arcs());
+        let total_flow = g.max_flow(VertexId(0), VertexId(1), Search::Bfs).unwrap();
         assert_eq!(total_flow, 10);
+        assert!(g.has_residual_arcs());
     }
 
     #[test]
     fn test_max_flow_2() {
-        let vertex_list = vec![0, 1, 2, 3, 4, 5];
-        let mut edge_list = vec![
-            (0, 1, FlowEdge{flow: 0, capacity: 11}),
-            (0, 2, FlowEdge{flow: 0, capacity: 12}),
-            (2, 1, FlowEdge{flow: 0, capacity: 1}),
-            (1, 3, FlowEdge{flow: 0, capacity: 12}),
-            (2, 4, FlowEdge{flow: 0, capacity: 11}),
-            (4, 3, FlowEdge{flow: 0, capacity: 7}),
-            (4, 5, FlowEdge{flow: 0, capacity: 4}),
-            (3, 5, FlowEdge{flow: 0, capacity: 19}),
+        let vertex_list = ids(&[0, 1, 2, 3, 4, 5]);
+        let edge_list = vec![
+            (VertexId(0), VertexId(1), FlowEdge{flow: 0, capacity: 11}),
+            (VertexId(0), VertexId(2), FlowEdge{flow: 0, capacity: 12}),
+            (VertexId(2), VertexId(1), FlowEdge{flow: 0, capacity: 1}),
+            (VertexId(1), VertexId(3), FlowEdge{flow: 0, capacity: 12}),
+            (VertexId(2), VertexId(4), FlowEdge{flow: 0, capacity: 11}),
+            (VertexId(4), VertexId(3), FlowEdge{flow: 0, capacity: 7}),
+            (VertexId(4), VertexId(5), FlowEdge{flow: 0, capacity: 4}),
+            (VertexId(3), VertexId(5), FlowEdge{flow: 0, capacity: 19}),
         ];
-        create_residual_edges(&mut edge_list);
-        let mut g = Graph::new(&vertex_list, &edge_list);
-        let total_flow = g.max_flow(0, 5, Search::Bfs);
+        let mut g = Graph::flow_network(&vertex_list, &edge_list);
+        let total_flow = g.max_flow(VertexId(0), VertexId(5), Search::Bfs).unwrap();
         assert_eq!(total_flow, 23);
     }
 
+    #[test]
+    fn test_max_flow_super_terminals() {
+        let mut edge_list = vec![
+            (VertexId(0), VertexId(2), FlowEdge{flow: 0, capacity: 4}),
+            (VertexId(1), VertexId(2), FlowEdge{flow: 0, capacity: 3}),
+            (VertexId(2), VertexId(3), FlowEdge{flow: 0, capacity: 6}),
+        ];
+        let (source, sink) = add_super_terminals(&mut edge_list, 4, &ids(&[0, 1]), &ids(&[3])).unwrap();
+        assert_eq!((source, sink), (VertexId(4), VertexId(5)));
+        let mut g = Graph::flow_network(&ids(&[0, 1, 2, 3, 4, 5]), &edge_list);
+        assert_eq!(g.max_flow(source, sink, Search::Bfs), Ok(6));
+
+        // A super arc capped at the largest capacity would limit the flow, so such totals fail
+        let big = FlowEdge{flow: 0, capacity: i32::MAX};
+        let mut edge_list = vec![(VertexId(0), VertexId(1), big), (VertexId(0), VertexId(2), big),
+                                 (VertexId(1), VertexId(3), big), (VertexId(2), VertexId(4), big)];
+        assert!(matches!(add_super_terminals(&mut edge_list, 5, &ids(&[0]), &ids(&[3, 4])), Err(Error::Overflow(_))));
+        assert_eq!(edge_list.len(), 4);
+        let (source, sink) = add_super_terminals(&mut edge_list, 5, &ids(&[1, 2]), &ids(&[3, 4])).unwrap();
+        let mut g = Graph::flow_network(&ids(&[0, 1, 2, 3, 4, 5, 6]), &edge_list);
+        assert_eq!(g.max_flow(source, sink, Search::Bfs), Ok(2 * i32::MAX as i64));
+
+        // Every one of many terminals gets the total of its own edges, including repeated and unconnected terminals
+        let n = 10_000;
+        let mut edge_list = (0..n).map(|i| (VertexId(i), VertexId(n + i), FlowEdge{flow: 0, capacity: 1 + i as i32}))
+            .collect::<Vec<_>>();
+        edge_list.push((VertexId(0), VertexId(n), FlowEdge{flow: 0, capacity: 5}));
+        let sources = (0..n).chain(vec![0, 2 * n]).map(VertexId).collect::<Vec<_>>();
+        let sinks = (n..2 * n).map(VertexId).collect::<Vec<_>>();
+        let (source, sink) = add_super_terminals(&mut edge_list, 2 * n + 1, &sources, &sinks).unwrap();
+        let super_edges = edge_list[n + 1..].iter().map(|&(u, v, e)| (u, v, e.capacity)).collect::<Vec<_>>();
+        assert_eq!(super_edges.len(), sources.len() + sinks.len());
+        assert_eq!(super_edges[0], (source, VertexId(0), 6));
+        assert_eq!(super_edges[n - 1], (source, VertexId(n - 1), n as i32));
+        assert_eq!(super_edges[n], super_edges[0]);
+        assert_eq!(super_edges[n + 1], (source, VertexId(2 * n), 0));
+        assert_eq!(super_edges[n + 2], (VertexId(n), sink, 6));
+        assert_eq!(super_edges[2 * n + 1], (VertexId(2 * n - 1), sink, n as i32));
+    }
+
+    #[test]
+    fn test_edge_sources() {
+        // Owned, borrowed and lazily computed edges build the same graphs, even when the size hint is 0
+        let vertexes = ids(&[0, 1, 2]);
+        let edge_list = edges(&[(0, 1, FlowEdge{flow: 0, capacity: 2}), (1, 2, FlowEdge{flow: 0, capacity: 3}),
+                                (0, 1, FlowEdge{flow: 0, capacity: 4})]);
+        let topology = |g: &Graph<FlowEdge>| (g.arcs.iter().map(|e| e.capacity).collect::<Vec<_>>(), g.heads.clone());
+        let lazy = || (0..3).map(|i| edge_list[i]);
+        let filtered = || edge_list.iter().filter(|e| e.2.capacity > 0);
+        let expected = topology(&Graph::new(&vertexes, &edge_list));
+        assert_eq!(topology(&Graph::new(&vertexes, edge_list.clone())), expected);
+        assert_eq!(topology(&Graph::new(&vertexes, edge_list.iter())), expected);
+        assert_eq!(topology(&Graph::try_new(&vertexes, lazy()).unwrap()), expected);
+        assert_eq!(topology(&Graph::try_new(&vertexes, filtered()).unwrap()), expected);
+
+        let expected = topology(&Graph::flow_network(&vertexes, &edge_list));
+        assert_eq!(topology(&Graph::flow_network(&vertexes, edge_list.clone())), expected);
+        assert_eq!(topology(&Graph::flow_network(&vertexes, lazy())), expected);
+        let streamed = Graph::try_flow_network(&vertexes, filtered(), DuplicatePolicy::Parallel, SelfLoopPolicy::Skip);
+        assert_eq!(topology(&streamed.unwrap()), expected);
+        let merged = Graph::try_flow_network(&vertexes, lazy(), DuplicatePolicy::Sum, SelfLoopPolicy::Skip).unwrap();
+        assert_eq!(topology(&merged).0, [6, 0, 3, 0]);
+    }
+
+    #[test]
+    fn test_flow_network() {
+        let edge_list = vec![
+            (VertexId(0), VertexId(1), FlowEdge{flow: 0, capacity: 4}),
+            (VertexId(0), VertexId(2), FlowEdge{flow: 0, capacity: 3}),
+            (VertexId(1), VertexId(2), FlowEdge{flow: 0, capacity: 4}),
+            (VertexId(2), VertexId(1), FlowEdge{flow: 0, capacity: 3}),
+            (VertexId(1), VertexId(3), FlowEdge{flow: 0, capacity: 3}),
+            (VertexId(2), VertexId(3), FlowEdge{flow: 0, capacity: 4}),
+        ];
+        let mut g = Graph::flow_network(&ids(&[0, 1, 2, 3]), &edge_list);
+        assert_eq!(g.n_edges(), 6);
+        assert_eq!(g.out_arcs(VertexId(3)).map(|a| g.head(a)).collect::<Vec<_>>(), ids(&[1, 2]));
+        assert_eq!(g.out_arcs(VertexId(1)).map(|a| g.head(a)).collect::<Vec<_>>(), ids(&[0, 2, 2, 3]));
+        let (a, b) = (g.find_edge(VertexId(1), VertexId(2)).unwrap(), g.find_edge(VertexId(2), VertexId(1)).unwrap());
+        assert_eq!((a ^ 1, g.tail(a), g.arcs[a].capacity, g.arcs[b].capacity), (a + 1, VertexId(1), 4, 3));
+        assert_eq!(g.max_flow(VertexId(0), VertexId(3), BFS), Ok(7));
+        assert!(g.arcs.iter().enumerate().all(|(a, arc)| arc.flow == -g.arcs[a ^ 1].flow));
+    }
+
+    #[test]
+    fn test_deterministic() {
+        let (source, sink, g) = generators::random_network(30, 120, 1..=5, 3);
+        let mut edges = g.edges().map(|(u, v, a)| (u, v, g.arcs[a])).collect::<Vec<_>>();
+        edges.reverse();
+        let mut h = Graph::new(&g.vertexes().collect::<Vec<_>>(), &edges);
+        let mut g = g;
+        g.set_deterministic(true);
+        h.set_deterministic(true);
+        assert!(g.out_arcs(VertexId(0)).map(|a| g.head(a)).collect::<Vec<_>>().windows(2).all(|w| w[0] <= w[1]));
+        assert_eq!(g.max_flow(source, sink, DFS), h.max_flow(source, sink, DFS));
+        assert!(h.is_deterministic());
+        let flows = |g: &Graph<FlowEdge>| {
+            let mut flows = g.edges().map(|(u, v, a)| (u, v, g.arcs[a].flow)).collect::<Vec<_>>();
+            flows.sort();
+            flows
+        };
+        assert_eq!(flows(&g), flows(&h));
+    }
+
+    #[test]
+    fn test_max_flow_exceeds_i32() {
+        let edges = vec![
+            (VertexId(0), VertexId(1), FlowEdge{capacity: i32::MAX, flow: 0}),
+            (VertexId(0), VertexId(2), FlowEdge{capacity: i32::MAX, flow: 0}),
+            (VertexId(1), VertexId(3), FlowEdge{capacity: i32::MAX, flow: 0}),
+            (VertexId(2), VertexId(3), FlowEdge{capacity: i32::MAX, flow: 0})
+        ];
+        let mut g = Graph::flow_network(&ids(&[0, 1, 2, 3]), &edges);
+        assert_eq!(g.max_flow(VertexId(0), VertexId(3), BFS), Ok(2 * i32::MAX as i64));
+    }
+
+    #[test]
+    fn test_max_flow_observed() {
+        let (source, sink, mut g) = flow_from_dicaps("data/dicaps/central.txt");
+        let mut flows: Vec<i64> = Vec::new();
+        let mut path_lengths: Vec<usize> = Vec::new();
+        let total_flow = g.max_flow_observed(source, sink, BFS, |p| {
+            assert_eq!(p.augmentations, flows.len() + 1);
+            assert!(p.flow <= p.upper_bound && p.progress() <= 1.0);
+            assert_eq!(p.flow, flows.last().unwrap_or(&0) + p.bottleneck as i64);
+            flows.push(p.flow);
+            path_lengths.push(p.path_length);
+            true
+        }).unwrap();
+        assert_eq!(total_flow, 5);
+        assert_eq!(flows.last(), Some(&5));
+        assert!(flows.windows(2).all(|w| w[0] < w[1]));
+        // Shortest augmenting paths never get shorter
+        assert!(path_lengths[0] >= 1 && path_lengths.windows(2).all(|w| w[0] <= w[1]));
+
+        let (source, sink, mut g) = flow_from_dicaps("data/dicaps/central.txt");
+        let total_flow = g.max_flow_observed(source, sink, BFS, |p| p.augmentations < 2).unwrap();
+        assert_eq!(total_flow, flows[1]);
+
+        // The trivial bound of 10 entering the sink is refined by the level cut through the two unit edges
+        let vertexes = (0..5).map(VertexId).collect::<Vec<_>>();
+        let edges = [(0, 1, 10), (0, 2, 10), (1, 3, 1), (2, 3, 1), (3, 4, 10)]
+            .iter()
+            .map(|&(u, v, capacity)| (VertexId(u), VertexId(v), FlowEdge{capacity, flow: 0}))
+            .collect::<Vec<_>>();
+        let mut g = Graph::flow_network(&vertexes, &edges);
+        let mut bounds: Vec<(i64, f64)> = Vec::new();
+        g.max_flow_observed(VertexId(0), VertexId(4), BFS, |p| {
+            bounds.push((p.upper_bound, p.progress()));
+            true
+        }).unwrap();
+        assert_eq!(bounds, [(2, 0.5), (2, 1.0)]);
+    }
+
+    #[test]
+    fn test_observed_augmentations() {
+        // Shortest paths first: the direct edge, then two arcs through 1 and three arcs through 2 and 4
+        let unit = |capacity| FlowEdge{flow: 0, capacity};
+        let edge_list = edges(&[(0, 3, unit(2)), (0, 1, unit(5)), (1, 3, unit(4)), (0, 2, unit(3)), (2, 4, unit(3)),
+                                (4, 3, unit(1))]);
+        let mut g = Graph::flow_network(&ids(&[0, 1, 2, 3, 4]), &edge_list);
+        let mut augmentations: Vec<(usize, usize, i32)> = Vec::new();
+        let (flow, stats) = g.max_flow_observed_with_stats(VertexId(0), VertexId(3), BFS, |p| {
+            augmentations.push((p.augmentations, p.path_length, p.bottleneck));
+            true
+        }).unwrap();
+        assert_eq!(augmentations, [(1, 1, 2), (2, 2, 4), (3, 3, 1)]);
+        assert_eq!(flow, augmentations.iter().map(|a| a.2 as i64).sum::<i64>());
+        assert_eq!(stats.pushes, 6);
+    }
+
+    #[test]
+    fn test_progress_upper_bound() {
+        let progress = |flow, upper_bound| Progress {augmentations: 1, path_length: 1, bottleneck: 1, flow, upper_bound};
+        assert_eq!((progress(3, 12).progress(), progress(12, 12).progress()), (0.25, 1.0));
+        assert_eq!((progress(0, 0).progress(), progress(5, 4).progress()), (1.0, 1.0));
+
+        // Four unit edges in the middle carry the flow, the capacities at the terminals only bound it by 40
+        let mut edge_list: Vec<(usize, usize, FlowEdge)> = Vec::new();
+        for i in 1..=4 {
+            edge_list.push((0, i, FlowEdge{flow: 0, capacity: 10}));
+            edge_list.push((i, i + 4, FlowEdge{flow: 0, capacity: 1}));
+            edge_list.push((i + 4, 9, FlowEdge{flow: 0, capacity: 10}));
+        }
+        let g = Graph::flow_network(&(0..10).map(VertexId).collect::<Vec<_>>(), edges(&edge_list));
+        let (source, sink, max_flow) = (VertexId(0), VertexId(9), 4);
+        let mut bounds: Vec<i64> = Vec::new();
+        g.clone().max_flow_observed(source, sink, BFS, |p| {
+            assert!(p.flow <= max_flow && max_flow <= p.upper_bound);
+            bounds.push(p.upper_bound);
+            true
+        }).unwrap();
+        // The first cut between the levels already proves the bound the flow reaches
+        assert_eq!(bounds, [4, 4, 4, 4]);
+        let mut h = g.clone();
+        assert_eq!(h.max_flow(source, sink, BFS), Ok(max_flow));
+        let flows = h.arcs.iter().map(|arc| arc.flow).collect::<Vec<_>>();
+        assert_eq!(h.level_cut(&flows, source, sink), 0);
+    }
+
+    #[test]
+    fn test_stats() {
+        let (_, _, mut g) = flow_from_dicaps("data/dicaps/central.txt");
+        let stats = g.stats();
+        assert_eq!((stats.vertexes, stats.edges, stats.components), (7, 12, 1));
+        assert_eq!((stats.min_out_degree, stats.max_out_degree, stats.mean_out_degree), (0, 3, 12.0 / 7.0));
+        assert_eq!((stats.min_capacity, stats.max_capacity, stats.mean_capacity), (1, 4, 2.0));
+        assert_eq!((stats.density, stats.zero_capacity_edges), (12.0 / 42.0, 0));
+        g.arcs[0].capacity = 0;
+        assert_eq!(g.stats().zero_capacity_edges, 1);
+        assert!(g.stats().to_string().starts_with("Vertexes:7\tEdges:12\tDensity:0.285714\tMinOutDegree:0"));
+        assert_eq!(Graph::flow_network(&ids(&[0, 1]), Vec::<(VertexId, VertexId, FlowEdge)>::new()).stats(),
+                   GraphStats {vertexes: 2, edges: 0, components: 2, ..GraphStats::default()});
+    }
+
+    #[test]
+    fn test_max_flow_with_stats() {
+        let (source, sink, g) = flow_from_dicaps("data/dicaps/central.txt");
+        let mut augmentations = 0;
+        let flow = g.clone().max_flow_observed(source, sink, BFS, |p| {
+            augmentations = p.augmentations;
+            true
+        }).unwrap();
+        let (total_flow, stats) = g.clone().max_flow_with_stats(source, sink, BFS).unwrap();
+        assert_eq!(total_flow, flow);
+        assert_eq!(stats.augmenting_paths, augmentations);
+        assert_eq!(stats.phases, augmentations + 1);
+        assert!(stats.vertexes_scanned >= stats.phases as u64);
+        assert!(stats.arcs_scanned >= stats.vertexes_scanned);
+
+        let mut path_arcs = 0;
+        let (_, observed) = g.clone().max_flow_observed_with_stats(source, sink, BFS, |p| {
+            path_arcs += p.path_length as u64;
+            true
+        }).unwrap();
+        assert_eq!((observed.augmenting_paths, observed.pushes), (stats.augmenting_paths, path_arcs));
+        assert!(observed.to_string().starts_with(&format!("Augmentations:{}\tPhases:{}\t", augmentations, stats.phases)));
+        // Augmenting path solvers do none of the operations of preflow solvers
+        let preflow = (stats.saturating_pushes, stats.nonsaturating_pushes, stats.relabels, stats.global_relabels,
+                       stats.gap_events);
+        assert_eq!(preflow, (0, 0, 0, 0, 0));
+        assert!(stats.to_string().ends_with("\tRelabels:0\tGlobalRelabels:0\tGapEvents:0"));
+    }
+
+    #[test]
+    fn test_reset_flow() {
+        let (source, sink, mut g) = flow_from_dicaps("data/dicaps/central.txt");
+        assert_eq!(g.max_flow(source, sink, BFS), Ok(5));
+        assert!(g.conserves_flow(source, sink));
+        assert!(!g.conserves_flow(source, VertexId(2)));
+        // Solving again between the same terminals continues from the maximum flow
+        assert_eq!(g.max_flow(source, sink, DFS), Ok(5));
+        g.reset_flow();
+        assert!(g.arcs.iter().all(|arc| arc.flow == 0));
+        let mut h = g.clone();
+        assert_eq!(g.max_flow(source, VertexId(2), BFS), Ok(5));
+        assert_eq!(h.max_flow(source, VertexId(2), DFS), Ok(5));
+    }
+
+    #[test]
+    fn test_flow_state() {
+        let (source, _, g) = generators::grid_network(6, 6, 1..=9, 5);
+        let shared = &g;
+        let sinks = ids(&[35, 30, 5, 21]);
+        let flows = std::thread::scope(|scope| {
+            let handles = sinks.iter().map(|&sink| scope.spawn(move || {
+                let mut state = FlowState::new(shared);
+                (state.max_flow(shared, source, sink, BFS), state)
+            })).collect::<Vec<_>>();
+            handles.into_iter().map(|h| h.join().unwrap()).collect::<Vec<_>>()
+        });
+        for (&sink, (flow, state)) in sinks.iter().zip(flows) {
+            let mut h = g.clone();
+            assert_eq!(flow, h.max_flow(source, sink, BFS));
+            assert_eq!(state, FlowState::from_graph(&h));
+        }
+        assert!(g.arcs.iter().all(|arc| arc.flow == 0));
+
+        let mut unpaired = Graph::new(&ids(&[0, 1]), [(VertexId(0), VertexId(1), FlowEdge{capacity: 1, flow: 0})]);
+        assert_eq!(FlowState::new(&unpaired).max_flow(&unpaired, VertexId(0), VertexId(1), BFS),
+                   Err(FlowError::MissingResidualArcs));
+        assert_eq!(unpaired.max_flow(VertexId(0), VertexId(1), BFS), Ok(1));
+    }
+
+    #[test]
+    fn test_max_flow_errors() {
+        let (source, sink, mut g) = flow_from_dicaps("data/dicaps/central.txt");
+        let n_vertexes = g.n_vertexes();
+        assert_eq!(g.max_flow(source, VertexId(n_vertexes), BFS),
+                   Err(FlowError::VertexOutOfRange {vertex: VertexId(n_vertexes), n_vertexes}));
+        assert_eq!(g.max_flow(source, source, BFS), Err(FlowError::SourceIsSink {vertex: source}));
+        assert_eq!(g.max_flow(sink, source, DFS), Err(FlowError::UnreachableSink {source: sink, sink: source}));
+        assert_eq!(g.max_flow(source, sink, BFS), Ok(5));
+        assert_eq!(g.max_flow(source, VertexId(2), BFS), Err(FlowError::ExistingFlow));
+    }
+
+    #[test]
+    fn test_search_buffers_reuse() {
+        // One set of buffers serves every search, each search forgets the vertexes visited by the previous one
+        let (source, sink, g) = flow_from_dicaps("data/dicaps/central.txt");
+        let residual = |a: ArcId| g.arcs[a].capacity > g.arcs[a].flow;
+        let mut buffers = SearchBuffers::new(g.n_vertexes());
+        let mut vertexes_scanned = 0;
+        for &search in &[BFS, DFS, BIDIRECTIONAL, BFS] {
+            let mut fresh = SearchBuffers::new(g.n_vertexes());
+            assert!(g.search_residual(&mut fresh, source, sink, search, residual));
+            assert!(g.search_residual(&mut buffers, source, sink, search, residual));
+            assert_eq!(g.residual_path(&buffers, source, sink), g.residual_path(&fresh, source, sink));
+            vertexes_scanned += fresh.vertexes_scanned;
+        }
+        assert_eq!(buffers.vertexes_scanned, vertexes_scanned);
+        assert!(!g.search_residual(&mut buffers, source, sink, BFS, |_| false));
+        assert_eq!(g.vertexes().filter(|&v| buffers.is_visited(v)).collect::<Vec<_>>(), [source]);
+    }
+
+    #[test]
+    fn test_arc_pairs() {
+        let (source, sink, mut g) = flow_from_dicaps("data/dicaps/central.txt");
+        assert_eq!(g.arcs.len(), 2 * g.n_edges());
+        assert!(g.has_residual_arcs());
+        // The reverse of every arc is the next or previous arc, joining the same vertexes the other way
+        for a in 0..g.arcs.len() {
+            assert_eq!((g.tail(a ^ 1), g.head(a ^ 1)), (g.head(a), g.tail(a)));
+        }
+        assert!(g.arcs.iter().skip(1).step_by(2).all(|arc| arc.capacity == 0));
+        let out_arcs = g.vertexes().map(|u| g.out_arcs(u).filter(|&a| g.tail(a) == u).count()).sum::<usize>();
+        assert_eq!(out_arcs, g.arcs.len());
+        assert_eq!(g.max_flow(source, sink, BFS), Ok(5));
+        assert!((0..g.arcs.len()).all(|a| g.arcs[a].flow == -g.arcs[a ^ 1].flow));
+    }
+
+    #[test]
+    fn test_augment_parent_arcs() {
+        // Parallel edges have separate pairs, so the flow goes over the arcs the search took and not the first one
+        let unit = |capacity| FlowEdge{flow: 0, capacity};
+        let mut g = Graph::flow_network(&ids(&[0, 1, 2]), edges(&[(0, 1, unit(1)), (0, 1, unit(2)), (1, 2, unit(3))]));
+        assert_eq!(g.max_flow(VertexId(0), VertexId(2), BFS), Ok(3));
+        assert_eq!(g.arcs.iter().step_by(2).map(|arc| arc.flow).collect::<Vec<_>>(), [1, 2, 3]);
+
+        // With a flow along 0 1 2 3 the only augmenting path cancels it over the reverse arc from 2 to 1
+        let edge_list = edges(&[(0, 1, unit(1)), (1, 2, unit(1)), (2, 3, unit(1)), (0, 2, unit(1)), (1, 3, unit(1))]);
+        let mut g = Graph::flow_network(&ids(&[0, 1, 2, 3]), edge_list);
+        for &a in &[0, 2, 4] {
+            g.arcs[a].flow = 1;
+            g.arcs[a + 1].flow = -1;
+        }
+        assert_eq!(g.max_flow(VertexId(0), VertexId(3), BFS), Ok(2));
+        assert_eq!(g.arcs.iter().step_by(2).map(|arc| arc.flow).collect::<Vec<_>>(), [1, 0, 1, 1, 1]);
+        assert!((0..g.arcs.len()).all(|a| g.arcs[a].flow == -g.arcs[a ^ 1].flow));
+    }
+
+    #[test]
+    fn test_search_engine() {
+        // A chain with shortcuts over more vertexes than a word of the visited bitset
+        let n = 130;
+        let mut edge_list = (0..n - 1).map(|i| (i, i + 1, FlowEdge{flow: 0, capacity: 1})).collect::<Vec<_>>();
+        edge_list.extend((0..n - 7).step_by(7).map(|i| (i, i + 7, FlowEdge{flow: 0, capacity: 1})));
+        let g = Graph::flow_network(&(0..n).map(VertexId).collect::<Vec<_>>(), edges(&edge_list));
+        let (source, sink) = (VertexId(0), VertexId(n - 1));
+        let distances = |follow: &dyn Fn(ArcId) -> bool| {
+            let mut distances = vec![u32::MAX; n];
+            distances[0] = 0;
+            let mut queue = std::collections::VecDeque::from(vec![source]);
+            while let Some(u) = queue.pop_front() {
+                for a in g.out_arcs(u) {
+                    let v = g.head(a);
+                    if follow(a) && distances[v] == u32::MAX {
+                        distances[v] = distances[u] + 1;
+                        queue.push_back(v);
+                    }
+                }
+            }
+            distances
+        };
+
+        // The iterators follow every arc, the reverse arcs too
+        let all = distances(&|_| true);
+        let visited = g.bfs_iter(source, sink).collect::<Vec<_>>();
+        assert_eq!(visited.last().map(|v| v.0), Some(sink));
+        for &(v, distance, parent) in &visited {
+            assert_eq!(distance, all[v]);
+            assert!(v == source || (all[parent] + 1 == distance && g.find_arc(parent, v).is_some()));
+        }
+        let visited = g.dfs_iter(source, sink).map(|(v, _, _)| v).collect::<HashSet<_>>();
+        assert!(visited.contains(&sink) && visited.len() <= n);
+
+        // Augmenting paths only follow residual arcs, and breadth first ones are shortest
+        let residual = distances(&|a| g.arcs[a].capacity > g.arcs[a].flow);
+        for &search in &[BFS, DFS, BIDIRECTIONAL] {
+            let path = g.augmenting_path(source, sink, search).unwrap();
+            assert!(path.windows(2).all(|w| w[0] < w[1] && g.find_arc(w[0], w[1]).is_some()));
+            if search != DFS {
+                assert_eq!(path.len(), residual[sink] as usize + 1);
+            }
+        }
+    }
+
+    #[test]
+    fn test_terminal_errors() {
+        // Vertex 4 has no edges, so the sink can not be reached from it
+        let edge_list = edges(&[(0, 1, FlowEdge{flow: 0, capacity: 2}), (1, 2, FlowEdge{flow: 0, capacity: 1})]);
+        let g = Graph::flow_network(&ids(&[0, 1, 2, 3, 4]), edge_list);
+        let cases = [(VertexId(0), VertexId(5), FlowError::VertexOutOfRange {vertex: VertexId(5), n_vertexes: 5}),
+                     (VertexId(7), VertexId(2), FlowError::VertexOutOfRange {vertex: VertexId(7), n_vertexes: 5}),
+                     (VertexId(1), VertexId(1), FlowError::SourceIsSink {vertex: VertexId(1)}),
+                     (VertexId(4), VertexId(2), FlowError::UnreachableSink {source: VertexId(4), sink: VertexId(2)}),
+                     (VertexId(2), VertexId(0), FlowError::UnreachableSink {source: VertexId(2), sink: VertexId(0)})];
+        for &(source, sink, ref error) in &cases {
+            for &search in &[BFS, DFS, BIDIRECTIONAL] {
+                let mut h = g.clone();
+                assert_eq!(h.max_flow(source, sink, search).as_ref(), Err(error));
+                assert_eq!(h.max_flow_observed(source, sink, search, |_| true).as_ref(), Err(error));
+                assert_eq!(h.max_flow_with_stats(source, sink, search).map(|r| r.0).as_ref(), Err(error));
+                assert_eq!(FlowState::new(&g).max_flow(&g, source, sink, search).as_ref(), Err(error));
+                // Failed calls leave the graph without flow
+                assert!(h.arcs.iter().all(|arc| arc.flow == 0));
+            }
+        }
+        assert_eq!(cases[0].2.to_string(), "vertex 5 is out of range for a graph of 5 vertexes");
+        assert_eq!(cases[2].2.to_string(), "vertex 1 is both the source and the sink");
+        assert_eq!(cases[3].2.to_string(), "sink 2 is unreachable from source 4");
+    }
+
+    #[cfg(feature = "log")]
+    #[test]
+    fn test_log_events() {
+        use std::cell::RefCell;
+        // Tests run on several threads, so each thread only records its own events
+        thread_local!(static EVENTS: RefCell<Vec<(log::Level, String)>> = const { RefCell::new(Vec::new()) });
+        struct Recorder;
+        impl log::Log for Recorder {
+            fn enabled(&self, _: &log::Metadata) -> bool {
+                true
+            }
+            fn log(&self, record: &log::Record) {
+                EVENTS.with(|events| events.borrow_mut().push((record.level(), record.args().to_string())));
+            }
+            fn flush(&self) {}
+        }
+        static RECORDER: Recorder = Recorder;
+        let _ = log::set_logger(&RECORDER);
+        log::set_max_level(log::LevelFilter::Trace);
+
+        let (source, sink, mut g) = flow_from_dicaps("data/dicaps/flow-graph.txt");
+        assert_eq!(g.max_flow(source, sink, BFS), Ok(10));
+        let edge_list = edges(&[(0, 1, FlowEdge{flow: 0, capacity: 1}), (0, 1, FlowEdge{flow: 0, capacity: 2})]);
+        merge_duplicate_edges(&edge_list, DuplicatePolicy::Sum).unwrap();
+        let events = EVENTS.with(|events| events.borrow().clone());
+        assert_eq!(events[0], (log::Level::Debug,
+                               "Parsed 62 bytes into a network with 4 vertexes and 5 edges".to_string()));
+        assert_eq!(events[1], (log::Level::Debug, "Max flow from 0 to 1 by Bfs on 4 vertexes and 5 edges".to_string()));
+        let augmentations = events.iter().filter(|e| e.0 == log::Level::Trace && e.1.starts_with("Augmentation "));
+        assert_eq!(augmentations.count(), 2);
+        let ended = "Max flow phase ended after 2 augmentations and 3 searches with flow 10".to_string();
+        assert!(events.contains(&(log::Level::Debug, ended)));
+        assert_eq!(events.last(), Some(&(log::Level::Trace, "Merging a duplicate edge (0, 1) by Sum".to_string())));
+    }
+
     enum FileType {
         Dicaps,
         Text
     }
 
-    fn test_flow_from_file(file_name: &str, flow: i32, file_type: FileType, search: Search) {
+    fn test_flow_from_file(file_name: &str, flow: i64, file_type: FileType, search: Search) {
         println!("Testing file: {}\n", file_name);
         let parsed = match file_type {
             FileType::Dicaps => flow_from_dicaps(file_name),
@@ -561,9 +2203,9 @@ mod tests {
         let sink = parsed.1;
         let mut g = parsed.2;
         println!("{:?}", g);
-        let total_flow = g.max_flow(source, sink, search);
+        let total_flow = g.max_flow(source, sink, search).unwrap();
         assert_eq!(total_flow, flow);
-        println!("");
+        println!();
     }
 
     #[test]
@@ -574,6 +2216,10 @@ mod tests {
         test_flow_from_file("data/dicaps/bipartite-flow.txt", 3, FileType::Dicaps, DFS);
         test_flow_from_file("data/dicaps/central.txt", 5, FileType::Dicaps, BFS);
         test_flow_from_file("data/dicaps/central.txt", 5, FileType::Dicaps, DFS);
+        test_flow_from_file("data/dicaps/comments.txt", 5, FileType::Dicaps, BFS);
+        test_flow_from_file("data/dicaps/comments.txt", 5, FileType::Dicaps, DFS);
+        test_flow_from_file("data/dicaps/multi-terminal.txt", 7, FileType::Dicaps, BFS);
+        test_flow_from_file("data/dicaps/multi-terminal.txt", 7, FileType::Dicaps, DFS);
         test_flow_from_file("data/txt/test_1.txt", 10, FileType::Text, BFS);
         test_flow_from_file("data/txt/test_1.txt", 10, FileType::Text, DFS);
         test_flow_from_file("data/txt/test_2.txt", 23, FileType::Text, BFS);
@@ -587,4 +2233,30 @@ mod tests {
         test_flow_from_file("data/txt/test_6.txt", 20, FileType::Text, BFS);
         test_flow_from_file("data/txt/test_6.txt", 20, FileType::Text, DFS);
     }
+
+    #[test]
+    fn test_bidirectional() {
+        test_flow_from_file("data/dicaps/flow-graph.txt", 10, FileType::Dicaps, BIDIRECTIONAL);
+        test_flow_from_file("data/dicaps/multi-terminal.txt", 7, FileType::Dicaps, BIDIRECTIONAL);
+        test_flow_from_file("data/txt/test_3.txt", 2000000000, FileType::Text, BIDIRECTIONAL);
+        test_flow_from_file("data/txt/test_5.txt", 256, FileType::Text, BIDIRECTIONAL);
+        testing::assert_agreement(3, 200, &testing::Bounds::default(), testing::search_solver(BFS),
+                                  testing::search_solver(BIDIRECTIONAL));
+
+        // On a long grid the searches meet in the middle instead of sweeping the whole grid
+        let (source, sink, g) = generators::grid_network(4, 200, 1..=10, 0);
+        let (bfs_flow, bfs_stats) = g.clone().max_flow_with_stats(source, sink, BFS).unwrap();
+        let (flow, stats) = g.clone().max_flow_with_stats(source, sink, BIDIRECTIONAL).unwrap();
+        assert_eq!(flow, bfs_flow);
+        assert!(stats.vertexes_scanned < bfs_stats.vertexes_scanned, "{} and {}", stats, bfs_stats);
+
+        // Graphs without residual arcs fall back to a forward search
+        let edges = [(VertexId(0), VertexId(1), FlowEdge{flow: 0, capacity: 1}),
+                     (VertexId(1), VertexId(2), FlowEdge{flow: 0, capacity: 1})];
+        let g = Graph::new(&ids(&[0, 1, 2]), edges);
+        assert_eq!(g.augmenting_path(VertexId(0), VertexId(2), BIDIRECTIONAL), Some(ids(&[0, 1, 2])));
+        let g = Graph::flow_network(&ids(&[0, 1, 2]), edges);
+        assert_eq!(g.augmenting_path(VertexId(0), VertexId(2), BIDIRECTIONAL), Some(ids(&[0, 1, 2])));
+        assert_eq!(g.augmenting_path(VertexId(2), VertexId(0), BIDIRECTIONAL), None);
+    }
 }
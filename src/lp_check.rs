@@ -0,0 +1,137 @@
+use minilp::{self, ComparisonOp, OptimizationDirection, Problem, Variable};
+use super::{Error, FlowEdge, FlowError, FlowGraph, Graph, VertexId, BFS};
+
+/// Largest number of edges of an instance solved through the LP solver, which is meant for small instances.
+pub const LP_EDGE_LIMIT: usize = 10_000;
+
+/// Difference allowed between a combinatorial and an LP result, relative to the larger of 1 and the result.
+pub const LP_TOLERANCE: f64 = 1e-6;
+
+/// Maximum flow of an instance found by `FlowGraph::max_flow` and by the LP solver, see `check_max_flow`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct LpCheck {
+    pub flow: i64,
+    pub lp_flow: f64
+}
+
+impl LpCheck {
+    /// Whether both flows are equal up to `LP_TOLERANCE`.
+    pub fn agrees(&self) -> bool {
+        close(self.flow as f64, self.lp_flow)
+    }
+}
+
+/// Solves the maximum flow linear program written by `write_lp` with the LP solver and returns the flow value.
+/// Fails if the terminals are invalid, the graph has more than `LP_EDGE_LIMIT` edges, or the solver fails.
+pub fn lp_max_flow(graph: &Graph<FlowEdge>, source: VertexId, sink: VertexId) -> Result<f64, Error> {
+    let mut problem = Problem::new(OptimizationDirection::Maximize);
+    let value = problem.add_var(1.0, (0.0, f64::INFINITY));
+    add_flow_constraints(&mut problem, graph, source, sink, |_| 0.0, (value, 0.0))?;
+    let solution = problem.solve().map_err(solver_error)?;
+    Ok(solution.objective())
+}
+
+/// Solves the minimum cost flow linear program written by `write_min_cost_lp` with the LP solver and returns the
+/// smallest cost of sending `value` units, or `None` if the network can not carry that much. Fails like
+/// `lp_max_flow` or if there is not one cost per edge.
+pub fn lp_min_cost_flow(graph: &Graph<FlowEdge>, source: VertexId, sink: VertexId, costs: &[i64], value: i64)
+                        -> Result<Option<f64>, Error> {
+    if costs.len() != graph.n_edges() {
+        return Err(Error::Construction(format!("Expected {} edge costs, got {}", graph.n_edges(), costs.len())));
+    }
+    let mut problem = Problem::new(OptimizationDirection::Minimize);
+    let value = problem.add_var(0.0, (value as f64, value as f64));
+    add_flow_constraints(&mut problem, graph, source, sink, |e| costs[e] as f64, (value, 0.0))?;
+    match problem.solve() {
+        Ok(solution) => Ok(Some(solution.objective())),
+        Err(minilp::Error::Infeasible) => Ok(None),
+        Err(error) => Err(solver_error(error))
+    }
+}
+
+/// Solves a copy of the graph from no flow with `FlowGraph::max_flow` and through the LP solver, so that the results
+/// can be compared with `LpCheck::agrees`. A sink which no flow reaches has a maximum flow of zero. Fails like
+/// `lp_max_flow` or if the combinatorial solver fails otherwise.
+pub fn check_max_flow(graph: &Graph<FlowEdge>, source: VertexId, sink: VertexId) -> Result<LpCheck, Error> {
+    let lp_flow = lp_max_flow(graph, source, sink)?;
+    let mut network = graph.clone();
+    network.reset_flow();
+    let flow = match network.max_flow(source, sink, BFS) {
+        Ok(flow) => flow,
+        Err(FlowError::UnreachableSink {..}) => 0,
+        Err(error) => return Err(error.into())
+    };
+    debug!("Max flow {} and LP max flow {} from {} to {}", flow, lp_flow, source, sink);
+    Ok(LpCheck {flow, lp_flow})
+}
+
+/// Adds a variable for the flow of every edge, bounded by its capacity and with the objective coefficient given by
+/// `objective`, and conservation constraints at every vertex other than the terminals. The flow leaving the source
+/// equals the `value` term.
+fn add_flow_constraints<O>(problem: &mut Problem,
+                           graph: &Graph<FlowEdge>,
+                           source: VertexId,
+                           sink: VertexId,
+                           objective: O,
+                           value: (Variable, f64)) -> Result<(), Error>
+    where O: Fn(usize) -> f64 {
+    graph.check_terminals(source, sink)?;
+    if graph.n_edges() > LP_EDGE_LIMIT {
+        return Err(Error::Limit(format!("{} edges are more than the {} solved through the LP solver",
+                                        graph.n_edges(), LP_EDGE_LIMIT)));
+    }
+    let mut terms: Vec<Vec<(Variable, f64)>> = vec![Vec::new(); graph.n_vertexes()];
+    for (e, (u, v, a)) in graph.edges().enumerate() {
+        let flow = problem.add_var(objective(e), (0.0, graph.arcs[a].capacity.max(0) as f64));
+        // Self loops leave and enter the same vertex, so they are left out of conservation
+        if u != v {
+            terms[u].push((flow, 1.0));
+            terms[v].push((flow, -1.0));
+        }
+    }
+    terms[source].push((value.0, -1.0));
+    problem.add_constraint(terms[source].as_slice(), ComparisonOp::Eq, value.1);
+    for v in graph.vertexes().filter(|&v| v != source && v != sink && !terms[v].is_empty()) {
+        problem.add_constraint(terms[v].as_slice(), ComparisonOp::Eq, 0.0);
+    }
+    Ok(())
+}
+
+fn solver_error(error: minilp::Error) -> Error {
+    Error::Construction(format!("LP solver failed: {}", error))
+}
+
+/// Whether two results are equal up to `LP_TOLERANCE`.
+fn close(expected: f64, actual: f64) -> bool {
+    (expected - actual).abs() <= LP_TOLERANCE * expected.abs().max(actual.abs()).max(1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use generators::random_network;
+
+    #[test]
+    fn test_check_max_flow() {
+        for seed in 0..10 {
+            let (source, sink, g) = random_network(12, 40, 1..=20, seed);
+            let check = check_max_flow(&g, source, sink).unwrap();
+            assert!(check.agrees(), "seed {}: {:?}", seed, check);
+        }
+    }
+
+    #[test]
+    fn test_lp_min_cost_flow() {
+        for seed in 0..10 {
+            let (source, sink, mut g) = random_network(12, 40, 1..=20, seed);
+            let costs = (0..g.n_edges()).map(|e| (e % 7) as i64).collect::<Vec<_>>();
+            let budgeted = match g.max_flow_within_budget(source, sink, &costs, i64::MAX / 2) {
+                Ok(budgeted) => budgeted,
+                Err(_) => continue
+            };
+            let lp_cost = lp_min_cost_flow(&g, source, sink, &costs, budgeted.value).unwrap().unwrap();
+            assert!(close(budgeted.cost as f64, lp_cost), "seed {}: {} and {}", seed, budgeted.cost, lp_cost);
+            assert_eq!(lp_min_cost_flow(&g, source, sink, &costs, budgeted.value + 1), Ok(None));
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use alloc::vec::Vec;
+use super::{Error, FlowEdge, FlowGraph, Graph, VertexId, BFS};
+
+/// Returns a maximum matching of the bipartite graph with left vertexes `0..n_left`, right vertexes `0..n_right` and
+/// `edges` from a left to a right vertex, as the indexes of the matched edges in increasing order. The matching is
+/// read off a maximum flow with unit capacities from a source joined to every left vertex to a sink joined from every
+/// right vertex. Fails if an edge has an endpoint out of range.
+pub fn maximum_matching(n_left: usize, n_right: usize, edges: &[(usize, usize)]) -> Result<Vec<usize>, Error> {
+    check_edges(n_left, n_right, edges)?;
+    let (source, sink) = (VertexId(n_left + n_right), VertexId(n_left + n_right + 1));
+    let unit = FlowEdge{capacity: 1, flow: 0};
+    // The edges of the input come first, so edge i is arc 2 * i
+    let network_edges = edges.iter()
+        .map(|&(u, v)| (VertexId(u), VertexId(n_left + v), unit))
+        .chain((0..n_left).map(|u| (source, VertexId(u), unit)))
+        .chain((0..n_right).map(|v| (VertexId(n_left + v), sink, unit)))
+        .collect::<Vec<_>>();
+    let vertexes = (0..n_left + n_right + 2).map(VertexId).collect::<Vec<_>>();
+    let mut network = Graph::flow_network(&vertexes, &network_edges);
+    // Without any edge the sink is unreachable and the matching is empty
+    let _ = network.max_flow(source, sink, BFS);
+    Ok((0..edges.len()).filter(|&e| network.arcs[2 * e].flow > 0).collect())
+}
+
+/// Returns a minimum edge cover of the bipartite graph described as in `maximum_matching`, a smallest set of edges
+/// touching every vertex, as the indexes of its edges in increasing order. The cover takes the edges of a maximum
+/// matching and the first edge of every vertex the matching leaves uncovered, which gives `n_left + n_right - m`
+/// edges for a matching of `m` edges, the minimum by Gallai's theorem. Fails if a vertex has no edge, in which case
+/// there is no cover, or an edge has an endpoint out of range.
+pub fn minimum_edge_cover(n_left: usize, n_right: usize, edges: &[(usize, usize)]) -> Result<Vec<usize>, Error> {
+    let matching = maximum_matching(n_left, n_right, edges)?;
+    let mut covered = vec![false; n_left + n_right];
+    let mut cover = vec![false; edges.len()];
+    for &e in &matching {
+        let (u, v) = edges[e];
+        covered[u] = true;
+        covered[n_left + v] = true;
+        cover[e] = true;
+    }
+    for (e, &(u, v)) in edges.iter().enumerate() {
+        // An edge with both endpoints uncovered would extend the matching, so at most one of them is uncovered
+        if !covered[u] || !covered[n_left + v] {
+            covered[u] = true;
+            covered[n_left + v] = true;
+            cover[e] = true;
+        }
+    }
+    if let Some(vertex) = covered.iter().position(|&c| !c) {
+        return Err(Error::Construction(if vertex < n_left {
+            format!("Left vertex {} has no edge to cover it", vertex)
+        } else {
+            format!("Right vertex {} has no edge to cover it", vertex - n_left)
+        }));
+    }
+    Ok((0..edges.len()).filter(|&e| cover[e]).collect())
+}
+
+fn check_edges(n_left: usize, n_right: usize, edges: &[(usize, usize)]) -> Result<(), Error> {
+    match edges.iter().find(|&&(u, v)| u >= n_left || v >= n_right) {
+        Some(&(u, v)) => Err(Error::Construction(format!("Edge ({}, {}) is not between {} left and {} right vertexes",
+                                                         u, v, n_left, n_right))),
+        None => Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_minimum_edge_cover() {
+        // A perfect matching is a minimum cover on its own
+        let edges = [(0, 0), (0, 1), (1, 1), (1, 2), (2, 2)];
+        let matching = maximum_matching(3, 3, &edges).unwrap();
+        assert_eq!(matching.len(), 3);
+        let cover = minimum_edge_cover(3, 3, &edges).unwrap();
+        assert_eq!(cover.len(), 3);
+        let mut touched = cover.iter().flat_map(|&e| [edges[e].0, 3 + edges[e].1]).collect::<Vec<_>>();
+        touched.sort_unstable();
+        touched.dedup();
+        assert_eq!(touched, [0, 1, 2, 3, 4, 5]);
+
+        // A star needs all of its edges
+        let star = [(0, 0), (0, 1), (0, 2)];
+        assert_eq!(maximum_matching(1, 3, &star).unwrap().len(), 1);
+        assert_eq!(minimum_edge_cover(1, 3, &star), Ok(vec![0, 1, 2]));
+
+        assert_eq!(maximum_matching(2, 2, &[]), Ok(vec![]));
+        assert!(matches!(minimum_edge_cover(2, 2, &[(0, 0)]), Err(Error::Construction(_))));
+        assert!(matches!(maximum_matching(1, 1, &[(0, 1)]), Err(Error::Construction(_))));
+    }
+}
@@ -0,0 +1,98 @@
+use alloc::vec::Vec;
+#[cfg(feature = "ndarray")]
+use ndarray::Array2;
+use super::{DuplicatePolicy, Error, FlowEdge, Graph, SelfLoopPolicy, VertexId};
+
+impl Graph<FlowEdge> {
+    /// Builds a flow network from a square matrix of capacities, the in-memory counterpart of the txt format: entry
+    /// `(u, v)` is the capacity of the edge from `u` to `v`. Entries of zero and the diagonal do not become edges, and
+    /// every edge gets its reverse arc. Fails if the matrix is not square or has a negative capacity.
+    #[cfg(feature = "ndarray")]
+    pub fn from_capacity_matrix(capacities: &Array2<i32>) -> Result<Graph<FlowEdge>, Error> {
+        let (rows, cols) = capacities.dim();
+        if rows != cols {
+            return Err(Error::Construction(format!("Capacity matrix is {} by {}, it must be square", rows, cols)));
+        }
+        from_entries(rows, capacities.indexed_iter().map(|(position, &capacity)| (position, capacity)))
+    }
+
+    /// Same as `from_capacity_matrix` for a matrix given as one vector of capacities per row, which needs no
+    /// `ndarray`. Fails if a row does not have one entry per row or a capacity is negative.
+    pub fn from_adjacency_matrix(matrix: &[Vec<i32>]) -> Result<Graph<FlowEdge>, Error> {
+        let n = matrix.len();
+        if let Some((u, row)) = matrix.iter().enumerate().find(|(_, row)| row.len() != n) {
+            return Err(Error::Construction(format!("Row {} of the adjacency matrix has {} entries instead of {}",
+                                                   u, row.len(), n)));
+        }
+        let entries = matrix.iter().enumerate()
+            .flat_map(|(u, row)| row.iter().enumerate().map(move |(v, &capacity)| ((u, v), capacity)));
+        from_entries(n, entries)
+    }
+}
+
+/// Builds the flow network of the `((u, v), capacity)` entries of an `n` by `n` matrix in row major order.
+fn from_entries<I>(n: usize, entries: I) -> Result<Graph<FlowEdge>, Error> where I: Iterator<Item = ((usize, usize), i32)> {
+    let mut edges: Vec<(VertexId, VertexId, FlowEdge)> = Vec::new();
+    for ((u, v), capacity) in entries {
+        if capacity < 0 {
+            return Err(Error::Construction(format!("Edge ({}, {}) has negative capacity {}", u, v, capacity)));
+        }
+        if capacity > 0 && u != v {
+            edges.push((VertexId(u), VertexId(v), FlowEdge{capacity, flow: 0}));
+        }
+    }
+    let vertexes = (0..n).map(VertexId).collect::<Vec<_>>();
+    Graph::try_flow_network(&vertexes, &edges, DuplicatePolicy::Parallel, SelfLoopPolicy::Skip)
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    #[cfg(feature = "ndarray")]
+    use ndarray::{arr2, Array2};
+    use {flow_from_txt, FlowEdge, FlowGraph, Graph, VertexId, BFS};
+
+    #[test]
+    #[cfg(feature = "ndarray")]
+    fn test_from_capacity_matrix() {
+        let capacities = arr2(&[[0, 5, 5, 0], [0, 0, 1, 5], [0, 0, 3, 5], [0, 0, 0, 0]]);
+        let mut g = Graph::from_capacity_matrix(&capacities).unwrap();
+        let pairs = g.edges().map(|(u, v, _)| (u.index(), v.index())).collect::<Vec<_>>();
+        assert_eq!(pairs, [(0, 1), (0, 2), (1, 2), (1, 3), (2, 3)]);
+        assert_eq!(g.max_flow(VertexId(0), VertexId(3), BFS), Ok(10));
+
+        // Same network as the txt parser builds from the same matrix
+        let (source, sink, mut txt) = flow_from_txt("data/txt/test_1.txt");
+        let n = txt.n_vertexes();
+        let mut matrix = Array2::<i32>::zeros((n, n));
+        for (u, v, a) in txt.edges() {
+            matrix[[u.index(), v.index()]] = txt.arcs[a].capacity;
+        }
+        let mut g = Graph::from_capacity_matrix(&matrix).unwrap();
+        assert_eq!(g.max_flow(source, sink, BFS), txt.max_flow(source, sink, BFS));
+
+        assert!(Graph::<FlowEdge>::from_capacity_matrix(&Array2::zeros((2, 3))).is_err());
+        assert!(Graph::<FlowEdge>::from_capacity_matrix(&arr2(&[[0, -1], [0, 0]])).is_err());
+    }
+
+    #[test]
+    fn test_from_adjacency_matrix() {
+        let mut g = Graph::from_adjacency_matrix(&[vec![0, 5, 5, 0], vec![0, 0, 1, 5], vec![0, 0, 3, 5], vec![0; 4]])
+            .unwrap();
+        let pairs = g.edges().map(|(u, v, _)| (u.index(), v.index())).collect::<Vec<_>>();
+        assert_eq!(pairs, [(0, 1), (0, 2), (1, 2), (1, 3), (2, 3)]);
+        assert_eq!(g.max_flow(VertexId(0), VertexId(3), BFS), Ok(10));
+
+        // Same flow as the txt parser gives for the same matrix
+        let (source, sink, mut txt) = flow_from_txt("data/txt/test_1.txt");
+        let mut matrix = vec![vec![0; txt.n_vertexes()]; txt.n_vertexes()];
+        for (u, v, a) in txt.edges() {
+            matrix[u][v.index()] = txt.arcs[a].capacity;
+        }
+        let mut g = Graph::from_adjacency_matrix(&matrix).unwrap();
+        assert_eq!(g.max_flow(source, sink, BFS), txt.max_flow(source, sink, BFS));
+
+        assert!(Graph::<FlowEdge>::from_adjacency_matrix(&[vec![0, 1], vec![0]]).is_err());
+        assert!(Graph::<FlowEdge>::from_adjacency_matrix(&[vec![0, -1], vec![0, 0]]).is_err());
+        assert_eq!(Graph::from_adjacency_matrix(&[]).unwrap().size(), (0, 0));
+    }
+}
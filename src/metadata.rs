@@ -0,0 +1,100 @@
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use super::{ArcId, Error, FlowEdge, Graph, VertexId};
+
+/// Data attached to the edges of a flow network, such as labels, ids of the objects the edges stand for or costs. The
+/// solvers never see it, it is kept next to the graph and indexed like its edges: entry `e` belongs to the edge of
+/// arcs `2 * e` and `2 * e + 1`, so arcs returned by a solver map back to their entries without bookkeeping.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EdgeMetadata<M> {
+    values: Vec<M>
+}
+
+impl<M> EdgeMetadata<M> {
+    /// Attaches one value per edge of `graph`, in the order of `Graph::edges`. Fails if there is not one value per edge
+    /// or the graph does not have its residual arcs, as arcs would then not map to edges.
+    pub fn new(graph: &Graph<FlowEdge>, values: Vec<M>) -> Result<EdgeMetadata<M>, Error> {
+        if !graph.has_residual_arcs() {
+            return Err(Error::Construction("Edge metadata needs a graph with residual arcs".to_string()));
+        }
+        if values.len() != graph.n_edges() {
+            return Err(Error::Construction(format!("Expected {} edge values, got {}", graph.n_edges(), values.len())));
+        }
+        Ok(EdgeMetadata {values})
+    }
+
+    /// Returns the value of edge `edge`.
+    pub fn get(&self, edge: usize) -> Option<&M> {
+        self.values.get(edge)
+    }
+
+    pub fn get_mut(&mut self, edge: usize) -> Option<&mut M> {
+        self.values.get_mut(edge)
+    }
+
+    /// Returns the value of the edge of `arc`, which is the same for the edge and its reverse arc.
+    pub fn of_arc(&self, arc: ArcId) -> &M {
+        &self.values[arc / 2]
+    }
+
+    /// Returns the values of the edges of `arcs`, such as an augmenting path or the arcs of `Graph::min_cut`.
+    pub fn of_arcs(&self, arcs: &[ArcId]) -> Vec<&M> {
+        arcs.iter().map(|&a| self.of_arc(a)).collect()
+    }
+
+    /// Iterates over the value and the flow of every edge carrying flow in `graph`, in the order of the edges.
+    pub fn flows<'a>(&'a self, graph: &'a Graph<FlowEdge>) -> impl Iterator<Item = (&'a M, i32)> + 'a {
+        graph.edges()
+            .filter(move |&(_, _, a)| graph.arcs[a].flow > 0)
+            .map(move |(_, _, a)| (self.of_arc(a), graph.arcs[a].flow))
+    }
+
+    pub fn values(&self) -> &[M] {
+        &self.values
+    }
+
+    pub fn into_values(self) -> Vec<M> {
+        self.values
+    }
+}
+
+impl Graph<FlowEdge> {
+    /// Builds a flow network like `flow_network` from edges carrying a value each, and returns the values as metadata
+    /// of the edges. Panics if the graph is invalid, see `try_new`.
+    pub fn flow_network_with_metadata<I, M>(vertex_list: &[VertexId], edge_list: I)
+                                            -> (Graph<FlowEdge>, EdgeMetadata<M>)
+        where I: IntoIterator<Item = (VertexId, VertexId, FlowEdge, M)> {
+        let (edges, values): (Vec<_>, Vec<_>) = edge_list.into_iter()
+            .map(|(u, v, edge, value)| ((u, v, edge), value))
+            .unzip();
+        let graph = Graph::flow_network(vertex_list, &edges);
+        (graph, EdgeMetadata {values})
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use {FlowGraph, BFS};
+
+    #[test]
+    fn test_edge_metadata() {
+        let vertexes = (0..4).map(VertexId).collect::<Vec<_>>();
+        let edges = [(0, 1, 3, "road"), (1, 3, 2, "rail"), (0, 2, 2, "ferry"), (2, 3, 4, "bridge")]
+            .iter()
+            .map(|&(u, v, capacity, label)| (VertexId(u), VertexId(v), FlowEdge{capacity, flow: 0}, label));
+        let (mut g, labels) = Graph::flow_network_with_metadata(&vertexes, edges);
+        assert_eq!(g.max_flow(VertexId(0), VertexId(3), BFS), Ok(4));
+        assert_eq!(labels.flows(&g).collect::<Vec<_>>(), [(&"road", 2), (&"rail", 2), (&"ferry", 2), (&"bridge", 2)]);
+        let mut cut = labels.of_arcs(&g.min_cut(VertexId(0)));
+        cut.sort_unstable();
+        assert_eq!(cut, [&"ferry", &"rail"]);
+        assert_eq!(labels.of_arc(1), &"road");
+        assert_eq!(labels.get(4), None);
+
+        assert!(EdgeMetadata::new(&g, vec![0; 4]).is_ok());
+        assert!(matches!(EdgeMetadata::new(&g, vec![0; 3]), Err(Error::Construction(_))));
+        let plain = Graph::new(&vertexes, [(VertexId(0), VertexId(1), FlowEdge{capacity: 1, flow: 0})]);
+        assert!(matches!(EdgeMetadata::new(&plain, vec![0]), Err(Error::Construction(_))));
+    }
+}
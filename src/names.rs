@@ -0,0 +1,99 @@
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use super::{ArcId, Error, FlowEdge, FlowError, Graph, Property, VertexId};
+
+impl<E: Property> Graph<E> {
+    /// Names the vertexes, such as the names of a DOT or CSV file, in the order of their ids. The names are kept when
+    /// the graph is rebuilt by `ensure_residual_arcs` or `merge_duplicates` and are used instead of the ids by the DOT
+    /// and CSV writers and the descriptions of paths, cuts and errors. An empty list removes the names. Fails if there
+    /// is not one name per vertex or a name is repeated.
+    pub fn set_vertex_names(&mut self, names: Vec<String>) -> Result<(), Error> {
+        if !names.is_empty() && names.len() != self.n_vertexes() {
+            return Err(Error::Construction(format!("Expected {} vertex names, got {}",
+                                                   self.n_vertexes(), names.len())));
+        }
+        let mut sorted = names.iter().collect::<Vec<_>>();
+        sorted.sort_unstable();
+        if let Some(pair) = sorted.windows(2).find(|pair| pair[0] == pair[1]) {
+            return Err(Error::Construction(format!("Vertex name {} is given more than once", pair[0])));
+        }
+        self.names = names;
+        Ok(())
+    }
+
+    /// Returns whether the vertexes have names, see `set_vertex_names`.
+    pub fn has_vertex_names(&self) -> bool {
+        !self.names.is_empty()
+    }
+
+    pub fn vertex_name(&self, v: VertexId) -> Option<&str> {
+        self.names.get(v.index()).map(|name| name.as_str())
+    }
+
+    /// Returns the name of `v`, or its id if the vertexes have no names.
+    pub fn vertex_label(&self, v: VertexId) -> String {
+        self.vertex_name(v).map_or_else(|| v.to_string(), |name| name.to_string())
+    }
+
+    /// Returns the vertex named `name`.
+    pub fn find_vertex(&self, name: &str) -> Option<VertexId> {
+        self.names.iter().position(|n| n == name).map(VertexId)
+    }
+
+    /// Describes a path given by its vertexes as their labels joined by arrows, such as `a -> b -> c`.
+    pub fn describe_path(&self, path: &[VertexId]) -> String {
+        path.iter().map(|&v| self.vertex_label(v)).collect::<Vec<_>>().join(" -> ")
+    }
+
+    /// Describes arcs, such as the arcs of `Graph::min_cut`, as the pairs of labels of their tail and head, such as
+    /// `(a, b), (c, d)`.
+    pub fn describe_arcs(&self, arcs: &[ArcId]) -> String {
+        let tails = self.tails();
+        arcs.iter()
+            .map(|&a| format!("({}, {})", self.vertex_label(tails[a]), self.vertex_label(self.head(a))))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+impl Graph<FlowEdge> {
+    /// Describes `error` like its `Display` implementation but with the labels of the vertexes, see `vertex_label`.
+    pub fn describe_error(&self, error: &FlowError) -> String {
+        match *error {
+            FlowError::SourceIsSink {vertex} =>
+                format!("vertex {} is both the source and the sink", self.vertex_label(vertex)),
+            FlowError::UnreachableSink {source, sink} =>
+                format!("sink {} is unreachable from source {}", self.vertex_label(sink), self.vertex_label(source)),
+            _ => error.to_string()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use {FlowGraph, BFS};
+
+    #[test]
+    fn test_vertex_names() {
+        let vertexes = (0..3).map(VertexId).collect::<Vec<_>>();
+        let edges = [(VertexId(0), VertexId(1), FlowEdge{capacity: 2, flow: 0})];
+        let mut g = Graph::flow_network(&vertexes, edges);
+        assert_eq!(g.vertex_label(VertexId(1)), "1");
+        let names = ["plant", "depot", "store"].iter().map(|n| n.to_string()).collect::<Vec<_>>();
+        g.set_vertex_names(names.clone()).unwrap();
+        assert_eq!(g.vertex_name(VertexId(2)), Some("store"));
+        assert_eq!(g.find_vertex("depot"), Some(VertexId(1)));
+        assert_eq!(g.describe_path(&[VertexId(0), VertexId(1)]), "plant -> depot");
+        assert_eq!(g.describe_arcs(&[0, 1]), "(plant, depot), (depot, plant)");
+        let error = g.max_flow(VertexId(0), VertexId(2), BFS).unwrap_err();
+        assert_eq!(g.describe_error(&error), "sink store is unreachable from source plant");
+        assert_eq!(g.merge_duplicates(Default::default()).unwrap().vertex_name(VertexId(0)), Some("plant"));
+
+        assert!(matches!(g.set_vertex_names(names[..2].to_vec()), Err(Error::Construction(_))));
+        let repeated = ["a", "b", "a"].iter().map(|n| n.to_string()).collect::<Vec<_>>();
+        assert!(matches!(g.set_vertex_names(repeated), Err(Error::Construction(_))));
+        g.set_vertex_names(Vec::new()).unwrap();
+        assert!(!g.has_vertex_names());
+    }
+}
@@ -0,0 +1,224 @@
+use alloc::string::ToString;
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use real::RealEdge;
+use super::{DuplicatePolicy, Error, FlowEdge, FlowError, Graph, SelfLoopPolicy, VertexId};
+
+/// Flow network together with its terminals, as built by `FlowNetworkBuilder`.
+#[derive(Debug, Clone)]
+pub struct FlowNetwork {
+    pub source: VertexId,
+    pub sink: VertexId,
+    pub graph: Graph<FlowEdge>
+}
+
+impl FlowNetwork {
+    /// Returns a builder for a network without vertexes.
+    pub fn builder() -> FlowNetworkBuilder {
+        FlowNetworkBuilder::default()
+    }
+
+    /// Returns the source, sink and graph, in the order the file loaders return them.
+    pub fn into_parts(self) -> (VertexId, VertexId, Graph<FlowEdge>) {
+        (self.source, self.sink, self.graph)
+    }
+
+    /// Multiplies every capacity by `factor`, rounded to the nearest integer, and scales the flow along, so that a
+    /// solver can continue from the scaled flow instead of starting over. An integral factor scales every cut and the
+    /// flow exactly, so a maximum flow stays maximum and `true` is returned. Any other factor scales the flow as far as
+    /// the rounded capacities allow and rounds it to an integral flow with `Graph::round_flow`, which is feasible and
+    /// a warm start for the solvers but may no longer be maximum, and `false` is returned. Fails if the factor is not
+    /// positive, a scaled capacity overflows, or the stored flow is not a feasible flow between the terminals.
+    #[cfg(feature = "std")]
+    pub fn rescale_capacities(&mut self, factor: f64) -> Result<bool, Error> {
+        if !factor.is_finite() || factor <= 0.0 {
+            return Err(Error::Construction(format!("Capacities can not be scaled by {}", factor)));
+        }
+        let mut capacities: Vec<i32> = Vec::with_capacity(self.graph.n_edges());
+        for (u, v, a) in self.graph.edges() {
+            let capacity = (self.graph.arcs[a].capacity as f64 * factor).round();
+            if capacity > i32::MAX as f64 || capacity < i32::MIN as f64 {
+                return Err(Error::Overflow(format!("Capacity of edge ({}, {}) overflows when scaled by {}",
+                                                   u, v, factor)));
+            }
+            capacities.push(capacity as i32);
+        }
+        if factor.fract() == 0.0 {
+            for (e, &capacity) in capacities.iter().enumerate() {
+                let edge = &mut self.graph.arcs[2 * e];
+                edge.capacity = capacity;
+                edge.flow *= factor as i32;
+                self.graph.arcs[2 * e + 1].flow = -self.graph.arcs[2 * e].flow;
+            }
+            return Ok(true);
+        }
+        // Shrinking the scaled flow uniformly keeps it conserved while it fits the rounded capacities
+        let shrink = self.graph.edges()
+            .zip(&capacities)
+            .filter(|&((_, _, a), _)| self.graph.arcs[a].flow > 0)
+            .map(|((_, _, a), &capacity)| capacity as f64 / (self.graph.arcs[a].flow as f64 * factor))
+            .fold(1.0, f64::min);
+        let edges = self.graph.edges()
+            .zip(&capacities)
+            .map(|((u, v, a), &capacity)| {
+                (u, v, RealEdge {capacity: capacity as f64, flow: self.graph.arcs[a].flow as f64 * factor * shrink})
+            })
+            .collect::<Vec<_>>();
+        let vertexes = self.graph.vertexes().collect::<Vec<_>>();
+        let mut real = Graph::real_flow_network(&vertexes, &edges)?;
+        for (e, &(_, _, edge)) in edges.iter().enumerate() {
+            real.arcs[2 * e].flow = edge.flow.max(0.0);
+            real.arcs[2 * e + 1].flow = -edge.flow.max(0.0);
+        }
+        real.round_flow(self.source, self.sink)?;
+        for (arc, real) in self.graph.arcs.iter_mut().zip(&real.arcs) {
+            arc.capacity = real.capacity as i32;
+            arc.flow = real.flow as i32;
+        }
+        Ok(false)
+    }
+}
+
+/// Collects the edges and terminals of a flow network, for example
+/// `FlowNetwork::builder().edge(0, 1, 5).edge(1, 2, 3).source(0).sink(2).build()`. The vertexes are numbered from 0 up
+/// to the largest vertex of an edge or terminal, and every edge gets its reverse arc when the network is built.
+#[derive(Debug, Clone, Default)]
+pub struct FlowNetworkBuilder {
+    edges: Vec<(VertexId, VertexId, FlowEdge)>,
+    n_vertexes: usize,
+    source: Option<VertexId>,
+    sink: Option<VertexId>,
+    duplicates: DuplicatePolicy,
+    self_loops: SelfLoopPolicy
+}
+
+impl FlowNetworkBuilder {
+    /// Adds an edge from `u` to `v` without flow.
+    pub fn edge(mut self, u: usize, v: usize, capacity: i32) -> FlowNetworkBuilder {
+        self.edges.push((VertexId(u), VertexId(v), FlowEdge{capacity, flow: 0}));
+        self
+    }
+
+    /// Adds the edges of `(u, v, capacity)` triples, see `edge`.
+    pub fn edges<I>(self, edges: I) -> FlowNetworkBuilder where I: IntoIterator<Item = (usize, usize, i32)> {
+        edges.into_iter().fold(self, |builder, (u, v, capacity)| builder.edge(u, v, capacity))
+    }
+
+    /// Makes the network have at least `n_vertexes` vertexes, for vertexes without edges.
+    pub fn vertexes(mut self, n_vertexes: usize) -> FlowNetworkBuilder {
+        self.n_vertexes = self.n_vertexes.max(n_vertexes);
+        self
+    }
+
+    pub fn source(mut self, source: usize) -> FlowNetworkBuilder {
+        self.source = Some(VertexId(source));
+        self
+    }
+
+    pub fn sink(mut self, sink: usize) -> FlowNetworkBuilder {
+        self.sink = Some(VertexId(sink));
+        self
+    }
+
+    /// Sets how parallel edges are treated, they are kept by default.
+    pub fn duplicates(mut self, duplicates: DuplicatePolicy) -> FlowNetworkBuilder {
+        self.duplicates = duplicates;
+        self
+    }
+
+    /// Sets how self loops are treated, they are skipped by the solvers by default.
+    pub fn self_loops(mut self, self_loops: SelfLoopPolicy) -> FlowNetworkBuilder {
+        self.self_loops = self_loops;
+        self
+    }
+
+    /// Builds the network. Fails if a terminal is missing, the source is the sink, an edge has a negative capacity, or
+    /// the graph is rejected by `Graph::try_flow_network`.
+    pub fn build(self) -> Result<FlowNetwork, Error> {
+        let source = self.source.ok_or_else(|| Error::Construction("Flow network has no source".to_string()))?;
+        let sink = self.sink.ok_or_else(|| Error::Construction("Flow network has no sink".to_string()))?;
+        if source == sink {
+            return Err(Error::Infeasible(FlowError::SourceIsSink {vertex: source}));
+        }
+        if let Some(&(u, v, edge)) = self.edges.iter().find(|&&(_, _, edge)| edge.capacity < 0) {
+            return Err(Error::Construction(format!("Edge ({}, {}) has negative capacity {}", u, v, edge.capacity)));
+        }
+        let n_vertexes = self.edges.iter()
+            .flat_map(|&(u, v, _)| [u, v])
+            .chain([source, sink])
+            .map(|v| v.index() + 1)
+            .fold(self.n_vertexes, usize::max);
+        let vertexes = (0..n_vertexes).map(VertexId).collect::<Vec<_>>();
+        let graph = Graph::try_flow_network(&vertexes, &self.edges, self.duplicates, self.self_loops)?;
+        Ok(FlowNetwork {source, sink, graph})
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use {FlowGraph, BFS};
+
+    #[test]
+    fn test_builder() {
+        let mut network = FlowNetwork::builder().edge(0, 1, 5).edge(1, 2, 3).source(0).sink(2).build().unwrap();
+        assert_eq!(network.graph.size(), (3, 2));
+        assert_eq!(network.graph.max_flow(network.source, network.sink, BFS), Ok(3));
+
+        // Terminals and extra vertexes count as vertexes, duplicate edges are merged as asked
+        let (source, sink, g) = FlowNetwork::builder()
+            .edges(vec![(1, 2, 4), (1, 2, 6)])
+            .duplicates(DuplicatePolicy::Sum)
+            .vertexes(5)
+            .source(1)
+            .sink(6)
+            .build()
+            .unwrap()
+            .into_parts();
+        assert_eq!((source, sink, g.size()), (VertexId(1), VertexId(6), (7, 1)));
+        assert_eq!(g.arcs[0].capacity, 10);
+
+        let builder = FlowNetwork::builder().edge(0, 1, 5);
+        assert!(matches!(builder.clone().sink(1).build(), Err(Error::Construction(_))));
+        assert!(matches!(builder.clone().source(0).build(), Err(Error::Construction(_))));
+        assert_eq!(builder.clone().source(1).sink(1).build().unwrap_err(),
+                   Error::Infeasible(FlowError::SourceIsSink {vertex: VertexId(1)}));
+        assert!(builder.clone().edge(1, 0, -1).source(0).sink(1).build().is_err());
+        assert!(builder.edge(1, 1, 2).self_loops(SelfLoopPolicy::Reject).source(0).sink(1).build().is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_rescale_capacities() {
+        use generators::random_network;
+        use verify::{certify, verify_flow, Violation};
+        for seed in 0..20 {
+            let (source, sink, graph) = random_network(15, 50, 1..=20, seed);
+            let mut network = FlowNetwork {source, sink, graph};
+            let value = match network.graph.max_flow(source, sink, BFS) {
+                Ok(value) => value,
+                Err(_) => continue
+            };
+            // An integral factor keeps the flow maximum
+            let mut tripled = network.clone();
+            assert_eq!(tripled.rescale_capacities(3.0), Ok(true));
+            assert!(certify(&tripled.graph, source, sink, 3 * value).is_ok(), "seed {}", seed);
+
+            // Other factors leave a feasible flow from which the solver finds the maximum flow of the new capacities
+            for &factor in &[0.5, 1.7] {
+                let mut scaled = network.clone();
+                assert_eq!(scaled.rescale_capacities(factor), Ok(false));
+                let violations = verify_flow(&scaled.graph, source, sink);
+                assert!(violations.iter().all(|v| matches!(v, Violation::NotMaximum {..})), "seed {}", seed);
+                let mut fresh = scaled.graph.clone();
+                fresh.reset_flow();
+                let expected = fresh.max_flow(source, sink, BFS).unwrap_or(0);
+                let warm = scaled.graph.max_flow(source, sink, BFS).unwrap_or(0);
+                assert_eq!(warm, expected, "seed {} factor {}", seed, factor);
+            }
+        }
+        let mut network = FlowNetwork::builder().edge(0, 1, 5).source(0).sink(1).build().unwrap();
+        assert!(network.rescale_capacities(-1.0).is_err());
+        assert!(matches!(network.rescale_capacities(1e10), Err(Error::Overflow(_))));
+    }
+}
@@ -0,0 +1,62 @@
+use rayon::prelude::*;
+use verify::{verify_flow, Violation};
+use super::{FlowEdge, FlowError, FlowGraph, FlowState, Graph, Search, VertexId};
+
+/// Computes the maximum flow between every pair of terminals on one shared graph, each query with its own
+/// `FlowState`. The flows stored in the graph are ignored and left untouched.
+pub fn max_flows(graph: &Graph<FlowEdge>,
+                 terminals: &[(VertexId, VertexId)],
+                 search: Search) -> Vec<Result<i64, FlowError>> {
+    terminals.par_iter()
+        .map(|&(source, sink)| FlowState::new(graph).max_flow(graph, source, sink, search))
+        .collect()
+}
+
+/// Solves independent flow problems, such as the components of a larger network, storing the flows in each graph.
+pub fn max_flow_each(problems: &mut [(VertexId, VertexId, Graph<FlowEdge>)],
+                     search: Search) -> Vec<Result<i64, FlowError>> {
+    problems.par_iter_mut()
+        .map(|&mut (source, sink, ref mut graph)| graph.max_flow(source, sink, search))
+        .collect()
+}
+
+/// Generates one network per seed, for generators such as `generators::random_network` wrapped in a closure.
+pub fn generate_each<F>(seeds: &[u64], generator: F) -> Vec<(VertexId, VertexId, Graph<FlowEdge>)>
+    where F: Fn(u64) -> (VertexId, VertexId, Graph<FlowEdge>) + Sync {
+    seeds.par_iter().map(|&seed| generator(seed)).collect()
+}
+
+/// Verifies the flows stored in each problem with `verify::verify_flow`.
+pub fn verify_each(problems: &[(VertexId, VertexId, Graph<FlowEdge>)]) -> Vec<Vec<Violation>> {
+    problems.par_iter().map(|&(source, sink, ref graph)| verify_flow(graph, source, sink)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use generators::random_network;
+    use BFS;
+
+    #[test]
+    fn test_parallel_batches() {
+        let mut problems = generate_each(&[1, 2, 3, 4], |seed| random_network(20, 60, 1..=9, seed));
+        assert_eq!(problems[2].2.n_edges(), random_network(20, 60, 1..=9, 3).2.n_edges());
+        let flows = max_flow_each(&mut problems, BFS);
+        for (flow, &(source, sink, ref graph)) in flows.iter().zip(&problems) {
+            let mut g = graph.clone();
+            g.reset_flow();
+            assert_eq!(*flow, g.max_flow(source, sink, BFS));
+        }
+        assert!(verify_each(&problems).iter().all(|violations| violations.is_empty()));
+
+        let (_, _, graph) = &problems[0];
+        let queries = [(0, 19), (19, 0), (3, 7), (5, 5)].map(|(u, v)| (VertexId(u), VertexId(v)));
+        let flows = max_flows(graph, &queries, BFS);
+        for (flow, &(source, sink)) in flows.iter().zip(&queries) {
+            let mut g = graph.clone();
+            g.reset_flow();
+            assert_eq!(*flow, g.max_flow(source, sink, BFS));
+        }
+        assert_eq!(flows[3], Err(FlowError::SourceIsSink {vertex: VertexId(5)}));
+    }
+}
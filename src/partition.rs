@@ -0,0 +1,182 @@
+use rand::Rng;
+use rand::seq::SliceRandom;
+use levels::UNREACHED;
+use super::{add_super_terminals, seeded_rng, FlowEdge, FlowGraph, Graph, VertexId, BFS};
+
+/// Two-way split of the vertexes of a graph, see `partition`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Partition {
+    /// Vertexes of the first side, in increasing order.
+    pub left: Vec<VertexId>,
+    /// Vertexes of the second side, in increasing order.
+    pub right: Vec<VertexId>,
+    /// Total capacity of the edges between the sides, in either direction.
+    pub cut_weight: i64
+}
+
+impl Partition {
+    /// Cut weight per pair of vertexes on opposite sides, the sparsity of the cut with unit demands between all pairs.
+    pub fn sparsity(&self) -> f64 {
+        self.cut_weight as f64 / (self.left.len() * self.right.len()) as f64
+    }
+}
+
+/// Splits the vertexes into two sides joined by edges of small total capacity, with sides of similar size. Directions
+/// are ignored, so an edge counts in the cut whichever side it leaves. Every trial picks a random number of random
+/// terminals for each side, computes a minimum cut between them, and the cut with the smallest weight per pair of
+/// vertexes on opposite sides is returned, which favors balanced cuts over cutting off a single vertex. At least one
+/// trial is made. Returns `None` for graphs with fewer than two vertexes, or if the edges at the terminals of every
+/// trial exceed the 32 bit capacity type.
+pub fn partition(graph: &Graph<FlowEdge>, trials: usize, seed: u64) -> Option<Partition> {
+    partition_with_rng(graph, trials, &mut seeded_rng(seed))
+}
+
+/// Same as `partition` but draws the random terminals from `rng`.
+pub fn partition_with_rng<R>(graph: &Graph<FlowEdge>, trials: usize, rng: &mut R) -> Option<Partition>
+    where R: Rng + ?Sized {
+    let n = graph.n_vertexes();
+    if n < 2 {
+        return None;
+    }
+    let undirected = undirected_edges(graph);
+    let mut vertexes = graph.vertexes().collect::<Vec<_>>();
+    let mut best: Option<Partition> = None;
+    for _ in 0..trials.max(1) {
+        vertexes.shuffle(rng);
+        let k = rng.gen_range(1..=(n / 4).max(1));
+        let mut edges = undirected.clone();
+        // The terminals of both sides are distinct, so only a total capacity beyond 32 bits fails, and the trial is
+        // skipped since a capped super arc could become the cut
+        let (source, sink) = match add_super_terminals(&mut edges, n, &vertexes[..k], &vertexes[k..2 * k]) {
+            Ok(terminals) => terminals,
+            Err(_) => continue
+        };
+        let all_vertexes = (0..n + 2).map(VertexId).collect::<Vec<_>>();
+        let mut network = Graph::flow_network(&all_vertexes, &edges);
+        // A sink which no flow reaches still has the vertexes the source does not reach on its side
+        let _ = network.max_flow(source, sink, BFS);
+        let levels = network.residual_levels(source);
+        let mut left_side = graph.vertexes().map(|v| levels[v] != UNREACHED).collect::<Vec<_>>();
+        // The sampled terminals stay on their sides even when they have no edges to carry flow
+        for (i, &v) in vertexes[..2 * k].iter().enumerate() {
+            left_side[v] = i < k;
+        }
+        keep_sparsest(&mut best, split(graph, &left_side));
+    }
+    best
+}
+
+/// Approximates the sparsest cut, the split of the vertexes with the smallest `Partition::sparsity`. Directions are
+/// ignored as in `partition`. A maximum flow is computed from the first vertex to every other vertex, and both the
+/// vertexes the source still reaches and those which do not reach the sink give a candidate cut, so the result is
+/// never sparser than the sparsest minimum cut separating the first vertex from another one, which includes every
+/// global minimum cut. The cut is not guaranteed to be the sparsest, but its sparsity is an upper bound of the
+/// smallest sparsity. Returns `None` for graphs with fewer than two vertexes.
+pub fn sparsest_cut(graph: &Graph<FlowEdge>) -> Option<Partition> {
+    let n = graph.n_vertexes();
+    if n < 2 {
+        return None;
+    }
+    let vertexes = graph.vertexes().collect::<Vec<_>>();
+    let network = Graph::flow_network(&vertexes, undirected_edges(graph));
+    let source = VertexId(0);
+    let mut best: Option<Partition> = None;
+    for sink in vertexes.into_iter().skip(1) {
+        let mut network = network.clone();
+        let _ = network.max_flow(source, sink, BFS);
+        let levels = network.residual_levels(source);
+        let distances = network.distances_to_sink(sink);
+        let source_side = graph.vertexes().map(|v| levels[v] != UNREACHED).collect::<Vec<_>>();
+        let sink_side = graph.vertexes().map(|v| distances[v] == UNREACHED).collect::<Vec<_>>();
+        keep_sparsest(&mut best, split(graph, &source_side));
+        keep_sparsest(&mut best, split(graph, &sink_side));
+    }
+    best
+}
+
+/// Returns both directions of every edge with capacity other than a self loop, without flow.
+fn undirected_edges(graph: &Graph<FlowEdge>) -> Vec<(VertexId, VertexId, FlowEdge)> {
+    let mut undirected: Vec<(VertexId, VertexId, FlowEdge)> = Vec::with_capacity(2 * graph.n_edges());
+    for (u, v, a) in graph.edges().filter(|&(u, v, a)| u != v && graph.arcs[a].capacity > 0) {
+        let capacity = graph.arcs[a].capacity;
+        undirected.push((u, v, FlowEdge{capacity, flow: 0}));
+        undirected.push((v, u, FlowEdge{capacity, flow: 0}));
+    }
+    undirected
+}
+
+/// Splits the vertexes by the side they are marked to be on, with the capacity of the edges between the sides.
+fn split(graph: &Graph<FlowEdge>, left_side: &[bool]) -> Partition {
+    let (left, right): (Vec<VertexId>, Vec<VertexId>) = graph.vertexes().partition(|&v| left_side[v]);
+    let cut_weight = graph.edges()
+        .filter(|&(u, v, _)| left_side[u] != left_side[v])
+        .map(|(_, _, a)| graph.arcs[a].capacity.max(0) as i64)
+        .sum::<i64>();
+    Partition {left, right, cut_weight}
+}
+
+/// Replaces the best partition by the candidate if it is sparser. Candidates with an empty side are not cuts.
+fn keep_sparsest(best: &mut Option<Partition>, candidate: Partition) {
+    if candidate.left.is_empty() || candidate.right.is_empty() {
+        return;
+    }
+    if best.as_ref().is_none_or(|best| candidate.sparsity() < best.sparsity()) {
+        *best = Some(candidate);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_partition() {
+        // Two cliques of five vertexes joined by a single light edge
+        let mut edges: Vec<(VertexId, VertexId, FlowEdge)> = Vec::new();
+        for offset in &[0, 5] {
+            for u in 0..5 {
+                for v in u + 1..5 {
+                    edges.push((VertexId(offset + u), VertexId(offset + v), FlowEdge{capacity: 10, flow: 0}));
+                }
+            }
+        }
+        edges.push((VertexId(4), VertexId(5), FlowEdge{capacity: 1, flow: 0}));
+        let vertexes = (0..10).map(VertexId).collect::<Vec<_>>();
+        let g = Graph::flow_network(&vertexes, &edges);
+        let mut result = partition(&g, 20, 0).unwrap();
+        if result.left.contains(&VertexId(5)) {
+            std::mem::swap(&mut result.left, &mut result.right);
+        }
+        let ids = |range: std::ops::Range<usize>| range.map(VertexId).collect::<Vec<_>>();
+        assert_eq!(result, Partition {left: ids(0..5), right: ids(5..10), cut_weight: 1});
+
+        let single = Graph::flow_network(&[VertexId(0)], &[] as &[(VertexId, VertexId, FlowEdge)]);
+        assert_eq!(partition(&single, 5, 0), None);
+    }
+
+    #[test]
+    fn test_sparsest_cut() {
+        // A heavy triangle joined to a heavy square by two light edges, and a leaf hanging from the square, whose edge
+        // is the lightest cut but cuts off a single vertex
+        let edges = [(0, 1, 10), (1, 2, 10), (2, 0, 10), (3, 4, 10), (4, 5, 10), (5, 6, 10), (6, 3, 10), (3, 5, 10),
+                     (2, 3, 2), (1, 6, 2), (6, 7, 2)]
+            .iter()
+            .map(|&(u, v, capacity)| (VertexId(u), VertexId(v), FlowEdge{capacity, flow: 0}))
+            .collect::<Vec<_>>();
+        let vertexes = (0..8).map(VertexId).collect::<Vec<_>>();
+        let g = Graph::flow_network(&vertexes, &edges);
+        let mut cut = sparsest_cut(&g).unwrap();
+        if cut.left.contains(&VertexId(3)) {
+            std::mem::swap(&mut cut.left, &mut cut.right);
+        }
+        let ids = |range: std::ops::Range<usize>| range.map(VertexId).collect::<Vec<_>>();
+        assert_eq!(cut, Partition {left: ids(0..3), right: ids(3..8), cut_weight: 4});
+        assert_eq!(cut.sparsity(), 4.0 / 15.0);
+
+        // Disconnected graphs have cuts of no weight
+        let edges = [(VertexId(0), VertexId(1), FlowEdge{capacity: 3, flow: 0})];
+        let g = Graph::flow_network(&vertexes[..3], edges);
+        assert_eq!(sparsest_cut(&g).map(|cut| cut.cut_weight), Some(0));
+        assert_eq!(sparsest_cut(&Graph::flow_network(&vertexes[..1], &edges[..0])), None);
+    }
+}
@@ -0,0 +1,302 @@
+use alloc::collections::{BTreeSet, BinaryHeap};
+use alloc::vec::Vec;
+use core::cmp::Reverse;
+use super::{ArcId, Error, FlowEdge, Graph, Property, VertexId};
+
+/// Path found by a shortest path search, as the arcs it takes and the vertexes it visits from source to sink.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WeightedPath {
+    /// Sum of the weights of the arcs.
+    pub cost: u64,
+    pub vertexes: Vec<VertexId>,
+    pub arcs: Vec<ArcId>
+}
+
+impl<E: Property> Graph<E> {
+    /// Returns up to `k` loopless paths from `source` to `sink` in order of increasing cost, by Yen's algorithm. The
+    /// weight of an arc is given by `weight`, which returns `None` for arcs that can not be used, such as the reverse
+    /// arcs of a flow network. Paths of equal cost are ordered by their arcs, so the result is deterministic.
+    pub fn k_shortest_paths<W>(&self, source: VertexId, sink: VertexId, k: usize, weight: W) -> Vec<WeightedPath>
+        where W: Fn(ArcId) -> Option<u64> {
+        assert!(source.index() < self.n_vertexes() && sink.index() < self.n_vertexes(),
+                "Terminals must be vertexes of the graph");
+        let mut removed_vertexes = vec![false; self.n_vertexes()];
+        let mut removed_arcs = vec![false; self.arcs.len()];
+        let mut paths: Vec<WeightedPath> = Vec::new();
+        match self.dijkstra(source, sink, &weight, &removed_vertexes, &removed_arcs) {
+            Some(path) if k > 0 => paths.push(path),
+            _ => return paths
+        }
+        // Candidates are ordered by cost and then by arcs, which also drops duplicates
+        let mut candidates: BTreeSet<(u64, Vec<ArcId>, Vec<VertexId>)> = BTreeSet::new();
+        while paths.len() < k {
+            let last = paths.len() - 1;
+            let mut root_cost = 0;
+            for i in 0..paths[last].arcs.len() {
+                let root_arcs = &paths[last].arcs[..i];
+                let spur = paths[last].vertexes[i];
+                // Deviating from the root at the spur vertex must avoid every path found so far which shares the root
+                for path in paths.iter().filter(|p| p.arcs.len() > i && &p.arcs[..i] == root_arcs) {
+                    removed_arcs[path.arcs[i]] = true;
+                }
+                for &v in &paths[last].vertexes[..i] {
+                    removed_vertexes[v] = true;
+                }
+                if let Some(spur_path) = self.dijkstra(spur, sink, &weight, &removed_vertexes, &removed_arcs) {
+                    let mut arcs = root_arcs.to_vec();
+                    arcs.extend(spur_path.arcs);
+                    let mut vertexes = paths[last].vertexes[..i].to_vec();
+                    vertexes.extend(spur_path.vertexes);
+                    candidates.insert((root_cost + spur_path.cost, arcs, vertexes));
+                }
+                removed_arcs.iter_mut().for_each(|r| *r = false);
+                removed_vertexes.iter_mut().for_each(|r| *r = false);
+                root_cost += weight(paths[last].arcs[i]).unwrap_or(0);
+            }
+            // A candidate equal to a found path is pushed again when its root is found by another spur vertex
+            let next = loop {
+                match candidates.pop_first() {
+                    Some((cost, arcs, vertexes)) => if paths.iter().all(|p| p.arcs != arcs) {
+                        break Some(WeightedPath {cost, vertexes, arcs});
+                    },
+                    None => break None
+                }
+            };
+            match next {
+                Some(path) => paths.push(path),
+                None => break
+            }
+        }
+        paths
+    }
+
+    /// Returns the dense matrix of arc weights, where entry `[u][v]` is the smallest weight of an arc from `u` to `v`,
+    /// 0 on the diagonal unless a self loop is cheaper, and `None` without an arc. The weight of an arc is given by
+    /// `weight`, which returns `None` for arcs that can not be used. This is the input of `floyd_warshall`.
+    pub fn weight_matrix<W>(&self, weight: W) -> Vec<Vec<Option<i64>>>
+        where W: Fn(ArcId) -> Option<i64> {
+        let n = self.n_vertexes();
+        let mut matrix = vec![vec![None; n]; n];
+        for (u, row) in self.vertexes().zip(matrix.iter_mut()) {
+            row[u] = Some(0);
+            for a in self.out_arcs(u) {
+                if let Some(w) = weight(a) {
+                    let entry = &mut row[self.head(a)];
+                    *entry = Some(entry.map_or(w, |e| e.min(w)));
+                }
+            }
+        }
+        matrix
+    }
+
+    /// Returns a cheapest path from `source` to `sink` by Dijkstra's algorithm, which neither visits removed vertexes
+    /// nor takes removed arcs.
+    fn dijkstra<W>(&self,
+                   source: VertexId,
+                   sink: VertexId,
+                   weight: &W,
+                   removed_vertexes: &[bool],
+                   removed_arcs: &[bool]) -> Option<WeightedPath>
+        where W: Fn(ArcId) -> Option<u64> {
+        let mut distances = vec![u64::MAX; self.n_vertexes()];
+        let mut parents: Vec<(VertexId, ArcId)> = vec![(VertexId(0), 0); self.n_vertexes()];
+        let mut heap = BinaryHeap::new();
+        distances[source] = 0;
+        heap.push(Reverse((0, source)));
+        while let Some(Reverse((distance, u))) = heap.pop() {
+            if u == sink {
+                break;
+            }
+            if distance > distances[u] {
+                continue;
+            }
+            for a in self.out_arcs(u) {
+                let v = self.head(a);
+                if removed_arcs[a] || removed_vertexes[v] {
+                    continue;
+                }
+                if let Some(w) = weight(a) {
+                    let next = distance.saturating_add(w);
+                    if next < distances[v] {
+                        distances[v] = next;
+                        parents[v] = (u, a);
+                        heap.push(Reverse((next, v)));
+                    }
+                }
+            }
+        }
+        if distances[sink] == u64::MAX {
+            return None;
+        }
+        let mut arcs: Vec<ArcId> = Vec::new();
+        let mut vertexes = vec![sink];
+        let mut node = sink;
+        while node != source {
+            // The parent vertex is kept because arcs are not paired with reverse arcs outside of flow networks
+            let (parent, arc) = parents[node];
+            arcs.push(arc);
+            node = parent;
+            vertexes.push(node);
+        }
+        arcs.reverse();
+        vertexes.reverse();
+        Some(WeightedPath {cost: distances[sink], vertexes, arcs})
+    }
+}
+
+impl Graph<FlowEdge> {
+    /// Returns a closed walk which takes every edge of the graph exactly once, as the arcs of the edges in the order
+    /// they are taken, or `None` if there is none. A circuit exists when every vertex has as many edges coming in as
+    /// going out and the edges are connected. A graph without edges has an empty circuit.
+    pub fn eulerian_circuit(&self) -> Option<Vec<ArcId>> {
+        self.eulerian_circuit_of(|_| true)
+    }
+
+    /// Same as `eulerian_circuit` but only for the edges whose arc is accepted by `include`, such as the edges with
+    /// flow of a circulation, which balances the number of edges at every vertex when all flows are equal.
+    pub fn eulerian_circuit_of<F>(&self, include: F) -> Option<Vec<ArcId>>
+        where F: Fn(ArcId) -> bool {
+        let mut balance = vec![0i64; self.n_vertexes()];
+        let mut n_included = 0;
+        let mut start = None;
+        for (u, v, _) in self.edges().filter(|&(_, _, a)| include(a)) {
+            balance[u] += 1;
+            balance[v] -= 1;
+            n_included += 1;
+            start.get_or_insert(u);
+        }
+        let start = match start {
+            Some(start) if balance.iter().all(|&b| b == 0) => start,
+            Some(_) => return None,
+            None => return Some(Vec::new())
+        };
+        // Hierholzer's algorithm: walk unused edges until stuck, then back up and splice in the detours
+        let mut next = vec![0; self.n_vertexes()];
+        let mut stack: Vec<(VertexId, Option<ArcId>)> = vec![(start, None)];
+        let mut circuit: Vec<ArcId> = Vec::with_capacity(n_included);
+        while let Some(&(u, entered_by)) = stack.last() {
+            // Reverse arcs are at odd indexes
+            let arcs = &self.adjacency[u];
+            while next[u] < arcs.len() && !(arcs[next[u]] & 1 == 0 && include(arcs[next[u]] as ArcId)) {
+                next[u] += 1;
+            }
+            if next[u] < arcs.len() {
+                let a = arcs[next[u]] as ArcId;
+                next[u] += 1;
+                stack.push((self.head(a), Some(a)));
+            } else {
+                stack.pop();
+                circuit.extend(entered_by);
+            }
+        }
+        if circuit.len() < n_included {
+            // Some edges are not connected to the start
+            return None;
+        }
+        circuit.reverse();
+        Some(circuit)
+    }
+}
+
+/// Computes the shortest path distances between all pairs of vertexes from a dense matrix of arc weights, such as
+/// `Graph::weight_matrix`, by the Floyd-Warshall algorithm in O(n^3). Entry `[u][v]` of the result is the distance
+/// from `u` to `v`, or `None` if `v` can not be reached. Weights may be negative, which is needed for the reduced costs
+/// of min cost flows. Fails if the matrix is not square or has a cycle of negative weight.
+pub fn floyd_warshall(matrix: &[Vec<Option<i64>>]) -> Result<Vec<Vec<Option<i64>>>, Error> {
+    let n = matrix.len();
+    if let Some(u) = matrix.iter().position(|row| row.len() != n) {
+        return Err(Error::Construction(format!("Row {} of the weight matrix has {} entries, expected {}",
+                                               u, matrix[u].len(), n)));
+    }
+    let mut distances = matrix.to_vec();
+    for k in 0..n {
+        // Row k only gets shorter through k itself, which needs a negative cycle, so a copy is as good as the row
+        let from_k = distances[k].clone();
+        for row in distances.iter_mut() {
+            let Some(to_k) = row[k] else { continue };
+            for (entry, from_k) in row.iter_mut().zip(&from_k) {
+                if let Some(from_k) = *from_k {
+                    let through_k = to_k.saturating_add(from_k);
+                    if entry.is_none_or(|d| through_k < d) {
+                        *entry = Some(through_k);
+                    }
+                }
+            }
+        }
+    }
+    match (0..n).find(|&v| distances[v][v].is_some_and(|d| d < 0)) {
+        Some(v) => Err(Error::Construction(format!("Vertex {} is on a cycle of negative weight", v))),
+        None => Ok(distances)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(vertexes: &[usize]) -> Vec<VertexId> {
+        vertexes.iter().map(|&v| VertexId(v)).collect()
+    }
+
+    fn graph<E: Copy + Default>(n_vertexes: usize, edges: &[(usize, usize, E)]) -> Graph<E> {
+        let vertexes = (0..n_vertexes).map(VertexId).collect::<Vec<_>>();
+        Graph::new(&vertexes, edges.iter().map(|&(u, v, e)| (VertexId(u), VertexId(v), e)))
+    }
+
+    fn network(n_vertexes: usize, edges: &[(usize, usize)]) -> Graph<FlowEdge> {
+        let vertexes = (0..n_vertexes).map(VertexId).collect::<Vec<_>>();
+        let edges = edges.iter().map(|&(u, v)| (VertexId(u), VertexId(v), FlowEdge{capacity: 1, flow: 0}));
+        Graph::flow_network(&vertexes, edges)
+    }
+
+    #[test]
+    fn test_k_shortest_paths() {
+        let edges: Vec<(usize, usize, u64)> = vec![(0, 1, 3), (0, 2, 2), (1, 3, 4), (2, 1, 1), (2, 3, 2),
+                                                   (2, 4, 3), (3, 4, 2), (3, 5, 1), (4, 5, 2)];
+        let g = graph(6, &edges);
+        let (source, sink) = (VertexId(0), VertexId(5));
+        let paths = g.k_shortest_paths(source, sink, 10, |a| Some(g.arcs[a]));
+        let found = paths.iter().map(|p| (p.cost, p.vertexes.iter().map(|v| v.index()).collect())).collect::<Vec<_>>();
+        assert_eq!(found, [(5, vec![0, 2, 3, 5]), (7, vec![0, 2, 4, 5]), (8, vec![0, 1, 3, 5]),
+                           (8, vec![0, 2, 1, 3, 5]), (8, vec![0, 2, 3, 4, 5]), (11, vec![0, 1, 3, 4, 5]),
+                           (11, vec![0, 2, 1, 3, 4, 5])]);
+        assert_eq!(paths[0].arcs, [1, 4, 7]);
+        assert_eq!(g.k_shortest_paths(source, sink, 2, |a| Some(g.arcs[a])).len(), 2);
+        assert_eq!(g.k_shortest_paths(sink, source, 3, |a| Some(g.arcs[a])), []);
+        assert_eq!(g.k_shortest_paths(source, sink, 3, |a| if a == 7 { None } else { Some(1) })[0].vertexes,
+                   ids(&[0, 2, 4, 5]));
+    }
+
+    #[test]
+    fn test_eulerian_circuit() {
+        let g = network(5, &[(0, 1), (1, 2), (2, 0), (0, 3), (3, 0), (2, 2)]);
+        let circuit = g.eulerian_circuit().unwrap();
+        assert_eq!(circuit.iter().map(|&a| (g.tail(a).index(), g.head(a).index())).collect::<Vec<_>>(),
+                   [(0, 1), (1, 2), (2, 2), (2, 0), (0, 3), (3, 0)]);
+        assert_eq!(g.eulerian_circuit_of(|a| a < 6).map(|c| c.len()), Some(3));
+        assert_eq!(g.eulerian_circuit_of(|a| a < 4), None);
+        assert_eq!(g.eulerian_circuit_of(|a| a == 10), Some(vec![10]));
+        assert_eq!(g.eulerian_circuit_of(|_| false), Some(vec![]));
+
+        let disconnected = network(4, &[(0, 1), (1, 0), (2, 3), (3, 2)]);
+        assert_eq!(disconnected.eulerian_circuit(), None);
+    }
+
+    #[test]
+    fn test_floyd_warshall() {
+        let edges: Vec<(usize, usize, i64)> = vec![(0, 1, 4), (0, 2, 1), (2, 1, 2), (1, 3, 1), (2, 3, 5),
+                                                   (3, 0, -3), (0, 1, 3)];
+        let g = graph(5, &edges);
+        let matrix = g.weight_matrix(|a| Some(g.arcs[a]));
+        assert_eq!(matrix[0], [Some(0), Some(3), Some(1), None, None]);
+        let distances = floyd_warshall(&matrix).unwrap();
+        assert_eq!(distances[0], [Some(0), Some(3), Some(1), Some(4), None]);
+        assert_eq!(distances[3], [Some(-3), Some(0), Some(-2), Some(0), None]);
+        assert_eq!(distances[4], [None, None, None, None, Some(0)]);
+
+        let g = graph(5, &edges.iter().map(|&(u, v, w)| (u, v, w - 1)).collect::<Vec<_>>());
+        assert_eq!(floyd_warshall(&g.weight_matrix(|a| Some(g.arcs[a]))).unwrap_err(),
+                   Error::Construction("Vertex 0 is on a cycle of negative weight".to_string()));
+        assert!(floyd_warshall(&[vec![Some(0), None], vec![Some(0)]]).is_err());
+    }
+}
@@ -0,0 +1,143 @@
+use std::convert::Infallible;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Cursor};
+use pyo3::exceptions::{PyIOError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyInt;
+use super::{detect_format_reader, flow_from_reader, DuplicatePolicy, FlowEdge, FlowError, FlowGraph, Format, Graph,
+            SelfLoopPolicy, VertexId, BFS, BIDIRECTIONAL, DFS};
+
+/// Vertexes are plain integers in Python.
+impl<'py> FromPyObject<'py> for VertexId {
+    fn extract_bound(object: &Bound<'py, PyAny>) -> PyResult<VertexId> {
+        object.extract::<usize>().map(VertexId)
+    }
+}
+
+impl<'py> IntoPyObject<'py> for VertexId {
+    type Target = PyInt;
+    type Output = Bound<'py, PyInt>;
+    type Error = Infallible;
+
+    fn into_pyobject(self, py: Python<'py>) -> Result<Bound<'py, PyInt>, Infallible> {
+        self.0.into_pyobject(py)
+    }
+}
+
+/// Flow network exposed to Python as `graph.Graph`.
+#[pyclass(name = "Graph")]
+pub struct PyGraph {
+    graph: Graph<FlowEdge>
+}
+
+#[pymethods]
+impl PyGraph {
+    /// Builds a flow network on `n_vertexes` vertexes from a list of `(from, to, capacity)` edges.
+    #[new]
+    fn new(n_vertexes: usize, edges: Vec<(VertexId, VertexId, i32)>) -> PyResult<PyGraph> {
+        let vertexes = (0..n_vertexes).map(VertexId).collect::<Vec<_>>();
+        let edges = edges.into_iter().map(|(u, v, capacity)| (u, v, FlowEdge{capacity, flow: 0})).collect::<Vec<_>>();
+        let graph = Graph::try_flow_network(&vertexes, &edges, DuplicatePolicy::Parallel, SelfLoopPolicy::Reject)
+            .map_err(|e| PyValueError::new_err(e.to_string()))?;
+        Ok(PyGraph {graph})
+    }
+
+    #[getter]
+    fn n_vertexes(&self) -> usize {
+        self.graph.n_vertexes()
+    }
+
+    #[getter]
+    fn n_edges(&self) -> usize {
+        self.graph.n_edges()
+    }
+
+    /// Returns the edges as `(from, to, capacity, flow)` in the order they were added.
+    fn edges(&self) -> Vec<(VertexId, VertexId, i32, i32)> {
+        self.graph.edges().map(|(u, v, a)| (u, v, self.graph.arcs[a].capacity, self.graph.arcs[a].flow)).collect()
+    }
+
+    /// Computes a maximum flow from `source` to `sink` with augmenting paths found by `"bfs"`, `"dfs"` or
+    /// `"bidirectional"`, and returns its value. The flows are kept on the edges, call `reset_flow` before solving
+    /// again.
+    #[pyo3(signature = (source, sink, algorithm = "bfs"))]
+    fn max_flow(&mut self, source: VertexId, sink: VertexId, algorithm: &str) -> PyResult<i64> {
+        let search = match algorithm {
+            "bfs" => BFS,
+            "dfs" => DFS,
+            "bidirectional" => BIDIRECTIONAL,
+            _ => return Err(PyValueError::new_err(format!("Unknown algorithm {}, use bfs, dfs or bidirectional",
+                                                          algorithm)))
+        };
+        match self.graph.max_flow(source, sink, search) {
+            Ok(flow) => Ok(flow),
+            Err(FlowError::UnreachableSink {..}) => Ok(0),
+            Err(e) => Err(PyValueError::new_err(e.to_string()))
+        }
+    }
+
+    /// Returns the `(from, to)` edges of a minimum cut, for a graph solved with `max_flow` from `source`.
+    fn min_cut(&self, source: VertexId) -> PyResult<Vec<(VertexId, VertexId)>> {
+        if source.index() >= self.graph.n_vertexes() {
+            return Err(PyValueError::new_err(format!("Vertex {} is not in the graph", source)));
+        }
+        Ok(self.graph.min_cut(source).into_iter().map(|a| (self.graph.tail(a), self.graph.head(a))).collect())
+    }
+
+    /// Removes the flow from every edge.
+    fn reset_flow(&mut self) {
+        self.graph.reset_flow();
+    }
+}
+
+fn parse_format(format: Option<&str>) -> PyResult<Option<Format>> {
+    format.map(|f| match f {
+        "dimacs" => Ok(Format::Dicaps),
+        "txt" => Ok(Format::Txt),
+        "dot" => Ok(Format::Dot),
+        "json" => Ok(Format::Json),
+        "csv" => Ok(Format::Csv),
+        _ => Err(PyValueError::new_err(format!("Unknown format {}, use dimacs, txt, dot, json or csv", f)))
+    }).transpose()
+}
+
+/// Parses a network with the parsers of the crate and raises their errors as a `ValueError`.
+fn parse<R: BufRead>(mut reader: R, format: Option<Format>) -> PyResult<(VertexId, VertexId, PyGraph)> {
+    let format = match format {
+        Some(format) => format,
+        None => {
+            let mut contents = Vec::new();
+            reader.read_to_end(&mut contents)?;
+            let format = detect_format_reader(&contents[..])
+                .ok_or_else(|| PyValueError::new_err("Could not detect the format of the network"))?;
+            return parse(Cursor::new(contents), Some(format));
+        }
+    };
+    let (source, sink, graph) = flow_from_reader(reader, format).map_err(|e| PyValueError::new_err(e.to_string()))?;
+    Ok((source, sink, PyGraph {graph}))
+}
+
+/// Reads a network file as `(source, sink, Graph)`, detecting its format when `format` is omitted.
+#[pyfunction]
+#[pyo3(signature = (path, format = None))]
+fn read_network(path: &str, format: Option<&str>) -> PyResult<(VertexId, VertexId, PyGraph)> {
+    let format = parse_format(format)?;
+    let f = File::open(path).map_err(|e| PyIOError::new_err(format!("Could not open {}: {}", path, e)))?;
+    parse(BufReader::new(f), format)
+}
+
+/// Parses a network from a string as `(source, sink, Graph)`, detecting its format when `format` is omitted.
+#[pyfunction]
+#[pyo3(signature = (text, format = None))]
+fn parse_network(text: &str, format: Option<&str>) -> PyResult<(VertexId, VertexId, PyGraph)> {
+    parse(text.as_bytes(), parse_format(format)?)
+}
+
+/// Python module `graph`.
+#[pymodule]
+fn graph(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyGraph>()?;
+    m.add_function(wrap_pyfunction!(python::read_network, m)?)?;
+    m.add_function(wrap_pyfunction!(python::parse_network, m)?)?;
+    Ok(())
+}
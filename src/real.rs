@@ -0,0 +1,330 @@
+use alloc::collections::VecDeque;
+#[cfg(feature = "std")]
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+use super::{ArcId, Error, FlowError, Graph, VertexId};
+
+/// Default tolerance of `Graph::real_max_flow`, relative to the largest capacity.
+pub const DEFAULT_TOLERANCE: f64 = 1e-9;
+
+/// Flows within this distance of an integer count as integral in `Graph::round_flow`.
+#[cfg(feature = "std")]
+pub const INTEGRAL_TOLERANCE: f64 = 1e-6;
+
+/// Marks vertexes which the cycle search has not reached.
+#[cfg(feature = "std")]
+const UNVISITED: usize = usize::MAX;
+
+/// Edge property of a flow network with real capacities, such as measured bandwidths. The flows are stored
+/// antisymmetrically like those of `FlowEdge`.
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct RealEdge {
+    pub capacity: f64,
+    pub flow: f64
+}
+
+impl RealEdge {
+    /// Remaining capacity of the arc.
+    pub fn residual(&self) -> f64 {
+        self.capacity - self.flow
+    }
+}
+
+impl Graph<RealEdge> {
+    /// Builds a flow network with real capacities, pairing every edge with a reverse arc without capacity like
+    /// `Graph::flow_network`. The flows of the edges are ignored. Fails if a capacity is negative, infinite or NaN, or
+    /// if the graph is invalid, see `try_new`.
+    pub fn real_flow_network<I>(vertex_list: &[VertexId], edge_list: I) -> Result<Graph<RealEdge>, Error>
+        where I: IntoIterator, I::Item: Borrow<(VertexId, VertexId, RealEdge)> {
+        let edges = edge_list.into_iter();
+        let mut graph = Graph::with_vertexes(vertex_list, 2 * edges.size_hint().0)?;
+        for edge in edges {
+            let &(u, v, RealEdge {capacity, ..}) = edge.borrow();
+            if !capacity.is_finite() || capacity < 0.0 {
+                return Err(Error::Construction(format!("Edge ({}, {}) has invalid capacity {}", u, v, capacity)));
+            }
+            graph.check_edge(u, v, 2)?;
+            graph.add_arc(u, v, RealEdge {capacity, flow: 0.0});
+            graph.add_arc(v, u, RealEdge::default());
+        }
+        graph.n_edges = graph.arcs.len() / 2;
+        Ok(graph)
+    }
+
+    /// Computes a maximum flow from source to sink by shortest augmenting paths and stores it in the arcs, replacing
+    /// any flow they had. Rounding leaves tiny residual capacities on saturated arcs, so an arc only counts as residual
+    /// while its residual capacity exceeds `tolerance` times the largest capacity, with tolerances below `f64::EPSILON`
+    /// raised to it. Every augmentation then saturates an arc, and the computation also stops after arcs times
+    /// vertexes augmentations, a bound shortest augmenting paths never reach with exact arithmetic, so rounding can
+    /// not make it run forever. The flow is feasible and conserved up to rounding, and maximum up to the tolerance.
+    /// Fails like `FlowGraph::max_flow`.
+    pub fn real_max_flow(&mut self, source: VertexId, sink: VertexId, tolerance: f64) -> Result<f64, FlowError> {
+        self.check_terminals(source, sink)?;
+        if !self.arcs.len().is_multiple_of(2) || self.arcs.iter().skip(1).step_by(2).any(|arc| arc.capacity != 0.0) {
+            return Err(FlowError::MissingResidualArcs);
+        }
+        let largest = self.arcs.iter().map(|arc| arc.capacity).fold(0.0, f64::max);
+        let epsilon = tolerance.max(f64::EPSILON) * largest;
+        debug!("Real max flow from {} to {} with epsilon {}", source, sink, epsilon);
+        for arc in &mut self.arcs {
+            arc.flow = 0.0;
+        }
+        let max_augmentations = self.arcs.len() * self.n_vertexes();
+        let mut parent_arcs: Vec<Option<ArcId>> = vec![None; self.n_vertexes()];
+        let mut queue: VecDeque<VertexId> = VecDeque::new();
+        let mut augmentations = 0;
+        while augmentations < max_augmentations {
+            parent_arcs.iter_mut().for_each(|p| *p = None);
+            queue.clear();
+            queue.push_back(source);
+            'search: while let Some(u) = queue.pop_front() {
+                for a in self.out_arcs(u) {
+                    let v = self.head(a);
+                    if v != source && parent_arcs[v].is_none() && self.arcs[a].residual() > epsilon {
+                        parent_arcs[v] = Some(a);
+                        if v == sink {
+                            break 'search;
+                        }
+                        queue.push_back(v);
+                    }
+                }
+            }
+            if parent_arcs[sink].is_none() {
+                break;
+            }
+            let path = self.parent_path(&parent_arcs, sink);
+            let bottleneck = path.iter().map(|&a| self.arcs[a].residual()).fold(f64::INFINITY, f64::min);
+            for &a in &path {
+                self.arcs[a].flow += bottleneck;
+                self.arcs[a ^ 1].flow -= bottleneck;
+            }
+            augmentations += 1;
+            trace!("Real augmentation {} along {} arcs with bottleneck {}", augmentations, path.len(), bottleneck);
+        }
+        if augmentations == 0 {
+            return Err(FlowError::UnreachableSink {source, sink});
+        }
+        Ok(self.out_arcs(source).filter(|a| a % 2 == 0).map(|a| self.arcs[a].flow).sum())
+    }
+
+    /// Turns a feasible fractional flow from source to sink into an integral flow of no smaller value, for networks
+    /// with integral capacities. Flows within `INTEGRAL_TOLERANCE` of an integer are snapped to it, then cycles of
+    /// edges with fractional flow are canceled one at a time, with a virtual edge from the sink back to the source
+    /// carrying the flow value so that paths between the terminals close a cycle too. Flow is pushed around a cycle in
+    /// the direction which does not decrease the value until one of its edges reaches an integer, which it keeps, so
+    /// every edge stays between the integers around its flow and within its capacity. Returns the value of the integral
+    /// flow, the flow leaving the source minus the flow entering it. Fails if a capacity is not integral or the flow is
+    /// not feasible. Rounding needs the `std` feature.
+    #[cfg(feature = "std")]
+    pub fn round_flow(&mut self, source: VertexId, sink: VertexId) -> Result<i64, Error> {
+        self.check_terminals(source, sink)?;
+        if !self.arcs.len().is_multiple_of(2) || self.arcs.iter().skip(1).step_by(2).any(|arc| arc.capacity != 0.0) {
+            return Err(Error::Infeasible(FlowError::MissingResidualArcs));
+        }
+        let snap = |flow: f64| if (flow - flow.round()).abs() <= INTEGRAL_TOLERANCE { flow.round() } else { flow };
+        let tails = self.tails();
+        for a in (0..self.arcs.len()).step_by(2) {
+            let RealEdge {capacity, flow} = self.arcs[a];
+            if capacity.fract() != 0.0 || !(-INTEGRAL_TOLERANCE..=capacity + INTEGRAL_TOLERANCE).contains(&flow) {
+                return Err(Error::Construction(format!("Edge ({}, {}) with flow {} and capacity {} is not part of a \
+                                                        feasible flow with integral capacities",
+                                                       tails[a], self.head(a), flow, capacity)));
+            }
+            self.set_flow(a, snap(flow).clamp(0.0, capacity));
+        }
+        // Edge m is the virtual edge from the sink to the source
+        let m = self.arcs.len() / 2;
+        // The reverse arcs leaving the source carry the negated flow entering it
+        let mut value = snap(self.out_arcs(source).map(|a| self.arcs[a].flow).sum::<f64>());
+        let mut canceled = 0;
+        loop {
+            let mut support: Vec<Vec<(usize, VertexId)>> = vec![Vec::new(); self.n_vertexes()];
+            for a in (0..self.arcs.len()).step_by(2).filter(|&a| self.arcs[a].flow.fract() != 0.0) {
+                support[tails[a]].push((a / 2, self.head(a)));
+                support[self.head(a)].push((a / 2, tails[a]));
+            }
+            if value.fract() != 0.0 {
+                support[sink].push((m, source));
+                support[source].push((m, sink));
+            }
+            if support.iter().all(Vec::is_empty) {
+                break;
+            }
+            let cycle = find_cycle(&support)
+                .ok_or_else(|| Error::Construction("Fractional flow is not conserved".to_string()))?;
+            // Every step goes along an edge from a vertex, in the direction of the edge or against it
+            let mut steps = cycle.iter()
+                .map(|&(e, from)| if e == m {
+                    (e, from == sink, value)
+                } else {
+                    (e, tails[2 * e] == from, self.arcs[2 * e].flow)
+                })
+                .collect::<Vec<_>>();
+            if steps.iter().any(|&(e, forward, _)| e == m && !forward) {
+                steps.iter_mut().for_each(|step| step.1 = !step.1);
+            }
+            let delta = steps.iter()
+                .map(|&(_, forward, flow)| if forward { flow.ceil() - flow } else { flow - flow.floor() })
+                .fold(f64::INFINITY, f64::min);
+            for &(e, forward, flow) in &steps {
+                let flow = snap(flow + if forward { delta } else { -delta });
+                if e == m {
+                    value = flow;
+                } else {
+                    self.set_flow(2 * e, flow);
+                }
+            }
+            canceled += 1;
+        }
+        debug!("Rounded the flow to value {} by canceling {} cycles", value, canceled);
+        Ok(value as i64)
+    }
+
+    /// Sets the flow of the edge of arc `a` and of its reverse arc.
+    #[cfg(feature = "std")]
+    fn set_flow(&mut self, a: ArcId, flow: f64) {
+        self.arcs[a].flow = flow;
+        self.arcs[a ^ 1].flow = -flow;
+    }
+
+    /// Returns the arcs of the path to `v` found by a search, from the source on.
+    fn parent_path(&self, parent_arcs: &[Option<ArcId>], mut v: VertexId) -> Vec<ArcId> {
+        let mut path: Vec<ArcId> = Vec::new();
+        while let Some(a) = parent_arcs[v] {
+            path.push(a);
+            v = self.head(a ^ 1);
+        }
+        path.reverse();
+        path
+    }
+}
+
+/// Returns a cycle of an undirected graph given by the `(edge, other endpoint)` pairs of every vertex, as the edges of
+/// the cycle with the vertex each is left from, or `None` if the graph is a forest.
+#[cfg(feature = "std")]
+fn find_cycle(adjacency: &[Vec<(usize, VertexId)>]) -> Option<Vec<(usize, VertexId)>> {
+    let mut position = vec![UNVISITED; adjacency.len()];
+    let mut finished = vec![false; adjacency.len()];
+    // Depth first search with an explicit stack of (vertex, edge it was entered by, next neighbor to look at)
+    let mut stack: Vec<(VertexId, usize, usize)> = Vec::new();
+    for root in (0..adjacency.len()).map(VertexId) {
+        if finished[root] || adjacency[root].is_empty() {
+            continue;
+        }
+        position[root] = 0;
+        stack.push((root, UNVISITED, 0));
+        while let Some(&mut (u, entered_by, ref mut next)) = stack.last_mut() {
+            match adjacency[u].get(*next) {
+                Some(&(e, w)) => {
+                    *next += 1;
+                    if e == entered_by || finished[w] {
+                        continue;
+                    }
+                    if position[w] == UNVISITED {
+                        position[w] = stack.len();
+                        stack.push((w, e, 0));
+                    } else {
+                        // A back edge to a vertex on the stack closes the cycle through the tree edges below it
+                        let mut cycle = stack.windows(2).skip(position[w]).map(|pair| (pair[1].1, pair[0].0))
+                            .collect::<Vec<_>>();
+                        cycle.push((e, u));
+                        return Some(cycle);
+                    }
+                },
+                None => {
+                    finished[u] = true;
+                    stack.pop();
+                }
+            }
+        }
+    }
+    None
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use generators::random_network;
+    use verify::certify;
+    use {FlowGraph, BFS, DFS};
+
+    fn network(n_vertexes: usize, edges: &[(usize, usize, f64)]) -> Graph<RealEdge> {
+        let vertexes = (0..n_vertexes).map(VertexId).collect::<Vec<_>>();
+        let edges = edges.iter().map(|&(u, v, capacity)| (VertexId(u), VertexId(v), RealEdge {capacity, flow: 0.0}));
+        Graph::real_flow_network(&vertexes, edges).unwrap()
+    }
+
+    #[test]
+    fn test_real_max_flow() {
+        let mut g = network(4, &[(0, 1, 0.1), (0, 2, 0.2), (1, 3, 0.3), (2, 3, 0.15), (1, 2, 0.7)]);
+        let flow = g.real_max_flow(VertexId(0), VertexId(3), DEFAULT_TOLERANCE).unwrap();
+        assert!((flow - 0.25).abs() < 1e-12, "{}", flow);
+        assert_eq!(network(3, &[(0, 1, 1.0)]).real_max_flow(VertexId(0), VertexId(2), DEFAULT_TOLERANCE),
+                   Err(FlowError::UnreachableSink {source: VertexId(0), sink: VertexId(2)}));
+        assert!(Graph::real_flow_network(&[VertexId(0), VertexId(1)],
+                                         [(VertexId(0), VertexId(1), RealEdge {capacity: f64::NAN, flow: 0.0})])
+            .is_err());
+
+        // Scaled integer networks give the scaled integer flow
+        for seed in 0..30 {
+            let (source, sink, mut g) = random_network(15, 50, 1..=20, seed);
+            let expected = g.max_flow(source, sink, BFS).unwrap_or(0) as f64 / 10.0;
+            let edges = g.edges()
+                .map(|(u, v, a)| (u.index(), v.index(), g.arcs[a].capacity as f64 / 10.0))
+                .collect::<Vec<_>>();
+            let mut real = network(g.n_vertexes(), &edges);
+            let flow = real.real_max_flow(source, sink, DEFAULT_TOLERANCE).unwrap_or(0.0);
+            assert!((flow - expected).abs() < 1e-9, "seed {}: {} instead of {}", seed, flow, expected);
+            for edge in real.arcs.iter().step_by(2) {
+                assert!(edge.flow >= -1e-12 && edge.residual() >= -1e-12, "seed {}: {:?}", seed, edge);
+            }
+        }
+    }
+
+    #[test]
+    fn test_round_flow() {
+        // Half a unit on each side of a diamond rounds to one unit along one side
+        let mut g = network(4, &[(0, 1, 1.0), (0, 2, 1.0), (1, 3, 1.0), (2, 3, 1.0), (1, 2, 1.0)]);
+        for &(a, flow) in &[(0, 0.5), (2, 0.5), (4, 0.5), (6, 0.5)] {
+            g.set_flow(a, flow);
+        }
+        assert_eq!(g.round_flow(VertexId(0), VertexId(3)), Ok(1));
+        assert!(g.arcs.iter().all(|arc| arc.flow.fract() == 0.0 && arc.flow <= arc.capacity));
+
+        // Averages of integral flows round to integral flows of at least their value
+        for seed in 0..30 {
+            let (source, sink, mut g) = random_network(12, 40, 1..=9, seed);
+            let mut bfs = g.clone();
+            let expected = match bfs.max_flow(source, sink, BFS) {
+                Ok(flow) => flow,
+                Err(_) => continue
+            };
+            let _ = g.max_flow(source, sink, DFS);
+            let edges = g.edges()
+                .map(|(u, v, a)| (u.index(), v.index(), g.arcs[a].capacity as f64))
+                .collect::<Vec<_>>();
+            for &weight in &[0.5, 0.3] {
+                let mut real = network(g.n_vertexes(), &edges);
+                for a in (0..real.arcs.len()).step_by(2) {
+                    real.set_flow(a, weight * g.arcs[a].flow as f64 + (1.0 - weight) * bfs.arcs[a].flow as f64);
+                }
+                assert_eq!(real.round_flow(source, sink), Ok(expected), "seed {}", seed);
+                let mut rounded = g.clone();
+                for (arc, real) in rounded.arcs.iter_mut().zip(&real.arcs) {
+                    arc.flow = real.flow as i32;
+                }
+                assert!(certify(&rounded, source, sink, expected).is_ok(), "seed {}", seed);
+            }
+            // Half of a maximum flow rounds up to at least half of its value
+            let mut real = network(g.n_vertexes(), &edges);
+            for a in (0..real.arcs.len()).step_by(2) {
+                real.set_flow(a, bfs.arcs[a].flow as f64 / 2.0);
+            }
+            let value = real.round_flow(source, sink).unwrap();
+            assert!(2 * value >= expected && value <= expected, "seed {}", seed);
+        }
+        let mut g = network(2, &[(0, 1, 1.5)]);
+        assert!(matches!(g.round_flow(VertexId(0), VertexId(1)), Err(Error::Construction(_))));
+    }
+}
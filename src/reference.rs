@@ -0,0 +1,66 @@
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use super::{Error, FlowEdge, Graph, VertexId};
+
+/// Largest number of vertexes `max_flow_exhaustive` accepts, which enumerates 2^18 cuts.
+pub const MAX_EXHAUSTIVE_VERTEXES: usize = 20;
+
+/// Computes the maximum flow value from `source` to `sink` as the smallest capacity of any cut separating them, by
+/// enumerating every subset of the other vertexes. It shares no code with the augmenting path solvers and is meant as
+/// a slow but obviously correct oracle for testing them on small graphs. The flows stored in the graph are ignored.
+/// Unlike `FlowGraph::max_flow` a sink no flow reaches gives a value of 0 instead of an error. Fails if a terminal is
+/// invalid or the graph has more than `MAX_EXHAUSTIVE_VERTEXES` vertexes.
+pub fn max_flow_exhaustive(graph: &Graph<FlowEdge>, source: VertexId, sink: VertexId) -> Result<i64, Error> {
+    graph.check_terminals(source, sink)?;
+    let n = graph.n_vertexes();
+    if n > MAX_EXHAUSTIVE_VERTEXES {
+        return Err(Error::Limit(format!("Exhaustive max flow is limited to {} vertexes, the graph has {}",
+                                        MAX_EXHAUSTIVE_VERTEXES, n)));
+    }
+    let others = graph.vertexes().filter(|&v| v != source && v != sink).collect::<Vec<_>>();
+    let mut source_side = vec![false; n];
+    source_side[source] = true;
+    let mut min_cut = i64::MAX;
+    for subset in 0..1u32 << others.len() {
+        for (i, &v) in others.iter().enumerate() {
+            source_side[v] = subset & (1 << i) != 0;
+        }
+        let capacity: i64 = graph.edges()
+            .filter(|&(u, v, _)| source_side[u] && !source_side[v])
+            .map(|(_, _, a)| graph.arcs[a].capacity as i64)
+            .sum();
+        min_cut = min_cut.min(capacity);
+    }
+    if min_cut < 0 {
+        return Err(Error::Construction("Graph has negative capacities".to_string()));
+    }
+    Ok(min_cut)
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use generators::random_network;
+    use {flow_from_dicaps, FlowError, FlowGraph, BFS, DFS};
+
+    #[test]
+    fn test_max_flow_exhaustive() {
+        let (source, sink, g) = flow_from_dicaps("data/dicaps/central.txt");
+        assert_eq!(max_flow_exhaustive(&g, source, sink), Ok(5));
+        for seed in 0..50 {
+            let (source, sink, g) = random_network(8, 20, 0..=9, seed);
+            let expected = max_flow_exhaustive(&g, source, sink).unwrap();
+            for &search in &[BFS, DFS] {
+                let flow = g.clone().max_flow(source, sink, search).or_else(|e| match e {
+                    FlowError::UnreachableSink {..} => Ok(0),
+                    e => Err(e)
+                });
+                assert_eq!(flow, Ok(expected), "seed {}", seed);
+            }
+        }
+        assert_eq!(max_flow_exhaustive(&g, source, source),
+                   Err(Error::Infeasible(FlowError::SourceIsSink {vertex: source})));
+        let (source, sink, large) = random_network(21, 40, 1..=9, 0);
+        assert!(matches!(max_flow_exhaustive(&large, source, sink), Err(Error::Limit(_))));
+    }
+}
@@ -0,0 +1,458 @@
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+use core::cmp::min;
+#[cfg(feature = "std")]
+use std::time::Instant;
+use cancel::{CancellationToken, SolveStatus};
+use levels::UNREACHED;
+use super::{ArcId, FlowEdge, FlowError, FlowState, Graph, Search, SolveStats, VertexId};
+
+/// Maximum flow computed by a `MaxFlowSolver`, with the flows kept apart from the graph.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MaxFlowResult {
+    /// Flow value, the net flow leaving the source.
+    pub value: i64,
+    /// Flow of every arc, write it to the graph with `FlowState::apply`.
+    pub flows: FlowState,
+    /// Work done by the solver. Fields an algorithm has no use for stay 0.
+    pub stats: SolveStats
+}
+
+/// Algorithm which computes a maximum flow. A solver reads only the topology and the capacities of a graph built with
+/// `Graph::flow_network` and starts without flow, the flows stored in the arcs are ignored. Like `FlowGraph::max_flow`
+/// it fails with `FlowError::UnreachableSink` if no flow reaches the sink.
+pub trait MaxFlowSolver {
+    /// Name of the algorithm, used in reports.
+    fn name(&self) -> &'static str;
+    fn solve(&self, graph: &Graph<FlowEdge>, source: VertexId, sink: VertexId) -> Result<MaxFlowResult, FlowError>;
+}
+
+/// Shortest augmenting paths found by breadth first search, the algorithm of `FlowGraph::max_flow` with `BFS`.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct EdmondsKarp;
+
+/// Augmenting paths found by depth first search, the algorithm of `FlowGraph::max_flow` with `DFS`.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct FordFulkerson;
+
+/// Blocking flows on the level graph of the residual network, one phase per level graph. Every phase pushes flow
+/// along shortest augmenting paths found by depth first search, keeping a current arc per vertex so that no arc is
+/// looked at twice after it was found useless.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct Dinic;
+
+/// FIFO push-relabel, which pushes excess from vertexes to neighbors with a smaller distance label and relabels the
+/// vertexes it can not push from. The value is known once no vertex but the terminals has excess.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct PushRelabel {
+    /// Recompute exact distance labels by a breadth first search from the sink after every n relabels.
+    pub global_relabel: bool,
+    /// Lift the vertexes above an empty label to n + 1 at once, they can no longer reach the sink.
+    pub gap_heuristic: bool
+}
+
+impl Default for PushRelabel {
+    fn default() -> PushRelabel {
+        PushRelabel {global_relabel: true, gap_heuristic: true}
+    }
+}
+
+impl Graph<FlowEdge> {
+    /// Computes a maximum flow from source to sink with `solver` and stores it in the arcs, see `MaxFlowSolver`. A
+    /// graph built with `Graph::new` is rebuilt with its residual arcs first. Returns the flow value and the statistics
+    /// of the solver.
+    pub fn max_flow_with(&mut self, solver: &dyn MaxFlowSolver, source: VertexId, sink: VertexId)
+                         -> Result<(i64, SolveStats), FlowError> {
+        self.check_terminals(source, sink)?;
+        self.ensure_residual_arcs();
+        let result = solver.solve(self, source, sink)?;
+        result.flows.apply(self);
+        Ok((result.value, result.stats))
+    }
+}
+
+impl MaxFlowSolver for EdmondsKarp {
+    fn name(&self) -> &'static str {
+        "edmonds-karp"
+    }
+
+    fn solve(&self, graph: &Graph<FlowEdge>, source: VertexId, sink: VertexId) -> Result<MaxFlowResult, FlowError> {
+        augmenting_paths(graph, source, sink, Search::Bfs)
+    }
+}
+
+impl MaxFlowSolver for FordFulkerson {
+    fn name(&self) -> &'static str {
+        "ford-fulkerson"
+    }
+
+    fn solve(&self, graph: &Graph<FlowEdge>, source: VertexId, sink: VertexId) -> Result<MaxFlowResult, FlowError> {
+        augmenting_paths(graph, source, sink, Search::Dfs)
+    }
+}
+
+fn augmenting_paths(graph: &Graph<FlowEdge>, source: VertexId, sink: VertexId, search: Search)
+                    -> Result<MaxFlowResult, FlowError> {
+    let mut flows = FlowState::new(graph);
+    let (value, stats) = graph.augment(&mut flows.flows, source, sink, search, None, |_| true)?;
+    Ok(MaxFlowResult {value, flows, stats})
+}
+
+/// Returns an error unless the terminals are valid and the graph has its residual arcs.
+fn check(graph: &Graph<FlowEdge>, source: VertexId, sink: VertexId) -> Result<(), FlowError> {
+    graph.check_terminals(source, sink)?;
+    if !graph.has_residual_arcs() {
+        return Err(FlowError::MissingResidualArcs);
+    }
+    Ok(())
+}
+
+/// Returns the result of a solver which started without flow, or the error of a sink no flow reaches.
+fn finish(source: VertexId, sink: VertexId, value: i64, flows: Vec<i32>, stats: SolveStats)
+          -> Result<MaxFlowResult, FlowError> {
+    if value == 0 {
+        return Err(FlowError::UnreachableSink {source, sink});
+    }
+    Ok(MaxFlowResult {value, flows: FlowState {flows}, stats})
+}
+
+impl MaxFlowSolver for Dinic {
+    fn name(&self) -> &'static str {
+        "dinic"
+    }
+
+    fn solve(&self, graph: &Graph<FlowEdge>, source: VertexId, sink: VertexId) -> Result<MaxFlowResult, FlowError> {
+        dinic(graph, source, sink, || false).map(|(result, _)| result)
+    }
+}
+
+impl Dinic {
+    /// Same as `solve` but polls `token` before every phase and stops once it is cancelled, returning the flow of the
+    /// phases done so far with the status `Cancelled`. The flow is feasible, blocking flows are pushed whole.
+    pub fn solve_cancellable(&self,
+                             graph: &Graph<FlowEdge>,
+                             source: VertexId,
+                             sink: VertexId,
+                             token: &CancellationToken) -> Result<(MaxFlowResult, SolveStatus), FlowError> {
+        dinic(graph, source, sink, || token.is_cancelled())
+    }
+}
+
+/// Dinic's algorithm, see `Dinic`, which stops before a phase when `cancelled` returns true.
+fn dinic<C>(graph: &Graph<FlowEdge>, source: VertexId, sink: VertexId, cancelled: C)
+            -> Result<(MaxFlowResult, SolveStatus), FlowError>
+    where C: Fn() -> bool {
+    #[cfg(feature = "std")]
+    let start_time = Instant::now();
+    check(graph, source, sink)?;
+    debug!("Dinic from {} to {} on {} vertexes and {} edges", source, sink, graph.n_vertexes(), graph.n_edges());
+    let mut flows = vec![0; graph.arcs.len()];
+    let mut stats = SolveStats::default();
+    let mut value: i64 = 0;
+    let mut levels = vec![UNREACHED; graph.n_vertexes()];
+    let mut current = vec![0; graph.n_vertexes()];
+    let mut queue: VecDeque<VertexId> = VecDeque::new();
+    let mut path: Vec<ArcId> = Vec::new();
+    let mut status = SolveStatus::Completed;
+    loop {
+        stats.phases += 1;
+        // Breadth first search from the source for the levels of the residual network
+        levels.iter_mut().for_each(|l| *l = UNREACHED);
+        levels[source] = 0;
+        queue.push_back(source);
+        while let Some(u) = queue.pop_front() {
+            stats.vertexes_scanned += 1;
+            for a in graph.out_arcs(u) {
+                stats.arcs_scanned += 1;
+                let v = graph.head(a);
+                if levels[v] == UNREACHED && graph.arcs[a].capacity > flows[a] {
+                    levels[v] = levels[u] + 1;
+                    queue.push_back(v);
+                }
+            }
+        }
+        if levels[sink] == UNREACHED {
+            break;
+        }
+        if cancelled() {
+            status = SolveStatus::Cancelled;
+            break;
+        }
+        trace!("Dinic phase {} with the sink on level {}", stats.phases, levels[sink]);
+        current.iter_mut().for_each(|c| *c = 0);
+        let mut u = source;
+        loop {
+            if u == sink {
+                let bottleneck = path.iter().map(|&a| graph.arcs[a].capacity - flows[a]).min().unwrap_or(0);
+                for &a in &path {
+                    flows[a] += bottleneck;
+                    flows[a ^ 1] -= bottleneck;
+                }
+                value += bottleneck as i64;
+                stats.augmenting_paths += 1;
+                stats.pushes += path.len() as u64;
+                path.clear();
+                u = source;
+                continue;
+            }
+            // Advance along the current arc of u if it is admissible, otherwise move the current arc on
+            match graph.adjacency[u].get(current[u]) {
+                Some(&a) => {
+                    let a = a as ArcId;
+                    let v = graph.head(a);
+                    if levels[v] == levels[u] + 1 && graph.arcs[a].capacity > flows[a] {
+                        path.push(a);
+                        u = v;
+                    } else {
+                        current[u] += 1;
+                    }
+                },
+                None => {
+                    // A dead end, no augmenting path of this phase passes through u again
+                    if u == source {
+                        break;
+                    }
+                    levels[u] = UNREACHED;
+                    let a = path.pop().expect("Only the source has an empty path");
+                    u = graph.head(a ^ 1);
+                    current[u] += 1;
+                }
+            }
+        }
+    }
+    #[cfg(feature = "std")]
+    {
+        stats.wall_time = start_time.elapsed();
+    }
+    debug!("Dinic ended after {} phases and {} augmentations with flow {}",
+           stats.phases, stats.augmenting_paths, value);
+    if status == SolveStatus::Cancelled {
+        return Ok((MaxFlowResult {value, flows: FlowState {flows}, stats}, status));
+    }
+    finish(source, sink, value, flows, stats).map(|result| (result, status))
+}
+
+/// Distance labels and excesses of a push-relabel computation.
+struct Labels {
+    labels: Vec<usize>,
+    /// Number of vertexes with every label below n, for the gap heuristic.
+    counts: Vec<usize>,
+    current: Vec<usize>
+}
+
+impl Labels {
+    fn set(&mut self, v: VertexId, label: usize) {
+        let n = self.counts.len();
+        if self.labels[v] < n {
+            self.counts[self.labels[v]] -= 1;
+        }
+        if label < n {
+            self.counts[label] += 1;
+        }
+        self.labels[v] = label;
+        self.current[v] = 0;
+    }
+
+    /// Sets the label of every vertex to its number of residual arcs from the sink, vertexes which can not reach the
+    /// sink keep their label if it is at least n and are lifted to n otherwise. The source keeps its label n.
+    fn global_relabel(&mut self, graph: &Graph<FlowEdge>, flows: &[i32], source: VertexId, sink: VertexId) {
+        let n = graph.n_vertexes();
+        let mut distances = vec![UNREACHED; n];
+        distances[sink] = 0;
+        let mut queue: VecDeque<VertexId> = VecDeque::new();
+        queue.push_back(sink);
+        while let Some(u) = queue.pop_front() {
+            for a in graph.out_arcs(u) {
+                // The arc from v to u is the reverse of a
+                let v = graph.head(a);
+                if v != source && distances[v] == UNREACHED && graph.arcs[a ^ 1].capacity > flows[a ^ 1] {
+                    distances[v] = distances[u] + 1;
+                    queue.push_back(v);
+                }
+            }
+        }
+        for v in graph.vertexes().filter(|&v| v != source) {
+            let label = if distances[v] == UNREACHED { self.labels[v].max(n) } else { distances[v] as usize };
+            self.set(v, label);
+        }
+    }
+}
+
+impl MaxFlowSolver for PushRelabel {
+    fn name(&self) -> &'static str {
+        "push-relabel"
+    }
+
+    fn solve(&self, graph: &Graph<FlowEdge>, source: VertexId, sink: VertexId) -> Result<MaxFlowResult, FlowError> {
+        #[cfg(feature = "std")]
+        let start_time = Instant::now();
+        check(graph, source, sink)?;
+        debug!("Push-relabel from {} to {} on {} vertexes and {} edges",
+               source, sink, graph.n_vertexes(), graph.n_edges());
+        let n = graph.n_vertexes();
+        let mut flows = vec![0; graph.arcs.len()];
+        let mut stats = SolveStats::default();
+        let mut excess: Vec<i64> = vec![0; n];
+        let mut labels = Labels {labels: vec![0; n], counts: vec![0; n], current: vec![0; n]};
+        labels.counts[0] = n - 1;
+        labels.labels[source] = n;
+        let mut active: VecDeque<VertexId> = VecDeque::new();
+        let mut queued = vec![false; n];
+        let activate = |v: VertexId, active: &mut VecDeque<VertexId>, queued: &mut [bool]| {
+            if v != source && v != sink && !queued[v] {
+                queued[v] = true;
+                active.push_back(v);
+            }
+        };
+        // Saturate every arc leaving the source
+        for a in graph.out_arcs(source) {
+            let v = graph.head(a);
+            let delta = graph.arcs[a].capacity - flows[a];
+            if delta > 0 && v != source {
+                flows[a] += delta;
+                flows[a ^ 1] -= delta;
+                excess[source] -= delta as i64;
+                excess[v] += delta as i64;
+                stats.pushes += 1;
+                stats.saturating_pushes += 1;
+                activate(v, &mut active, &mut queued);
+            }
+        }
+        if self.global_relabel {
+            labels.global_relabel(graph, &flows, source, sink);
+            stats.global_relabels += 1;
+        }
+        // Excess goes back to the source only over the edges leaving it, never over an edge entering it, so the flow
+        // value is the flow on the edges leaving the source as for the other solvers
+        let residual = |a: ArcId, flows: &[i32]| if a & 1 == 0 && graph.head(a) == source {
+            0
+        } else {
+            graph.arcs[a].capacity - flows[a]
+        };
+        let mut since_global_relabel = 0;
+        while let Some(u) = active.pop_front() {
+            queued[u] = false;
+            stats.vertexes_scanned += 1;
+            while excess[u] > 0 {
+                match graph.adjacency[u].get(labels.current[u]) {
+                    Some(&a) => {
+                        let a = a as ArcId;
+                        let v = graph.head(a);
+                        let residual = residual(a, &flows);
+                        stats.arcs_scanned += 1;
+                        if residual > 0 && labels.labels[u] == labels.labels[v] + 1 {
+                            let delta = min(excess[u], residual as i64) as i32;
+                            flows[a] += delta;
+                            flows[a ^ 1] -= delta;
+                            excess[u] -= delta as i64;
+                            excess[v] += delta as i64;
+                            stats.pushes += 1;
+                            if delta == residual {
+                                stats.saturating_pushes += 1;
+                            } else {
+                                stats.nonsaturating_pushes += 1;
+                            }
+                            activate(v, &mut active, &mut queued);
+                        } else {
+                            labels.current[u] += 1;
+                        }
+                    },
+                    None => {
+                        // No admissible arc is left, so u is lifted just above its lowest residual neighbor
+                        let old = labels.labels[u];
+                        let label = graph.out_arcs(u)
+                            .filter(|&a| residual(a, &flows) > 0)
+                            .map(|a| labels.labels[graph.head(a)] + 1)
+                            .min()
+                            .expect("A vertex with excess has a residual arc back to the source");
+                        labels.set(u, label);
+                        stats.relabels += 1;
+                        since_global_relabel += 1;
+                        if self.gap_heuristic && old < n && labels.counts[old] == 0 {
+                            stats.gap_events += 1;
+                            for v in graph.vertexes() {
+                                if labels.labels[v] > old && labels.labels[v] < n && v != source {
+                                    labels.set(v, n + 1);
+                                }
+                            }
+                        }
+                        if self.global_relabel && since_global_relabel >= n {
+                            labels.global_relabel(graph, &flows, source, sink);
+                            stats.global_relabels += 1;
+                            since_global_relabel = 0;
+                        }
+                    }
+                }
+            }
+        }
+        #[cfg(feature = "std")]
+        {
+            stats.wall_time = start_time.elapsed();
+        }
+        debug!("Push-relabel ended after {} pushes, {} relabels and {} global relabels with flow {}",
+               stats.pushes, stats.relabels, stats.global_relabels, excess[sink]);
+        finish(source, sink, excess[sink], flows, stats)
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use generators::random_network;
+    use testing::{assert_agreement, search_solver, Bounds};
+    use verify::certify;
+    use {flow_from_dicaps, FlowGraph, BFS};
+
+    fn solvers() -> Vec<Box<dyn MaxFlowSolver>> {
+        vec![Box::new(EdmondsKarp), Box::new(FordFulkerson), Box::new(Dinic), Box::new(PushRelabel::default()),
+             Box::new(PushRelabel {global_relabel: false, gap_heuristic: false})]
+    }
+
+    #[test]
+    fn test_solvers() {
+        for file_name in &["data/dicaps/central.txt", "data/dicaps/flow-graph.txt", "data/dicaps/bipartite-flow.txt"] {
+            let (source, sink, g) = flow_from_dicaps(file_name);
+            let expected = g.clone().max_flow(source, sink, BFS).unwrap();
+            for solver in solvers() {
+                let result = solver.solve(&g, source, sink).unwrap();
+                assert_eq!(result.value, expected, "{} on {}", solver.name(), file_name);
+                let mut solved = g.clone();
+                result.flows.apply(&mut solved);
+                assert!(certify(&solved, source, sink, result.value).is_ok(), "{}", solver.name());
+            }
+        }
+        let (source, sink, g) = random_network(4, 0, 1..=1, 0);
+        for solver in solvers() {
+            assert_eq!(solver.solve(&g, source, sink), Err(FlowError::UnreachableSink {source, sink}));
+            assert_eq!(solver.solve(&g, source, source), Err(FlowError::SourceIsSink {vertex: source}));
+        }
+    }
+
+    #[test]
+    fn test_push_relabel_stats() {
+        let (source, sink, g) = flow_from_dicaps("data/dicaps/central.txt");
+        let stats = PushRelabel::default().solve(&g, source, sink).unwrap().stats;
+        assert_eq!(stats.saturating_pushes + stats.nonsaturating_pushes, stats.pushes);
+        assert!(stats.saturating_pushes > 0 && stats.nonsaturating_pushes > 0, "{}", stats);
+        assert!(stats.relabels > 0 && stats.global_relabels > 0 && stats.gap_events > 0, "{}", stats);
+        // Global relabels are not phases, and every gap is found by a relabel
+        assert_eq!((stats.phases, stats.augmenting_paths), (0, 0));
+        assert!(stats.gap_events <= stats.relabels);
+
+        let plain = PushRelabel {global_relabel: false, gap_heuristic: false}.solve(&g, source, sink).unwrap().stats;
+        assert_eq!(plain.saturating_pushes + plain.nonsaturating_pushes, plain.pushes);
+        assert_eq!((plain.global_relabels, plain.gap_events), (0, 0));
+        assert!(plain.relabels > 0);
+        assert!(plain.to_string().contains("\tRelabels:"));
+    }
+
+    #[test]
+    fn test_solvers_agree() {
+        let bounds = Bounds {vertexes: 2..=30, edges: 1..=120, capacity: 0..=20};
+        for (seed, solver) in solvers().into_iter().enumerate() {
+            assert_agreement(seed as u64, 100, &bounds, search_solver(BFS), |g: &mut Graph<FlowEdge>, source, sink| {
+                g.max_flow_with(&*solver, source, sink).map(|(flow, _)| flow)
+            });
+        }
+    }
+}
@@ -0,0 +1,117 @@
+use std::collections::BinaryHeap;
+use rand::Rng;
+use real::RealEdge;
+use super::{seeded_rng, FlowEdge, Graph, VertexId};
+
+/// Factor of `ln n / epsilon^2` in the sampling rate of `sparsify`.
+pub const OVERSAMPLING: f64 = 3.0;
+
+/// Returns a lower bound of the connectivity between the endpoints of every edge, the Nagamochi-Ibaraki forest index
+/// of a maximum adjacency ordering. Directions are ignored, edges without capacity and self loops get 0.
+pub fn connectivity_bounds(graph: &Graph<FlowEdge>) -> Vec<i64> {
+    let mut bounds = vec![0i64; graph.n_edges()];
+    let mut attachment = vec![0i64; graph.n_vertexes()];
+    let mut scanned = vec![false; graph.n_vertexes()];
+    let mut heap: BinaryHeap<(i64, VertexId)> = BinaryHeap::new();
+    for root in graph.vertexes() {
+        if scanned[root] {
+            continue;
+        }
+        heap.push((0, root));
+        // Vertexes are scanned in order of the capacity joining them to the scanned vertexes, stale entries are skipped
+        while let Some((_, u)) = heap.pop() {
+            if scanned[u] {
+                continue;
+            }
+            scanned[u] = true;
+            for a in graph.out_arcs(u) {
+                let v = graph.head(a);
+                let capacity = graph.arcs[a & !1].capacity as i64;
+                if scanned[v] || capacity <= 0 {
+                    continue;
+                }
+                attachment[v] += capacity;
+                bounds[a / 2] = attachment[v];
+                heap.push((attachment[v], v));
+            }
+        }
+    }
+    bounds
+}
+
+/// Samples a cut sparsifier of the graph: every edge is kept with probability `min(1, rho * c / k)`, where `c` is its
+/// capacity, `k` its bound from `connectivity_bounds` and `rho = OVERSAMPLING * ln n / epsilon^2`, and a kept edge
+/// gets capacity `c / p` so every cut keeps its capacity in expectation. With high probability every cut, counting
+/// the edges crossing it in either direction, is within a factor `1 ± epsilon` of its capacity in the graph, so
+/// minimum cuts and maximum flows of undirected networks can be approximated on the smaller graph. Kept edges keep
+/// their direction. The same seed gives the same graph.
+pub fn sparsify(graph: &Graph<FlowEdge>, epsilon: f64, seed: u64) -> Graph<RealEdge> {
+    sparsify_with_rng(graph, epsilon, &mut seeded_rng(seed))
+}
+
+/// Same as `sparsify` but samples the edges with `rng`.
+pub fn sparsify_with_rng<R>(graph: &Graph<FlowEdge>, epsilon: f64, rng: &mut R) -> Graph<RealEdge>
+    where R: Rng + ?Sized {
+    let rho = OVERSAMPLING * (graph.n_vertexes().max(2) as f64).ln() / (epsilon * epsilon);
+    let bounds = connectivity_bounds(graph);
+    let mut edges: Vec<(VertexId, VertexId, RealEdge)> = Vec::new();
+    for (u, v, a) in graph.edges() {
+        let capacity = graph.arcs[a].capacity as f64;
+        if u == v || capacity <= 0.0 {
+            continue;
+        }
+        let p = (rho * capacity / bounds[a / 2] as f64).min(1.0);
+        if p >= 1.0 || rng.gen_bool(p) {
+            edges.push((u, v, RealEdge {capacity: capacity / p, flow: 0.0}));
+        }
+    }
+    debug!("Sparsifier keeps {} of {} edges", edges.len(), graph.n_edges());
+    let vertexes = graph.vertexes().collect::<Vec<_>>();
+    Graph::real_flow_network(&vertexes, &edges).expect("Sampled capacities are finite and positive")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use real::DEFAULT_TOLERANCE;
+
+    fn complete_graph(n: usize) -> Graph<FlowEdge> {
+        let vertexes = (0..n).map(VertexId).collect::<Vec<_>>();
+        let edges = (0..n)
+            .flat_map(|u| (u + 1..n).map(move |v| (VertexId(u), VertexId(v), FlowEdge{capacity: 1, flow: 0})));
+        Graph::flow_network(&vertexes, edges)
+    }
+
+    #[test]
+    fn test_connectivity_bounds() {
+        // The edges of a path are bounded by their capacity, those of a complete graph by n - 1, its connectivity
+        let vertexes = (0..4).map(VertexId).collect::<Vec<_>>();
+        let edges = (0..3).map(|u| (VertexId(u), VertexId(u + 1), FlowEdge{capacity: 2, flow: 0}));
+        assert_eq!(connectivity_bounds(&Graph::flow_network(&vertexes, edges)), [2, 2, 2]);
+        let bounds = connectivity_bounds(&complete_graph(6));
+        assert!(bounds.iter().all(|&b| (1..=5).contains(&b)) && bounds.contains(&5));
+    }
+
+    #[test]
+    fn test_sparsify() {
+        let g = complete_graph(120);
+        let sparse = sparsify(&g, 0.5, 7);
+        assert!(sparse.n_edges() < g.n_edges(), "{} edges", sparse.n_edges());
+        assert_eq!(sparsify(&g, 0.5, 7).arcs, sparse.arcs);
+        let tails = sparse.tails();
+        let mut rng = seeded_rng(0);
+        let sides = vec![(0..120).map(|v| v == 0).collect::<Vec<_>>(), (0..120).map(|v| v < 60).collect(),
+                         (0..120).map(|_| rng.gen_bool(0.3)).collect()];
+        for side in &sides {
+            let expected = g.edges().filter(|&(u, v, _)| side[u] != side[v]).count() as f64;
+            let capacity = (0..sparse.arcs.len()).step_by(2)
+                .filter(|&a| side[tails[a]] != side[sparse.head(a)])
+                .map(|a| sparse.arcs[a].capacity)
+                .sum::<f64>();
+            assert!((capacity / expected - 1.0).abs() <= 0.5, "{} instead of {}", capacity, expected);
+        }
+        // Edges sampled with probability 1 keep their capacity
+        let mut edge = sparsify(&complete_graph(2), 0.1, 0);
+        assert_eq!(edge.real_max_flow(VertexId(0), VertexId(1), DEFAULT_TOLERANCE), Ok(1.0));
+    }
+}
@@ -0,0 +1,269 @@
+use alloc::collections::{BinaryHeap, VecDeque};
+use alloc::vec::Vec;
+use core::cmp::Reverse;
+use super::{ArcId, Error, FlowEdge, FlowError, FlowGraph, Graph, VertexId, BFS};
+
+/// Largest number of simple paths enumerated by `Graph::k_splittable_flow` to find the best pair of paths exactly.
+pub const EXACT_PATH_LIMIT: usize = 1000;
+
+/// Flow found by `Graph::k_splittable_flow`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SplittableFlow {
+    /// Total flow of the paths.
+    pub value: i64,
+    /// Edges of every path as their arcs from source to sink, with the flow the path carries, largest flow first.
+    pub paths: Vec<(Vec<ArcId>, i64)>,
+    /// Whether the value is proven to be the largest flow over at most `k` paths.
+    pub exact: bool
+}
+
+impl Graph<FlowEdge> {
+    /// Computes a flow from `source` to `sink` carried by at most `k` paths, each with its own amount of flow. When a
+    /// maximum flow decomposes into at most `k` paths it is returned. Otherwise a single path is best as the widest
+    /// path, and a pair of paths is best as the pair with the largest flow among the simple paths, which are enumerated
+    /// when there are at most `EXACT_PATH_LIMIT` of them. In the other cases the result is the better of the `k`
+    /// largest paths of a maximum flow decomposition and `k` widest paths taken one after the other, and is not marked
+    /// exact. The flow of the paths is left in the graph. Fails if the terminals are invalid.
+    pub fn k_splittable_flow(&mut self, source: VertexId, sink: VertexId, k: usize) -> Result<SplittableFlow, Error> {
+        self.check_terminals(source, sink)?;
+        if !self.has_residual_arcs() {
+            return Err(Error::Infeasible(FlowError::MissingResidualArcs));
+        }
+        let capacities = self.arcs.iter()
+            .enumerate()
+            .map(|(a, arc)| if a.is_multiple_of(2) { arc.capacity.max(0) as i64 } else { 0 })
+            .collect::<Vec<_>>();
+        let mut decomposition = self.max_flow_paths(source, sink);
+        let (mut paths, exact) = if decomposition.len() <= k {
+            (decomposition, true)
+        } else if k == 0 {
+            (Vec::new(), true)
+        } else if k == 1 {
+            (self.widest_path(source, sink, &capacities).into_iter().collect(), true)
+        } else {
+            let best_pair = if k == 2 { self.best_path_pair(source, sink, &capacities) } else { None };
+            match best_pair {
+                Some(pair) => (pair, true),
+                None => {
+                    let mut remaining = capacities.clone();
+                    let mut greedy: Vec<(Vec<ArcId>, i64)> = Vec::with_capacity(k);
+                    while greedy.len() < k {
+                        match self.widest_path(source, sink, &remaining) {
+                            Some((path, width)) => {
+                                path.iter().for_each(|&a| remaining[a] -= width);
+                                greedy.push((path, width));
+                            },
+                            None => break
+                        }
+                    }
+                    decomposition.truncate(k);
+                    let total = |paths: &[(Vec<ArcId>, i64)]| paths.iter().map(|&(_, amount)| amount).sum::<i64>();
+                    (if total(&greedy) > total(&decomposition) { greedy } else { decomposition }, false)
+                }
+            }
+        };
+        paths.sort_by_key(|&(_, amount)| Reverse(amount));
+        self.reset_flow();
+        for (path, amount) in &paths {
+            for &a in path {
+                self.arcs[a].flow += *amount as i32;
+                self.arcs[a ^ 1].flow -= *amount as i32;
+            }
+        }
+        let value = paths.iter().map(|&(_, amount)| amount).sum();
+        debug!("{}-splittable flow of {} over {} paths, exact: {}", k, value, paths.len(), exact);
+        Ok(SplittableFlow {value, paths, exact})
+    }
+
+    /// Decomposes a maximum flow into paths, largest flow first. Cycles of the flow are left out.
+    fn max_flow_paths(&self, source: VertexId, sink: VertexId) -> Vec<(Vec<ArcId>, i64)> {
+        let mut network = self.clone();
+        network.reset_flow();
+        if network.max_flow(source, sink, BFS).is_err() {
+            return Vec::new();
+        }
+        let mut flows = network.arcs.iter()
+            .enumerate()
+            .map(|(a, arc)| if a.is_multiple_of(2) { arc.flow.max(0) as i64 } else { 0 })
+            .collect::<Vec<_>>();
+        let mut paths: Vec<(Vec<ArcId>, i64)> = Vec::new();
+        while let Some(path) = self.find_path(source, sink, |a| flows[a] > 0) {
+            let amount = path.iter().map(|&a| flows[a]).min().unwrap_or(0);
+            path.iter().for_each(|&a| flows[a] -= amount);
+            paths.push((path, amount));
+        }
+        paths.sort_by_key(|&(_, amount)| Reverse(amount));
+        paths
+    }
+
+    /// Breadth first search over the edges accepted by `usable`, returning the arcs of a shortest path.
+    fn find_path<U>(&self, source: VertexId, sink: VertexId, usable: U) -> Option<Vec<ArcId>>
+        where U: Fn(ArcId) -> bool {
+        let mut parent_arcs: Vec<Option<ArcId>> = vec![None; self.n_vertexes()];
+        let mut queue: VecDeque<VertexId> = VecDeque::new();
+        queue.push_back(source);
+        while let Some(u) = queue.pop_front() {
+            for a in self.out_arcs(u).filter(|&a| a.is_multiple_of(2) && usable(a)) {
+                let v = self.head(a);
+                if v != source && parent_arcs[v].is_none() {
+                    parent_arcs[v] = Some(a);
+                    queue.push_back(v);
+                }
+            }
+        }
+        parent_arcs[sink].map(|_| self.path_to(&parent_arcs, sink))
+    }
+
+    /// Returns the path whose smallest remaining capacity is largest, with that capacity, by a variant of Dijkstra's
+    /// algorithm which keeps the largest bottleneck to every vertex instead of the shortest distance.
+    fn widest_path(&self, source: VertexId, sink: VertexId, remaining: &[i64]) -> Option<(Vec<ArcId>, i64)> {
+        let mut widths = vec![0i64; self.n_vertexes()];
+        let mut parent_arcs: Vec<Option<ArcId>> = vec![None; self.n_vertexes()];
+        let mut heap: BinaryHeap<(i64, VertexId)> = BinaryHeap::new();
+        widths[source] = i64::MAX;
+        heap.push((i64::MAX, source));
+        while let Some((width, u)) = heap.pop() {
+            if width < widths[u] {
+                continue;
+            }
+            if u == sink {
+                break;
+            }
+            for a in self.out_arcs(u).filter(|&a| a.is_multiple_of(2)) {
+                let (v, through) = (self.head(a), width.min(remaining[a]));
+                if v != source && through > widths[v] {
+                    widths[v] = through;
+                    parent_arcs[v] = Some(a);
+                    heap.push((through, v));
+                }
+            }
+        }
+        if widths[sink] == 0 {
+            return None;
+        }
+        Some((self.path_to(&parent_arcs, sink), widths[sink]))
+    }
+
+    /// Returns the pair of simple paths carrying the most flow together, or `None` if there are more than
+    /// `EXACT_PATH_LIMIT` simple paths. Two paths carry their bottlenecks, except that the edges they share also bound
+    /// the sum of their flows.
+    fn best_path_pair(&self, source: VertexId, sink: VertexId, capacities: &[i64]) -> Option<Vec<(Vec<ArcId>, i64)>> {
+        let paths = self.simple_paths(source, sink, capacities)?;
+        let bottlenecks = paths.iter()
+            .map(|path| path.iter().map(|&a| capacities[a]).min().unwrap_or(0))
+            .collect::<Vec<_>>();
+        let mut on_path = vec![false; self.arcs.len()];
+        let mut best: Option<(i64, usize, usize)> = None;
+        for i in 0..paths.len() {
+            paths[i].iter().for_each(|&a| on_path[a] = true);
+            for j in i + 1..paths.len() {
+                let shared = paths[j].iter().filter(|&&a| on_path[a]).map(|&a| capacities[a]).min();
+                let value = shared.unwrap_or(i64::MAX).min(bottlenecks[i] + bottlenecks[j]);
+                if best.is_none_or(|(best_value, _, _)| value > best_value) {
+                    best = Some((value, i, j));
+                }
+            }
+            paths[i].iter().for_each(|&a| on_path[a] = false);
+        }
+        Some(match best {
+            Some((value, i, j)) => {
+                let first = bottlenecks[i].min(value);
+                let mut pair = vec![(paths[i].clone(), first), (paths[j].clone(), value - first)];
+                pair.retain(|&(_, amount)| amount > 0);
+                pair
+            },
+            // With a single path there is no pair to improve on the widest path
+            None => self.widest_path(source, sink, capacities).into_iter().collect()
+        })
+    }
+
+    /// Enumerates the simple paths over edges with capacity by depth first search, or returns `None` once there are
+    /// more than `EXACT_PATH_LIMIT` of them.
+    fn simple_paths(&self, source: VertexId, sink: VertexId, capacities: &[i64]) -> Option<Vec<Vec<ArcId>>> {
+        let out_arcs = self.vertexes()
+            .map(|u| self.out_arcs(u).filter(|&a| capacities[a] > 0).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        let mut paths: Vec<Vec<ArcId>> = Vec::new();
+        let mut on_path = vec![false; self.n_vertexes()];
+        let mut path: Vec<ArcId> = Vec::new();
+        // Every entry is a vertex of the current path with the position of its next arc to follow
+        let mut stack: Vec<(VertexId, usize)> = vec![(source, 0)];
+        on_path[source] = true;
+        while let Some(&mut (u, ref mut next)) = stack.last_mut() {
+            match out_arcs[u].get(*next) {
+                Some(&a) => {
+                    *next += 1;
+                    let v = self.head(a);
+                    if v == sink {
+                        let mut found = path.clone();
+                        found.push(a);
+                        paths.push(found);
+                        if paths.len() > EXACT_PATH_LIMIT {
+                            return None;
+                        }
+                    } else if !on_path[v] {
+                        on_path[v] = true;
+                        path.push(a);
+                        stack.push((v, 0));
+                    }
+                },
+                None => {
+                    on_path[u] = false;
+                    stack.pop();
+                    path.pop();
+                }
+            }
+        }
+        Some(paths)
+    }
+
+    fn path_to(&self, parent_arcs: &[Option<ArcId>], mut v: VertexId) -> Vec<ArcId> {
+        let mut path: Vec<ArcId> = Vec::new();
+        while let Some(a) = parent_arcs[v] {
+            path.push(a);
+            v = self.head(a ^ 1);
+        }
+        path.reverse();
+        path
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn network(edges: &[(usize, usize, i32)]) -> Graph<FlowEdge> {
+        let n = edges.iter().map(|&(u, v, _)| u.max(v) + 1).max().unwrap_or(0);
+        let vertexes = (0..n).map(VertexId).collect::<Vec<_>>();
+        let edges = edges.iter()
+            .map(|&(u, v, capacity)| (VertexId(u), VertexId(v), FlowEdge{capacity, flow: 0}))
+            .collect::<Vec<_>>();
+        Graph::flow_network(&vertexes, &edges)
+    }
+
+    #[test]
+    fn test_k_splittable_flow() {
+        // Three disjoint routes of capacities 5, 4 and 3
+        let mut g = network(&[(0, 1, 5), (1, 4, 5), (0, 2, 4), (2, 4, 4), (0, 3, 3), (3, 4, 3)]);
+        let (source, sink) = (VertexId(0), VertexId(4));
+        let values = (0..4)
+            .map(|k| g.k_splittable_flow(source, sink, k).map(|flow| (flow.value, flow.exact)))
+            .collect::<Vec<_>>();
+        assert_eq!(values, [Ok((0, true)), Ok((5, true)), Ok((9, true)), Ok((12, true))]);
+        assert!(g.conserves_flow(source, sink));
+
+        // Two routes share the edge out of the source, which bounds their sum
+        let mut g = network(&[(0, 1, 6), (1, 3, 4), (1, 2, 4), (2, 3, 4), (0, 2, 1)]);
+        let flow = g.k_splittable_flow(VertexId(0), VertexId(3), 2).unwrap();
+        assert_eq!((flow.value, flow.exact, flow.paths.len()), (6, true, 2));
+        assert_eq!(g.arcs[0].flow, 6);
+
+        // More than two paths are chosen greedily, which finds the largest routes here
+        let mut g = network(&[(0, 1, 5), (1, 5, 5), (0, 2, 4), (2, 5, 4), (0, 3, 3), (3, 5, 3), (0, 4, 2), (4, 5, 2)]);
+        let flow = g.k_splittable_flow(VertexId(0), VertexId(5), 3).unwrap();
+        assert_eq!((flow.value, flow.exact), (12, false));
+        assert_eq!(flow.paths.iter().map(|&(_, amount)| amount).collect::<Vec<_>>(), [5, 4, 3]);
+
+        assert_eq!(g.k_splittable_flow(VertexId(5), VertexId(0), 2).map(|flow| flow.value), Ok(0));
+    }
+}
@@ -0,0 +1,247 @@
+use alloc::collections::BinaryHeap;
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use rand::Rng;
+#[cfg(feature = "std")]
+use rand::rngs::StdRng;
+#[cfg(feature = "std")]
+use rand::seq::SliceRandom;
+#[cfg(feature = "std")]
+use super::seeded_rng;
+use super::{ArcId, FlowEdge, FlowError, Graph, Search, SearchBuffers, VertexId};
+
+/// Rule choosing the next augmenting path of `Graph::max_flow_with_strategy`, so that selection rules can be tried
+/// without touching the solver. The searches of `FlowGraph::max_flow` are strategies, `Search::Bfs` for shortest paths
+/// and `Search::Dfs` for depth first paths, and so is any closure with the signature of `next_path`.
+pub trait PathStrategy {
+    /// Returns the arcs of a path from `source` to `sink` whose arcs all have residual capacity, in order from the
+    /// source, or `None` to stop. The flow is only maximum if `None` is returned once no such path is left.
+    fn next_path(&mut self, graph: &Graph<FlowEdge>, source: VertexId, sink: VertexId) -> Option<Vec<ArcId>>;
+}
+
+impl PathStrategy for Search {
+    fn next_path(&mut self, graph: &Graph<FlowEdge>, source: VertexId, sink: VertexId) -> Option<Vec<ArcId>> {
+        let mut buffers = SearchBuffers::new(graph.n_vertexes());
+        if !graph.search_residual(&mut buffers, source, sink, *self, |a| graph.arcs[a].residual() > 0) {
+            return None;
+        }
+        let mut path: Vec<ArcId> = Vec::new();
+        let mut node = sink;
+        while node != source {
+            let arc = buffers.parent_arcs[node] as ArcId;
+            path.push(arc);
+            node = graph.tail(arc);
+        }
+        path.reverse();
+        Some(path)
+    }
+}
+
+impl<F> PathStrategy for F where F: FnMut(&Graph<FlowEdge>, VertexId, VertexId) -> Option<Vec<ArcId>> {
+    fn next_path(&mut self, graph: &Graph<FlowEdge>, source: VertexId, sink: VertexId) -> Option<Vec<ArcId>> {
+        self(graph, source, sink)
+    }
+}
+
+/// Paths of largest bottleneck, found by a variant of Dijkstra's algorithm which keeps the largest residual capacity
+/// of a path to every vertex instead of the shortest distance. Needs at most about `m log(U)` augmentations for `m`
+/// edges and a largest capacity `U`.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct Fattest;
+
+impl PathStrategy for Fattest {
+    fn next_path(&mut self, graph: &Graph<FlowEdge>, source: VertexId, sink: VertexId) -> Option<Vec<ArcId>> {
+        let mut widths = vec![0; graph.n_vertexes()];
+        let mut parent_arcs: Vec<Option<ArcId>> = vec![None; graph.n_vertexes()];
+        let mut heap: BinaryHeap<(i32, VertexId)> = BinaryHeap::new();
+        widths[source] = i32::MAX;
+        heap.push((i32::MAX, source));
+        while let Some((width, u)) = heap.pop() {
+            if u == sink {
+                break;
+            }
+            if width < widths[u] {
+                continue;
+            }
+            for a in graph.out_arcs(u) {
+                let (v, through) = (graph.head(a), width.min(graph.arcs[a].residual()));
+                if v != source && through > widths[v] {
+                    widths[v] = through;
+                    parent_arcs[v] = Some(a);
+                    heap.push((through, v));
+                }
+            }
+        }
+        path_to(graph, &parent_arcs, sink)
+    }
+}
+
+/// Paths found by depth first searches which scan the residual arcs of every vertex in a random order, seeded so that
+/// runs can be repeated.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone)]
+pub struct Random<R = StdRng> {
+    rng: R
+}
+
+#[cfg(feature = "std")]
+impl Random {
+    pub fn new(seed: u64) -> Random {
+        Random::from_rng(seeded_rng(seed))
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: Rng> Random<R> {
+    /// Makes a strategy which shuffles the arcs with `rng`, which may be a `&mut` borrow of a generator shared with
+    /// other seeded steps.
+    pub fn from_rng(rng: R) -> Random<R> {
+        Random {rng}
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: Rng> PathStrategy for Random<R> {
+    fn next_path(&mut self, graph: &Graph<FlowEdge>, source: VertexId, sink: VertexId) -> Option<Vec<ArcId>> {
+        let mut parent_arcs: Vec<Option<ArcId>> = vec![None; graph.n_vertexes()];
+        let mut visited = vec![false; graph.n_vertexes()];
+        let mut stack = vec![source];
+        visited[source] = true;
+        while let Some(u) = stack.pop() {
+            let mut arcs = graph.out_arcs(u).filter(|&a| graph.arcs[a].residual() > 0).collect::<Vec<_>>();
+            arcs.shuffle(&mut self.rng);
+            for a in arcs {
+                let v = graph.head(a);
+                if !visited[v] {
+                    visited[v] = true;
+                    parent_arcs[v] = Some(a);
+                    if v == sink {
+                        return path_to(graph, &parent_arcs, sink);
+                    }
+                    stack.push(v);
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Paths given in advance by their vertexes, from the source to the sink, such as the adversarial paths of
+/// `generators::zigzag_paths`. Every step takes the first path with residual capacity after the one taken last, in
+/// turn, along the first residual arc between each pair of consecutive vertexes. Stops once none of the paths has
+/// residual capacity, so the flow is only maximum if the paths leave no augmenting path.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Scripted {
+    paths: Vec<Vec<VertexId>>,
+    next: usize
+}
+
+impl Scripted {
+    pub fn new(paths: Vec<Vec<VertexId>>) -> Scripted {
+        Scripted {paths, next: 0}
+    }
+}
+
+impl PathStrategy for Scripted {
+    fn next_path(&mut self, graph: &Graph<FlowEdge>, _: VertexId, _: VertexId) -> Option<Vec<ArcId>> {
+        for i in 0..self.paths.len() {
+            let k = (self.next + i) % self.paths.len();
+            let residual_arc = |u: VertexId, v: VertexId| {
+                graph.out_arcs(u).find(|&a| graph.head(a) == v && graph.arcs[a].residual() > 0)
+            };
+            let arcs = self.paths[k].windows(2).map(|pair| residual_arc(pair[0], pair[1])).collect::<Option<Vec<_>>>();
+            if arcs.is_some() {
+                self.next = k + 1;
+                return arcs;
+            }
+        }
+        None
+    }
+}
+
+/// Returns the arcs of the path to `sink` given by the arc every vertex was reached by, or `None` if it was not
+/// reached.
+fn path_to(graph: &Graph<FlowEdge>, parent_arcs: &[Option<ArcId>], sink: VertexId) -> Option<Vec<ArcId>> {
+    parent_arcs[sink]?;
+    let mut path: Vec<ArcId> = Vec::new();
+    let mut v = sink;
+    while let Some(a) = parent_arcs[v] {
+        path.push(a);
+        v = graph.tail(a);
+    }
+    path.reverse();
+    Some(path)
+}
+
+impl Graph<FlowEdge> {
+    /// Computes a maximum flow from `source` to `sink` like `FlowGraph::max_flow`, augmenting along the paths chosen
+    /// by `strategy` until it returns `None`. Flow already in the graph is kept and augmented, and a graph without
+    /// residual arcs is rebuilt with them first. Fails like `max_flow`. Panics if the strategy returns a path which is
+    /// not a residual path from the source to the sink.
+    pub fn max_flow_with_strategy<S>(&mut self, source: VertexId, sink: VertexId, strategy: &mut S)
+                                     -> Result<i64, FlowError>
+        where S: PathStrategy {
+        self.check_terminals(source, sink)?;
+        self.ensure_residual_arcs();
+        if !self.conserves_flow(source, sink) {
+            return Err(FlowError::ExistingFlow);
+        }
+        let mut augmentations = 0;
+        while let Some(path) = strategy.next_path(self, source, sink) {
+            let mut tail = source;
+            for &a in &path {
+                assert!(self.tail(a) == tail && self.arcs[a].residual() > 0,
+                        "Path strategy returned arc {} which is not a residual arc leaving {}", a, tail);
+                tail = self.head(a);
+            }
+            assert!(!path.is_empty() && tail == sink, "Path strategy returned a path which does not reach sink {}", sink);
+            let bottleneck = path.iter().map(|&a| self.arcs[a].residual()).min().unwrap_or(0);
+            for &a in &path {
+                self.arcs[a].flow += bottleneck;
+                self.arcs[a ^ 1].flow -= bottleneck;
+            }
+            augmentations += 1;
+            trace!("Strategy augmentation {} along {} arcs with bottleneck {}", augmentations, path.len(), bottleneck);
+        }
+        let flow = self.out_arcs(source)
+            .filter(|a| a.is_multiple_of(2))
+            .map(|a| self.arcs[a].flow as i64)
+            .sum::<i64>();
+        debug!("Max flow {} from {} to {} after {} strategy augmentations", flow, source, sink, augmentations);
+        if flow == 0 && augmentations == 0 {
+            return Err(FlowError::UnreachableSink {source, sink});
+        }
+        Ok(flow)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use generators::random_network;
+    use {FlowGraph, BFS};
+
+    #[test]
+    fn test_max_flow_with_strategy() {
+        for seed in 0..10 {
+            let (source, sink, g) = random_network(15, 50, 1..=20, seed);
+            let expected = g.clone().max_flow(source, sink, BFS);
+            assert_eq!(g.clone().max_flow_with_strategy(source, sink, &mut Search::Bfs), expected);
+            assert_eq!(g.clone().max_flow_with_strategy(source, sink, &mut Search::Dfs), expected);
+            assert_eq!(g.clone().max_flow_with_strategy(source, sink, &mut Fattest), expected);
+            assert_eq!(g.clone().max_flow_with_strategy(source, sink, &mut Random::new(seed)), expected);
+        }
+
+        // A closure which always takes the fattest path counts the augmentations
+        let (source, sink, mut g) = random_network(15, 50, 1..=20, 3);
+        let mut paths = 0;
+        let mut counting = |graph: &Graph<FlowEdge>, s: VertexId, t: VertexId| {
+            paths += 1;
+            Fattest.next_path(graph, s, t)
+        };
+        let flow = g.max_flow_with_strategy(source, sink, &mut counting);
+        assert_eq!(flow, random_network(15, 50, 1..=20, 3).2.max_flow(source, sink, BFS));
+        assert!(paths > 1);
+        assert!(g.conserves_flow(source, sink));
+    }
+}
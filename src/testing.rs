@@ -0,0 +1,276 @@
+use std::fmt;
+use std::ops::RangeInclusive;
+use rand::Rng;
+use generators::random_network;
+use verify::{certify, Violation};
+use super::{seeded_rng, write_dicaps, FlowEdge, FlowError, FlowGraph, Graph, Search, VertexId};
+
+/// Sizes of the random networks generated by `check_agreement`. The number of edges is capped at one edge per pair of
+/// vertexes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bounds {
+    pub vertexes: RangeInclusive<usize>,
+    pub edges: RangeInclusive<usize>,
+    pub capacity: RangeInclusive<i32>
+}
+
+impl Default for Bounds {
+    fn default() -> Bounds {
+        Bounds {vertexes: 2..=12, edges: 1..=40, capacity: 0..=20}
+    }
+}
+
+/// Reason why two solvers do not agree on a network. Solvers are numbered 0 and 1 in the order they were passed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Disagreement {
+    /// A solver failed with an error other than `FlowError::UnreachableSink`.
+    Error { solver: usize, error: FlowError },
+    /// The flow left in the graph by a solver is not a maximum flow of the value it returned.
+    InvalidFlow { solver: usize, violations: Vec<Violation> },
+    /// Both flows are valid but their values differ, which means a solver reports the wrong value.
+    Values { first: i64, second: i64 }
+}
+
+/// Network on which two solvers disagree, without flow, for reproducing the failure.
+#[derive(Debug, Clone)]
+pub struct Failure {
+    /// Number of the generated network, counting from 0.
+    pub case: usize,
+    pub source: VertexId,
+    pub sink: VertexId,
+    pub graph: Graph<FlowEdge>,
+    pub disagreement: Disagreement
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.disagreement {
+            Disagreement::Error {solver, ref error} => write!(f, "solver {} failed: {}", solver, error)?,
+            Disagreement::InvalidFlow {solver, ref violations} => {
+                let violations = violations.iter().map(|v| v.to_string()).collect::<Vec<_>>();
+                write!(f, "solver {} left an invalid flow: {}", solver, violations.join(", "))?
+            },
+            Disagreement::Values {first, second} => write!(f, "flow values differ: {} and {}", first, second)?
+        }
+        writeln!(f, " on case {}, network in DIMACS:", self.case)?;
+        let mut network: Vec<u8> = Vec::new();
+        write_dicaps(&mut network, &self.graph, self.source, self.sink).map_err(|_| fmt::Error)?;
+        write!(f, "{}", String::from_utf8_lossy(&network))
+    }
+}
+
+/// Returns a solver for `check_agreement` which runs `FlowGraph::max_flow` with `search`.
+pub fn search_solver(search: Search) -> impl FnMut(&mut Graph<FlowEdge>, VertexId, VertexId) -> Result<i64, FlowError> {
+    move |graph, source, sink| graph.max_flow(source, sink, search)
+}
+
+/// Generates `cases` random networks within `bounds` from `seed`, solves each with both solvers and checks that they
+/// leave valid maximum flows of equal value, see `verify::certify`. A solver gets a network without flow and returns
+/// the flow value, leaving the flows in the graph. A sink no flow reaches may be reported as
+/// `FlowError::UnreachableSink` or as a value of 0. Returns the number of networks checked, or the first network on
+/// which the solvers disagree.
+pub fn check_agreement<A, B>(seed: u64, cases: usize, bounds: &Bounds, first: A, second: B)
+                             -> Result<usize, Box<Failure>>
+    where A: FnMut(&mut Graph<FlowEdge>, VertexId, VertexId) -> Result<i64, FlowError>,
+          B: FnMut(&mut Graph<FlowEdge>, VertexId, VertexId) -> Result<i64, FlowError> {
+    check_agreement_with_rng(&mut seeded_rng(seed), cases, bounds, first, second)
+}
+
+/// Same as `check_agreement` but draws the networks from `rng`.
+pub fn check_agreement_with_rng<R, A, B>(rng: &mut R, cases: usize, bounds: &Bounds, mut first: A, mut second: B)
+                                         -> Result<usize, Box<Failure>>
+    where R: Rng + ?Sized,
+          A: FnMut(&mut Graph<FlowEdge>, VertexId, VertexId) -> Result<i64, FlowError>,
+          B: FnMut(&mut Graph<FlowEdge>, VertexId, VertexId) -> Result<i64, FlowError> {
+    assert!(*bounds.vertexes.start() >= 2, "A flow network needs at least two vertexes");
+    for case in 0..cases {
+        let n_vertexes = rng.gen_range(bounds.vertexes.clone());
+        let max_edges = n_vertexes * (n_vertexes - 1) / 2;
+        let n_edges = rng.gen_range(bounds.edges.clone()).min(max_edges);
+        let (source, sink, graph) = random_network(n_vertexes, n_edges, bounds.capacity.clone(), rng.gen());
+        let failure = |disagreement| Box::new(Failure {case, source, sink, graph: graph.clone(), disagreement});
+        let first_value = solve(&graph, source, sink, 0, &mut first).map_err(failure)?;
+        let second_value = solve(&graph, source, sink, 1, &mut second).map_err(failure)?;
+        if first_value != second_value {
+            return Err(failure(Disagreement::Values {first: first_value, second: second_value}));
+        }
+    }
+    Ok(cases)
+}
+
+/// Same as `check_agreement` but panics with the failing network when the solvers disagree, for use in tests.
+pub fn assert_agreement<A, B>(seed: u64, cases: usize, bounds: &Bounds, first: A, second: B)
+    where A: FnMut(&mut Graph<FlowEdge>, VertexId, VertexId) -> Result<i64, FlowError>,
+          B: FnMut(&mut Graph<FlowEdge>, VertexId, VertexId) -> Result<i64, FlowError> {
+    if let Err(failure) = check_agreement(seed, cases, bounds, first, second) {
+        panic!("{}", failure);
+    }
+}
+
+/// Solves a copy of the graph with a solver and certifies its flow.
+fn solve<S>(graph: &Graph<FlowEdge>, source: VertexId, sink: VertexId, solver: usize, solve: &mut S)
+            -> Result<i64, Disagreement>
+    where S: FnMut(&mut Graph<FlowEdge>, VertexId, VertexId) -> Result<i64, FlowError> {
+    let mut g = graph.clone();
+    let value = match solve(&mut g, source, sink) {
+        Ok(value) => value,
+        Err(FlowError::UnreachableSink {..}) => 0,
+        Err(error) => return Err(Disagreement::Error {solver, error})
+    };
+    certify(&g, source, sink, value).map_err(|violations| Disagreement::InvalidFlow {solver, violations})?;
+    Ok(value)
+}
+
+/// Shrinks a network on which `predicate` holds, such as two solvers disagreeing, into a small network on which it
+/// still holds, by delta debugging. Chunks of edges of halving sizes are removed, then single vertexes other than the
+/// terminals, then capacities are halved or decremented while they stay positive, and all of it is repeated until
+/// nothing more can be removed or reduced. The predicate gets networks without flow, numbered from 0 without gaps, and
+/// the result is minimal in that removing any edge or vertex or lowering any capacity by one makes it fail. Flows of
+/// the graph are dropped. Panics if the predicate does not hold on the given network.
+pub fn shrink<P>(graph: &Graph<FlowEdge>, source: VertexId, sink: VertexId, mut predicate: P)
+                 -> (VertexId, VertexId, Graph<FlowEdge>)
+    where P: FnMut(&Graph<FlowEdge>, VertexId, VertexId) -> bool {
+    let mut current = Reproducer {
+        n_vertexes: graph.n_vertexes(),
+        source: source.index(),
+        sink: sink.index(),
+        edges: graph.edges().map(|(u, v, a)| (u.index(), v.index(), graph.arcs[a].capacity)).collect()
+    };
+    let mut holds = |candidate: &Reproducer| predicate(&candidate.graph(), VertexId(candidate.source),
+                                                       VertexId(candidate.sink));
+    assert!(holds(&current), "The predicate must hold on the network to shrink");
+    let mut tests = 1;
+    loop {
+        let before = current.clone();
+        let mut size = current.edges.len();
+        while size > 0 {
+            let mut start = 0;
+            while start < current.edges.len() {
+                let mut candidate = current.clone();
+                candidate.edges.drain(start..(start + size).min(current.edges.len()));
+                tests += 1;
+                if holds(&candidate) {
+                    current = candidate;
+                } else {
+                    start += size;
+                }
+            }
+            size /= 2;
+        }
+        // Going down keeps the vertexes left to try at their numbers
+        for v in (0..current.n_vertexes).rev() {
+            if v == current.source || v == current.sink {
+                continue;
+            }
+            let candidate = current.without_vertex(v);
+            tests += 1;
+            if holds(&candidate) {
+                current = candidate;
+            }
+        }
+        for e in 0..current.edges.len() {
+            while current.edges[e].2 > 1 {
+                let capacity = current.edges[e].2;
+                let mut reduced = false;
+                for lower in [capacity / 2, capacity - 1] {
+                    let mut candidate = current.clone();
+                    candidate.edges[e].2 = lower;
+                    tests += 1;
+                    if holds(&candidate) {
+                        current = candidate;
+                        reduced = true;
+                        break;
+                    }
+                }
+                if !reduced {
+                    break;
+                }
+            }
+        }
+        if current == before {
+            break;
+        }
+    }
+    debug!("Shrunk {} vertexes and {} edges to {} vertexes and {} edges with {} tests",
+           graph.n_vertexes(), graph.n_edges(), current.n_vertexes, current.edges.len(), tests);
+    (VertexId(current.source), VertexId(current.sink), current.graph())
+}
+
+/// Network being shrunk by `shrink`, as `(from, to, capacity)` edges between vertexes `0..n_vertexes`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Reproducer {
+    n_vertexes: usize,
+    source: usize,
+    sink: usize,
+    edges: Vec<(usize, usize, i32)>
+}
+
+impl Reproducer {
+    fn graph(&self) -> Graph<FlowEdge> {
+        let vertexes = (0..self.n_vertexes).map(VertexId).collect::<Vec<_>>();
+        let edges = self.edges.iter().map(|&(u, v, capacity)| (VertexId(u), VertexId(v), FlowEdge{capacity, flow: 0}));
+        Graph::flow_network(&vertexes, edges)
+    }
+
+    /// Removes a vertex and its edges, the vertexes after it move down by one.
+    fn without_vertex(&self, removed: usize) -> Reproducer {
+        let renumber = |v: usize| if v > removed { v - 1 } else { v };
+        Reproducer {
+            n_vertexes: self.n_vertexes - 1,
+            source: renumber(self.source),
+            sink: renumber(self.sink),
+            edges: self.edges.iter()
+                .filter(|&&(u, v, _)| u != removed && v != removed)
+                .map(|&(u, v, capacity)| (renumber(u), renumber(v), capacity))
+                .collect()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use {BFS, DFS};
+
+    #[test]
+    fn test_check_agreement() {
+        assert_eq!(check_agreement(7, 30, &Bounds::default(), search_solver(BFS), search_solver(DFS)).unwrap(), 30);
+        assert_agreement(8, 10, &Bounds {vertexes: 20..=30, edges: 40..=80, capacity: 1..=5},
+                         search_solver(DFS), search_solver(BFS));
+
+        let off_by_one = |g: &mut Graph<FlowEdge>, source, sink| g.max_flow(source, sink, BFS).map(|v| v + 1);
+        let failure = check_agreement(7, 30, &Bounds::default(), search_solver(BFS), off_by_one).unwrap_err();
+        assert!(matches!(failure.disagreement, Disagreement::InvalidFlow {solver: 1, ..}));
+        let mut g = failure.graph.clone();
+        assert!(g.max_flow(failure.source, failure.sink, BFS).is_ok());
+        assert!(failure.to_string().contains(&format!("on case {}, network in DIMACS:\np max", failure.case)));
+    }
+
+    #[test]
+    fn test_shrink() {
+        let at_least_five = |g: &Graph<FlowEdge>, source, sink| g.clone().max_flow(source, sink, BFS).unwrap_or(0) >= 5;
+        let (source, sink, g) = random_network(12, 40, 1..=20, 3);
+        assert!(at_least_five(&g, source, sink));
+        let (source, sink, g) = shrink(&g, source, sink, at_least_five);
+        assert!(g.n_vertexes() < 12 && g.n_edges() < 40, "{:?}", g.size());
+        assert_eq!(g.clone().max_flow(source, sink, BFS), Ok(5));
+        // Every edge is needed at its capacity
+        for e in 0..g.n_edges() {
+            let mut lower = g.clone();
+            lower.arcs[2 * e].capacity -= 1;
+            assert!(!at_least_five(&lower, source, sink));
+        }
+
+        // Shrinking a disagreement keeps the solvers disagreeing
+        let off_by_one = |g: &mut Graph<FlowEdge>, source, sink| g.max_flow(source, sink, BFS).map(|v| v + 1);
+        let failure = check_agreement(7, 30, &Bounds::default(), search_solver(BFS), off_by_one).unwrap_err();
+        let disagree = |g: &Graph<FlowEdge>, source, sink| {
+            solve(g, source, sink, 0, &mut search_solver(BFS)) != solve(g, source, sink, 1, &mut { off_by_one })
+        };
+        let (source, sink, g) = shrink(&failure.graph, failure.source, failure.sink, disagree);
+        // A single path of unit capacities is left
+        assert_eq!(g.n_edges(), g.n_vertexes() - 1);
+        assert!(g.edges().all(|(_, _, a)| g.arcs[a].capacity == 1));
+        assert!(disagree(&g, source, sink));
+    }
+}
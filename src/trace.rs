@@ -0,0 +1,356 @@
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::fs::{self, File};
+#[cfg(feature = "std")]
+use std::io::{self, BufWriter, Read, Write};
+#[cfg(feature = "std")]
+use std::path::Path;
+#[cfg(feature = "std")]
+use serde_json;
+use super::{ArcId, Error, FlowEdge, FlowError, Graph, Search, SearchBuffers, VertexId, BFS};
+
+/// One augmentation of a max flow computation: the vertexes of the path from the source to the sink and the flow
+/// pushed along it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceStep {
+    pub path: Vec<VertexId>,
+    pub bottleneck: i32
+}
+
+/// Layout of the frames written by `Trace::write_frames`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FrameFormat {
+    /// DOT digraph with `flow/capacity` edge labels, saturated edges dashed and the last augmenting path in red.
+    Dot,
+    /// JSON object with the step, the flow value, the last augmenting path and the `from`, `to`, `capacity` and `flow`
+    /// of every edge.
+    Json
+}
+
+/// Every augmenting path of a max flow computation in the order they were pushed, which replays exactly how the flow
+/// was built.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Trace {
+    pub source: VertexId,
+    pub sink: VertexId,
+    pub search: Search,
+    pub steps: Vec<TraceStep>
+}
+
+impl Graph<FlowEdge> {
+    /// Same as `FlowGraph::max_flow` but also returns the trace of every augmenting path.
+    pub fn max_flow_traced(&mut self,
+                           source: VertexId,
+                           sink: VertexId,
+                           search: Search) -> Result<(i64, Trace), FlowError> {
+        let mut steps: Vec<TraceStep> = Vec::new();
+        let (flow, _) = self.augment_arcs(source, sink, search, Some(&mut steps), |_| true)?;
+        Ok((flow, Trace {source, sink, search, steps}))
+    }
+
+    /// Returns an iterator which finds the next augmenting path from `source` to `sink`, pushes its bottleneck and
+    /// yields it every time it is advanced, so a computation can be stepped, logged or stopped by the caller. Paths are
+    /// found by breadth first search unless another search is set with `AugmentingPaths::search`. Running the iterator
+    /// to the end leaves a maximum flow in the graph. A graph built with `Graph::new` is rebuilt with its residual arcs
+    /// first. Fails if a terminal is not a vertex, the source is the sink, or the flow in the graph is not conserved.
+    pub fn augmenting_paths(&mut self, source: VertexId, sink: VertexId) -> Result<AugmentingPaths<'_>, FlowError> {
+        self.check_terminals(source, sink)?;
+        self.ensure_residual_arcs();
+        if !self.conserves_flow(source, sink) {
+            return Err(FlowError::ExistingFlow);
+        }
+        let buffers = SearchBuffers::new(self.n_vertexes());
+        Ok(AugmentingPaths {graph: self, source, sink, search: BFS, buffers, flow: 0})
+    }
+}
+
+/// Iterator over the augmenting paths of a max flow computation, see `Graph::augmenting_paths`.
+pub struct AugmentingPaths<'a> {
+    graph: &'a mut Graph<FlowEdge>,
+    source: VertexId,
+    sink: VertexId,
+    search: Search,
+    buffers: SearchBuffers,
+    flow: i64
+}
+
+impl<'a> AugmentingPaths<'a> {
+    /// Finds the following paths with `search`.
+    pub fn search(mut self, search: Search) -> AugmentingPaths<'a> {
+        self.search = search;
+        self
+    }
+
+    /// Flow pushed by the paths yielded so far.
+    pub fn flow(&self) -> i64 {
+        self.flow
+    }
+}
+
+impl<'a> Iterator for AugmentingPaths<'a> {
+    type Item = TraceStep;
+
+    fn next(&mut self) -> Option<TraceStep> {
+        let graph = &mut *self.graph;
+        if !graph.search_residual(&mut self.buffers, self.source, self.sink, self.search,
+                                  |a| graph.arcs[a].residual() > 0) {
+            return None;
+        }
+        let path = graph.residual_path(&self.buffers, self.source, self.sink);
+        let parent_arcs = &self.buffers.parent_arcs;
+        let arcs = path.iter().skip(1).map(|&v| parent_arcs[v] as ArcId).collect::<Vec<_>>();
+        let bottleneck = arcs.iter().map(|&a| graph.arcs[a].residual()).min().unwrap_or(0);
+        for &a in &arcs {
+            graph.arcs[a].flow += bottleneck;
+            graph.arcs[a ^ 1].flow -= bottleneck;
+        }
+        self.flow += bottleneck as i64;
+        trace!("Stepped augmentation along {} arcs with bottleneck {}, flow {}", arcs.len(), bottleneck, self.flow);
+        Some(TraceStep {path, bottleneck})
+    }
+}
+
+impl Trace {
+    /// Pushes the flow of the first `n_steps` steps through `graph`, which should be the traced graph without flow,
+    /// and returns the flow value reached. Every step pushes its bottleneck over residual arcs between consecutive
+    /// vertexes of its path, preferring the arc used by the solver. Fails if a step has no such arc.
+    pub fn replay(&self, graph: &mut Graph<FlowEdge>, n_steps: usize) -> Result<i64, Error> {
+        let mut flow = 0;
+        for i in 0..n_steps.min(self.steps.len()) {
+            flow += self.replay_step(graph, i)?;
+        }
+        Ok(flow)
+    }
+
+    /// Pushes the flow of step `i` through `graph` and returns it, see `replay`.
+    fn replay_step(&self, graph: &mut Graph<FlowEdge>, i: usize) -> Result<i64, Error> {
+        let step = &self.steps[i];
+        for pair in step.path.windows(2) {
+            let (u, v) = (pair[0], pair[1]);
+            let arc = if u.index() < graph.n_vertexes() {
+                graph.out_arcs(u).find(|&a| graph.head(a) == v && graph.arcs[a].residual() >= step.bottleneck)
+            } else {
+                None
+            };
+            let arc = arc.ok_or_else(|| Error::Construction(
+                format!("Step {} has no residual arc ({}, {}) for a flow of {}", i, u, v, step.bottleneck)))?;
+            graph.arcs[arc].flow += step.bottleneck;
+            graph.arcs[arc ^ 1].flow -= step.bottleneck;
+        }
+        Ok(step.bottleneck as i64)
+    }
+
+    /// Writes one snapshot of `graph`, which should be the traced graph without flow, per step of the trace to
+    /// `directory` for animations of the computation. The files are named `frame_00000.dot` or `.json` and so on,
+    /// frame 0 shows the graph before the first augmentation and frame `i` the flow after step `i`. Creates the
+    /// directory if needed and returns the number of frames written.
+    #[cfg(feature = "std")]
+    pub fn write_frames<P: AsRef<Path>>(&self,
+                                        graph: &Graph<FlowEdge>,
+                                        directory: P,
+                                        format: FrameFormat) -> io::Result<usize> {
+        let directory = directory.as_ref();
+        fs::create_dir_all(directory)?;
+        let extension = match format {
+            FrameFormat::Dot => "dot",
+            FrameFormat::Json => "json"
+        };
+        let mut g = graph.clone();
+        let mut flow = 0;
+        for i in 0..=self.steps.len() {
+            let path: &[VertexId] = if i == 0 { &[] } else { &self.steps[i - 1].path };
+            if i > 0 {
+                flow += self.replay_step(&mut g, i - 1)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e.to_string()))?;
+            }
+            let file = File::create(directory.join(format!("frame_{:05}.{}", i, extension)))?;
+            let mut writer = BufWriter::new(file);
+            match format {
+                FrameFormat::Dot => write_dot_frame(&mut writer, &g, self.source, self.sink, path)?,
+                FrameFormat::Json => write_json_frame(&mut writer, &g, i, flow, path)?
+            }
+            writer.flush()?;
+        }
+        Ok(self.steps.len() + 1)
+    }
+
+    /// Writes the trace as a JSON object with `source`, `sink`, `search` and a list of `steps`, each step being an
+    /// object with the `path` and the `bottleneck`.
+    #[cfg(feature = "std")]
+    pub fn write_json<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let search = match self.search {
+            Search::Bfs => "bfs",
+            Search::Dfs => "dfs",
+            Search::Bidirectional => "bidirectional"
+        };
+        writeln!(writer, "{{")?;
+        writeln!(writer, "  \"source\": {},", self.source)?;
+        writeln!(writer, "  \"sink\": {},", self.sink)?;
+        writeln!(writer, "  \"search\": \"{}\",", search)?;
+        write!(writer, "  \"steps\": [")?;
+        for (i, step) in self.steps.iter().enumerate() {
+            let separator = if i == 0 { "" } else { "," };
+            write!(writer, "{}\n    {{\"path\": {:?}, \"bottleneck\": {}}}", separator, step.path, step.bottleneck)?;
+        }
+        writeln!(writer, "\n  ]")?;
+        writeln!(writer, "}}")
+    }
+
+    /// Reads a trace written by `write_json`.
+    #[cfg(feature = "std")]
+    pub fn read_json<R: Read>(reader: R) -> Result<Trace, Error> {
+        let invalid = |message: &str| Error::Construction(message.to_string());
+        let value: serde_json::Value = serde_json::from_reader(reader)
+            .map_err(|e| Error::Parse {line: e.line(), message: e.to_string()})?;
+        let vertex = |v: &serde_json::Value| v.as_u64().map(|v| VertexId(v as usize));
+        let search = match value.get("search").and_then(|s| s.as_str()) {
+            Some("bfs") => Search::Bfs,
+            Some("dfs") => Search::Dfs,
+            Some("bidirectional") => Search::Bidirectional,
+            _ => return Err(invalid("Expected \"bfs\", \"dfs\" or \"bidirectional\" for \"search\""))
+        };
+        let mut steps: Vec<TraceStep> = Vec::new();
+        for step in value.get("steps").and_then(|s| s.as_array()).ok_or_else(|| invalid("Expected a list of steps"))? {
+            let path = step.get("path").and_then(|p| p.as_array())
+                .and_then(|p| p.iter().map(vertex).collect::<Option<Vec<_>>>())
+                .ok_or_else(|| invalid("Expected a list of vertexes for \"path\""))?;
+            let bottleneck = step.get("bottleneck").and_then(|b| b.as_i64()).filter(|b| *b > 0 && *b <= i32::MAX as i64)
+                .ok_or_else(|| invalid("Expected a positive integer for \"bottleneck\""))?;
+            steps.push(TraceStep {path, bottleneck: bottleneck as i32});
+        }
+        Ok(Trace {
+            source: value.get("source").and_then(vertex).ok_or_else(|| invalid("Expected a vertex for \"source\""))?,
+            sink: value.get("sink").and_then(vertex).ok_or_else(|| invalid("Expected a vertex for \"sink\""))?,
+            search,
+            steps
+        })
+    }
+}
+
+/// Writes the flow of every edge as a DOT digraph, see `FrameFormat::Dot`.
+#[cfg(feature = "std")]
+fn write_dot_frame<W: Write>(writer: &mut W,
+                             graph: &Graph<FlowEdge>,
+                             source: VertexId,
+                             sink: VertexId,
+                             path: &[VertexId]) -> io::Result<()> {
+    let on_path = |u: VertexId, v: VertexId| path.windows(2).any(|p| (p[0], p[1]) == (u, v) || (p[0], p[1]) == (v, u));
+    writeln!(writer, "digraph max_flow {{")?;
+    // Every vertex gets a statement, so frames keep the same vertexes and layout
+    for v in graph.vertexes() {
+        if v == source {
+            writeln!(writer, "    {} [shape=box, label=\"{} (source)\"];", v, graph.vertex_label(v))?;
+        } else if v == sink {
+            writeln!(writer, "    {} [shape=box, label=\"{} (sink)\"];", v, graph.vertex_label(v))?;
+        } else if let Some(name) = graph.vertex_name(v) {
+            writeln!(writer, "    {} [label=\"{}\"];", v, name)?;
+        } else {
+            writeln!(writer, "    {};", v)?;
+        }
+    }
+    for (u, v, a) in graph.edges() {
+        let edge = graph.arcs[a];
+        let mut attributes = format!("label=\"{}/{}\"", edge.flow, edge.capacity);
+        if edge.residual() == 0 {
+            attributes.push_str(", style=dashed");
+        }
+        if on_path(u, v) {
+            attributes.push_str(", color=red, penwidth=2");
+        }
+        writeln!(writer, "    {} -> {} [{}];", u, v, attributes)?;
+    }
+    writeln!(writer, "}}")
+}
+
+/// Writes the flow of every edge as a JSON object, see `FrameFormat::Json`.
+#[cfg(feature = "std")]
+fn write_json_frame<W: Write>(writer: &mut W,
+                              graph: &Graph<FlowEdge>,
+                              step: usize,
+                              flow: i64,
+                              path: &[VertexId]) -> io::Result<()> {
+    writeln!(writer, "{{")?;
+    writeln!(writer, "  \"step\": {},", step)?;
+    writeln!(writer, "  \"flow\": {},", flow)?;
+    writeln!(writer, "  \"path\": {:?},", path)?;
+    write!(writer, "  \"edges\": [")?;
+    for (i, (u, v, a)) in graph.edges().enumerate() {
+        let separator = if i == 0 { "" } else { "," };
+        write!(writer, "{}\n    {{\"from\": {}, \"to\": {}, \"capacity\": {}, \"flow\": {}}}",
+               separator, u, v, graph.arcs[a].capacity, graph.arcs[a].flow)?;
+    }
+    writeln!(writer, "\n  ]")?;
+    writeln!(writer, "}}")
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use std::{env, process};
+    use super::*;
+    use verify::verify_flow;
+    use {flow_from_dicaps, FlowGraph, BFS, DFS};
+
+    #[test]
+    fn test_max_flow_traced() {
+        let (source, sink, g) = flow_from_dicaps("data/dicaps/flow-graph.txt");
+        let (flow, trace) = g.clone().max_flow_traced(source, sink, BFS).unwrap();
+        assert_eq!(flow, 10);
+        let paths = trace.steps.iter().map(|s| (s.path.iter().map(|v| v.index()).collect(), s.bottleneck));
+        assert_eq!(paths.collect::<Vec<(Vec<_>, _)>>(), [(vec![0, 2, 1], 5), (vec![0, 3, 1], 5)]);
+
+        let mut replayed = g.clone();
+        assert_eq!(trace.replay(&mut replayed, 1), Ok(5));
+        assert_eq!(trace.replay(&mut replayed, 1).unwrap_err(),
+                   Error::Construction("Step 0 has no residual arc (0, 2) for a flow of 5".to_string()));
+
+        let (source, sink, g) = flow_from_dicaps("data/dicaps/central.txt");
+        let (flow, trace) = g.clone().max_flow_traced(source, sink, DFS).unwrap();
+        let mut json: Vec<u8> = Vec::new();
+        trace.write_json(&mut json).unwrap();
+        let read = Trace::read_json(&json[..]).unwrap();
+        assert_eq!(read, trace);
+        let mut replayed = g.clone();
+        assert_eq!(read.replay(&mut replayed, read.steps.len()), Ok(flow));
+        assert_eq!(verify_flow(&replayed, source, sink), vec![]);
+        assert!(Trace::read_json("{\"source\": 0}".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_augmenting_paths() {
+        let (source, sink, g) = flow_from_dicaps("data/dicaps/central.txt");
+        let (flow, trace) = g.clone().max_flow_traced(source, sink, DFS).unwrap();
+        let mut stepped = g.clone();
+        let steps = stepped.augmenting_paths(source, sink).unwrap().search(DFS).collect::<Vec<_>>();
+        assert_eq!(steps, trace.steps);
+        assert_eq!(verify_flow(&stepped, source, sink), vec![]);
+
+        // Stopping early leaves a feasible flow which solving continues
+        let mut stepped = g.clone();
+        let mut paths = stepped.augmenting_paths(source, sink).unwrap();
+        let first = paths.next().unwrap();
+        assert_eq!(paths.flow(), first.bottleneck as i64);
+        assert!(stepped.conserves_flow(source, sink));
+        assert_eq!(stepped.max_flow(source, sink, BFS), Ok(flow));
+        assert_eq!(stepped.augmenting_paths(source, sink).unwrap().next(), None);
+        assert!(stepped.augmenting_paths(source, source).is_err());
+    }
+
+    #[test]
+    fn test_write_frames() {
+        let (source, sink, g) = flow_from_dicaps("data/dicaps/flow-graph.txt");
+        let (_, trace) = g.clone().max_flow_traced(source, sink, BFS).unwrap();
+        let directory = env::temp_dir().join(format!("max_flow_frames_{}", process::id()));
+        assert_eq!(trace.write_frames(&g, &directory, FrameFormat::Dot).unwrap(), 3);
+        let first = fs::read_to_string(directory.join("frame_00000.dot")).unwrap();
+        assert!(first.contains("0 -> 2 [label=\"0/5\"];"));
+        let last = fs::read_to_string(directory.join("frame_00002.dot")).unwrap();
+        assert!(last.contains("0 -> 3 [label=\"5/5\", style=dashed, color=red, penwidth=2];"));
+
+        assert_eq!(trace.write_frames(&g, &directory, FrameFormat::Json).unwrap(), 3);
+        let frame: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(directory.join("frame_00001.json")).unwrap()).unwrap();
+        assert_eq!((frame["step"].as_u64(), frame["flow"].as_i64()), (Some(1), Some(5)));
+        assert_eq!(frame["path"], serde_json::json!([0, 2, 1]));
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
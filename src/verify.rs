@@ -0,0 +1,284 @@
+use alloc::vec::Vec;
+use core::fmt;
+use levels::UNREACHED;
+use super::{ArcId, FlowEdge, FlowGraph, Graph, VertexId, BFS};
+#[cfg(feature = "std")]
+use super::Solution;
+
+/// Reason why a flow is not a valid maximum flow.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Violation {
+    /// A solution assigns flow to a pair of vertexes which is not an edge of the graph.
+    UnknownEdge { from: VertexId, to: VertexId },
+    /// The flow on an edge is negative or exceeds its capacity.
+    Capacity { from: VertexId, to: VertexId, flow: i32, capacity: i32 },
+    /// The reverse arc of an edge does not carry the negated flow of the edge.
+    Antisymmetry { from: VertexId, to: VertexId, flow: i32, reverse_flow: i32 },
+    /// The flow into an internal vertex differs from the flow out of it by `excess`.
+    Conservation { vertex: VertexId, excess: i64 },
+    /// The flow value claimed by a solution differs from the flow leaving the source.
+    Value { claimed: i64, actual: i64 },
+    /// The residual graph still has a path from source to sink, so the flow is not maximum.
+    NotMaximum { path: Vec<VertexId> },
+    /// The cut left by the flow has a capacity other than the flow value.
+    CutCapacity { flow: i64, capacity: i64 }
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Violation::UnknownEdge {from, to} => write!(f, "flow assigned to ({}, {}) which is not an edge", from, to),
+            Violation::Capacity {from, to, flow, capacity} =>
+                write!(f, "flow {} on edge ({}, {}) is outside of [0, {}]", flow, from, to, capacity),
+            Violation::Antisymmetry {from, to, flow, reverse_flow} =>
+                write!(f, "edge ({}, {}) has flow {} but its reverse arc has flow {}", from, to, flow, reverse_flow),
+            Violation::Conservation {vertex, excess} => write!(f, "vertex {} has an excess of {}", vertex, excess),
+            Violation::Value {claimed, actual} =>
+                write!(f, "claimed flow value {} differs from the actual value {}", claimed, actual),
+            Violation::NotMaximum {ref path} => write!(f, "flow is not maximum, augmenting path {:?}", path),
+            Violation::CutCapacity {flow, capacity} =>
+                write!(f, "flow value {} differs from the capacity {} of the residual cut", flow, capacity)
+        }
+    }
+}
+
+/// Proof that a flow is maximum by the max-flow min-cut theorem: a cut separating the terminals whose capacity equals
+/// the flow value. Checking a certificate only needs the capacities of the cut edges, not the solver.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Certificate {
+    /// Net flow leaving the source.
+    pub flow_value: i64,
+    /// Whether each vertex is reached from the source in the residual graph.
+    pub source_side: Vec<bool>,
+    /// Arcs of the edges from the source side to the sink side, which are all saturated.
+    pub cut: Vec<ArcId>,
+    /// Sum of the capacities of the cut edges, equal to `flow_value`.
+    pub cut_capacity: i64
+}
+
+/// Returns the net flow leaving `source`.
+pub fn flow_value(graph: &Graph<FlowEdge>, source: VertexId) -> i64 {
+    graph.out_arcs(source)
+        .filter(|a| a % 2 == 0)
+        .map(|a| graph.arcs[a].flow as i64)
+        .sum()
+}
+
+/// Sets the flow of each `(from, to, flow)` triple on the graph, keeping the residual arcs in sync. Parallel edges
+/// take the triples of their pair in the order of the edges, those with capacity first as `write_solution` only writes
+/// them. Entries which are not edges of the graph, or beyond the number of edges of their pair, are returned as
+/// violations and ignored.
+pub fn apply_flows(graph: &mut Graph<FlowEdge>, flows: &[(VertexId, VertexId, i32)]) -> Vec<Violation> {
+    let mut violations: Vec<Violation> = Vec::new();
+    let mut assigned = vec![false; graph.arcs.len()];
+    for &(u, v, flow) in flows {
+        let next = if u.index() < graph.n_vertexes() {
+            let mut unassigned = graph.out_arcs(u).filter(|&a| a % 2 == 0 && graph.head(a) == v && !assigned[a]);
+            let first = unassigned.next();
+            first.filter(|&a| graph.arcs[a].capacity > 0)
+                .or_else(|| unassigned.find(|&a| graph.arcs[a].capacity > 0))
+                .or(first)
+        } else {
+            None
+        };
+        match next {
+            Some(arc) => {
+                assigned[arc] = true;
+                graph.arcs[arc].flow = flow;
+                graph.arcs[arc ^ 1].flow = -flow;
+            },
+            None => violations.push(Violation::UnknownEdge {from: u, to: v})
+        }
+    }
+    violations
+}
+
+/// Checks that the flows stored in the graph form a maximum flow from `source` to `sink`: every edge flow is within
+/// its capacity and negated on its reverse arc, flow is conserved at every vertex other than the terminals and no
+/// augmenting path is left in the residual graph. Returns every violation found, so an empty list means the flow is a
+/// valid maximum flow.
+pub fn verify_flow(graph: &Graph<FlowEdge>, source: VertexId, sink: VertexId) -> Vec<Violation> {
+    let mut violations = feasibility_violations(graph, source, sink);
+    if violations.is_empty() {
+        // The residual search is only meaningful for a feasible flow
+        if let Some(path) = graph.augmenting_path(source, sink, BFS) {
+            violations.push(Violation::NotMaximum {path});
+        }
+    }
+    violations
+}
+
+/// Certifies that the flows stored in the graph form a maximum flow of value `value`, as returned by a solver. The
+/// flow must be feasible as for `verify_flow`, its value must be `value`, and the edges leaving the vertexes the source
+/// reaches in the residual graph must form a cut with capacity `value` which separates the sink. Returns the cut as a
+/// certificate, or every violation found.
+pub fn certify(graph: &Graph<FlowEdge>, source: VertexId, sink: VertexId, value: i64)
+               -> Result<Certificate, Vec<Violation>> {
+    let mut violations = feasibility_violations(graph, source, sink);
+    if !violations.is_empty() {
+        return Err(violations);
+    }
+    let flow_value = flow_value(graph, source);
+    if flow_value != value {
+        violations.push(Violation::Value {claimed: value, actual: flow_value});
+    }
+    let source_side = graph.residual_levels(source).into_iter().map(|l| l != UNREACHED).collect::<Vec<_>>();
+    if source_side[sink] {
+        violations.extend(graph.augmenting_path(source, sink, BFS).map(|path| Violation::NotMaximum {path}));
+        return Err(violations);
+    }
+    let cut = graph.min_cut(source);
+    let cut_capacity = cut.iter().map(|&a| graph.arcs[a].capacity as i64).sum();
+    if cut_capacity != flow_value {
+        violations.push(Violation::CutCapacity {flow: flow_value, capacity: cut_capacity});
+    }
+    if violations.is_empty() {
+        Ok(Certificate {flow_value, source_side, cut, cut_capacity})
+    } else {
+        Err(violations)
+    }
+}
+
+/// Returns the capacity, antisymmetry and conservation violations of the flows stored in the graph.
+fn feasibility_violations(graph: &Graph<FlowEdge>, source: VertexId, sink: VertexId) -> Vec<Violation> {
+    let mut violations: Vec<Violation> = Vec::new();
+    let mut excess = vec![0i64; graph.n_vertexes()];
+    for (u, v, arc) in graph.edges() {
+        let edge = graph.arcs[arc];
+        if edge.flow < 0 || edge.flow > edge.capacity {
+            violations.push(Violation::Capacity {from: u, to: v, flow: edge.flow, capacity: edge.capacity});
+        }
+        let reverse_flow = graph.arcs[arc ^ 1].flow;
+        if reverse_flow != -edge.flow {
+            violations.push(Violation::Antisymmetry {from: u, to: v, flow: edge.flow, reverse_flow});
+        }
+        excess[u] -= edge.flow as i64;
+        excess[v] += edge.flow as i64;
+    }
+    for (vertex, e) in graph.vertexes().zip(excess) {
+        if vertex != source && vertex != sink && e != 0 {
+            violations.push(Violation::Conservation {vertex, excess: e});
+        }
+    }
+    violations
+}
+
+/// Applies a solution to a graph without flow and verifies it, including the flow value claimed by the solution.
+#[cfg(feature = "std")]
+pub fn verify_solution(graph: &mut Graph<FlowEdge>,
+                       source: VertexId,
+                       sink: VertexId,
+                       solution: &Solution) -> Vec<Violation> {
+    let mut violations = apply_flows(graph, &solution.flows);
+    violations.extend(verify_flow(graph, source, sink));
+    if let Some(claimed) = solution.value {
+        let actual = flow_value(graph, source);
+        if claimed != actual {
+            violations.push(Violation::Value {claimed, actual});
+        }
+    }
+    violations
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use {flow_from_dicaps, flow_from_reader, read_solution, write_solution, Format};
+
+    fn ids(vertexes: &[usize]) -> Vec<VertexId> {
+        vertexes.iter().map(|&v| VertexId(v)).collect()
+    }
+
+    #[test]
+    fn test_verify_max_flow() {
+        let (source, sink, mut g) = flow_from_dicaps("data/dicaps/central.txt");
+        g.max_flow(source, sink, BFS).unwrap();
+        assert_eq!(verify_flow(&g, source, sink), vec![]);
+        assert_eq!(flow_value(&g, source), 5);
+
+        let arc = g.find_edge(VertexId(0), VertexId(1)).unwrap();
+        g.arcs[arc ^ 1].flow += 1;
+        assert_eq!(verify_flow(&g, source, sink), vec![Violation::Antisymmetry {
+            from: VertexId(0), to: VertexId(1), flow: g.arcs[arc].flow, reverse_flow: 1 - g.arcs[arc].flow
+        }]);
+    }
+
+    #[test]
+    fn test_verify_infeasible_flow() {
+        // Every capacity, antisymmetry and conservation violation is reported, and no residual path is searched
+        let (source, sink, mut g) = flow_from_dicaps("data/dicaps/flow-graph.txt");
+        for &(u, v, flow, reverse_flow) in &[(0, 2, 6, -6), (0, 3, 2, -1), (2, 3, -1, 1), (2, 1, 6, -6)] {
+            let arc = g.find_edge(VertexId(u), VertexId(v)).unwrap();
+            g.arcs[arc].flow = flow;
+            g.arcs[arc ^ 1].flow = reverse_flow;
+        }
+        let violations = verify_flow(&g, source, sink);
+        assert_eq!(violations, vec![
+            Violation::Capacity {from: VertexId(0), to: VertexId(2), flow: 6, capacity: 5},
+            Violation::Antisymmetry {from: VertexId(0), to: VertexId(3), flow: 2, reverse_flow: -1},
+            Violation::Capacity {from: VertexId(2), to: VertexId(3), flow: -1, capacity: 1},
+            Violation::Capacity {from: VertexId(2), to: VertexId(1), flow: 6, capacity: 5},
+            Violation::Conservation {vertex: VertexId(2), excess: 1},
+            Violation::Conservation {vertex: VertexId(3), excess: 1}
+        ]);
+        assert_eq!(violations[0].to_string(), "flow 6 on edge (0, 2) is outside of [0, 5]");
+        assert_eq!(violations[1].to_string(), "edge (0, 3) has flow 2 but its reverse arc has flow -1");
+        assert_eq!(violations[4].to_string(), "vertex 2 has an excess of 1");
+    }
+
+    #[test]
+    fn test_certify() {
+        let (source, sink, mut g) = flow_from_dicaps("data/dicaps/flow-graph.txt");
+        let flow = g.max_flow(source, sink, BFS).unwrap();
+        let certificate = certify(&g, source, sink, flow).unwrap();
+        assert_eq!((certificate.flow_value, certificate.cut_capacity), (10, 10));
+        assert_eq!(certificate.source_side, [true, false, false, false]);
+        let edge = |u, v| g.find_edge(VertexId(u), VertexId(v)).unwrap();
+        assert_eq!(certificate.cut, [edge(0, 2), edge(0, 3)]);
+        assert_eq!(certify(&g, source, sink, 9).unwrap_err(), vec![Violation::Value {claimed: 9, actual: 10}]);
+
+        g.reset_flow();
+        g.max_flow_observed(source, sink, BFS, |p| p.augmentations < 1).unwrap();
+        assert_eq!(certify(&g, source, sink, 5).unwrap_err(), vec![Violation::NotMaximum {path: ids(&[0, 3, 1])}]);
+        let arc = g.find_edge(VertexId(0), VertexId(3)).unwrap();
+        g.arcs[arc].flow = 1;
+        g.arcs[arc ^ 1].flow = -1;
+        assert_eq!(certify(&g, source, sink, 6).unwrap_err(), vec![Violation::Conservation {vertex: VertexId(3), excess: 1}]);
+    }
+
+    #[test]
+    fn test_verify_solution() {
+        let (source, sink, g) = flow_from_dicaps("data/dicaps/flow-graph.txt");
+        let solution = read_solution("s 10\nf 0 2 5\nf 0 3 5\nf 2 1 5\nf 3 1 5\n".as_bytes()).unwrap();
+        assert_eq!(verify_solution(&mut g.clone(), source, sink, &solution), vec![]);
+
+        let solution = read_solution("s 9\nf 0 2 6\nf 0 3 3\nf 2 1 6\nf 3 1 3\nf 1 0 1\n".as_bytes()).unwrap();
+        let violations = verify_solution(&mut g.clone(), source, sink, &solution);
+        assert_eq!(violations[0], Violation::UnknownEdge {from: VertexId(1), to: VertexId(0)});
+        assert_eq!(violations[1], Violation::Capacity {from: VertexId(0), to: VertexId(2), flow: 6, capacity: 5});
+
+        let solution = read_solution("s 8\nf 0 2 4\nf 0 3 4\nf 2 1 4\nf 3 1 4\n".as_bytes()).unwrap();
+        let violations = verify_solution(&mut g.clone(), source, sink, &solution);
+        assert_eq!(violations, vec![Violation::NotMaximum {path: ids(&[0, 2, 1])}]);
+    }
+
+    #[test]
+    fn test_verify_solution_parallel_edges() {
+        // Every line of a pair of vertexes goes to the next of its parallel edges
+        let input = "p max 3 3\nn 0 s\nn 2 t\na 0 1 4\na 0 1 3\na 1 2 10\n";
+        let (source, sink, g) = flow_from_reader(input.as_bytes(), Format::Dicaps).unwrap();
+        let mut solved = g.clone();
+        assert_eq!(solved.max_flow(source, sink, BFS), Ok(7));
+        let mut buffer: Vec<u8> = Vec::new();
+        write_solution(&mut buffer, &solved, source).unwrap();
+        let solution = read_solution(buffer.as_slice()).unwrap();
+        assert_eq!(verify_solution(&mut g.clone(), source, sink, &solution), vec![]);
+
+        let solution = read_solution("s 7\nf 0 1 3\nf 0 1 4\nf 1 2 7\n".as_bytes()).unwrap();
+        assert_eq!(verify_solution(&mut g.clone(), source, sink, &solution),
+                   vec![Violation::Capacity {from: VertexId(0), to: VertexId(1), flow: 4, capacity: 3}]);
+        let solution = read_solution("s 7\nf 0 1 4\nf 0 1 3\nf 0 1 0\nf 1 2 7\n".as_bytes()).unwrap();
+        assert_eq!(verify_solution(&mut g.clone(), source, sink, &solution),
+                   vec![Violation::UnknownEdge {from: VertexId(0), to: VertexId(1)}]);
+    }
+}
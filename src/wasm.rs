@@ -0,0 +1,108 @@
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use wasm_bindgen::prelude::*;
+use levels::UNREACHED;
+use super::{DuplicatePolicy, Error, FlowEdge, FlowError, FlowGraph, Graph, SelfLoopPolicy, VertexId, BFS, DFS};
+
+/// Maximum flow of a network solved from JavaScript, with a minimum cut.
+#[wasm_bindgen]
+#[derive(Debug, Clone, PartialEq)]
+pub struct FlowResult {
+    value: f64,
+    flows: Vec<i32>,
+    source_side: Vec<u8>,
+    cut: Vec<u32>
+}
+
+#[wasm_bindgen]
+impl FlowResult {
+    /// Value of the maximum flow, exact up to 2^53.
+    #[wasm_bindgen(getter)]
+    pub fn value(&self) -> f64 {
+        self.value
+    }
+
+    /// Flow of every edge, in the order of the input arrays.
+    #[wasm_bindgen(getter)]
+    pub fn flows(&self) -> Vec<i32> {
+        self.flows.clone()
+    }
+
+    /// 1 for every vertex on the source side of the minimum cut and 0 for every vertex on the sink side.
+    #[wasm_bindgen(getter, js_name = sourceSide)]
+    pub fn source_side(&self) -> Vec<u8> {
+        self.source_side.clone()
+    }
+
+    /// Indexes of the edges from the source side to the sink side, which are saturated and sum up to the flow value.
+    #[wasm_bindgen(getter)]
+    pub fn cut(&self) -> Vec<u32> {
+        self.cut.clone()
+    }
+}
+
+/// Solves the maximum flow problem of a network given as typed arrays, where edge `i` goes from `tails[i]` to
+/// `heads[i]` with capacity `capacities[i]`. Augmenting paths are found by breadth first search, or by depth first
+/// search when `depth_first` is set. Throws if the arrays differ in length, an edge has an invalid vertex or is a self
+/// loop, or a terminal is invalid.
+#[wasm_bindgen(js_name = maxFlow)]
+pub fn max_flow(n_vertexes: u32,
+                tails: &[u32],
+                heads: &[u32],
+                capacities: &[i32],
+                source: u32,
+                sink: u32,
+                depth_first: bool) -> Result<FlowResult, JsError> {
+    solve(n_vertexes, tails, heads, capacities, source, sink, depth_first).map_err(|e| JsError::new(&e.to_string()))
+}
+
+fn solve(n_vertexes: u32,
+         tails: &[u32],
+         heads: &[u32],
+         capacities: &[i32],
+         source: u32,
+         sink: u32,
+         depth_first: bool) -> Result<FlowResult, Error> {
+    if tails.len() != heads.len() || tails.len() != capacities.len() {
+        return Err(Error::Construction("The tails, heads and capacities differ in length".to_string()));
+    }
+    let vertex = |v: u32| VertexId(v as usize);
+    let vertexes = (0..n_vertexes).map(vertex).collect::<Vec<_>>();
+    let edges = (0..tails.len())
+        .map(|i| (vertex(tails[i]), vertex(heads[i]), FlowEdge{capacity: capacities[i], flow: 0}))
+        .collect::<Vec<_>>();
+    // Keeping every edge keeps edge `i` on arc `2 * i`
+    let mut g = Graph::try_flow_network(&vertexes, &edges, DuplicatePolicy::Parallel, SelfLoopPolicy::Reject)?;
+    let (source, sink) = (vertex(source), vertex(sink));
+    let value = match g.max_flow(source, sink, if depth_first { DFS } else { BFS }) {
+        Ok(value) => value,
+        Err(FlowError::UnreachableSink {..}) => 0,
+        Err(e) => return Err(e.into())
+    };
+    Ok(FlowResult {
+        value: value as f64,
+        flows: g.edges().map(|(_, _, a)| g.arcs[a].flow).collect(),
+        source_side: g.residual_levels(source).iter().map(|&l| (l != UNREACHED) as u8).collect(),
+        cut: g.min_cut(source).into_iter().map(|a| (a / 2) as u32).collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_solve() {
+        let result = solve(4, &[0, 0, 1, 1, 2], &[1, 2, 2, 3, 3], &[3, 2, 1, 2, 3], 0, 3, false).unwrap();
+        assert_eq!(result.value(), 5.0);
+        assert_eq!(result.flows(), [3, 2, 1, 2, 3]);
+        assert_eq!(result.source_side(), [1, 0, 0, 0]);
+        assert_eq!(result.cut(), [0, 1]);
+
+        let result = solve(3, &[0], &[1], &[4], 0, 2, true).unwrap();
+        assert_eq!((result.value(), result.source_side()), (0.0, vec![1, 1, 0]));
+        assert!(solve(2, &[0, 1], &[1], &[1], 0, 1, false).is_err());
+        assert!(solve(2, &[0, 1], &[1, 1], &[1, 1], 0, 1, false).is_err());
+        assert!(solve(2, &[0], &[1], &[1], 0, 0, false).is_err());
+    }
+}